@@ -0,0 +1,806 @@
+use super::replay_cache::{MemoryReplayCache, ReplayCache, ReplayEntry};
+use crate::error::*;
+use crate::utils::{decrypt, encrypt};
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    ApRep, ApReq, Asn1Object, Authenticator, AuthorizationData, EncApRepPart,
+    EncTicketPart, EncryptedData, EncryptionKey, HostAddress, PrincipalName,
+    Realm,
+};
+use himmelblau_kerberos_constants::address_types::{IPV4, IPV6};
+use himmelblau_kerberos_constants::ap_options::{
+    AP_OPTION_MUTUAL_REQUIRED, AP_OPTION_USE_SESSION_KEY,
+};
+use himmelblau_kerberos_constants::error_codes::{
+    KRB_AP_ERR_BADADDR, KRB_AP_ERR_BADKEYVER, KRB_AP_ERR_BADMATCH,
+    KRB_AP_ERR_BADVERSION, KRB_AP_ERR_BAD_INTEGRITY, KRB_AP_ERR_METHOD,
+    KRB_AP_ERR_MSG_TYPE, KRB_AP_ERR_NOKEY, KRB_AP_ERR_NOT_US, KRB_AP_ERR_SKEW,
+    KRB_AP_ERR_TKT_EXPIRED, KRB_AP_ERR_TKT_NYV,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_AP_REP_ENC_PART, KEY_USAGE_AP_REQ_AUTHEN, KEY_USAGE_AS_REP_TICKET,
+};
+use himmelblau_kerberos_constants::message_types::{KRB_AP_REP, KRB_AP_REQ};
+use himmelblau_kerberos_constants::protocol_version::PVNO;
+use himmelblau_kerberos_constants::ticket_flags::INVALID;
+use himmelblau_kerberos_keytab::{Keytab, KeytabEntry};
+use std::fs;
+use std::net::IpAddr;
+
+/// Default maximum difference allowed between the clocks of the client and
+/// the acceptor
+pub const DEFAULT_ACCEPTOR_CLOCKSKEW_SECONDS: i64 = 300;
+
+/// Accepts the AP-REQs sent by the clients to the services, by decrypting
+/// their tickets with the keys of the services stored in a keytab.
+///
+/// The keytab can include keys of several services and several versions
+/// of each key, so the key is selected by the service principal, key
+/// version and encryption type of the ticket. The AP-REQs that cannot be
+/// accepted are rejected with [Error::ApReqRejected], which includes the
+/// KRB_AP_ERR code to report to the client.
+///
+/// The authenticators of the accepted AP-REQs are stored in a replay cache,
+/// in order to reject them with KRB_AP_ERR_REPEAT if they are sent again.
+/// By default, the cache is kept in memory; a [FileReplayCache] can be
+/// used to share it among several processes.
+///
+/// [FileReplayCache]: super::FileReplayCache
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_asn1::{ApReq, Asn1Object};
+/// # let raw_ap_req: Vec<u8> = unimplemented!();
+///
+/// let acceptor = Acceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+/// let (_, ap_req) = ApReq::parse(&raw_ap_req).unwrap();
+/// let ap_req_info = acceptor.accept(&ap_req, None).unwrap();
+///
+/// println!("Authenticated {:?}", ap_req_info.cname);
+/// ```
+pub struct Acceptor {
+    keytab: Keytab,
+    principal: Option<(Realm, PrincipalName)>,
+    clockskew: Duration,
+    replay_cache: Option<Box<dyn ReplayCache>>,
+}
+
+/// Data of an accepted AP-REQ: the client, the keys and the authorization
+/// data of the ticket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApReqInfo {
+    /// Realm of the client.
+    pub crealm: Realm,
+    /// Principal name of the client.
+    pub cname: PrincipalName,
+    /// Realm of the service.
+    pub srealm: Realm,
+    /// Principal name of the service.
+    pub sname: PrincipalName,
+    /// Session key of the ticket.
+    pub session_key: EncryptionKey,
+    /// Subkey proposed by the client.
+    pub subkey: Option<EncryptionKey>,
+    /// Initial sequence number of the messages sent by the client.
+    pub seq_number: Option<u32>,
+    /// Authorization data of the ticket, like the PAC.
+    pub authorization_data: Option<AuthorizationData>,
+    /// Whether the client requires an AP-REP.
+    pub mutual_required: bool,
+    /// Decrypted part of the ticket.
+    pub enc_ticket_part: EncTicketPart,
+    /// Decrypted authenticator.
+    pub authenticator: Authenticator,
+}
+
+impl Acceptor {
+    pub fn new(keytab: Keytab) -> Self {
+        return Self {
+            keytab,
+            principal: None,
+            clockskew: Duration::seconds(DEFAULT_ACCEPTOR_CLOCKSKEW_SECONDS),
+            replay_cache: Some(Box::new(MemoryReplayCache::new())),
+        };
+    }
+
+    /// Creates an acceptor with the keys of the keytab file.
+    pub fn from_keytab_file(path: &str) -> Result<Self> {
+        let data = fs::read(path).map_err(|_| Error::IOError)?;
+        let (_, keytab) =
+            Keytab::parse(&data).map_err(|_| Error::BinaryParseError)?;
+        return Ok(Self::new(keytab));
+    }
+
+    pub fn keytab(&self) -> &Keytab {
+        return &self.keytab;
+    }
+
+    pub fn principal(&self) -> Option<&(Realm, PrincipalName)> {
+        return self.principal.as_ref();
+    }
+
+    /// Restricts the accepted tickets to the ones of the given service and
+    /// realm. By default, the tickets of any service of the keytab are
+    /// accepted.
+    pub fn set_principal(&mut self, principal: Option<(Realm, PrincipalName)>) {
+        self.principal = principal;
+    }
+
+    pub fn clockskew(&self) -> Duration {
+        return self.clockskew;
+    }
+
+    /// Sets the maximum difference allowed between the clocks of the
+    /// client and the acceptor.
+    pub fn set_clockskew(&mut self, clockskew: Duration) {
+        self.clockskew = clockskew;
+    }
+
+    pub fn replay_cache(&self) -> Option<&dyn ReplayCache> {
+        return self.replay_cache.as_deref();
+    }
+
+    /// Sets the cache used to detect replayed authenticators. In case of
+    /// None, the replay detection is disabled.
+    pub fn set_replay_cache(
+        &mut self,
+        replay_cache: Option<Box<dyn ReplayCache>>,
+    ) {
+        self.replay_cache = replay_cache;
+    }
+
+    /// Accepts the AP-REQ, checking the ticket and the authenticator. In
+    /// case the address of the client is given, it must be included in
+    /// the addresses of the ticket, if any.
+    pub fn accept(
+        &self,
+        ap_req: &ApReq,
+        client_address: Option<IpAddr>,
+    ) -> Result<ApReqInfo> {
+        if ap_req.pvno != PVNO {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_BADVERSION));
+        }
+        if ap_req.msg_type != KRB_AP_REQ {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_MSG_TYPE));
+        }
+        // User to user tickets are encrypted with the session key of a TGT
+        if *ap_req.ap_options & AP_OPTION_USE_SESSION_KEY != 0 {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_METHOD));
+        }
+
+        let ticket = &ap_req.ticket;
+        if let Some((realm, principal)) = &self.principal {
+            if *realm != ticket.realm
+                || principal.name_string != ticket.sname.name_string
+            {
+                return Err(Error::ApReqRejected(KRB_AP_ERR_NOT_US));
+            }
+        }
+
+        let enc_ticket_part = self.decrypt_ticket(
+            &ticket.realm,
+            &ticket.sname,
+            &ticket.enc_part,
+        )?;
+
+        let session_key = &enc_ticket_part.key;
+        let raw_authenticator = decrypt(
+            session_key,
+            KEY_USAGE_AP_REQ_AUTHEN,
+            &ap_req.authenticator,
+        )
+        .map_err(|_| Error::ApReqRejected(KRB_AP_ERR_BAD_INTEGRITY))?;
+        let (_, authenticator) = Authenticator::parse(&raw_authenticator)
+            .map_err(|_| Error::ApReqRejected(KRB_AP_ERR_BAD_INTEGRITY))?;
+
+        if authenticator.crealm != enc_ticket_part.crealm
+            || authenticator.cname != enc_ticket_part.cname
+        {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_BADMATCH));
+        }
+
+        self.check_times(&enc_ticket_part, &authenticator, Utc::now())?;
+        check_address(&enc_ticket_part, client_address)?;
+
+        let ap_req_info = ApReqInfo {
+            crealm: enc_ticket_part.crealm.clone(),
+            cname: enc_ticket_part.cname.clone(),
+            srealm: ticket.realm.clone(),
+            sname: ticket.sname.clone(),
+            session_key: session_key.clone(),
+            subkey: authenticator.subkey.clone(),
+            seq_number: authenticator.seq_number,
+            authorization_data: enc_ticket_part.authorization_data.clone(),
+            mutual_required: *ap_req.ap_options & AP_OPTION_MUTUAL_REQUIRED
+                != 0,
+            enc_ticket_part,
+            authenticator,
+        };
+
+        if let Some(replay_cache) = &self.replay_cache {
+            replay_cache.store(&ReplayEntry::new(
+                ap_req,
+                &ap_req_info,
+                self.clockskew,
+            ))?;
+        }
+
+        return Ok(ap_req_info);
+    }
+
+    /// Decrypts the ticket with the key of the service. The key whose
+    /// version matches the one of the ticket is tried first, and then the
+    /// rest of versions, newest first, in case the key was changed and the
+    /// keytab was not updated with the same version numbers.
+    fn decrypt_ticket(
+        &self,
+        realm: &Realm,
+        sname: &PrincipalName,
+        enc_part: &EncryptedData,
+    ) -> Result<EncTicketPart> {
+        let entries: Vec<&KeytabEntry> = self
+            .keytab
+            .entries
+            .iter()
+            .filter(|entry| entry_matches_principal(entry, realm, sname))
+            .collect();
+        if entries.is_empty() {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_NOT_US));
+        }
+
+        let mut entries: Vec<&KeytabEntry> = entries
+            .into_iter()
+            .filter(|entry| entry.key.keytype as i32 == enc_part.etype)
+            .collect();
+        if entries.is_empty() {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_NOKEY));
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry_kvno(entry)));
+        if let Some(kvno) = enc_part.kvno {
+            entries.sort_by_key(|entry| !kvno_matches(entry, kvno));
+        }
+
+        for entry in entries.iter() {
+            let key = EncryptionKey::new(
+                entry.key.keytype as i32,
+                entry.key.keyvalue.clone(),
+            );
+            if let Ok(raw_enc_ticket_part) =
+                decrypt(&key, KEY_USAGE_AS_REP_TICKET, enc_part)
+            {
+                let (_, enc_ticket_part) = EncTicketPart::parse(
+                    &raw_enc_ticket_part,
+                )
+                .map_err(|_| Error::ApReqRejected(KRB_AP_ERR_BAD_INTEGRITY))?;
+                return Ok(enc_ticket_part);
+            }
+        }
+
+        match enc_part.kvno {
+            Some(kvno)
+                if !entries.iter().any(|entry| kvno_matches(entry, kvno)) =>
+            {
+                return Err(Error::ApReqRejected(KRB_AP_ERR_BADKEYVER));
+            }
+            _ => {
+                return Err(Error::ApReqRejected(KRB_AP_ERR_BAD_INTEGRITY));
+            }
+        }
+    }
+
+    /// Checks that the authenticator was created recently and the ticket
+    /// is valid at this moment, with the allowed clock skew.
+    fn check_times(
+        &self,
+        enc_ticket_part: &EncTicketPart,
+        authenticator: &Authenticator,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let ctime = authenticator.ctime.time.time;
+        if (now - ctime).abs() > self.clockskew {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_SKEW));
+        }
+
+        let starttime = enc_ticket_part
+            .starttime
+            .as_ref()
+            .unwrap_or(&enc_ticket_part.authtime)
+            .time
+            .time;
+        if starttime - self.clockskew > now
+            || *enc_ticket_part.flags & INVALID != 0
+        {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_TKT_NYV));
+        }
+
+        if enc_ticket_part.endtime.time.time + self.clockskew < now {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_TKT_EXPIRED));
+        }
+
+        return Ok(());
+    }
+}
+
+impl ApReqInfo {
+    /// Builds the AP-REP to authenticate the service against the client,
+    /// with the subkey and initial sequence number chosen by the service.
+    pub fn build_ap_rep(
+        &self,
+        subkey: Option<EncryptionKey>,
+        seq_number: Option<u32>,
+    ) -> Result<ApRep> {
+        let enc_ap_rep_part = EncApRepPart {
+            ctime: self.authenticator.ctime.clone(),
+            cusec: self.authenticator.cusec,
+            subkey,
+            seq_number,
+        };
+
+        return Ok(ApRep {
+            pvno: PVNO,
+            msg_type: KRB_AP_REP,
+            enc_part: encrypt(
+                &self.session_key,
+                KEY_USAGE_AP_REP_ENC_PART,
+                &enc_ap_rep_part.build(),
+            )?,
+        });
+    }
+}
+
+fn entry_matches_principal(
+    entry: &KeytabEntry,
+    realm: &Realm,
+    sname: &PrincipalName,
+) -> bool {
+    return entry.realm.data == realm.as_bytes()
+        && entry.components.len() == sname.name_string.len()
+        && entry
+            .components
+            .iter()
+            .zip(sname.name_string.iter())
+            .all(|(component, name)| component.data == name.as_bytes());
+}
+
+/// Version of the key of the entry, which is only stored in 8 bits in old
+/// keytabs.
+fn entry_kvno(entry: &KeytabEntry) -> u32 {
+    return entry.vno.unwrap_or(entry.vno8 as u32);
+}
+
+fn kvno_matches(entry: &KeytabEntry, kvno: u32) -> bool {
+    if entry.vno.is_none() {
+        return entry.vno8 as u32 == kvno & 0xff;
+    }
+    return entry_kvno(entry) == kvno;
+}
+
+/// Checks that the address of the client is one of the addresses of the
+/// ticket. Tickets without addresses can be used from any address.
+fn check_address(
+    enc_ticket_part: &EncTicketPart,
+    client_address: Option<IpAddr>,
+) -> Result<()> {
+    let (caddr, client_address) = match (&enc_ticket_part.caddr, client_address)
+    {
+        (Some(caddr), Some(client_address)) if !caddr.is_empty() => {
+            (caddr, client_address)
+        }
+        _ => {
+            return Ok(());
+        }
+    };
+
+    let client_host_address = match client_address {
+        IpAddr::V4(ip) => HostAddress::new(IPV4, ip.octets().to_vec()),
+        IpAddr::V6(ip) => HostAddress::new(IPV6, ip.octets().to_vec()),
+    };
+    if !caddr.contains(&client_host_address) {
+        return Err(Error::ApReqRejected(KRB_AP_ERR_BADADDR));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::super::ap_request::ApRequest;
+    use super::super::ApReqBuilder;
+    use super::*;
+    use crate::credentials::Credential;
+    use himmelblau_kerberos_asn1::{
+        EncAsRepPart, KerberosString, Ticket, TransitedEncoding,
+    };
+    use himmelblau_kerberos_constants::error_codes::KRB_AP_ERR_REPEAT;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_INST,
+    };
+    use himmelblau_kerberos_crypto::new_kerberos_cipher;
+    use himmelblau_kerberos_keytab::{CountedOctetString, KeyBlock};
+    use std::net::Ipv4Addr;
+
+    pub fn service_principal(host: &str) -> PrincipalName {
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("HTTP"));
+        sname.push(KerberosString::from(host));
+        return sname;
+    }
+
+    pub fn service_key(kvno: u8) -> EncryptionKey {
+        return EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![kvno; 32]);
+    }
+
+    pub fn keytab_entry(
+        host: &str,
+        kvno: u8,
+        key: &EncryptionKey,
+    ) -> KeytabEntry {
+        return KeytabEntry::new(
+            CountedOctetString::new(b"KINGDOM.HEARTS".to_vec()),
+            vec![
+                CountedOctetString::new(b"HTTP".to_vec()),
+                CountedOctetString::new(host.as_bytes().to_vec()),
+            ],
+            NT_SRV_INST as u32,
+            0,
+            kvno,
+            KeyBlock::new(key.keytype as u16, key.keyvalue.clone()),
+            Some(kvno as u32),
+        );
+    }
+
+    /// Keytab with two versions of the key of HTTP/web.kingdom.hearts and
+    /// the key of HTTP/www.kingdom.hearts
+    pub fn new_keytab() -> Keytab {
+        return Keytab::new(
+            5,
+            2,
+            vec![
+                keytab_entry("web.kingdom.hearts", 1, &service_key(1)),
+                keytab_entry("web.kingdom.hearts", 2, &service_key(2)),
+                keytab_entry("www.kingdom.hearts", 3, &service_key(3)),
+            ],
+        );
+    }
+
+    pub fn session_key() -> EncryptionKey {
+        return EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+    }
+
+    pub fn new_enc_ticket_part() -> EncTicketPart {
+        let now = Utc::now();
+        return EncTicketPart {
+            key: session_key(),
+            crealm: Realm::from("KINGDOM.HEARTS"),
+            cname: PrincipalName::new(
+                NT_PRINCIPAL,
+                KerberosString::from("mickey"),
+            ),
+            transited: TransitedEncoding::default(),
+            authtime: now.into(),
+            endtime: (now + Duration::hours(10)).into(),
+            ..Default::default()
+        };
+    }
+
+    /// Creates a credential whose ticket is encrypted with the key of the
+    /// service.
+    pub fn create_credential(
+        sname: PrincipalName,
+        service_key: &EncryptionKey,
+        kvno: Option<u32>,
+        enc_ticket_part: &EncTicketPart,
+    ) -> Credential {
+        let cipher = new_kerberos_cipher(service_key.keytype).unwrap();
+        let ticket = Ticket::new(
+            Realm::from("KINGDOM.HEARTS"),
+            sname.clone(),
+            EncryptedData::new(
+                service_key.keytype,
+                kvno,
+                cipher.encrypt(
+                    &service_key.keyvalue,
+                    KEY_USAGE_AS_REP_TICKET,
+                    &enc_ticket_part.build(),
+                ),
+            ),
+        );
+
+        return Credential::new(
+            enc_ticket_part.crealm.clone(),
+            enc_ticket_part.cname.clone(),
+            ticket,
+            EncAsRepPart {
+                key: enc_ticket_part.key.clone(),
+                srealm: Realm::from("KINGDOM.HEARTS"),
+                sname,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Credential with a ticket for HTTP/web.kingdom.hearts, encrypted with
+    /// the second version of its key
+    pub fn new_credential() -> Credential {
+        return create_credential(
+            service_principal("web.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &new_enc_ticket_part(),
+        );
+    }
+
+    fn new_ap_request(credential: &Credential) -> ApRequest {
+        let mut builder = ApReqBuilder::new(credential);
+        builder.generate_subkey().unwrap();
+        builder.set_seq_number(Some(1234));
+        builder.set_mutual_required(true);
+        return builder.build().unwrap();
+    }
+
+    fn accept(
+        kvno: Option<u32>,
+        service_key: &EncryptionKey,
+        enc_ticket_part: &EncTicketPart,
+    ) -> Result<ApReqInfo> {
+        let credential = create_credential(
+            service_principal("web.kingdom.hearts"),
+            service_key,
+            kvno,
+            enc_ticket_part,
+        );
+        let ap_request = new_ap_request(&credential);
+        return Acceptor::new(new_keytab()).accept(ap_request.ap_req(), None);
+    }
+
+    #[test]
+    fn accept_ap_req() {
+        let enc_ticket_part = new_enc_ticket_part();
+        let credential = create_credential(
+            service_principal("web.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        let ap_request = new_ap_request(&credential);
+
+        let ap_req_info = Acceptor::new(new_keytab())
+            .accept(ap_request.ap_req(), None)
+            .unwrap();
+
+        assert_eq!(enc_ticket_part.cname, ap_req_info.cname);
+        assert_eq!(enc_ticket_part.crealm, ap_req_info.crealm);
+        assert_eq!(service_principal("web.kingdom.hearts"), ap_req_info.sname);
+        assert_eq!(session_key(), ap_req_info.session_key);
+        assert_eq!(ap_request.subkey().cloned(), ap_req_info.subkey);
+        assert_eq!(Some(1234), ap_req_info.seq_number);
+        assert!(ap_req_info.mutual_required);
+
+        let ap_rep = ap_req_info.build_ap_rep(None, Some(5678)).unwrap();
+        let ap_rep_info = ap_request.verify_ap_rep(&ap_rep.build()).unwrap();
+        assert_eq!(Some(5678), ap_rep_info.seq_number);
+    }
+
+    #[test]
+    fn reject_replayed_ap_req() {
+        let credential = new_credential();
+        let ap_request = new_ap_request(&credential);
+        let mut acceptor = Acceptor::new(new_keytab());
+
+        acceptor.accept(ap_request.ap_req(), None).unwrap();
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_REPEAT),
+            acceptor.accept(ap_request.ap_req(), None).unwrap_err()
+        );
+
+        acceptor.set_replay_cache(None);
+        assert!(acceptor.accept(ap_request.ap_req(), None).is_ok());
+    }
+
+    #[test]
+    fn accept_ap_req_of_another_service_of_the_keytab() {
+        let credential = create_credential(
+            service_principal("www.kingdom.hearts"),
+            &service_key(3),
+            Some(3),
+            &new_enc_ticket_part(),
+        );
+        let ap_request = new_ap_request(&credential);
+
+        let acceptor = Acceptor::new(new_keytab());
+        assert!(acceptor.accept(ap_request.ap_req(), None).is_ok());
+
+        let mut acceptor = Acceptor::new(new_keytab());
+        acceptor.set_principal(Some((
+            Realm::from("KINGDOM.HEARTS"),
+            service_principal("web.kingdom.hearts"),
+        )));
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_NOT_US),
+            acceptor.accept(ap_request.ap_req(), None).unwrap_err()
+        );
+
+        acceptor.set_principal(Some((
+            Realm::from("KINGDOM.HEARTS"),
+            service_principal("www.kingdom.hearts"),
+        )));
+        assert!(acceptor.accept(ap_request.ap_req(), None).is_ok());
+    }
+
+    #[test]
+    fn reject_ap_req_of_another_realm() {
+        let credential = new_credential();
+        let ap_request = new_ap_request(&credential);
+
+        let mut acceptor = Acceptor::new(new_keytab());
+        acceptor.set_principal(Some((
+            Realm::from("DARK.WORLD"),
+            service_principal("web.kingdom.hearts"),
+        )));
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_NOT_US),
+            acceptor.accept(ap_request.ap_req(), None).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn accept_ap_req_with_previous_key_version() {
+        assert!(
+            accept(Some(1), &service_key(1), &new_enc_ticket_part()).is_ok()
+        );
+    }
+
+    #[test]
+    fn accept_ap_req_with_unknown_key_version_of_known_key() {
+        assert!(
+            accept(Some(9), &service_key(2), &new_enc_ticket_part()).is_ok()
+        );
+        assert!(accept(None, &service_key(1), &new_enc_ticket_part()).is_ok());
+    }
+
+    #[test]
+    fn reject_ap_req_with_unknown_key() {
+        let unknown_key = service_key(9);
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_BADKEYVER),
+            accept(Some(9), &unknown_key, &new_enc_ticket_part()).unwrap_err()
+        );
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_BAD_INTEGRITY),
+            accept(Some(2), &unknown_key, &new_enc_ticket_part()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_without_key_of_etype() {
+        let aes128_key =
+            EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![2; 16]);
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_NOKEY),
+            accept(Some(2), &aes128_key, &new_enc_ticket_part()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_of_unknown_service() {
+        let credential = create_credential(
+            service_principal("ftp.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &new_enc_ticket_part(),
+        );
+        let ap_request = new_ap_request(&credential);
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_NOT_US),
+            Acceptor::new(new_keytab())
+                .accept(ap_request.ap_req(), None)
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_with_expired_ticket() {
+        let mut enc_ticket_part = new_enc_ticket_part();
+        enc_ticket_part.endtime = (Utc::now() - Duration::hours(1)).into();
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_TKT_EXPIRED),
+            accept(Some(2), &service_key(2), &enc_ticket_part).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_with_postdated_ticket() {
+        let mut enc_ticket_part = new_enc_ticket_part();
+        enc_ticket_part.starttime =
+            Some((Utc::now() + Duration::hours(1)).into());
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_TKT_NYV),
+            accept(Some(2), &service_key(2), &enc_ticket_part).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_with_client_mismatch() {
+        let enc_ticket_part = new_enc_ticket_part();
+        let mut credential = create_credential(
+            service_principal("web.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        credential = Credential::new(
+            enc_ticket_part.crealm.clone(),
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("goofy")),
+            credential.ticket().clone(),
+            EncAsRepPart {
+                key: session_key(),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_BADMATCH),
+            Acceptor::new(new_keytab())
+                .accept(new_ap_request(&credential).ap_req(), None)
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_with_skewed_authenticator() {
+        let credential = new_credential();
+        let ap_request = new_ap_request(&credential);
+        let mut acceptor = Acceptor::new(new_keytab());
+        acceptor.set_clockskew(Duration::seconds(60));
+
+        let mut authenticator = ap_request.authenticator().clone();
+        authenticator.ctime = (Utc::now() - Duration::minutes(5)).into();
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_SKEW),
+            acceptor
+                .check_times(&new_enc_ticket_part(), &authenticator, Utc::now())
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_ap_req_from_address_not_in_ticket() {
+        let mut enc_ticket_part = new_enc_ticket_part();
+        enc_ticket_part.caddr =
+            Some(vec![HostAddress::new(IPV4, vec![10, 0, 0, 1])]);
+        let credential = create_credential(
+            service_principal("web.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        let ap_request = new_ap_request(&credential);
+        let acceptor = Acceptor::new(new_keytab());
+
+        assert!(acceptor
+            .accept(
+                ap_request.ap_req(),
+                Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+            )
+            .is_ok());
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_BADADDR),
+            acceptor
+                .accept(
+                    ap_request.ap_req(),
+                    Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+                )
+                .unwrap_err()
+        );
+    }
+}
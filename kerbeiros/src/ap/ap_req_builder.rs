@@ -0,0 +1,221 @@
+use super::ap_request::ApRequest;
+use crate::credentials::Credential;
+use crate::error::*;
+use crate::utils::encrypt;
+use himmelblau_kerberos_asn1::{
+    ApReq, Asn1Object, Authenticator, Checksum, EncryptionKey,
+};
+use himmelblau_kerberos_constants::ap_options::{
+    AP_OPTION_MUTUAL_REQUIRED, AP_OPTION_USE_SESSION_KEY,
+};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AP_REQ_AUTHEN;
+use himmelblau_kerberos_crypto::Key;
+use rand::Rng;
+
+/// Builds an AP-REQ to authenticate the client against an application
+/// service, by using a service ticket.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # fn send_to_service(_: &[u8]) -> Vec<u8> { unimplemented!() }
+/// # let credential: Credential = unimplemented!();
+///
+/// let mut builder = ApReqBuilder::new(&credential);
+/// builder.generate_subkey().unwrap();
+/// builder.generate_seq_number();
+/// builder.set_mutual_required(true);
+///
+/// let ap_request = builder.build().unwrap();
+/// let raw_ap_rep = send_to_service(&ap_request.build());
+/// let ap_rep_info = ap_request.verify_ap_rep(&raw_ap_rep).unwrap();
+/// ```
+pub struct ApReqBuilder<'a> {
+    credential: &'a Credential,
+    ap_options: u32,
+    subkey: Option<EncryptionKey>,
+    seq_number: Option<u32>,
+    checksum: Option<Checksum>,
+}
+
+impl<'a> ApReqBuilder<'a> {
+    pub fn new(credential: &'a Credential) -> Self {
+        return Self {
+            credential,
+            ap_options: 0,
+            subkey: None,
+            seq_number: None,
+            checksum: None,
+        };
+    }
+
+    pub fn ap_options(&self) -> u32 {
+        return self.ap_options;
+    }
+
+    pub fn set_ap_options(&mut self, ap_options: u32) {
+        self.ap_options = ap_options;
+    }
+
+    /// Sets whether the service must reply with an AP-REP to authenticate
+    /// itself against the client.
+    pub fn set_mutual_required(&mut self, mutual_required: bool) {
+        if mutual_required {
+            self.ap_options |= AP_OPTION_MUTUAL_REQUIRED;
+        } else {
+            self.ap_options &= !AP_OPTION_MUTUAL_REQUIRED;
+        }
+    }
+
+    pub fn subkey(&self) -> Option<&EncryptionKey> {
+        return self.subkey.as_ref();
+    }
+
+    /// Sets the subkey proposed by the client to protect the messages of
+    /// the session, instead of the session key of the ticket.
+    pub fn set_subkey(&mut self, subkey: Option<EncryptionKey>) {
+        self.subkey = subkey;
+    }
+
+    /// Generates a random subkey, of the same type as the session key of
+    /// the ticket.
+    pub fn generate_subkey(&mut self) -> Result<()> {
+        let etype = self.credential.key().keytype;
+        let key = Key::random(etype)?;
+        self.subkey = Some(EncryptionKey::new(etype, key.as_bytes().to_vec()));
+        return Ok(());
+    }
+
+    pub fn seq_number(&self) -> Option<u32> {
+        return self.seq_number;
+    }
+
+    /// Sets the initial sequence number of the messages sent by the client.
+    pub fn set_seq_number(&mut self, seq_number: Option<u32>) {
+        self.seq_number = seq_number;
+    }
+
+    /// Generates a random initial sequence number. Only 30 bits are used,
+    /// since some implementations fail with bigger values.
+    pub fn generate_seq_number(&mut self) {
+        self.seq_number = Some(rand::thread_rng().gen::<u32>() & 0x3fffffff);
+    }
+
+    /// Sets the checksum of the authenticator, which binds application
+    /// data to the AP-REQ.
+    pub fn set_checksum(&mut self, checksum: Option<Checksum>) {
+        self.checksum = checksum;
+    }
+
+    /// Builds the AP-REQ. The USE-SESSION-KEY option is set for user to
+    /// user tickets, which are encrypted with the session key of the TGT
+    /// of the service.
+    pub fn build(&self) -> Result<ApRequest> {
+        let authenticator = Authenticator {
+            crealm: self.credential.crealm().clone(),
+            cname: self.credential.cname().clone(),
+            cksum: self.checksum.clone(),
+            subkey: self.subkey.clone(),
+            seq_number: self.seq_number,
+            ..Default::default()
+        };
+
+        let mut ap_options = self.ap_options;
+        if self.credential.is_skey() {
+            ap_options |= AP_OPTION_USE_SESSION_KEY;
+        }
+
+        let ap_req = ApReq {
+            ap_options: ap_options.into(),
+            ticket: self.credential.ticket().clone(),
+            authenticator: encrypt(
+                self.credential.key(),
+                KEY_USAGE_AP_REQ_AUTHEN,
+                &authenticator.build(),
+            )?,
+            ..Default::default()
+        };
+
+        return Ok(ApRequest::new(
+            ap_req,
+            self.credential.key().clone(),
+            authenticator,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ap::test::{new_credential, session_key};
+    use crate::utils::decrypt;
+    use himmelblau_kerberos_asn1::Ticket;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+
+    fn decrypt_authenticator(ap_req: &ApReq) -> Authenticator {
+        let raw_authenticator = decrypt(
+            &session_key(),
+            KEY_USAGE_AP_REQ_AUTHEN,
+            &ap_req.authenticator,
+        )
+        .unwrap();
+        return Authenticator::parse(&raw_authenticator).unwrap().1;
+    }
+
+    #[test]
+    fn build_ap_req_with_options() {
+        let credential = new_credential();
+        let checksum = Checksum {
+            cksumtype: HMAC_SHA1_96_AES256,
+            checksum: vec![1; 12],
+        };
+        let mut builder = ApReqBuilder::new(&credential);
+        builder.generate_subkey().unwrap();
+        builder.set_seq_number(Some(1234));
+        builder.set_checksum(Some(checksum.clone()));
+        builder.set_mutual_required(true);
+
+        let ap_request = builder.build().unwrap();
+        let ap_req = ap_request.ap_req();
+        assert_eq!(AP_OPTION_MUTUAL_REQUIRED, *ap_req.ap_options);
+        assert_eq!(credential.ticket(), &ap_req.ticket);
+
+        let authenticator = decrypt_authenticator(ap_req);
+        assert_eq!(credential.crealm(), &authenticator.crealm);
+        assert_eq!(credential.cname(), &authenticator.cname);
+        assert_eq!(Some(checksum), authenticator.cksum);
+        assert_eq!(Some(1234), authenticator.seq_number);
+        assert_eq!(builder.subkey().cloned(), authenticator.subkey);
+        assert_eq!(
+            AES256_CTS_HMAC_SHA1_96,
+            authenticator.subkey.unwrap().keytype
+        );
+    }
+
+    #[test]
+    fn build_ap_req_without_options() {
+        let credential = new_credential();
+        let mut builder = ApReqBuilder::new(&credential);
+        builder.set_mutual_required(true);
+        builder.set_mutual_required(false);
+
+        let ap_request = builder.build().unwrap();
+        assert_eq!(0, *ap_request.ap_req().ap_options);
+
+        let authenticator = decrypt_authenticator(ap_request.ap_req());
+        assert_eq!(None, authenticator.cksum);
+        assert_eq!(None, authenticator.subkey);
+        assert_eq!(None, authenticator.seq_number);
+    }
+
+    #[test]
+    fn build_ap_req_with_user_to_user_ticket() {
+        let mut credential = new_credential();
+        credential.set_second_ticket(Some(Ticket::default()));
+
+        let ap_request = ApReqBuilder::new(&credential).build().unwrap();
+        assert_eq!(AP_OPTION_USE_SESSION_KEY, *ap_request.ap_req().ap_options);
+    }
+}
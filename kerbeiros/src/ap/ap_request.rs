@@ -0,0 +1,264 @@
+use crate::error::*;
+use himmelblau_kerberos_asn1::{
+    ApRep, ApReq, Asn1Object, Authenticator, EncApRepPart, EncryptionKey,
+    KrbError,
+};
+use himmelblau_kerberos_constants::ap_options::AP_OPTION_MUTUAL_REQUIRED;
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AP_REP_ENC_PART;
+use himmelblau_kerberos_constants::message_types::KRB_AP_REP;
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
+
+/// AP-REQ built by [ApReqBuilder](super::ApReqBuilder), along with the
+/// session key and authenticator required to verify the AP-REP of the
+/// service.
+#[derive(Debug, Clone)]
+pub struct ApRequest {
+    ap_req: ApReq,
+    session_key: EncryptionKey,
+    authenticator: Authenticator,
+}
+
+/// Keys and sequence number of the service, retrieved from a verified
+/// AP-REP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApRepInfo {
+    /// Key to protect the messages of the session: the subkey of the
+    /// service or, if there is none, the subkey of the client or the
+    /// session key of the ticket.
+    pub key: EncryptionKey,
+    /// Subkey chosen by the service.
+    pub subkey: Option<EncryptionKey>,
+    /// Initial sequence number of the messages sent by the service.
+    pub seq_number: Option<u32>,
+}
+
+impl ApRequest {
+    pub(crate) fn new(
+        ap_req: ApReq,
+        session_key: EncryptionKey,
+        authenticator: Authenticator,
+    ) -> Self {
+        return Self {
+            ap_req,
+            session_key,
+            authenticator,
+        };
+    }
+
+    pub fn ap_req(&self) -> &ApReq {
+        return &self.ap_req;
+    }
+
+    /// Encodes the AP-REQ, to be sent to the service.
+    pub fn build(&self) -> Vec<u8> {
+        return self.ap_req.build();
+    }
+
+    pub fn session_key(&self) -> &EncryptionKey {
+        return &self.session_key;
+    }
+
+    pub fn authenticator(&self) -> &Authenticator {
+        return &self.authenticator;
+    }
+
+    pub fn subkey(&self) -> Option<&EncryptionKey> {
+        return self.authenticator.subkey.as_ref();
+    }
+
+    pub fn seq_number(&self) -> Option<u32> {
+        return self.authenticator.seq_number;
+    }
+
+    pub fn mutual_required(&self) -> bool {
+        return *self.ap_req.ap_options & AP_OPTION_MUTUAL_REQUIRED != 0;
+    }
+
+    /// Key to protect the messages of the session in case the service does
+    /// not choose a subkey: the subkey of the client or, if there is none,
+    /// the session key of the ticket.
+    pub fn key(&self) -> &EncryptionKey {
+        return self.subkey().unwrap_or(&self.session_key);
+    }
+
+    /// Parses and verifies the AP-REP sent by the service. A KRB-ERROR
+    /// response is returned as [Error::KrbErrorResponse].
+    pub fn verify_ap_rep(&self, raw_ap_rep: &[u8]) -> Result<ApRepInfo> {
+        match ApRep::parse(raw_ap_rep) {
+            Ok((_, ap_rep)) => {
+                return self.verify_parsed_ap_rep(&ap_rep);
+            }
+            Err(error) => {
+                if let Ok((_, krb_error)) = KrbError::parse(raw_ap_rep) {
+                    return Err(Error::KrbErrorResponse(krb_error));
+                }
+                return Err(error.into());
+            }
+        }
+    }
+
+    /// Verifies the AP-REP sent by the service, checking that it is
+    /// encrypted with the session key and includes the time of the
+    /// authenticator.
+    pub fn verify_parsed_ap_rep(&self, ap_rep: &ApRep) -> Result<ApRepInfo> {
+        if ap_rep.msg_type != KRB_AP_REP {
+            return Err(Error::MutualAuthenticationFailed);
+        }
+
+        let cipher = new_kerberos_cipher(self.session_key.keytype)?;
+        let raw_enc_ap_rep_part = cipher
+            .decrypt(
+                &self.session_key.keyvalue,
+                KEY_USAGE_AP_REP_ENC_PART,
+                &ap_rep.enc_part.cipher,
+            )
+            .map_err(|_| Error::MutualAuthenticationFailed)?;
+        let (_, enc_ap_rep_part) = EncApRepPart::parse(&raw_enc_ap_rep_part)?;
+
+        if enc_ap_rep_part.ctime.timestamp()
+            != self.authenticator.ctime.timestamp()
+            || enc_ap_rep_part.cusec != self.authenticator.cusec
+        {
+            return Err(Error::MutualAuthenticationFailed);
+        }
+
+        return Ok(ApRepInfo {
+            key: enc_ap_rep_part
+                .subkey
+                .clone()
+                .unwrap_or_else(|| self.key().clone()),
+            subkey: enc_ap_rep_part.subkey,
+            seq_number: enc_ap_rep_part.seq_number,
+        });
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::super::test::{new_credential, session_key};
+    use super::super::ApReqBuilder;
+    use super::*;
+    use himmelblau_kerberos_asn1::EncryptedData;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::protocol_version::PVNO;
+
+    /// Builds the AP-REP of the service for the authenticator.
+    pub fn create_ap_rep(
+        session_key: &EncryptionKey,
+        authenticator: &Authenticator,
+        subkey: Option<EncryptionKey>,
+        seq_number: Option<u32>,
+    ) -> ApRep {
+        let enc_ap_rep_part = EncApRepPart {
+            ctime: authenticator.ctime.clone(),
+            cusec: authenticator.cusec,
+            subkey,
+            seq_number,
+        };
+        let cipher = new_kerberos_cipher(session_key.keytype).unwrap();
+        return ApRep {
+            pvno: PVNO,
+            msg_type: KRB_AP_REP,
+            enc_part: EncryptedData::new(
+                session_key.keytype,
+                None,
+                cipher.encrypt(
+                    &session_key.keyvalue,
+                    KEY_USAGE_AP_REP_ENC_PART,
+                    &enc_ap_rep_part.build(),
+                ),
+            ),
+        };
+    }
+
+    fn new_ap_request() -> ApRequest {
+        let credential = new_credential();
+        let mut builder = ApReqBuilder::new(&credential);
+        builder.generate_subkey().unwrap();
+        builder.generate_seq_number();
+        builder.set_mutual_required(true);
+        return builder.build().unwrap();
+    }
+
+    #[test]
+    fn verify_ap_rep_with_service_subkey() {
+        let ap_request = new_ap_request();
+        let subkey = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]);
+        let ap_rep = create_ap_rep(
+            &session_key(),
+            ap_request.authenticator(),
+            Some(subkey.clone()),
+            Some(5678),
+        );
+
+        assert!(ap_request.mutual_required());
+        assert_eq!(
+            ApRepInfo {
+                key: subkey.clone(),
+                subkey: Some(subkey),
+                seq_number: Some(5678),
+            },
+            ap_request.verify_ap_rep(&ap_rep.build()).unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_ap_rep_without_service_subkey() {
+        let ap_request = new_ap_request();
+        let ap_rep = create_ap_rep(
+            &session_key(),
+            ap_request.authenticator(),
+            None,
+            None,
+        );
+
+        let ap_rep_info = ap_request.verify_ap_rep(&ap_rep.build()).unwrap();
+        assert_eq!(ap_request.subkey(), Some(&ap_rep_info.key));
+        assert_eq!(None, ap_rep_info.subkey);
+        assert_eq!(None, ap_rep_info.seq_number);
+    }
+
+    #[test]
+    fn error_verifying_ap_rep_of_another_authenticator() {
+        let ap_request = new_ap_request();
+        let mut authenticator = ap_request.authenticator().clone();
+        authenticator.cusec = (authenticator.cusec + 1) % 1000000;
+        let ap_rep =
+            create_ap_rep(&session_key(), &authenticator, None, Some(1));
+
+        assert_eq!(
+            Error::MutualAuthenticationFailed,
+            ap_request.verify_ap_rep(&ap_rep.build()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_verifying_ap_rep_with_wrong_key() {
+        let ap_request = new_ap_request();
+        let wrong_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let ap_rep =
+            create_ap_rep(&wrong_key, ap_request.authenticator(), None, None);
+
+        assert_eq!(
+            Error::MutualAuthenticationFailed,
+            ap_request.verify_ap_rep(&ap_rep.build()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_with_krb_error_instead_of_ap_rep() {
+        let ap_request = new_ap_request();
+        let krb_error = KrbError {
+            pvno: PVNO,
+            msg_type: 30,
+            error_code: 37,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Error::KrbErrorResponse(krb_error.clone()),
+            ap_request.verify_ap_rep(&krb_error.build()).unwrap_err()
+        );
+    }
+}
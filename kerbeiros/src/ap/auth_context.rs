@@ -0,0 +1,557 @@
+use super::replay_cache::{MemoryReplayCache, ReplayCache, ReplayEntry};
+use super::{
+    ApRepInfo, ApReqInfo, ApRequest, DEFAULT_ACCEPTOR_CLOCKSKEW_SECONDS,
+};
+use crate::error::*;
+use chrono::{Duration, Timelike, Utc};
+use himmelblau_kerberos_asn1::{
+    Asn1Object, Checksum, EncKrbPrivPart, EncryptedData, EncryptionKey,
+    HostAddress, KrbPriv, KrbSafe, KrbSafeBody,
+};
+use himmelblau_kerberos_constants::address_types::DIRECTIONAL;
+use himmelblau_kerberos_constants::error_codes::{
+    KRB_AP_ERR_BADADDR, KRB_AP_ERR_BADORDER, KRB_AP_ERR_BADVERSION,
+    KRB_AP_ERR_BAD_INTEGRITY, KRB_AP_ERR_INAPP_CKSUM, KRB_AP_ERR_MODIFIED,
+    KRB_AP_ERR_MSG_TYPE, KRB_AP_ERR_REPEAT, KRB_AP_ERR_SKEW,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_KRB_PRIV_ENC_PART, KEY_USAGE_KRB_SAFE_CKSUM,
+};
+use himmelblau_kerberos_constants::message_types::{KRB_PRIV, KRB_SAFE};
+use himmelblau_kerberos_constants::protocol_version::PVNO;
+use himmelblau_kerberos_crypto::{
+    checksum_type_from_etype, keyed_checksum, new_kerberos_cipher,
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// State shared by the client and the service after an AP exchange, used
+/// to protect their messages with KRB-SAFE (integrity) and KRB-PRIV
+/// (confidentiality) messages, as described in RFC 4120 sections 3.4
+/// and 3.5.
+///
+/// The messages are protected with the subkey negotiated in the AP
+/// exchange or, if there is none, the session key of the ticket. Each
+/// message includes:
+///
+/// - The next sequence number of the sender, in case it was exchanged in
+///   the AP-REQ or AP-REP. The received sequence numbers must follow the
+///   initial one of the peer, otherwise the message is rejected with
+///   KRB_AP_ERR_BADORDER.
+/// - The time of the sender, unless timestamps are disabled. The received
+///   timestamps must be within the clock skew (KRB_AP_ERR_SKEW) and are
+///   stored in a replay cache to reject the messages that are sent again
+///   (KRB_AP_ERR_REPEAT).
+/// - The address of the sender, which is a directional address by
+///   default (RFC 4120 section 8.1), and the address of the receiver if
+///   known. When the address of the peer is set, the addresses of the
+///   received messages are verified (KRB_AP_ERR_BADADDR).
+///
+/// The messages that cannot be verified are rejected with
+/// [Error::MessageRejected].
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # let ap_request: ApRequest = unimplemented!();
+/// # let ap_rep_info: ApRepInfo = unimplemented!();
+///
+/// let mut auth_context =
+///     AuthContext::from_ap_request(&ap_request, Some(&ap_rep_info));
+/// let krb_priv = auth_context.build_priv(b"secret").unwrap();
+/// ```
+pub struct AuthContext {
+    key: EncryptionKey,
+    local_address: HostAddress,
+    remote_address: Option<HostAddress>,
+    local_seq_number: Option<u32>,
+    remote_seq_number: Option<u32>,
+    use_timestamps: bool,
+    clockskew: Duration,
+    replay_cache: Option<Box<dyn ReplayCache>>,
+}
+
+impl AuthContext {
+    /// Creates a context that protects the messages with the given key and
+    /// sends them from the given address.
+    pub fn new(key: EncryptionKey, local_address: HostAddress) -> Self {
+        return Self {
+            key,
+            local_address,
+            remote_address: None,
+            local_seq_number: None,
+            remote_seq_number: None,
+            use_timestamps: true,
+            clockskew: Duration::seconds(DEFAULT_ACCEPTOR_CLOCKSKEW_SECONDS),
+            replay_cache: Some(Box::new(MemoryReplayCache::new())),
+        };
+    }
+
+    /// Creates the context of the client, from the AP-REQ sent to the
+    /// service and, in case of mutual authentication, its AP-REP.
+    pub fn from_ap_request(
+        ap_request: &ApRequest,
+        ap_rep_info: Option<&ApRepInfo>,
+    ) -> Self {
+        let key = match ap_rep_info {
+            Some(ap_rep_info) => ap_rep_info.key.clone(),
+            None => ap_request.key().clone(),
+        };
+        let mut auth_context = Self::new(key, directional_address(true));
+        auth_context.local_seq_number = ap_request.seq_number();
+        auth_context.remote_seq_number =
+            ap_rep_info.and_then(|ap_rep_info| ap_rep_info.seq_number);
+        return auth_context;
+    }
+
+    /// Creates the context of the service, from the accepted AP-REQ and,
+    /// in case of mutual authentication, the subkey and sequence number
+    /// sent in the AP-REP.
+    pub fn from_ap_req_info(
+        ap_req_info: &ApReqInfo,
+        ap_rep_subkey: Option<&EncryptionKey>,
+        ap_rep_seq_number: Option<u32>,
+    ) -> Self {
+        let key = ap_rep_subkey
+            .or(ap_req_info.subkey.as_ref())
+            .unwrap_or(&ap_req_info.session_key)
+            .clone();
+        let mut auth_context = Self::new(key, directional_address(false));
+        auth_context.local_seq_number = ap_rep_seq_number;
+        auth_context.remote_seq_number = ap_req_info.seq_number;
+        return auth_context;
+    }
+
+    pub fn key(&self) -> &EncryptionKey {
+        return &self.key;
+    }
+
+    pub fn local_address(&self) -> &HostAddress {
+        return &self.local_address;
+    }
+
+    pub fn set_local_address(&mut self, local_address: HostAddress) {
+        self.local_address = local_address;
+    }
+
+    pub fn remote_address(&self) -> Option<&HostAddress> {
+        return self.remote_address.as_ref();
+    }
+
+    /// Sets the address of the peer, which is included in the sent
+    /// messages and must match the sender address of the received ones.
+    /// In case of None, the addresses are not verified.
+    pub fn set_remote_address(&mut self, remote_address: Option<HostAddress>) {
+        self.remote_address = remote_address;
+    }
+
+    /// Sequence number of the next message to send.
+    pub fn local_seq_number(&self) -> Option<u32> {
+        return self.local_seq_number;
+    }
+
+    pub fn set_local_seq_number(&mut self, seq_number: Option<u32>) {
+        self.local_seq_number = seq_number;
+    }
+
+    /// Sequence number expected in the next received message. In case of
+    /// None, the sequence numbers are not verified.
+    pub fn remote_seq_number(&self) -> Option<u32> {
+        return self.remote_seq_number;
+    }
+
+    pub fn set_remote_seq_number(&mut self, seq_number: Option<u32>) {
+        self.remote_seq_number = seq_number;
+    }
+
+    pub fn use_timestamps(&self) -> bool {
+        return self.use_timestamps;
+    }
+
+    /// Sets whether the messages include the time of the sender, which is
+    /// then required and verified in the received messages.
+    pub fn set_use_timestamps(&mut self, use_timestamps: bool) {
+        self.use_timestamps = use_timestamps;
+    }
+
+    pub fn clockskew(&self) -> Duration {
+        return self.clockskew;
+    }
+
+    pub fn set_clockskew(&mut self, clockskew: Duration) {
+        self.clockskew = clockskew;
+    }
+
+    /// Sets the cache used to detect replayed messages by their
+    /// timestamps. In case of None, the replay detection is disabled.
+    pub fn set_replay_cache(
+        &mut self,
+        replay_cache: Option<Box<dyn ReplayCache>>,
+    ) {
+        self.replay_cache = replay_cache;
+    }
+
+    /// Builds a KRB-SAFE message, which includes the user data in clear
+    /// along with a keyed checksum.
+    pub fn build_safe(&mut self, user_data: &[u8]) -> Result<KrbSafe> {
+        let safe_body = self.new_body(user_data);
+        return Ok(KrbSafe {
+            pvno: PVNO,
+            msg_type: KRB_SAFE,
+            cksum: self.safe_checksum(&safe_body)?,
+            safe_body,
+        });
+    }
+
+    /// Keyed checksum of the KRB-SAFE body, of the type that goes along
+    /// with the key.
+    fn safe_checksum(&self, safe_body: &KrbSafeBody) -> Result<Checksum> {
+        let cksumtype = checksum_type_from_etype(self.key.keytype)?;
+        let checksum = keyed_checksum(
+            cksumtype,
+            &self.key.keyvalue,
+            KEY_USAGE_KRB_SAFE_CKSUM,
+            &safe_body.build(),
+        )?;
+        return Ok(Checksum {
+            cksumtype,
+            checksum,
+        });
+    }
+
+    /// Verifies a KRB-SAFE message and returns its user data.
+    pub fn verify_safe(&mut self, krb_safe: &KrbSafe) -> Result<Vec<u8>> {
+        check_header(krb_safe.pvno, krb_safe.msg_type, KRB_SAFE)?;
+
+        // The checksum must be of the type of the key, since other types
+        // may require keys of other sizes
+        let checksum = self.safe_checksum(&krb_safe.safe_body)?;
+        if krb_safe.cksum.cksumtype != checksum.cksumtype {
+            return Err(Error::MessageRejected(KRB_AP_ERR_INAPP_CKSUM));
+        }
+        if !bool::from(checksum.checksum.ct_eq(&krb_safe.cksum.checksum)) {
+            return Err(Error::MessageRejected(KRB_AP_ERR_MODIFIED));
+        }
+
+        self.check_body(&krb_safe.safe_body, &krb_safe.cksum.checksum)?;
+        return Ok(krb_safe.safe_body.user_data.clone());
+    }
+
+    /// Builds a KRB-PRIV message, which includes the user data encrypted.
+    pub fn build_priv(&mut self, user_data: &[u8]) -> Result<KrbPriv> {
+        // The encrypted part has the same fields as the KRB-SAFE body
+        let body = self.new_body(user_data);
+        let enc_krb_priv_part = EncKrbPrivPart {
+            user_data: body.user_data,
+            timestamp: body.timestamp,
+            usec: body.usec,
+            seq_number: body.seq_number,
+            s_address: body.s_address,
+            r_address: body.r_address,
+        };
+
+        let cipher = new_kerberos_cipher(self.key.keytype)?;
+        return Ok(KrbPriv {
+            pvno: PVNO,
+            msg_type: KRB_PRIV,
+            enc_part: EncryptedData::new(
+                self.key.keytype,
+                None,
+                cipher.encrypt(
+                    &self.key.keyvalue,
+                    KEY_USAGE_KRB_PRIV_ENC_PART,
+                    &enc_krb_priv_part.build(),
+                ),
+            ),
+        });
+    }
+
+    /// Decrypts and verifies a KRB-PRIV message and returns its user data.
+    pub fn decrypt_priv(&mut self, krb_priv: &KrbPriv) -> Result<Vec<u8>> {
+        check_header(krb_priv.pvno, krb_priv.msg_type, KRB_PRIV)?;
+
+        let cipher = new_kerberos_cipher(self.key.keytype)?;
+        let raw_enc_krb_priv_part = cipher
+            .decrypt(
+                &self.key.keyvalue,
+                KEY_USAGE_KRB_PRIV_ENC_PART,
+                &krb_priv.enc_part.cipher,
+            )
+            .map_err(|_| Error::MessageRejected(KRB_AP_ERR_BAD_INTEGRITY))?;
+        let (_, enc_krb_priv_part) =
+            EncKrbPrivPart::parse(&raw_enc_krb_priv_part).map_err(|_| {
+                Error::MessageRejected(KRB_AP_ERR_BAD_INTEGRITY)
+            })?;
+
+        let body = KrbSafeBody {
+            user_data: enc_krb_priv_part.user_data,
+            timestamp: enc_krb_priv_part.timestamp,
+            usec: enc_krb_priv_part.usec,
+            seq_number: enc_krb_priv_part.seq_number,
+            s_address: enc_krb_priv_part.s_address,
+            r_address: enc_krb_priv_part.r_address,
+        };
+        self.check_body(&body, &krb_priv.enc_part.cipher)?;
+        return Ok(body.user_data);
+    }
+
+    /// Fields of the next message to send, which increments the local
+    /// sequence number.
+    fn new_body(&mut self, user_data: &[u8]) -> KrbSafeBody {
+        let mut body = KrbSafeBody {
+            user_data: user_data.to_vec(),
+            seq_number: self.local_seq_number,
+            s_address: self.local_address.clone(),
+            r_address: self.remote_address.clone(),
+            ..Default::default()
+        };
+
+        if self.use_timestamps {
+            let now = Utc::now();
+            body.timestamp = Some(now.into());
+            body.usec = Some((now.nanosecond() / 1000 % 1000000) as i32);
+        }
+
+        if let Some(seq_number) = self.local_seq_number {
+            self.local_seq_number = Some(seq_number.wrapping_add(1));
+        }
+        return body;
+    }
+
+    /// Verifies the addresses, timestamp and sequence number of a received
+    /// message, identified in the replay cache by the given bytes.
+    fn check_body(
+        &mut self,
+        body: &KrbSafeBody,
+        message_id: &[u8],
+    ) -> Result<()> {
+        if let Some(remote_address) = &self.remote_address {
+            if &body.s_address != remote_address {
+                return Err(Error::MessageRejected(KRB_AP_ERR_BADADDR));
+            }
+            if let Some(r_address) = &body.r_address {
+                if r_address != &self.local_address {
+                    return Err(Error::MessageRejected(KRB_AP_ERR_BADADDR));
+                }
+            }
+        }
+
+        if self.use_timestamps {
+            let timestamp = match &body.timestamp {
+                Some(timestamp) => timestamp.time.time,
+                None => return Err(Error::MessageRejected(KRB_AP_ERR_SKEW)),
+            };
+            let now = Utc::now();
+            if (now - timestamp).abs() > self.clockskew {
+                return Err(Error::MessageRejected(KRB_AP_ERR_SKEW));
+            }
+
+            if let Some(replay_cache) = &self.replay_cache {
+                let entry = ReplayEntry {
+                    client: address_to_string(&body.s_address),
+                    server: body
+                        .r_address
+                        .as_ref()
+                        .map(address_to_string)
+                        .unwrap_or_default(),
+                    ctime: timestamp,
+                    cusec: body.usec.unwrap_or(0),
+                    authenticator_hash: Sha256::digest(message_id).to_vec(),
+                    expiration: timestamp + self.clockskew,
+                };
+                replay_cache.store(&entry).map_err(|error| match error {
+                    Error::ApReqRejected(KRB_AP_ERR_REPEAT) => {
+                        Error::MessageRejected(KRB_AP_ERR_REPEAT)
+                    }
+                    error => error,
+                })?;
+            }
+        }
+
+        if let Some(remote_seq_number) = self.remote_seq_number {
+            if body.seq_number != Some(remote_seq_number) {
+                return Err(Error::MessageRejected(KRB_AP_ERR_BADORDER));
+            }
+            self.remote_seq_number = Some(remote_seq_number.wrapping_add(1));
+        }
+
+        return Ok(());
+    }
+}
+
+/// Directional address (RFC 4120 section 8.1) of the messages sent by the
+/// initiator of the AP exchange (the client) or by the acceptor.
+pub fn directional_address(initiator: bool) -> HostAddress {
+    let direction: u32 = if initiator { 0 } else { 1 };
+    return HostAddress::new(DIRECTIONAL, direction.to_be_bytes().to_vec());
+}
+
+fn check_header(
+    pvno: i32,
+    msg_type: i32,
+    expected_msg_type: i32,
+) -> Result<()> {
+    if pvno != PVNO {
+        return Err(Error::MessageRejected(KRB_AP_ERR_BADVERSION));
+    }
+    if msg_type != expected_msg_type {
+        return Err(Error::MessageRejected(KRB_AP_ERR_MSG_TYPE));
+    }
+    return Ok(());
+}
+
+fn address_to_string(address: &HostAddress) -> String {
+    return format!("{}:{:?}", address.addr_type, address.address);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::address_types::IPV4;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES128;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+    };
+
+    fn new_auth_contexts(key: EncryptionKey) -> (AuthContext, AuthContext) {
+        let mut client =
+            AuthContext::new(key.clone(), directional_address(true));
+        client.set_local_seq_number(Some(100));
+        client.set_remote_seq_number(Some(200));
+        client.set_remote_address(Some(directional_address(false)));
+
+        let mut service = AuthContext::new(key, directional_address(false));
+        service.set_local_seq_number(Some(200));
+        service.set_remote_seq_number(Some(100));
+        service.set_remote_address(Some(directional_address(true)));
+
+        return (client, service);
+    }
+
+    fn aes_key() -> EncryptionKey {
+        return EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![5; 16]);
+    }
+
+    #[test]
+    fn exchange_safe_messages() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+
+        let krb_safe = client.build_safe(b"hello").unwrap();
+        assert_eq!(Some(100), krb_safe.safe_body.seq_number);
+        assert_eq!(b"hello".to_vec(), service.verify_safe(&krb_safe).unwrap());
+
+        let krb_safe = service.build_safe(b"bye").unwrap();
+        assert_eq!(b"bye".to_vec(), client.verify_safe(&krb_safe).unwrap());
+
+        assert_eq!(Some(101), client.local_seq_number());
+        assert_eq!(Some(201), client.remote_seq_number());
+    }
+
+    #[test]
+    fn exchange_priv_messages() {
+        let key = EncryptionKey::new(RC4_HMAC, vec![6; 16]);
+        let (mut client, mut service) = new_auth_contexts(key);
+
+        for user_data in [b"first".to_vec(), b"second".to_vec()] {
+            let krb_priv = client.build_priv(&user_data).unwrap();
+            assert_eq!(user_data, service.decrypt_priv(&krb_priv).unwrap());
+        }
+    }
+
+    #[test]
+    fn reject_modified_safe_message() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+        let mut krb_safe = client.build_safe(b"hello").unwrap();
+        krb_safe.safe_body.user_data = b"world".to_vec();
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_MODIFIED),
+            service.verify_safe(&krb_safe).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_safe_message_with_checksum_of_another_type() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![5; 32]);
+        let (mut client, mut service) = new_auth_contexts(key);
+        let mut krb_safe = client.build_safe(b"hello").unwrap();
+        krb_safe.cksum.cksumtype = HMAC_SHA1_96_AES128;
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_INAPP_CKSUM),
+            service.verify_safe(&krb_safe).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_priv_message_with_wrong_key() {
+        let (mut client, _) = new_auth_contexts(aes_key());
+        let (_, mut service) = new_auth_contexts(EncryptionKey::new(
+            AES128_CTS_HMAC_SHA1_96,
+            vec![7; 16],
+        ));
+        let krb_priv = client.build_priv(b"hello").unwrap();
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_BAD_INTEGRITY),
+            service.decrypt_priv(&krb_priv).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_messages_out_of_order() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+        let _ = client.build_priv(b"lost").unwrap();
+        let krb_priv = client.build_priv(b"hello").unwrap();
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_BADORDER),
+            service.decrypt_priv(&krb_priv).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_replayed_message() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+        client.set_local_seq_number(None);
+        service.set_remote_seq_number(None);
+        let krb_safe = client.build_safe(b"hello").unwrap();
+
+        service.verify_safe(&krb_safe).unwrap();
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_REPEAT),
+            service.verify_safe(&krb_safe).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_message_with_skewed_timestamp() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+        service.set_clockskew(Duration::seconds(60));
+        let mut krb_safe = client.build_safe(b"hello").unwrap();
+        krb_safe.safe_body.timestamp =
+            Some((Utc::now() - Duration::minutes(5)).into());
+        krb_safe.cksum = client.safe_checksum(&krb_safe.safe_body).unwrap();
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_SKEW),
+            service.verify_safe(&krb_safe).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_message_from_unexpected_address() {
+        let (mut client, mut service) = new_auth_contexts(aes_key());
+        client.set_local_address(HostAddress::new(IPV4, vec![10, 0, 0, 1]));
+        let krb_priv = client.build_priv(b"hello").unwrap();
+
+        assert_eq!(
+            Error::MessageRejected(KRB_AP_ERR_BADADDR),
+            service.decrypt_priv(&krb_priv).unwrap_err()
+        );
+
+        service.set_remote_address(None);
+        assert!(service.decrypt_priv(&krb_priv).is_ok());
+    }
+}
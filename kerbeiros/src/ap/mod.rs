@@ -0,0 +1,25 @@
+//! AP exchange (RFC 4120 section 3.2), used by the clients to authenticate
+//! against the application services with a service ticket, and by the
+//! services to accept the tickets with the keys of a keytab, detecting
+//! the replayed authenticators with a replay cache. After the exchange,
+//! both sides can protect their messages with an [AuthContext].
+
+mod ap_req_builder;
+pub use ap_req_builder::ApReqBuilder;
+
+mod ap_request;
+pub use ap_request::{ApRepInfo, ApRequest};
+
+mod acceptor;
+#[cfg(test)]
+pub(crate) use acceptor::test;
+pub use acceptor::{Acceptor, ApReqInfo, DEFAULT_ACCEPTOR_CLOCKSKEW_SECONDS};
+
+mod auth_context;
+pub use auth_context::{directional_address, AuthContext};
+
+mod replay_cache;
+pub use replay_cache::{
+    FileReplayCache, MemoryReplayCache, ReplayCache, ReplayEntry,
+    REPLAY_TAG_SIZE,
+};
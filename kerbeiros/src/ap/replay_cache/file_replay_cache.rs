@@ -0,0 +1,250 @@
+use super::{ReplayCache, ReplayEntry, REPLAY_TAG_SIZE};
+use crate::error::*;
+use chrono::Utc;
+use fs2::FileExt;
+use himmelblau_kerberos_constants::error_codes::KRB_AP_ERR_REPEAT;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Size of the records: the tag of the entry and its expiration time
+const RECORD_SIZE: u64 = REPLAY_TAG_SIZE as u64 + 4;
+
+/// Number of records of the first table of the file
+const FIRST_TABLE_RECORDS: u64 = 1023;
+
+/// Replay cache stored in a file, which can be shared by several
+/// processes, in the spirit of the rcache2 format of MIT Kerberos.
+///
+/// The file is a sequence of hash tables of fixed size records, each table
+/// twice as big as the previous one. Each record contains the tag of an
+/// entry and its expiration time, as 32 bits big-endian seconds since
+/// the epoch. An entry is looked up in one slot of each table, given by
+/// its tag, until a record that was never written, and it is stored in the
+/// first slot which is empty or expired, so new tables are only added when
+/// the file gets crowded. The file is locked during each lookup, and empty
+/// records are all zeros.
+pub struct FileReplayCache {
+    path: PathBuf,
+}
+
+impl FileReplayCache {
+    /// Creates a cache that stores the entries in the given file, which is
+    /// created the first time an entry is stored.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        return Self {
+            path: path.as_ref().to_path_buf(),
+        };
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    fn store_in_file(
+        &self,
+        file: &mut File,
+        entry: &ReplayEntry,
+    ) -> Result<()> {
+        let tag = entry.tag();
+        let hash = u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as u64;
+        let now = Utc::now().timestamp();
+        let file_size = file.metadata().map_err(|_| Error::IOError)?.len();
+
+        let mut table_offset = 0;
+        let mut table_records = FIRST_TABLE_RECORDS;
+        let mut free_offset = None;
+        loop {
+            let offset = table_offset + (hash % table_records) * RECORD_SIZE;
+            let record = read_record(file, offset, file_size)?;
+            let (record_tag, record_expiration) =
+                record.split_at(REPLAY_TAG_SIZE);
+            let record_expiration = u32::from_be_bytes([
+                record_expiration[0],
+                record_expiration[1],
+                record_expiration[2],
+                record_expiration[3],
+            ]) as i64;
+
+            // The later tables may still hold the entry when the slots of
+            // the previous ones have expired, so all of them are looked up
+            // until a record that was never written
+            if record == [0; RECORD_SIZE as usize] {
+                let offset = free_offset.unwrap_or(offset);
+                return write_record(file, offset, &tag, entry);
+            }
+            if record_expiration < now {
+                free_offset = free_offset.or(Some(offset));
+            } else if record_tag == tag {
+                return Err(Error::ApReqRejected(KRB_AP_ERR_REPEAT));
+            }
+
+            table_offset += table_records * RECORD_SIZE;
+            table_records *= 2;
+        }
+    }
+}
+
+/// Reads the record at the offset, which is empty when it is beyond the
+/// end of the file.
+fn read_record(
+    file: &mut File,
+    offset: u64,
+    file_size: u64,
+) -> Result<[u8; RECORD_SIZE as usize]> {
+    let mut record = [0; RECORD_SIZE as usize];
+    if offset + RECORD_SIZE <= file_size {
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut record))
+            .map_err(|_| Error::IOError)?;
+    }
+    return Ok(record);
+}
+
+fn write_record(
+    file: &mut File,
+    offset: u64,
+    tag: &[u8; REPLAY_TAG_SIZE],
+    entry: &ReplayEntry,
+) -> Result<()> {
+    let expiration = entry.expiration.timestamp().clamp(0, u32::MAX as i64);
+    let mut record = tag.to_vec();
+    record.extend_from_slice(&(expiration as u32).to_be_bytes());
+
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(&record))
+        .map_err(|_| Error::IOError)?;
+    return Ok(());
+}
+
+impl ReplayCache for FileReplayCache {
+    fn store(&self, entry: &ReplayEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)
+            .map_err(|_| Error::IOError)?;
+
+        file.lock_exclusive().map_err(|_| Error::IOError)?;
+        let result = self.store_in_file(&mut file, entry);
+        let _ = file.unlock();
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::new_entry;
+    use super::*;
+    use chrono::Duration;
+    use std::fs;
+
+    fn temp_cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "kerbeiros_{}_{}.rcache",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        return path;
+    }
+
+    #[test]
+    fn reject_replayed_entry_from_another_instance() {
+        let path = temp_cache_path("replayed");
+        let expiration = Utc::now() + Duration::minutes(5);
+
+        FileReplayCache::new(&path)
+            .store(&new_entry(1, expiration))
+            .unwrap();
+        FileReplayCache::new(&path)
+            .store(&new_entry(2, expiration))
+            .unwrap();
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_REPEAT),
+            FileReplayCache::new(&path)
+                .store(&new_entry(1, expiration))
+                .unwrap_err()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reuse_records_of_expired_entries() {
+        let path = temp_cache_path("expired");
+        let cache = FileReplayCache::new(&path);
+        let expiration = Utc::now() - Duration::minutes(1);
+
+        cache.store(&new_entry(1, expiration)).unwrap();
+        let file_size = fs::metadata(&path).unwrap().len();
+        cache.store(&new_entry(1, expiration)).unwrap();
+
+        assert_eq!(file_size, fs::metadata(&path).unwrap().len());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn store_colliding_entries_in_next_table() {
+        let path = temp_cache_path("colliding");
+        let cache = FileReplayCache::new(&path);
+        let expiration = Utc::now() + Duration::minutes(5);
+
+        // Fill the slot of the first table with another entry
+        let entry = new_entry(1, expiration);
+        let tag = entry.tag();
+        let hash = u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as u64;
+        let mut file = File::create(&path).unwrap();
+        file.seek(SeekFrom::Start((hash % FIRST_TABLE_RECORDS) * RECORD_SIZE))
+            .unwrap();
+        file.write_all(&[0xff; RECORD_SIZE as usize]).unwrap();
+        drop(file);
+
+        cache.store(&entry).unwrap();
+        assert!(
+            fs::metadata(&path).unwrap().len()
+                > FIRST_TABLE_RECORDS * RECORD_SIZE
+        );
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_REPEAT),
+            cache.store(&entry).unwrap_err()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reject_replayed_entry_after_colliding_entry_expires() {
+        let path = temp_cache_path("colliding_expired");
+        let cache = FileReplayCache::new(&path);
+        let entry = new_entry(1, Utc::now() + Duration::minutes(5));
+        let tag = entry.tag();
+        let hash = u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as u64;
+        let offset = (hash % FIRST_TABLE_RECORDS) * RECORD_SIZE;
+
+        // Fill the slot of the first table with another entry, which
+        // expires after the entry is stored in the next table
+        let mut record = vec![0xff; REPLAY_TAG_SIZE];
+        record.extend_from_slice(
+            &((Utc::now().timestamp() + 60) as u32).to_be_bytes(),
+        );
+        let mut file = File::create(&path).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&record).unwrap();
+        drop(file);
+        cache.store(&entry).unwrap();
+
+        let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(offset + REPLAY_TAG_SIZE as u64))
+            .unwrap();
+        file.write_all(&1u32.to_be_bytes()).unwrap();
+        drop(file);
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_REPEAT),
+            cache.store(&entry).unwrap_err()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
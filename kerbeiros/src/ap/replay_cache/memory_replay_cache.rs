@@ -0,0 +1,79 @@
+use super::{ReplayCache, ReplayEntry, REPLAY_TAG_SIZE};
+use crate::error::*;
+use chrono::{DateTime, Utc};
+use himmelblau_kerberos_constants::error_codes::KRB_AP_ERR_REPEAT;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Replay cache that keeps the entries in memory, so they are only shared
+/// by the acceptors of the same process.
+#[derive(Default)]
+pub struct MemoryReplayCache {
+    entries: Mutex<HashMap<[u8; REPLAY_TAG_SIZE], DateTime<Utc>>>,
+}
+
+impl MemoryReplayCache {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Number of entries stored, including the expired ones that were not
+    /// discarded yet.
+    pub fn len(&self) -> usize {
+        return self.entries.lock().unwrap().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+}
+
+impl ReplayCache for MemoryReplayCache {
+    fn store(&self, entry: &ReplayEntry) -> Result<()> {
+        let now = Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, expiration| *expiration >= now);
+
+        let tag = entry.tag();
+        if entries.contains_key(&tag) {
+            return Err(Error::ApReqRejected(KRB_AP_ERR_REPEAT));
+        }
+        entries.insert(tag, entry.expiration);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::new_entry;
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn reject_replayed_entry() {
+        let cache = MemoryReplayCache::new();
+        let expiration = Utc::now() + Duration::minutes(5);
+
+        cache.store(&new_entry(1, expiration)).unwrap();
+        cache.store(&new_entry(2, expiration)).unwrap();
+
+        assert_eq!(
+            Error::ApReqRejected(KRB_AP_ERR_REPEAT),
+            cache.store(&new_entry(1, expiration)).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn discard_expired_entries() {
+        let cache = MemoryReplayCache::new();
+        let expiration = Utc::now() - Duration::seconds(1);
+
+        cache.store(&new_entry(1, expiration)).unwrap();
+        cache.store(&new_entry(1, expiration)).unwrap();
+        cache
+            .store(&new_entry(2, Utc::now() + Duration::minutes(5)))
+            .unwrap();
+
+        assert_eq!(1, cache.len());
+    }
+}
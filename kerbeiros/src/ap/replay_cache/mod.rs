@@ -0,0 +1,121 @@
+//! Replay caches, which store the authenticators accepted recently in
+//! order to reject the AP-REQs that are sent again (RFC 4120 section
+//! 3.2.3).
+
+use super::ApReqInfo;
+use crate::error::*;
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{ApReq, PrincipalName, Realm};
+use sha2::{Digest, Sha256};
+
+mod memory_replay_cache;
+pub use memory_replay_cache::MemoryReplayCache;
+
+mod file_replay_cache;
+pub use file_replay_cache::FileReplayCache;
+
+/// Size of the tags that identify the entries in the caches
+pub const REPLAY_TAG_SIZE: usize = 12;
+
+/// Stores the authenticators that were accepted, until they expire.
+pub trait ReplayCache: Send + Sync {
+    /// Stores the entry, failing with
+    /// [ApReqRejected](Error::ApReqRejected)(KRB_AP_ERR_REPEAT) in case
+    /// the entry is already stored and has not expired.
+    fn store(&self, entry: &ReplayEntry) -> Result<()>;
+}
+
+/// Authenticator stored in a replay cache, identified by the client, the
+/// service, the time of the authenticator and the hash of its ciphertext.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayEntry {
+    pub client: String,
+    pub server: String,
+    pub ctime: DateTime<Utc>,
+    pub cusec: i32,
+    pub authenticator_hash: Vec<u8>,
+    /// Time from which the entry can be discarded, since the
+    /// authenticator would be rejected for being too old.
+    pub expiration: DateTime<Utc>,
+}
+
+impl ReplayEntry {
+    /// Creates the entry of an accepted AP-REQ, which expires when the
+    /// time of the authenticator is out of the clock skew.
+    pub fn new(
+        ap_req: &ApReq,
+        ap_req_info: &ApReqInfo,
+        clockskew: Duration,
+    ) -> Self {
+        let ctime = ap_req_info.authenticator.ctime.time.time;
+        return Self {
+            client: principal_to_string(
+                &ap_req_info.cname,
+                &ap_req_info.crealm,
+            ),
+            server: principal_to_string(
+                &ap_req_info.sname,
+                &ap_req_info.srealm,
+            ),
+            ctime,
+            cusec: ap_req_info.authenticator.cusec,
+            authenticator_hash: Sha256::digest(&ap_req.authenticator.cipher)
+                .to_vec(),
+            expiration: ctime + clockskew,
+        };
+    }
+
+    /// Tag that identifies the entry, made of the first bytes of the
+    /// SHA-256 hash of the fields of the entry.
+    pub fn tag(&self) -> [u8; REPLAY_TAG_SIZE] {
+        let mut hasher = Sha256::new();
+        for field in [self.client.as_bytes(), self.server.as_bytes()] {
+            hasher.update((field.len() as u32).to_be_bytes());
+            hasher.update(field);
+        }
+        hasher.update(self.ctime.timestamp().to_be_bytes());
+        hasher.update(self.cusec.to_be_bytes());
+        hasher.update(&self.authenticator_hash);
+
+        let mut tag = [0; REPLAY_TAG_SIZE];
+        tag.copy_from_slice(&hasher.finalize()[..REPLAY_TAG_SIZE]);
+        return tag;
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        return self.expiration < now;
+    }
+}
+
+/// Represents a principal in the form `name/instance@REALM`.
+fn principal_to_string(name: &PrincipalName, realm: &Realm) -> String {
+    return format!("{}@{}", name.name_string.join("/"), realm);
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    pub fn new_entry(cusec: i32, expiration: DateTime<Utc>) -> ReplayEntry {
+        return ReplayEntry {
+            client: "mickey@KINGDOM.HEARTS".to_string(),
+            server: "HTTP/web.kingdom.hearts@KINGDOM.HEARTS".to_string(),
+            ctime: expiration - Duration::minutes(5),
+            cusec,
+            authenticator_hash: vec![1; 32],
+            expiration,
+        };
+    }
+
+    #[test]
+    fn entries_with_different_fields_have_different_tags() {
+        let expiration = Utc::now();
+        let entry = new_entry(1, expiration);
+        let mut other_server = entry.clone();
+        other_server.server = "HTTP/www.kingdom.hearts@KINGDOM.HEARTS".into();
+
+        assert_eq!(entry.tag(), new_entry(1, expiration).tag());
+        assert_ne!(entry.tag(), new_entry(2, expiration).tag());
+        assert_ne!(entry.tag(), other_server.tag());
+    }
+}
@@ -0,0 +1,755 @@
+use super::profile::{Profile, ProfileValue};
+use crate::{Error, Result};
+use chrono::Duration;
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, DES_CBC_CRC, DES_CBC_MD5,
+    RC4_HMAC,
+};
+use himmelblau_kerberos_crypto::is_supported_etype;
+use std::collections::HashSet;
+use std::env;
+use std::path::Path;
+
+/// Default location of the Kerberos configuration
+pub const DEFAULT_KRB5_CONFIG_PATH: &str = "/etc/krb5.conf";
+
+/// Default maximum size of the messages sent over UDP, as in MIT
+pub const DEFAULT_UDP_PREFERENCE_LIMIT: usize = 1465;
+
+/// Default maximum clock skew allowed, of 5 minutes
+pub const DEFAULT_CLOCKSKEW_SECONDS: i64 = 300;
+
+/// Kerberos client configuration, read from a MIT-style krb5.conf file.
+///
+/// The settings of the `[libdefaults]`, `[realms]`, `[domain_realm]` and
+/// `[capaths]` sections used by the requesters are exposed by methods,
+/// whereas any other can be retrieved through the [Profile].
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerbeiros::config::Krb5Config;
+/// use ascii::AsciiString;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// // Read KRB5_CONFIG or /etc/krb5.conf
+/// let config = Krb5Config::load().unwrap();
+///
+/// let tgt_requester = TgtRequester::from_krb5_config(&config).unwrap();
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// let user_key = Key::Secret("S3cr3t".to_string());
+/// let tgt = tgt_requester.request(&username, Some(&user_key)).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Krb5Config {
+    profile: Profile,
+}
+
+impl Krb5Config {
+    pub fn parse(content: &str) -> Result<Self> {
+        return Ok(Self {
+            profile: Profile::parse(content)?,
+        });
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        return Ok(Self {
+            profile: Profile::from_file(path)?,
+        });
+    }
+
+    /// Reads the files of the KRB5_CONFIG environment variable, separated
+    /// by colons, or /etc/krb5.conf if it is not defined. Missing files
+    /// are ignored, so an empty configuration is retrieved if none exists.
+    pub fn load() -> Result<Self> {
+        let paths = env::var("KRB5_CONFIG")
+            .unwrap_or_else(|_| DEFAULT_KRB5_CONFIG_PATH.to_string());
+        let paths: Vec<&str> =
+            paths.split(':').filter(|path| !path.is_empty()).collect();
+
+        return Ok(Self {
+            profile: Profile::from_files(&paths)?,
+        });
+    }
+
+    pub fn profile(&self) -> &Profile {
+        return &self.profile;
+    }
+
+    fn libdefault(&self, tag: &str) -> Option<&str> {
+        return self.profile.value(&["libdefaults", tag]);
+    }
+
+    fn libdefault_boolean(&self, tag: &str, default: bool) -> bool {
+        return self
+            .libdefault(tag)
+            .and_then(parse_boolean)
+            .unwrap_or(default);
+    }
+
+    fn libdefault_time(&self, tag: &str) -> Result<Option<Duration>> {
+        return self
+            .libdefault(tag)
+            .map(|value| {
+                parse_delta_time(value).ok_or_else(|| {
+                    Error::ConfigError(format!("invalid {}: {}", tag, value))
+                })
+            })
+            .transpose();
+    }
+
+    pub fn default_realm(&self) -> Option<&str> {
+        return self.libdefault("default_realm");
+    }
+
+    /// Retrieves the etypes for the AS-REQ, or None if not configured.
+    pub fn default_tkt_enctypes(&self) -> Option<Vec<i32>> {
+        return self.libdefault("default_tkt_enctypes").map(parse_enctypes);
+    }
+
+    /// Retrieves the etypes for the TGS-REQ, or None if not configured.
+    pub fn default_tgs_enctypes(&self) -> Option<Vec<i32>> {
+        return self.libdefault("default_tgs_enctypes").map(parse_enctypes);
+    }
+
+    /// Retrieves the etypes allowed for session keys, or None if not
+    /// configured.
+    pub fn permitted_enctypes(&self) -> Option<Vec<i32>> {
+        return self.libdefault("permitted_enctypes").map(parse_enctypes);
+    }
+
+    /// Retrieves the etypes supported by this library among the permitted
+    /// default_tkt_enctypes, or None if neither are configured.
+    pub fn supported_tkt_etypes(&self) -> Option<HashSet<i32>> {
+        return self.supported_etypes(self.default_tkt_enctypes());
+    }
+
+    /// Retrieves the etypes supported by this library among the permitted
+    /// default_tgs_enctypes, or None if neither are configured.
+    pub fn supported_tgs_etypes(&self) -> Option<HashSet<i32>> {
+        return self.supported_etypes(self.default_tgs_enctypes());
+    }
+
+    fn supported_etypes(
+        &self,
+        etypes: Option<Vec<i32>>,
+    ) -> Option<HashSet<i32>> {
+        let permitted = self.permitted_enctypes();
+        let etypes = etypes.or_else(|| permitted.clone())?;
+
+        return Some(
+            etypes
+                .into_iter()
+                .filter(|etype| is_supported_etype(*etype))
+                .filter(|etype| match &permitted {
+                    Some(permitted) => permitted.contains(etype),
+                    None => true,
+                })
+                .collect(),
+        );
+    }
+
+    /// Indicates whether the KDCs of the realms without configured
+    /// KDCs are discovered through DNS, true by default.
+    pub fn dns_lookup_kdc(&self) -> bool {
+        return self.libdefault_boolean("dns_lookup_kdc", true);
+    }
+
+    /// Indicates whether the realm of the hosts is discovered through DNS
+    /// TXT records, false by default.
+    pub fn dns_lookup_realm(&self) -> bool {
+        return self.libdefault_boolean("dns_lookup_realm", false);
+    }
+
+    /// Retrieves the maximum size of the messages sent over UDP. Bigger
+    /// messages are sent over TCP, so a limit of 1 means to always use TCP.
+    pub fn udp_preference_limit(&self) -> Result<usize> {
+        match self.libdefault("udp_preference_limit") {
+            Some(value) => {
+                return value.trim().parse().map_err(|_| {
+                    Error::ConfigError(format!(
+                        "invalid udp_preference_limit: {}",
+                        value
+                    ))
+                });
+            }
+            None => {
+                return Ok(DEFAULT_UDP_PREFERENCE_LIMIT);
+            }
+        }
+    }
+
+    /// Retrieves the maximum clock skew allowed, 5 minutes by default.
+    pub fn clockskew(&self) -> Result<Duration> {
+        return Ok(self
+            .libdefault_time("clockskew")?
+            .unwrap_or_else(|| Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS)));
+    }
+
+    /// Retrieves the lifetime requested for the tickets, or None if not
+    /// configured.
+    pub fn ticket_lifetime(&self) -> Result<Option<Duration>> {
+        return self.libdefault_time("ticket_lifetime");
+    }
+
+    /// Retrieves the renewable lifetime requested for the tickets, or None
+    /// if not configured. A zero lifetime means to not request renewable
+    /// tickets.
+    pub fn renew_lifetime(&self) -> Result<Option<Duration>> {
+        return self.libdefault_time("renew_lifetime");
+    }
+
+    /// Retrieves the realms defined in the `[realms]` section.
+    pub fn realms(&self) -> Vec<&str> {
+        return match self.profile.section("realms") {
+            Some(section) => section
+                .relations()
+                .iter()
+                .filter_map(|(realm, value)| match value {
+                    ProfileValue::Section(_) => Some(realm.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+
+    /// Retrieves the KDCs of the realm, in the form `host[:port]`. The KDC
+    /// proxies, given as URLs, are excluded.
+    pub fn kdcs(&self, realm: &str) -> Vec<&str> {
+        return self
+            .profile
+            .values(&["realms", realm, "kdc"])
+            .into_iter()
+            .filter(|kdc| !is_kdc_proxy_url(kdc))
+            .collect();
+    }
+
+    /// Retrieves the first KDC proxy (MS-KKDCP) of the realm, given as a
+    /// kdc in the form `https://host[:port]/path`.
+    pub fn kdc_proxy_url(&self, realm: &str) -> Option<&str> {
+        return self
+            .profile
+            .values(&["realms", realm, "kdc"])
+            .into_iter()
+            .find(|kdc| is_kdc_proxy_url(kdc));
+    }
+
+    /// Retrieves the kpasswd servers of the realm, in the form
+    /// `host[:port]`.
+    pub fn kpasswd_servers(&self, realm: &str) -> Vec<&str> {
+        return self.profile.values(&["realms", realm, "kpasswd_server"]);
+    }
+
+    /// Retrieves the admin servers of the realm, in the form `host[:port]`.
+    pub fn admin_servers(&self, realm: &str) -> Vec<&str> {
+        return self.profile.values(&["realms", realm, "admin_server"]);
+    }
+
+    /// Retrieves the realm of the host from the `[domain_realm]` section.
+    /// An exact match of the host name takes precedence over the domains,
+    /// written with a leading dot, and the closest domain over its parents.
+    pub fn realm_of_host(&self, hostname: &str) -> Option<&str> {
+        let hostname = hostname.trim_end_matches('.').to_lowercase();
+        let domain_realm = self.profile.section("domain_realm")?;
+
+        if let Some(realm) = domain_realm.value(&hostname) {
+            return Some(realm);
+        }
+
+        let mut domain = hostname.as_str();
+        while let Some(dot) = domain.find('.') {
+            domain = &domain[dot..];
+            if let Some(realm) = domain_realm.value(domain) {
+                return Some(realm);
+            }
+            domain = &domain[1..];
+        }
+
+        return None;
+    }
+
+    /// Retrieves the intermediate realms to traverse from the client realm
+    /// to the service realm. An empty path means that there is a direct
+    /// trust, indicated with ".", whereas None means the path is not
+    /// configured.
+    pub fn capath(
+        &self,
+        client_realm: &str,
+        service_realm: &str,
+    ) -> Option<Vec<String>> {
+        let values =
+            self.profile
+                .values(&["capaths", client_realm, service_realm]);
+        if values.is_empty() {
+            return None;
+        }
+
+        return Some(
+            values
+                .into_iter()
+                .filter(|realm| *realm != ".")
+                .map(|realm| realm.to_string())
+                .collect(),
+        );
+    }
+
+    /// Retrieves all the paths of the `[capaths]` section, as tuples of
+    /// client realm, service realm and intermediate realms.
+    pub fn capaths(&self) -> Vec<(String, String, Vec<String>)> {
+        let mut capaths = Vec::new();
+        let section = match self.profile.section("capaths") {
+            Some(section) => section,
+            None => return capaths,
+        };
+
+        for (client_realm, value) in section.relations().iter() {
+            if let ProfileValue::Section(client_section) = value {
+                for (service_realm, _) in client_section.relations().iter() {
+                    let already_added = capaths.iter().any(|(c, s, _)| {
+                        c == client_realm && s == service_realm
+                    });
+                    if already_added {
+                        continue;
+                    }
+
+                    if let Some(path) = self.capath(client_realm, service_realm)
+                    {
+                        capaths.push((
+                            client_realm.clone(),
+                            service_realm.clone(),
+                            path,
+                        ));
+                    }
+                }
+            }
+        }
+
+        return capaths;
+    }
+}
+
+/// Default etypes of the library, used for the DEFAULT keyword
+const DEFAULT_ENCTYPES: [i32; 3] =
+    [AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC];
+
+/// Parses a list of etype names, separated by spaces or commas. Families
+/// like "aes", the DEFAULT keyword and removals with "-" are supported,
+/// whereas unknown names are ignored.
+fn parse_enctypes(value: &str) -> Vec<i32> {
+    let mut etypes: Vec<i32> = Vec::new();
+
+    for name in value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|name| !name.is_empty())
+    {
+        let (remove, name) = match name.strip_prefix('-') {
+            Some(name) => (true, name.strip_prefix('+').unwrap_or(name)),
+            None => (false, name.strip_prefix('+').unwrap_or(name)),
+        };
+
+        let named_etypes: Vec<i32> = if name == "DEFAULT" {
+            DEFAULT_ENCTYPES.to_vec()
+        } else {
+            enctype_by_name(&name.to_lowercase())
+        };
+
+        for etype in named_etypes {
+            if remove {
+                etypes.retain(|e| *e != etype);
+            } else if !etypes.contains(&etype) {
+                etypes.push(etype);
+            }
+        }
+    }
+
+    return etypes;
+}
+
+fn enctype_by_name(name: &str) -> Vec<i32> {
+    match name {
+        "aes256-cts-hmac-sha1-96" | "aes256-cts" | "aes256-sha1" => {
+            return vec![AES256_CTS_HMAC_SHA1_96];
+        }
+        "aes128-cts-hmac-sha1-96" | "aes128-cts" | "aes128-sha1" => {
+            return vec![AES128_CTS_HMAC_SHA1_96];
+        }
+        "arcfour-hmac" | "rc4-hmac" | "arcfour-hmac-md5" => {
+            return vec![RC4_HMAC];
+        }
+        "des-cbc-crc" => {
+            return vec![DES_CBC_CRC];
+        }
+        "des-cbc-md5" | "des" => {
+            return vec![DES_CBC_MD5];
+        }
+        "aes" => {
+            return vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96];
+        }
+        "rc4" => {
+            return vec![RC4_HMAC];
+        }
+        _ => {
+            return Vec::new();
+        }
+    }
+}
+
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "y" | "yes" | "true" | "t" | "1" | "on" => {
+            return Some(true);
+        }
+        "n" | "no" | "false" | "nil" | "0" | "off" => {
+            return Some(false);
+        }
+        _ => {
+            return None;
+        }
+    }
+}
+
+/// Parses a relative time in the formats of krb5.conf: seconds ("300"),
+/// units ("1d2h30m15s", where any unit may be omitted), "h:m[:s]" and
+/// "Nd h:m[:s]".
+fn parse_delta_time(value: &str) -> Option<Duration> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if value.is_empty() {
+        return None;
+    }
+
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        return Duration::try_seconds(value.parse().ok()?);
+    }
+
+    if value.contains(':') {
+        let (days, clock) = match value.split_once('d') {
+            Some((days, clock)) => (days.parse::<i64>().ok()?, clock),
+            None => (0, value.as_str()),
+        };
+
+        let parts = clock
+            .split(':')
+            .map(|part| part.parse::<i64>().ok())
+            .collect::<Option<Vec<i64>>>()?;
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes] => (hours, minutes, 0),
+            [hours, minutes, seconds] => (hours, minutes, seconds),
+            _ => return None,
+        };
+        if minutes > 59 || seconds > 59 {
+            return None;
+        }
+
+        return Duration::try_days(days)?
+            .checked_add(&Duration::try_hours(hours)?)?
+            .checked_add(&Duration::try_minutes(minutes)?)?
+            .checked_add(&Duration::try_seconds(seconds)?);
+    }
+
+    let mut duration = Duration::zero();
+    let mut units = "dhms";
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        // Units must appear once, in decreasing order
+        let position = units.find(c)?;
+        units = &units[position + 1..];
+        let amount: i64 = number.parse().ok()?;
+        number.clear();
+
+        let amount = match c {
+            'd' => Duration::try_days(amount)?,
+            'h' => Duration::try_hours(amount)?,
+            'm' => Duration::try_minutes(amount)?,
+            _ => Duration::try_seconds(amount)?,
+        };
+        duration = duration.checked_add(&amount)?;
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    return Some(duration);
+}
+
+fn is_kdc_proxy_url(kdc: &str) -> bool {
+    return kdc.to_lowercase().starts_with("https://");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KRB5_CONF: &str = r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tkt_enctypes = aes256-cts-hmac-sha1-96 rc4-hmac des-cbc-md5
+    default_tgs_enctypes = DEFAULT -rc4
+    permitted_enctypes = aes, arcfour-hmac
+    dns_lookup_kdc = false
+    dns_lookup_realm = yes
+    udp_preference_limit = 1
+    clockskew = 2m
+    ticket_lifetime = 10h
+    renew_lifetime = 7d
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kdc = 10.0.0.2:750
+        admin_server = kdc1.kingdom.hearts
+        kpasswd_server = kdc1.kingdom.hearts:464
+    }
+    DISNEY.COM = {
+        kdc = kdc.disney.com
+    }
+
+[domain_realm]
+    .kingdom.hearts = KINGDOM.HEARTS
+    kingdom.hearts = KINGDOM.HEARTS
+    .disney.kingdom.hearts = DISNEY.COM
+    castle.disney.kingdom.hearts = KINGDOM.HEARTS
+
+[capaths]
+    KINGDOM.HEARTS = {
+        DISNEY.COM = .
+        PIXAR.COM = DISNEY.COM
+        DREAMWORKS.COM = DISNEY.COM
+        DREAMWORKS.COM = PIXAR.COM
+    }
+"#;
+
+    #[test]
+    fn parse_libdefaults() {
+        let config = Krb5Config::parse(KRB5_CONF).unwrap();
+
+        assert_eq!(Some("KINGDOM.HEARTS"), config.default_realm());
+        assert_eq!(
+            Some(vec![AES256_CTS_HMAC_SHA1_96, RC4_HMAC, DES_CBC_MD5]),
+            config.default_tkt_enctypes()
+        );
+        assert_eq!(
+            Some(vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96]),
+            config.default_tgs_enctypes()
+        );
+        assert_eq!(
+            Some(vec![
+                AES256_CTS_HMAC_SHA1_96,
+                AES128_CTS_HMAC_SHA1_96,
+                RC4_HMAC
+            ]),
+            config.permitted_enctypes()
+        );
+        assert!(!config.dns_lookup_kdc());
+        assert!(config.dns_lookup_realm());
+        assert_eq!(1, config.udp_preference_limit().unwrap());
+        assert_eq!(Duration::minutes(2), config.clockskew().unwrap());
+        assert_eq!(
+            Some(Duration::hours(10)),
+            config.ticket_lifetime().unwrap()
+        );
+        assert_eq!(Some(Duration::days(7)), config.renew_lifetime().unwrap());
+    }
+
+    #[test]
+    fn supported_permitted_etypes() {
+        let config = Krb5Config::parse(KRB5_CONF).unwrap();
+
+        assert_eq!(
+            Some(
+                [AES256_CTS_HMAC_SHA1_96, RC4_HMAC]
+                    .iter()
+                    .cloned()
+                    .collect()
+            ),
+            config.supported_tkt_etypes()
+        );
+        assert_eq!(
+            Some(
+                [AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96]
+                    .iter()
+                    .cloned()
+                    .collect()
+            ),
+            config.supported_tgs_etypes()
+        );
+
+        let config = Krb5Config::parse(
+            "[libdefaults]\npermitted_enctypes = rc4-hmac des-cbc-md5\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Some([RC4_HMAC].iter().cloned().collect()),
+            config.supported_tkt_etypes()
+        );
+        assert_eq!(None, Krb5Config::default().supported_tgs_etypes());
+    }
+
+    #[test]
+    fn default_libdefaults() {
+        let config = Krb5Config::parse("").unwrap();
+
+        assert_eq!(None, config.default_realm());
+        assert_eq!(None, config.default_tkt_enctypes());
+        assert_eq!(None, config.permitted_enctypes());
+        assert!(config.dns_lookup_kdc());
+        assert!(!config.dns_lookup_realm());
+        assert_eq!(
+            DEFAULT_UDP_PREFERENCE_LIMIT,
+            config.udp_preference_limit().unwrap()
+        );
+        assert_eq!(Duration::minutes(5), config.clockskew().unwrap());
+        assert_eq!(None, config.ticket_lifetime().unwrap());
+        assert_eq!(None, config.renew_lifetime().unwrap());
+    }
+
+    #[test]
+    fn error_with_invalid_libdefaults() {
+        let config = Krb5Config::parse(
+            "[libdefaults]\nclockskew = 5x\nudp_preference_limit = big\n\
+             ticket_lifetime = 99999999999999999\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Error::ConfigError("invalid clockskew: 5x".into()),
+            config.clockskew().unwrap_err()
+        );
+        assert_eq!(
+            Error::ConfigError("invalid udp_preference_limit: big".into()),
+            config.udp_preference_limit().unwrap_err()
+        );
+        assert_eq!(
+            Error::ConfigError(
+                "invalid ticket_lifetime: 99999999999999999".into()
+            ),
+            config.ticket_lifetime().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn parse_realms() {
+        let config = Krb5Config::parse(KRB5_CONF).unwrap();
+
+        assert_eq!(vec!["KINGDOM.HEARTS", "DISNEY.COM"], config.realms());
+        assert_eq!(
+            vec!["kdc1.kingdom.hearts", "10.0.0.2:750"],
+            config.kdcs("KINGDOM.HEARTS")
+        );
+        assert_eq!(
+            vec!["kdc1.kingdom.hearts:464"],
+            config.kpasswd_servers("KINGDOM.HEARTS")
+        );
+        assert_eq!(
+            vec!["kdc1.kingdom.hearts"],
+            config.admin_servers("KINGDOM.HEARTS")
+        );
+        assert!(config.kdcs("PIXAR.COM").is_empty());
+    }
+
+    #[test]
+    fn parse_kdc_proxy_url() {
+        let config = Krb5Config::parse(
+            r#"
+[realms]
+    DARK.WORLD = {
+        kdc = HTTPS://kdcproxy.dark.world/KdcProxy
+        kdc = kdc.dark.world
+    }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(vec!["kdc.dark.world"], config.kdcs("DARK.WORLD"));
+        assert_eq!(
+            Some("HTTPS://kdcproxy.dark.world/KdcProxy"),
+            config.kdc_proxy_url("DARK.WORLD")
+        );
+        assert_eq!(None, config.kdc_proxy_url("KINGDOM.HEARTS"));
+    }
+
+    #[test]
+    fn realm_of_host_by_domain_realm() {
+        let config = Krb5Config::parse(KRB5_CONF).unwrap();
+
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            config.realm_of_host("kingdom.hearts")
+        );
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            config.realm_of_host("Web.Kingdom.Hearts.")
+        );
+        assert_eq!(
+            Some("DISNEY.COM"),
+            config.realm_of_host("ride.disney.kingdom.hearts")
+        );
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            config.realm_of_host("castle.disney.kingdom.hearts")
+        );
+        assert_eq!(None, config.realm_of_host("pixar.com"));
+    }
+
+    #[test]
+    fn parse_capaths() {
+        let config = Krb5Config::parse(KRB5_CONF).unwrap();
+
+        assert_eq!(
+            Some(Vec::<String>::new()),
+            config.capath("KINGDOM.HEARTS", "DISNEY.COM")
+        );
+        assert_eq!(
+            Some(vec!["DISNEY.COM".to_string(), "PIXAR.COM".to_string()]),
+            config.capath("KINGDOM.HEARTS", "DREAMWORKS.COM")
+        );
+        assert_eq!(None, config.capath("DISNEY.COM", "KINGDOM.HEARTS"));
+        assert_eq!(3, config.capaths().len());
+    }
+
+    #[test]
+    fn parse_enctype_lists() {
+        assert_eq!(
+            vec![AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            parse_enctypes("DEFAULT, -aes256-cts")
+        );
+        assert_eq!(
+            vec![RC4_HMAC],
+            parse_enctypes("camellia256-cts-cmac +arcfour-hmac-md5")
+        );
+        assert!(parse_enctypes("aes -aes").is_empty());
+    }
+
+    #[test]
+    fn parse_delta_times() {
+        assert_eq!(Some(Duration::seconds(300)), parse_delta_time("300"));
+        assert_eq!(Some(Duration::minutes(5)), parse_delta_time("5m"));
+        assert_eq!(Some(Duration::minutes(90)), parse_delta_time("1h30m"));
+        assert_eq!(
+            Some(Duration::days(1) + Duration::seconds(15)),
+            parse_delta_time("1d 15s")
+        );
+        assert_eq!(
+            Some(Duration::hours(10) + Duration::minutes(30)),
+            parse_delta_time("10:30")
+        );
+        assert_eq!(
+            Some(Duration::days(2) + Duration::seconds(3723)),
+            parse_delta_time("2d1:02:03")
+        );
+        assert_eq!(None, parse_delta_time("1m1h"));
+        assert_eq!(None, parse_delta_time("10:75"));
+        assert_eq!(None, parse_delta_time("1h5"));
+        assert_eq!(None, parse_delta_time(""));
+        assert_eq!(None, parse_delta_time("99999999999999999"));
+        assert_eq!(None, parse_delta_time("99999999999999d"));
+        assert_eq!(None, parse_delta_time("99999999999999d1:00"));
+    }
+}
@@ -0,0 +1,7 @@
+//! Kerberos client configuration, read from krb5.conf files
+
+mod profile;
+pub use profile::*;
+
+mod krb5_config;
+pub use krb5_config::*;
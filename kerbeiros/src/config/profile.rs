@@ -0,0 +1,568 @@
+use crate::{Error, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Maximum nesting of include directives, in order to stop include loops
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Value of a relation of the profile: a string or a subsection
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileValue {
+    Value(String),
+    Section(ProfileSection),
+}
+
+/// Section of the profile, with the relations in the order they appear.
+/// The same tag may appear several times, like the kdc of a realm.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfileSection {
+    relations: Vec<(String, ProfileValue)>,
+}
+
+impl ProfileSection {
+    pub fn relations(&self) -> &[(String, ProfileValue)] {
+        return &self.relations;
+    }
+
+    /// Retrieves the string values of the tag.
+    pub fn values(&self, tag: &str) -> Vec<&str> {
+        return self
+            .relations
+            .iter()
+            .filter_map(|(name, value)| match value {
+                ProfileValue::Value(value) if name == tag => {
+                    Some(value.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// Retrieves the first string value of the tag.
+    pub fn value(&self, tag: &str) -> Option<&str> {
+        return self.values(tag).into_iter().next();
+    }
+
+    /// Retrieves the subsections of the tag.
+    pub fn subsections(&self, tag: &str) -> Vec<&ProfileSection> {
+        return self
+            .relations
+            .iter()
+            .filter_map(|(name, value)| match value {
+                ProfileValue::Section(section) if name == tag => Some(section),
+                _ => None,
+            })
+            .collect();
+    }
+
+    fn push(&mut self, tag: String, value: ProfileValue) {
+        self.relations.push((tag, value));
+    }
+
+    /// Appends the relations to the top-level section with the same name,
+    /// since a section can be split between several files.
+    fn merge_section(&mut self, name: String, section: ProfileSection) {
+        for (tag, value) in self.relations.iter_mut() {
+            if let ProfileValue::Section(existing) = value {
+                if *tag == name {
+                    existing.relations.extend(section.relations);
+                    return;
+                }
+            }
+        }
+        self.push(name, ProfileValue::Section(section));
+    }
+}
+
+/// Configuration in the profile format of MIT Kerberos, used by krb5.conf.
+///
+/// The profile is composed of top-level sections, like `[libdefaults]`,
+/// which contain `tag = value` relations or `tag = { ... }` subsections.
+/// The `include` and `includedir` directives are supported. The final
+/// marker `*` is accepted, but it has no effect.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    root: ProfileSection,
+}
+
+impl Profile {
+    /// Parses the profile from a string. The include directives are
+    /// resolved relative to the current directory.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut profile = Self::default();
+        parse_into(&mut profile.root, content, 0)?;
+        return Ok(profile);
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut profile = Self::default();
+        parse_file_into(&mut profile.root, path.as_ref(), 0)?;
+        return Ok(profile);
+    }
+
+    /// Parses several files into the same profile, so the values of the
+    /// first files take precedence. Missing files are skipped.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut profile = Self::default();
+        for path in paths.iter() {
+            match parse_file_into(&mut profile.root, path.as_ref(), 0) {
+                Err(Error::IOError) => {}
+                result => result?,
+            }
+        }
+        return Ok(profile);
+    }
+
+    /// Retrieves the top-level section.
+    pub fn section(&self, name: &str) -> Option<&ProfileSection> {
+        return self.root.subsections(name).into_iter().next();
+    }
+
+    /// Retrieves the values of the relation found by following the path
+    /// of sections and tags, such as `["realms", "CONTOSO.COM", "kdc"]`.
+    pub fn values(&self, path: &[&str]) -> Vec<&str> {
+        let (tag, section_path) = match path.split_last() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        let mut sections = vec![&self.root];
+        for name in section_path.iter() {
+            sections = sections
+                .into_iter()
+                .flat_map(|section| section.subsections(name))
+                .collect();
+        }
+
+        return sections
+            .into_iter()
+            .flat_map(|section| section.values(tag))
+            .collect();
+    }
+
+    /// Retrieves the first value of the relation in the path.
+    pub fn value(&self, path: &[&str]) -> Option<&str> {
+        return self.values(path).into_iter().next();
+    }
+}
+
+/// Reads the file into the profile. A missing file is reported as
+/// [Error::IOError], any other failure as [Error::ConfigError].
+fn parse_file_into(
+    root: &mut ProfileSection,
+    path: &Path,
+    depth: usize,
+) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::IOError);
+        }
+        Err(error) => {
+            return Err(Error::ConfigError(format!(
+                "{}: {}",
+                path.display(),
+                error
+            )));
+        }
+    };
+
+    return parse_into(root, &content, depth).map_err(|error| match error {
+        Error::ConfigError(message) => {
+            Error::ConfigError(format!("{}: {}", path.display(), message))
+        }
+        error => error,
+    });
+}
+
+fn parse_dir_into(
+    root: &mut ProfileSection,
+    dir: &Path,
+    depth: usize,
+) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|error| {
+        Error::ConfigError(format!("{}: {}", dir.display(), error))
+    })?;
+
+    // Same filter as MIT: skip hidden files and editor backups
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(
+            |path| match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => {
+                    !name.starts_with('.')
+                        && (name.ends_with(".conf")
+                            || name.chars().all(|c| {
+                                c.is_ascii_alphanumeric()
+                                    || c == '-'
+                                    || c == '_'
+                            }))
+                }
+                None => false,
+            },
+        )
+        .collect();
+    paths.sort();
+
+    for path in paths.iter() {
+        parse_file_into(root, path, depth)?;
+    }
+    return Ok(());
+}
+
+fn parse_into(
+    root: &mut ProfileSection,
+    content: &str,
+    depth: usize,
+) -> Result<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(Error::ConfigError("too many nested includes".into()));
+    }
+
+    let mut current: Option<(String, ProfileSection)> = None;
+    let mut groups: Vec<(String, ProfileSection)> = Vec::new();
+
+    for (i, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim();
+        let line_error = |message: &str| {
+            return Error::ConfigError(format!("line {}: {}", i + 1, message));
+        };
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if groups.is_empty() {
+            let include = directive(line, "includedir")
+                .map(|path| (path, true))
+                .or_else(|| {
+                    directive(line, "include").map(|path| (path, false))
+                });
+
+            if let Some((path, is_dir)) = include {
+                // Keep the order of the relations before and after
+                if let Some((name, section)) = current.take() {
+                    root.merge_section(name.clone(), section);
+                    current = Some((name, ProfileSection::default()));
+                }
+
+                let path = Path::new(path);
+                if is_dir {
+                    parse_dir_into(root, path, depth + 1)?;
+                } else {
+                    match parse_file_into(root, path, depth + 1) {
+                        Err(Error::IOError) => {
+                            return Err(line_error(&format!(
+                                "cannot include {}",
+                                path.display()
+                            )));
+                        }
+                        result => result?,
+                    }
+                }
+                continue;
+            }
+
+            if directive(line, "module").is_some() {
+                continue;
+            }
+        }
+
+        if line.starts_with('[') {
+            if !groups.is_empty() {
+                return Err(line_error("unclosed subsection"));
+            }
+
+            let end =
+                line.find(']').ok_or_else(|| line_error("missing ']'"))?;
+            let name = line[1..end].trim();
+            if let Some((name, section)) = current.take() {
+                root.merge_section(name, section);
+            }
+            current = Some((name.to_string(), ProfileSection::default()));
+            continue;
+        }
+
+        if line.starts_with('}') {
+            let (tag, group) =
+                groups.pop().ok_or_else(|| line_error("unexpected '}'"))?;
+            innermost_section(&mut current, &mut groups)
+                .ok_or_else(|| line_error("relation outside of a section"))?
+                .push(tag, ProfileValue::Section(group));
+            continue;
+        }
+
+        let (tag, value) = line
+            .split_once('=')
+            .ok_or_else(|| line_error("missing '='"))?;
+        let tag = tag.trim().trim_end_matches('*').trim_end().to_string();
+        let value = value.trim();
+
+        if value == "{" {
+            if current.is_none() {
+                return Err(line_error("relation outside of a section"));
+            }
+            groups.push((tag, ProfileSection::default()));
+            continue;
+        }
+
+        let value = parse_value(value).ok_or_else(|| {
+            return line_error("unterminated quoted string");
+        })?;
+        innermost_section(&mut current, &mut groups)
+            .ok_or_else(|| line_error("relation outside of a section"))?
+            .push(tag, ProfileValue::Value(value));
+    }
+
+    if !groups.is_empty() {
+        return Err(Error::ConfigError("unclosed subsection".into()));
+    }
+
+    if let Some((name, section)) = current.take() {
+        root.merge_section(name, section);
+    }
+
+    return Ok(());
+}
+
+fn innermost_section<'a>(
+    current: &'a mut Option<(String, ProfileSection)>,
+    groups: &'a mut [(String, ProfileSection)],
+) -> Option<&'a mut ProfileSection> {
+    if let Some((_, group)) = groups.last_mut() {
+        return Some(group);
+    }
+    return current.as_mut().map(|(_, section)| section);
+}
+
+/// Returns the argument of the directive, if the line contains it.
+fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let argument = line.strip_prefix(name)?;
+    if !argument.starts_with(char::is_whitespace) {
+        return None;
+    }
+    return Some(argument.trim());
+}
+
+/// Removes the quotes and escapes of a value. Unquoted values are
+/// taken literally.
+fn parse_value(value: &str) -> Option<String> {
+    let quoted = match value.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => return Some(value.to_string()),
+    };
+
+    let mut parsed = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(parsed),
+            '\\' => match chars.next()? {
+                'n' => parsed.push('\n'),
+                't' => parsed.push('\t'),
+                'b' => parsed.push('\u{8}'),
+                c => parsed.push(c),
+            },
+            c => parsed.push(c),
+        }
+    }
+
+    return None;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "kerbeiros-profile-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn parse_sections_and_relations() {
+        let profile = Profile::parse(
+            r#"
+# comment
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    ; another comment
+    forwardable = true
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kdc = kdc2.kingdom.hearts:750
+        auth_to_local = {
+            rule = "RULE:[1:$1]"
+        }
+    }
+
+[libdefaults]
+    default_realm = DISNEY.COM
+    ticket_lifetime* = 10h
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["KINGDOM.HEARTS", "DISNEY.COM"],
+            profile.values(&["libdefaults", "default_realm"])
+        );
+        assert_eq!(
+            Some("10h"),
+            profile.value(&["libdefaults", "ticket_lifetime"])
+        );
+        assert_eq!(
+            vec!["kdc1.kingdom.hearts", "kdc2.kingdom.hearts:750"],
+            profile.values(&["realms", "KINGDOM.HEARTS", "kdc"])
+        );
+        assert_eq!(
+            Some("RULE:[1:$1]"),
+            profile.value(&[
+                "realms",
+                "KINGDOM.HEARTS",
+                "auth_to_local",
+                "rule"
+            ])
+        );
+        assert_eq!(None, profile.value(&["realms", "DISNEY.COM", "kdc"]));
+    }
+
+    #[test]
+    fn parse_quoted_values() {
+        assert_eq!(Some("plain value".to_string()), parse_value("plain value"));
+        assert_eq!(
+            Some("a \"quoted\"\tvalue".to_string()),
+            parse_value(r#""a \"quoted\"\tvalue""#)
+        );
+        assert_eq!(None, parse_value(r#""unterminated"#));
+    }
+
+    #[test]
+    fn error_parsing_invalid_profiles() {
+        assert_eq!(
+            Error::ConfigError("line 1: relation outside of a section".into()),
+            Profile::parse("default_realm = KINGDOM.HEARTS").unwrap_err()
+        );
+        assert_eq!(
+            Error::ConfigError("line 2: missing '='".into()),
+            Profile::parse("[libdefaults]\ndefault_realm").unwrap_err()
+        );
+        assert_eq!(
+            Error::ConfigError("unclosed subsection".into()),
+            Profile::parse("[realms]\nKINGDOM.HEARTS = {\nkdc = kdc")
+                .unwrap_err()
+        );
+        assert_eq!(
+            Error::ConfigError("line 2: unexpected '}'".into()),
+            Profile::parse("[realms]\n}").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn parse_include_and_includedir() {
+        let dir = temp_dir("include");
+        let confd = dir.join("krb5.conf.d");
+        fs::create_dir_all(&confd).unwrap();
+        fs::write(
+            confd.join("10-realms.conf"),
+            "[realms]\nKINGDOM.HEARTS = {\nkdc = kdc1\n}\n",
+        )
+        .unwrap();
+        fs::write(confd.join(".hidden"), "[libdefaults]\nclockskew = 1\n")
+            .unwrap();
+        fs::write(confd.join("backup~"), "[libdefaults]\nclockskew = 2\n")
+            .unwrap();
+        fs::write(
+            dir.join("extra.conf"),
+            "[libdefaults]\nforwardable = false\n",
+        )
+        .unwrap();
+
+        let main = dir.join("krb5.conf");
+        fs::write(
+            &main,
+            format!(
+                "includedir {}\n[libdefaults]\ndefault_realm = KINGDOM.HEARTS\ninclude {}\nclockskew = 60\n",
+                confd.display(),
+                dir.join("extra.conf").display()
+            ),
+        )
+        .unwrap();
+
+        let profile = Profile::from_file(&main).unwrap();
+        assert_eq!(
+            vec!["kdc1"],
+            profile.values(&["realms", "KINGDOM.HEARTS", "kdc"])
+        );
+        assert_eq!(vec!["60"], profile.values(&["libdefaults", "clockskew"]));
+        assert_eq!(
+            Some("false"),
+            profile.value(&["libdefaults", "forwardable"])
+        );
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            profile.value(&["libdefaults", "default_realm"])
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn error_including_missing_file() {
+        let error = Profile::parse(
+            "include /nonexistent/kerbeiros/krb5.conf\n[libdefaults]\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            Error::ConfigError(
+                "line 1: cannot include /nonexistent/kerbeiros/krb5.conf"
+                    .into()
+            ),
+            error
+        );
+    }
+
+    #[test]
+    fn error_with_include_loop() {
+        let dir = temp_dir("loop");
+        let path = dir.join("krb5.conf");
+        fs::write(&path, format!("include {}\n", path.display())).unwrap();
+
+        match Profile::from_file(&path).unwrap_err() {
+            Error::ConfigError(message) => {
+                assert!(message.ends_with("too many nested includes"));
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn skip_missing_files() {
+        let dir = temp_dir("missing");
+        let path = dir.join("krb5.conf");
+        fs::write(&path, "[libdefaults]\ndefault_realm = KINGDOM.HEARTS\n")
+            .unwrap();
+
+        let profile =
+            Profile::from_files(&[dir.join("nonexistent.conf"), path]).unwrap();
+        assert_eq!(
+            Some("KINGDOM.HEARTS"),
+            profile.value(&["libdefaults", "default_realm"])
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
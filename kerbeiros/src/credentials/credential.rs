@@ -26,6 +26,7 @@ pub struct Credential {
     cname: PrincipalName,
     ticket: Ticket,
     client_part: EncAsRepPart,
+    second_ticket: Option<Ticket>,
 }
 
 impl Credential {
@@ -40,6 +41,7 @@ impl Credential {
             cname,
             ticket,
             client_part,
+            second_ticket: None,
         };
     }
 
@@ -95,6 +97,22 @@ impl Credential {
         return self.client_part.encrypted_pa_data.as_ref();
     }
 
+    /// Ticket whose session key was used to encrypt this ticket, in case
+    /// of being retrieved in a user-to-user exchange.
+    pub fn second_ticket(&self) -> Option<&Ticket> {
+        return self.second_ticket.as_ref();
+    }
+
+    pub fn set_second_ticket(&mut self, second_ticket: Option<Ticket>) {
+        self.second_ticket = second_ticket;
+    }
+
+    /// Indicates whether the ticket is encrypted with the session key of
+    /// another ticket instead of a long-term key (user-to-user).
+    pub fn is_skey(&self) -> bool {
+        return self.second_ticket.is_some();
+    }
+
     /// Saves the credential into a file by using the ccache format, used by Linux.
     pub fn save_into_ccache_file(self, path: &str) -> Result<()> {
         return CredentialWarehouse::from(self).save_into_ccache_file(path);
@@ -171,13 +189,23 @@ impl TryFrom<CredentialEntry> for Credential {
             enc_part.encrypted_pa_data = Some(method_data);
         }
 
-        return Ok(Self::new(crealm, cname, ticket, enc_part));
+        let mut credential = Self::new(crealm, cname, ticket, enc_part);
+
+        if credential_entry.is_skey != 0
+            && !credential_entry.second_ticket.data.is_empty()
+        {
+            let (_, second_ticket) =
+                Ticket::parse(&credential_entry.second_ticket.data)?;
+            credential.second_ticket = Some(second_ticket);
+        }
+
+        return Ok(credential);
     }
 }
 
 impl From<Credential> for CredentialEntry {
     fn from(val: Credential) -> Self {
-        let is_skey = 0;
+        let is_skey = val.is_skey() as u8;
 
         let time = TimesMapper::authtime_starttime_endtime_renew_till_to_times(
             val.authtime(),
@@ -218,6 +246,11 @@ impl From<Credential> for CredentialEntry {
                 );
         }
 
+        if let Some(second_ticket) = val.second_ticket() {
+            ccache_credential.second_ticket =
+                CountedOctetString::new(second_ticket.build());
+        }
+
         return ccache_credential;
     }
 }
@@ -574,4 +607,37 @@ mod test {
             Credential::try_from(ccache_credential).unwrap()
         );
     }
+
+    #[test]
+    fn convert_u2u_credential_to_ccache_credential_and_back() {
+        let realm = Realm::from("KINGDOM.HEARTS");
+        let cname =
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey"));
+        let second_ticket = Ticket::new(
+            realm.clone(),
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt")),
+            EncryptedData::new(AES256_CTS_HMAC_SHA1_96, None, vec![1; 16]),
+        );
+
+        let mut credential = Credential::new(
+            realm.clone(),
+            cname.clone(),
+            Ticket::new(realm.clone(), cname.clone(), EncryptedData::default()),
+            EncAsRepPart {
+                starttime: Some(Utc::now().into()),
+                srealm: realm,
+                sname: cname,
+                ..Default::default()
+            },
+        );
+        credential.set_second_ticket(Some(second_ticket.clone()));
+
+        let ccache_credential = CredentialEntry::from(credential.clone());
+        assert_eq!(1, ccache_credential.is_skey);
+        assert_eq!(second_ticket.build(), ccache_credential.second_ticket.data);
+
+        let converted = Credential::try_from(ccache_credential).unwrap();
+        assert!(converted.is_skey());
+        assert_eq!(Some(&second_ticket), converted.second_ticket());
+    }
 }
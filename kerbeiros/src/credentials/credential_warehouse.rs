@@ -4,7 +4,7 @@ use super::mappers::CredentialWarehouseKrbCredMapper;
 use crate::error;
 use crate::mappers::PrincipalMapper;
 use getset::Getters;
-use himmelblau_kerberos_asn1::{KrbCred, PrincipalName, Realm};
+use himmelblau_kerberos_asn1::{EncryptionKey, KrbCred, PrincipalName, Realm};
 use himmelblau_kerberos_ccache::{CCache, Header};
 use std::convert::TryFrom;
 ///
@@ -49,6 +49,28 @@ impl CredentialWarehouse {
         return CredentialWarehouseKrbCredMapper::credential_warehouse_to_krb_cred(self);
     }
 
+    /// Produces a KRB-CRED encrypted with the given key, usually the session
+    /// key or subkey of an AP-REQ, in order to delegate the credentials.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn into_encrypted_krb_cred(
+        &self,
+        key: &EncryptionKey,
+    ) -> error::Result<KrbCred> {
+        return CredentialWarehouseKrbCredMapper::credential_warehouse_to_encrypted_krb_cred(self, key);
+    }
+
+    /// Imports the credentials of a KRB-CRED encrypted with the given key,
+    /// such as one delegated by a client inside an AP-REQ.
+    pub fn from_encrypted_krb_cred(
+        krb_cred: KrbCred,
+        key: &EncryptionKey,
+    ) -> error::Result<Self> {
+        return CredentialWarehouseKrbCredMapper::krb_cred_to_credential_warehouse(
+            krb_cred,
+            Some(key),
+        );
+    }
+
     /// Saves the credentials into a file by using the ccache format, used by Linux.
     pub fn save_into_ccache_file(&self, path: &str) -> error::Result<()> {
         return CredentialFileConverter::save_into_ccache_file(self, path);
@@ -91,6 +113,16 @@ impl TryFrom<CCache> for CredentialWarehouse {
     }
 }
 
+impl TryFrom<KrbCred> for CredentialWarehouse {
+    type Error = error::Error;
+
+    fn try_from(krb_cred: KrbCred) -> Result<Self, Self::Error> {
+        return CredentialWarehouseKrbCredMapper::krb_cred_to_credential_warehouse(
+            krb_cred, None,
+        );
+    }
+}
+
 impl From<CredentialWarehouse> for CCache {
     fn from(val: CredentialWarehouse) -> Self {
         let header = Header::default();
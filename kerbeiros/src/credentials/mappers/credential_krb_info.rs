@@ -1,10 +1,11 @@
 use super::super::credential::*;
-use crate::Result;
+use crate::messages::PreauthHints;
+use crate::{Error, Result};
 use himmelblau_kerberos_asn1::{
-    AsRep, Asn1Object, EncAsRepPart, EtypeInfo2, KrbCredInfo, Ticket,
+    AsRep, Asn1Object, EncAsRepPart, EncTgsRepPart, EncryptionKey, KrbCredInfo,
+    TgsRep, Ticket,
 };
 use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REP_ENC_PART;
-use himmelblau_kerberos_constants::pa_data_types::PA_ETYPE_INFO2;
 use himmelblau_kerberos_crypto::new_kerberos_cipher;
 use himmelblau_kerberos_crypto::Key;
 
@@ -43,14 +44,43 @@ impl CredentialKrbInfoMapper {
         return (krb_cred_info, credential.ticket().clone());
     }
 
+    pub fn krb_info_and_ticket_to_credential(
+        krb_cred_info: KrbCredInfo,
+        ticket: Ticket,
+    ) -> Result<Credential> {
+        let crealm = krb_cred_info.prealm.ok_or(Error::NoPrincipalName)?;
+        let cname = krb_cred_info.pname.ok_or(Error::NoPrincipalName)?;
+
+        let enc_part = EncAsRepPart {
+            key: krb_cred_info.key,
+            flags: krb_cred_info.flags.unwrap_or_default(),
+            authtime: krb_cred_info.authtime.unwrap_or_default(),
+            starttime: krb_cred_info.starttime,
+            endtime: krb_cred_info.endtime.unwrap_or_default(),
+            renew_till: krb_cred_info.renew_till,
+            srealm: krb_cred_info
+                .srealm
+                .unwrap_or_else(|| ticket.realm.clone()),
+            sname: krb_cred_info.sname.unwrap_or_else(|| ticket.sname.clone()),
+            caddr: krb_cred_info.caddr,
+            ..Default::default()
+        };
+
+        return Ok(Credential::new(crealm, cname, ticket, enc_part));
+    }
+
+    /// Decrypts the AS-REP, deriving the key from the password with the
+    /// salt and s2kparams of the AS-REP padata or, if missing, of the hints
+    /// supplied by the KDC in the KDC_ERR_PREAUTH_REQUIRED error.
     pub fn kdc_rep_to_credential(
         key: &Key,
         kdc_rep: AsRep,
+        hints: Option<&PreauthHints>,
     ) -> Result<Credential> {
         let plaintext = match key {
             Key::Secret(password) => {
                 Self::decrypt_enc_kdc_rep_part_with_password(
-                    password, &kdc_rep,
+                    password, &kdc_rep, hints,
                 )?
             }
             cipher_key => Self::decrypt_enc_kdc_rep_part_with_cipher_key(
@@ -68,38 +98,72 @@ impl CredentialKrbInfoMapper {
         ));
     }
 
+    pub fn tgs_rep_to_credential(
+        key: &EncryptionKey,
+        key_usage: i32,
+        nonce: u32,
+        tgs_rep: TgsRep,
+    ) -> Result<Credential> {
+        let cipher = new_kerberos_cipher(tgs_rep.enc_part.etype)?;
+        let plaintext = cipher.decrypt(
+            &key.keyvalue,
+            key_usage,
+            &tgs_rep.enc_part.cipher,
+        )?;
+
+        // Some KDCs tag the TGS-REP encrypted part as EncAsRepPart
+        let enc_tgs_rep_part = match EncTgsRepPart::parse(&plaintext) {
+            Ok((_, enc_tgs_rep_part)) => enc_tgs_rep_part,
+            Err(_) => EncAsRepPart::parse(&plaintext)?.1.into(),
+        };
+
+        if enc_tgs_rep_part.nonce != nonce {
+            return Err(Error::NonceMismatch(nonce, enc_tgs_rep_part.nonce));
+        }
+
+        return Ok(Credential::new(
+            tgs_rep.crealm,
+            tgs_rep.cname,
+            tgs_rep.ticket,
+            enc_tgs_rep_part.into(),
+        ));
+    }
+
     fn decrypt_enc_kdc_rep_part_with_password(
         password: &str,
         kdc_rep: &AsRep,
+        hints: Option<&PreauthHints>,
     ) -> Result<Vec<u8>> {
-        let cipher = new_kerberos_cipher(kdc_rep.enc_part.etype)?;
-        return Ok(cipher.generate_key_from_string_and_decrypt(
-            password,
-            &Self::as_rep_encryption_salt(kdc_rep),
+        let etype = kdc_rep.enc_part.etype;
+        let cipher = new_kerberos_cipher(etype)?;
+
+        let hint = PreauthHints::from_padata(
+            kdc_rep.padata.as_deref().unwrap_or_default(),
+        )
+        .etype_hint(etype)
+        .or_else(|| hints.and_then(|hints| hints.etype_hint(etype)));
+
+        let (salt, s2kparams) = match hint {
+            Some(hint) => (hint.salt, hint.s2kparams),
+            None => (None, Vec::new()),
+        };
+        let salt = salt.unwrap_or_else(|| {
+            cipher.generate_salt(
+                &kdc_rep.crealm,
+                &kdc_rep.cname.name_string.concat(),
+            )
+        });
+
+        let key = cipher.generate_key_from_string_with_params(
+            password, &salt, &s2kparams,
+        )?;
+        return Ok(cipher.decrypt(
+            &key,
             KEY_USAGE_AS_REP_ENC_PART,
             &kdc_rep.enc_part.cipher,
         )?);
     }
 
-    fn as_rep_encryption_salt(as_rep: &AsRep) -> Vec<u8> {
-        if let Some(padata) = &as_rep.padata {
-            for entry_data in padata.iter() {
-                if entry_data.padata_type == PA_ETYPE_INFO2 {
-                    let etype_info =
-                        EtypeInfo2::parse(&entry_data.padata_value).unwrap().1;
-
-                    for info2_entry in etype_info.iter() {
-                        if let Some(salt) = &info2_entry.salt {
-                            return salt.as_bytes().to_vec();
-                        }
-                    }
-                }
-            }
-        }
-
-        return Vec::new();
-    }
-
     fn decrypt_enc_kdc_rep_part_with_cipher_key(
         key: &Key,
         kdc_rep: &AsRep,
@@ -323,7 +387,32 @@ mod test {
             credential,
             CredentialKrbInfoMapper::kdc_rep_to_credential(
                 &Key::Secret("Minnie1234".to_string()),
-                as_rep
+                as_rep,
+                None
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_and_decrypt_enc_part_aes256_with_password_and_kdc_hints() {
+        let mut as_rep = create_as_rep_aes256_to_decrypt();
+        let padata = as_rep.padata.take().unwrap();
+        let credential = create_credential_to_check_decryption();
+
+        assert!(CredentialKrbInfoMapper::kdc_rep_to_credential(
+            &Key::Secret("Minnie1234".to_string()),
+            as_rep.clone(),
+            None
+        )
+        .is_err());
+
+        assert_eq!(
+            credential,
+            CredentialKrbInfoMapper::kdc_rep_to_credential(
+                &Key::Secret("Minnie1234".to_string()),
+                as_rep,
+                Some(&PreauthHints::from_padata(&padata))
             )
             .unwrap()
         );
@@ -343,7 +432,8 @@ mod test {
                     0x9f, 0xa3, 0xd8, 0xb2, 0xe1, 0x46, 0x16, 0xaa, 0xca, 0xb5,
                     0x49, 0xfd
                 ]),
-                as_rep
+                as_rep,
+                None
             )
             .unwrap()
         );
@@ -364,7 +454,8 @@ mod test {
                     0x61, 0x7f, 0x72, 0xfd, 0xbc, 0x85, 0x1c, 0x45, 0x9a, 0x1c,
                     0x39, 0xbf, 0x83, 0x23, 0x56, 0x09
                 ]),
-                as_rep
+                as_rep,
+                None
             )
             .unwrap()
         );
@@ -1,9 +1,13 @@
 use super::super::*;
 use super::*;
+use crate::{Error, Result};
+use chrono::{Timelike, Utc};
 use himmelblau_kerberos_asn1::{
-    Asn1Object, EncKrbCredPart, EncryptedData, KrbCred,
+    Asn1Object, EncKrbCredPart, EncryptedData, EncryptionKey, KrbCred, Ticket,
 };
 use himmelblau_kerberos_constants::etypes::NO_ENCRYPTION;
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_KRB_CRED_ENC_PART;
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
 
 pub struct CredentialWarehouseKrbCredMapper {}
 
@@ -11,6 +15,51 @@ impl CredentialWarehouseKrbCredMapper {
     pub fn credential_warehouse_to_krb_cred(
         warehouse: &CredentialWarehouse,
     ) -> KrbCred {
+        let (seq_of_tickets, enc_krb_cred_part) =
+            Self::credential_warehouse_to_tickets_and_enc_part(warehouse);
+
+        return KrbCred::new(
+            seq_of_tickets,
+            EncryptedData {
+                etype: NO_ENCRYPTION,
+                kvno: None,
+                cipher: enc_krb_cred_part.build(),
+            },
+        );
+    }
+
+    /// Produces a KRB-CRED whose encrypted part is protected with the
+    /// given key, usually the session key or subkey of an AP-REQ, in
+    /// order to delegate the credentials to a service.
+    pub fn credential_warehouse_to_encrypted_krb_cred(
+        warehouse: &CredentialWarehouse,
+        key: &EncryptionKey,
+    ) -> Result<KrbCred> {
+        let (seq_of_tickets, mut enc_krb_cred_part) =
+            Self::credential_warehouse_to_tickets_and_enc_part(warehouse);
+
+        let now = Utc::now();
+        enc_krb_cred_part.timestamp = Some(now.into());
+        enc_krb_cred_part.usec = Some((now.nanosecond() / 1000) as i32);
+
+        let cipher = new_kerberos_cipher(key.keytype)?;
+        return Ok(KrbCred::new(
+            seq_of_tickets,
+            EncryptedData {
+                etype: key.keytype,
+                kvno: None,
+                cipher: cipher.encrypt(
+                    &key.keyvalue,
+                    KEY_USAGE_KRB_CRED_ENC_PART,
+                    &enc_krb_cred_part.build(),
+                ),
+            },
+        ));
+    }
+
+    fn credential_warehouse_to_tickets_and_enc_part(
+        warehouse: &CredentialWarehouse,
+    ) -> (Vec<Ticket>, EncKrbCredPart) {
         let credentials = warehouse.credentials();
         let mut seq_of_tickets = Vec::new();
         let mut seq_of_krb_cred_info = Vec::new();
@@ -33,14 +82,58 @@ impl CredentialWarehouseKrbCredMapper {
             r_address: None,
         };
 
-        return KrbCred::new(
-            seq_of_tickets,
-            EncryptedData {
-                etype: NO_ENCRYPTION,
-                kvno: None,
-                cipher: enc_krb_cred_part.build(),
-            },
-        );
+        return (seq_of_tickets, enc_krb_cred_part);
+    }
+
+    /// Extracts the credentials of a KRB-CRED. The key is only required
+    /// in case the encrypted part is actually encrypted, and when it is
+    /// given, the encrypted part must be encrypted, so the credentials
+    /// cannot be replaced by unprotected ones.
+    pub fn krb_cred_to_credential_warehouse(
+        krb_cred: KrbCred,
+        key: Option<&EncryptionKey>,
+    ) -> Result<CredentialWarehouse> {
+        let raw_enc_part = match (krb_cred.enc_part.etype, key) {
+            (NO_ENCRYPTION, None) => krb_cred.enc_part.cipher,
+            (NO_ENCRYPTION, Some(_)) => return Err(Error::UnencryptedKrbCred),
+            (_, Some(key)) => new_kerberos_cipher(key.keytype)?.decrypt(
+                &key.keyvalue,
+                KEY_USAGE_KRB_CRED_ENC_PART,
+                &krb_cred.enc_part.cipher,
+            )?,
+            (_, None) => return Err(Error::NoKeyProvided),
+        };
+
+        let enc_krb_cred_part = EncKrbCredPart::parse(&raw_enc_part)?.1;
+
+        if enc_krb_cred_part.ticket_info.len() != krb_cred.tickets.len() {
+            return Err(Error::NotAvailableData(
+                "KrbCredInfo for each ticket".to_string(),
+            ));
+        }
+
+        let mut credentials = Vec::with_capacity(krb_cred.tickets.len());
+        for (krb_cred_info, ticket) in enc_krb_cred_part
+            .ticket_info
+            .into_iter()
+            .zip(krb_cred.tickets)
+        {
+            credentials.push(
+                CredentialKrbInfoMapper::krb_info_and_ticket_to_credential(
+                    krb_cred_info,
+                    ticket,
+                )?,
+            );
+        }
+
+        let first_credential =
+            credentials.first().ok_or(Error::NoPrincipalName)?;
+
+        return Ok(CredentialWarehouse::new(
+            first_credential.crealm().clone(),
+            first_credential.cname().clone(),
+            credentials,
+        ));
     }
 }
 
@@ -162,6 +255,76 @@ mod test {
         );
     }
 
+    #[test]
+    fn encrypted_krb_cred_to_credential_warehouse() {
+        let realm = Realm::from("KINGDOM.HEARTS");
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
+        sname.push(realm.clone());
+        let pname =
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey"));
+
+        let credential = Credential::new(
+            realm.clone(),
+            pname.clone(),
+            create_ticket(realm.clone(), sname.clone()),
+            EncAsRepPart {
+                key: EncryptionKey::new(RC4_HMAC, vec![3; 16]),
+                flags: TicketFlags::from(ticket_flags::FORWARDED),
+                authtime: Utc
+                    .with_ymd_and_hms(2019, 4, 18, 6, 0, 31)
+                    .unwrap()
+                    .into(),
+                starttime: Some(
+                    Utc.with_ymd_and_hms(2019, 4, 18, 6, 0, 31).unwrap().into(),
+                ),
+                endtime: Utc
+                    .with_ymd_and_hms(2019, 4, 18, 16, 0, 31)
+                    .unwrap()
+                    .into(),
+                srealm: realm,
+                sname,
+                ..Default::default()
+            },
+        );
+        let warehouse = CredentialWarehouse::from(credential);
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![5; 32]);
+
+        let krb_cred =
+            CredentialWarehouseKrbCredMapper::credential_warehouse_to_encrypted_krb_cred(
+                &warehouse, &key,
+            )
+            .unwrap();
+        assert_eq!(AES256_CTS_HMAC_SHA1_96, krb_cred.enc_part.etype);
+
+        assert_eq!(
+            warehouse,
+            CredentialWarehouseKrbCredMapper::krb_cred_to_credential_warehouse(
+                krb_cred.clone(),
+                Some(&key),
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Err(crate::Error::NoKeyProvided),
+            CredentialWarehouseKrbCredMapper::krb_cred_to_credential_warehouse(
+                krb_cred, None,
+            )
+        );
+
+        let unencrypted_krb_cred =
+            CredentialWarehouseKrbCredMapper::credential_warehouse_to_krb_cred(
+                &warehouse,
+            );
+        assert_eq!(
+            Err(crate::Error::UnencryptedKrbCred),
+            CredentialWarehouseKrbCredMapper::krb_cred_to_credential_warehouse(
+                unencrypted_krb_cred,
+                Some(&key),
+            )
+        );
+    }
+
     fn create_krb_cred_info(
         encryption_key: EncryptionKey,
         prealm: Realm,
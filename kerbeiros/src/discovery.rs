@@ -0,0 +1,815 @@
+//! Discovery of the KDCs and realms through DNS (RFC 4120 section 7.2.3).
+//!
+//! The DNS queries are performed through the [DnsResolver] trait, so a
+//! custom source of records can be provided instead of the system resolver.
+
+use crate::config::Krb5Config;
+use crate::transporter::{TransportProtocol, DEFAULT_KERBEROS_PORT};
+use crate::{Error, Result};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::Resolver as HickoryResolver;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+
+/// Default port of the kpasswd service
+pub const DEFAULT_KPASSWD_PORT: u16 = 464;
+
+/// SRV record, as defined in RFC 2782
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+impl SrvRecord {
+    pub fn new(priority: u16, weight: u16, port: u16, target: &str) -> Self {
+        return Self {
+            priority,
+            weight,
+            port,
+            target: target.to_string(),
+        };
+    }
+}
+
+/// Source of DNS records used to discover the KDCs
+pub trait DnsResolver: Send + Sync {
+    /// Retrieves the SRV records of the name. No records is not an error.
+    fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>>;
+
+    /// Retrieves the TXT records of the name. No records is not an error.
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>>;
+
+    /// Retrieves the A and AAAA records of the host.
+    fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>>;
+}
+
+/// Resolver of the system DNS configuration, shared by all the
+/// [SystemDnsResolver] instances. It is kept for the whole process since it
+/// owns a tokio runtime, which cannot be dropped from an async context.
+static SYSTEM_RESOLVER: OnceLock<HickoryResolver> = OnceLock::new();
+
+/// Resolver that uses the DNS configuration of the system
+pub struct SystemDnsResolver {}
+
+impl SystemDnsResolver {
+    pub fn new() -> Self {
+        return Self {};
+    }
+
+    /// Retrieves the resolver of the system configuration, which is
+    /// created on the first lookup. In case the configuration cannot be
+    /// read, it is retried on the next lookup.
+    fn hickory_resolver(name: &str) -> Result<&'static HickoryResolver> {
+        if let Some(resolver) = SYSTEM_RESOLVER.get() {
+            return Ok(resolver);
+        }
+        let resolver = HickoryResolver::from_system_conf()
+            .map_err(|_| Error::NameResolutionError(name.to_string()))?;
+        return Ok(SYSTEM_RESOLVER.get_or_init(|| resolver));
+    }
+}
+
+/// Maps the errors of a lookup, where no records is not an error.
+fn no_records_or_error<T>(error: ResolveError, name: &str) -> Result<Vec<T>> {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { .. } => {
+            return Ok(Vec::new());
+        }
+        _ => {
+            return Err(Error::NameResolutionError(name.to_string()));
+        }
+    }
+}
+
+impl Default for SystemDnsResolver {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl DnsResolver for SystemDnsResolver {
+    fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>> {
+        let resolver = Self::hickory_resolver(name)?;
+        match resolver.srv_lookup(name) {
+            Ok(srv_lookup) => {
+                return Ok(srv_lookup
+                    .iter()
+                    .map(|srv| SrvRecord {
+                        priority: srv.priority(),
+                        weight: srv.weight(),
+                        port: srv.port(),
+                        target: srv.target().to_utf8(),
+                    })
+                    .collect());
+            }
+            Err(error) => {
+                return no_records_or_error(error, name);
+            }
+        }
+    }
+
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>> {
+        let resolver = Self::hickory_resolver(name)?;
+        match resolver.txt_lookup(name) {
+            Ok(txt_lookup) => {
+                return Ok(txt_lookup
+                    .iter()
+                    .map(|txt| {
+                        txt.iter()
+                            .map(|data| String::from_utf8_lossy(data))
+                            .collect()
+                    })
+                    .collect());
+            }
+            Err(error) => {
+                return no_records_or_error(error, name);
+            }
+        }
+    }
+
+    fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>> {
+        return dns_lookup::lookup_host(host)
+            .map_err(|_| Error::NameResolutionError(host.to_string()));
+    }
+}
+
+/// Address of a KDC, given by a host name or IP address and a port
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KdcEndpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl KdcEndpoint {
+    pub fn new(host: &str, port: u16) -> Self {
+        return Self {
+            host: host.to_string(),
+            port,
+        };
+    }
+
+    /// Parses an address in the form `host[:port]`, where IPv6 addresses
+    /// with port must be enclosed in brackets, like `[fe80::1]:88`. The
+    /// port 88 is used by default.
+    pub fn parse(address: &str) -> Result<Self> {
+        return Self::parse_with_default_port(address, DEFAULT_KERBEROS_PORT);
+    }
+
+    /// Parses an address in the form `host[:port]`, like
+    /// [parse](Self::parse), but using the given port by default.
+    pub fn parse_with_default_port(
+        address: &str,
+        default_port: u16,
+    ) -> Result<Self> {
+        let (host, port) = split_host_port(address, default_port)
+            .ok_or_else(|| Error::NameResolutionError(address.to_string()))?;
+        if host.is_empty() {
+            return Err(Error::NameResolutionError(address.to_string()));
+        }
+        return Ok(Self::new(host, port));
+    }
+
+    /// Resolves the socket addresses of the endpoint. IP addresses are not
+    /// queried to the resolver.
+    pub fn resolve(
+        &self,
+        resolver: &dyn DnsResolver,
+    ) -> Result<Vec<SocketAddr>> {
+        if let Ok(ip) = self.host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, self.port)]);
+        }
+
+        return Ok(resolver
+            .lookup_ip(&self.host)?
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, self.port))
+            .collect());
+    }
+}
+
+impl From<IpAddr> for KdcEndpoint {
+    fn from(ip: IpAddr) -> Self {
+        return Self::new(&ip.to_string(), DEFAULT_KERBEROS_PORT);
+    }
+}
+
+impl From<SocketAddr> for KdcEndpoint {
+    fn from(address: SocketAddr) -> Self {
+        return Self::new(&address.ip().to_string(), address.port());
+    }
+}
+
+impl fmt::Display for KdcEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            return write!(f, "[{}]:{}", self.host, self.port);
+        }
+        return write!(f, "{}:{}", self.host, self.port);
+    }
+}
+
+/// Locates the KDCs of the realms, among the ones configured for each realm
+/// or, if there are none, through DNS.
+#[derive(Clone)]
+pub struct KdcLocator {
+    resolver: Arc<dyn DnsResolver>,
+    realm_kdcs: HashMap<String, Vec<KdcEndpoint>>,
+    dns_lookup_kdc: bool,
+}
+
+impl KdcLocator {
+    pub fn new() -> Self {
+        return Self {
+            resolver: Arc::new(SystemDnsResolver::new()),
+            realm_kdcs: HashMap::new(),
+            dns_lookup_kdc: true,
+        };
+    }
+
+    /// Creates a locator with the KDCs of the `[realms]` section and the
+    /// dns_lookup_kdc setting of the configuration.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        let mut locator = Self::new();
+        locator.set_dns_lookup_kdc(config.dns_lookup_kdc());
+        for realm in config.realms() {
+            let kdcs = config
+                .kdcs(realm)
+                .into_iter()
+                .map(|kdc| {
+                    KdcEndpoint::parse(kdc).map_err(|_| {
+                        Error::ConfigError(format!(
+                            "invalid kdc of {}: {}",
+                            realm, kdc
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<KdcEndpoint>>>()?;
+            if !kdcs.is_empty() {
+                locator.set_realm_kdcs(realm, kdcs);
+            }
+        }
+        return Ok(locator);
+    }
+
+    /// Sets the KDCs of the realm, which are tried in order.
+    pub fn set_realm_kdcs(&mut self, realm: &str, kdcs: Vec<KdcEndpoint>) {
+        self.realm_kdcs.insert(realm.to_uppercase(), kdcs);
+    }
+
+    pub fn realm_kdcs(&self, realm: &str) -> Option<&Vec<KdcEndpoint>> {
+        return self.realm_kdcs.get(&realm.to_uppercase());
+    }
+
+    pub fn dns_lookup_kdc(&self) -> bool {
+        return self.dns_lookup_kdc;
+    }
+
+    /// Sets whether the KDCs of the realms without configured KDCs are
+    /// discovered through DNS.
+    pub fn set_dns_lookup_kdc(&mut self, dns_lookup_kdc: bool) {
+        self.dns_lookup_kdc = dns_lookup_kdc;
+    }
+
+    pub fn set_resolver(&mut self, resolver: Box<dyn DnsResolver>) {
+        self.resolver = Arc::from(resolver);
+    }
+
+    /// Retrieves the resolver, to resolve the host names of the KDCs.
+    pub fn resolver(&self) -> Arc<dyn DnsResolver> {
+        return self.resolver.clone();
+    }
+
+    /// Retrieves the KDCs of the realm, the configured ones or, if there
+    /// are none, the ones discovered through DNS.
+    pub fn kdc_endpoints(
+        &self,
+        realm: &str,
+        transport_protocol: TransportProtocol,
+    ) -> Result<Vec<KdcEndpoint>> {
+        if let Some(kdcs) = self.realm_kdcs(realm) {
+            return Ok(kdcs.clone());
+        }
+
+        // The KDC proxy locates the KDCs by itself
+        if transport_protocol == TransportProtocol::KdcProxy {
+            return Ok(Vec::new());
+        }
+
+        if self.dns_lookup_kdc {
+            return Ok(discover_kdcs(
+                &*self.resolver,
+                realm,
+                transport_protocol,
+            )?
+            .into_iter()
+            .map(KdcEndpoint::from)
+            .collect());
+        }
+
+        return Err(Error::NameResolutionError(realm.to_string()));
+    }
+
+    /// Retrieves the KDCs of the realm like
+    /// [kdc_endpoints](Self::kdc_endpoints), but sending the DNS queries
+    /// from a blocking task, since the resolvers are synchronous.
+    #[cfg(feature = "async")]
+    pub async fn async_kdc_endpoints(
+        &self,
+        realm: &str,
+        transport_protocol: TransportProtocol,
+    ) -> Result<Vec<KdcEndpoint>> {
+        if let Some(kdcs) = self.realm_kdcs(realm) {
+            return Ok(kdcs.clone());
+        }
+
+        let locator = self.clone();
+        let realm = realm.to_string();
+        return tokio::task::spawn_blocking(move || {
+            return locator.kdc_endpoints(&realm, transport_protocol);
+        })
+        .await
+        .map_err(|_| Error::NetworkError)?;
+    }
+
+    /// Retrieves the addresses of the KDCs of the realm. The configured
+    /// host names that cannot be resolved are skipped.
+    pub fn locate_kdcs(
+        &self,
+        realm: &str,
+        transport_protocol: TransportProtocol,
+    ) -> Result<Vec<SocketAddr>> {
+        let mut addresses = Vec::new();
+        for kdc in self.kdc_endpoints(realm, transport_protocol)? {
+            if let Ok(kdc_addresses) = kdc.resolve(&*self.resolver) {
+                addresses.extend(kdc_addresses);
+            }
+        }
+
+        if addresses.is_empty() {
+            return Err(Error::NameResolutionError(realm.to_string()));
+        }
+        return Ok(addresses);
+    }
+}
+
+impl Default for KdcLocator {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Splits a server address in the form `host[:port]`, where IPv6 hosts
+/// with port must be enclosed in brackets. A transport prefix like
+/// `tcp/` is ignored.
+fn split_host_port(address: &str, default_port: u16) -> Option<(&str, u16)> {
+    let address = address.trim();
+    let address = address
+        .strip_prefix("tcp/")
+        .or_else(|| address.strip_prefix("udp/"))
+        .unwrap_or(address);
+
+    if let Some(bracketed) = address.strip_prefix('[') {
+        let (host, rest) = bracketed.split_once(']')?;
+        if rest.is_empty() {
+            return Some((host, default_port));
+        }
+        return Some((host, rest.strip_prefix(':')?.parse().ok()?));
+    }
+
+    match address.split_once(':') {
+        Some((host, port)) if !port.contains(':') => {
+            return Some((host, port.parse().ok()?));
+        }
+        _ => {
+            return Some((address, default_port));
+        }
+    }
+}
+
+/// Discovers the KDCs of the realm through the `_kerberos._tcp` or
+/// `_kerberos._udp` SRV records, ordered by priority and weight. In case
+/// there are no SRV records, the realm name is resolved as a host.
+pub fn discover_kdcs(
+    resolver: &dyn DnsResolver,
+    realm: &str,
+    transport_protocol: TransportProtocol,
+) -> Result<Vec<SocketAddr>> {
+    return discover_service(
+        resolver,
+        "_kerberos",
+        realm,
+        transport_protocol,
+        DEFAULT_KERBEROS_PORT,
+    );
+}
+
+/// Discovers the kpasswd servers of the realm through the `_kpasswd._tcp`
+/// or `_kpasswd._udp` SRV records. In case there are no SRV records, the
+/// realm name is resolved as a host.
+pub fn discover_kpasswd_servers(
+    resolver: &dyn DnsResolver,
+    realm: &str,
+    transport_protocol: TransportProtocol,
+) -> Result<Vec<SocketAddr>> {
+    return discover_service(
+        resolver,
+        "_kpasswd",
+        realm,
+        transport_protocol,
+        DEFAULT_KPASSWD_PORT,
+    );
+}
+
+/// Discovers the realm of a host through the `_kerberos` TXT records of
+/// the host and its parent domains.
+pub fn discover_realm(
+    resolver: &dyn DnsResolver,
+    hostname: &str,
+) -> Result<Option<String>> {
+    let labels: Vec<&str> = hostname
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|label| !label.is_empty())
+        .collect();
+
+    // The top level domain is not queried
+    for i in 0..labels.len().saturating_sub(1) {
+        let name = format!("_kerberos.{}.", labels[i..].join("."));
+        if let Some(realm) = resolver.lookup_txt(&name)?.into_iter().next() {
+            return Ok(Some(realm));
+        }
+    }
+
+    return Ok(None);
+}
+
+fn discover_service(
+    resolver: &dyn DnsResolver,
+    service: &str,
+    realm: &str,
+    transport_protocol: TransportProtocol,
+    default_port: u16,
+) -> Result<Vec<SocketAddr>> {
+    let protocol = match transport_protocol {
+        TransportProtocol::TCP | TransportProtocol::KdcProxy => "_tcp",
+        TransportProtocol::UDP => "_udp",
+    };
+    let name = format!("{}.{}.{}.", service, protocol, realm);
+    let records = resolver.lookup_srv(&name)?;
+
+    // A single record with target "." means the service is not available
+    if records.len() == 1 && records[0].target.trim_end_matches('.').is_empty()
+    {
+        return Err(Error::NameResolutionError(realm.to_string()));
+    }
+
+    let mut addresses = Vec::new();
+
+    if records.is_empty() {
+        for ip in resolver.lookup_ip(realm)? {
+            addresses.push(SocketAddr::new(ip, default_port));
+        }
+    } else {
+        for record in sort_srv_records(records, &mut rand::thread_rng()) {
+            let target = record.target.trim_end_matches('.');
+            if let Ok(ips) = resolver.lookup_ip(target) {
+                for ip in ips {
+                    addresses.push(SocketAddr::new(ip, record.port));
+                }
+            }
+        }
+    }
+
+    if addresses.is_empty() {
+        return Err(Error::NameResolutionError(realm.to_string()));
+    }
+
+    return Ok(addresses);
+}
+
+/// Sorts the records by priority and, within the same priority, selects
+/// them randomly in proportion to their weight (RFC 2782).
+fn sort_srv_records<R: Rng>(
+    mut records: Vec<SrvRecord>,
+    rng: &mut R,
+) -> Vec<SrvRecord> {
+    // records with zero weight go first, so they are unlikely selected
+    records.sort_by_key(|record| (record.priority, record.weight != 0));
+
+    let mut sorted_records = Vec::with_capacity(records.len());
+    while !records.is_empty() {
+        let priority = records[0].priority;
+        let group_len = records
+            .iter()
+            .take_while(|record| record.priority == priority)
+            .count();
+
+        let total_weight: u32 = records[..group_len]
+            .iter()
+            .map(|record| record.weight as u32)
+            .sum();
+        let selected_weight = rng.gen_range(0..=total_weight);
+
+        let mut running_weight = 0;
+        let mut selected = group_len - 1;
+        for (i, record) in records[..group_len].iter().enumerate() {
+            running_weight += record.weight as u32;
+            if running_weight >= selected_weight {
+                selected = i;
+                break;
+            }
+        }
+
+        sorted_records.push(records.remove(selected));
+    }
+
+    return sorted_records;
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use hickory_resolver::proto::op::{Query, ResponseCode};
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    /// Resolver that retrieves the records from memory
+    #[derive(Default)]
+    pub struct FakeDnsResolver {
+        pub srv_records: HashMap<String, Vec<SrvRecord>>,
+        pub txt_records: HashMap<String, Vec<String>>,
+        pub ip_records: HashMap<String, Vec<IpAddr>>,
+    }
+
+    impl DnsResolver for FakeDnsResolver {
+        fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>> {
+            return Ok(self.srv_records.get(name).cloned().unwrap_or_default());
+        }
+
+        fn lookup_txt(&self, name: &str) -> Result<Vec<String>> {
+            return Ok(self.txt_records.get(name).cloned().unwrap_or_default());
+        }
+
+        fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>> {
+            return self
+                .ip_records
+                .get(host)
+                .cloned()
+                .ok_or_else(|| Error::NameResolutionError(host.to_string()));
+        }
+    }
+
+    fn ip(last: u8) -> IpAddr {
+        return IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+    }
+
+    fn new_fake_resolver() -> FakeDnsResolver {
+        let mut resolver = FakeDnsResolver::default();
+        resolver.srv_records.insert(
+            "_kerberos._tcp.KINGDOM.HEARTS.".to_string(),
+            vec![
+                SrvRecord::new(10, 0, 88, "kdc2.kingdom.hearts."),
+                SrvRecord::new(0, 100, 8888, "kdc1.kingdom.hearts."),
+            ],
+        );
+        resolver.srv_records.insert(
+            "_kpasswd._udp.KINGDOM.HEARTS.".to_string(),
+            vec![SrvRecord::new(0, 0, 464, "kdc1.kingdom.hearts.")],
+        );
+        resolver
+            .ip_records
+            .insert("kdc1.kingdom.hearts".to_string(), vec![ip(1)]);
+        resolver
+            .ip_records
+            .insert("kdc2.kingdom.hearts".to_string(), vec![ip(2)]);
+        resolver
+            .ip_records
+            .insert("DISNEY.COM".to_string(), vec![ip(3)]);
+        return resolver;
+    }
+
+    #[test]
+    fn discover_kdcs_by_srv_priority() {
+        let resolver = new_fake_resolver();
+
+        assert_eq!(
+            vec![SocketAddr::new(ip(1), 8888), SocketAddr::new(ip(2), 88)],
+            discover_kdcs(&resolver, "KINGDOM.HEARTS", TransportProtocol::TCP)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn discover_kdcs_by_realm_host_without_srv_records() {
+        let resolver = new_fake_resolver();
+
+        assert_eq!(
+            vec![SocketAddr::new(ip(3), DEFAULT_KERBEROS_PORT)],
+            discover_kdcs(&resolver, "DISNEY.COM", TransportProtocol::UDP)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn error_discovering_unavailable_service() {
+        let mut resolver = new_fake_resolver();
+        resolver.srv_records.insert(
+            "_kerberos._udp.KINGDOM.HEARTS.".to_string(),
+            vec![SrvRecord::new(0, 0, 0, ".")],
+        );
+
+        assert_eq!(
+            Error::NameResolutionError("KINGDOM.HEARTS".to_string()),
+            discover_kdcs(&resolver, "KINGDOM.HEARTS", TransportProtocol::UDP)
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn discover_kpasswd_servers_by_srv() {
+        let resolver = new_fake_resolver();
+
+        assert_eq!(
+            vec![SocketAddr::new(ip(1), 464)],
+            discover_kpasswd_servers(
+                &resolver,
+                "KINGDOM.HEARTS",
+                TransportProtocol::UDP
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn discover_realm_by_txt_of_parent_domain() {
+        let mut resolver = new_fake_resolver();
+        resolver.txt_records.insert(
+            "_kerberos.kingdom.hearts.".to_string(),
+            vec!["KINGDOM.HEARTS".to_string()],
+        );
+
+        assert_eq!(
+            Some("KINGDOM.HEARTS".to_string()),
+            discover_realm(&resolver, "castle.disney.kingdom.hearts").unwrap()
+        );
+        assert_eq!(None, discover_realm(&resolver, "disney.com").unwrap());
+    }
+
+    #[test]
+    fn map_lookup_errors_of_system_resolver() {
+        let no_records = ResolveError::from(ResolveErrorKind::NoRecordsFound {
+            query: Box::new(Query::new()),
+            soa: None,
+            negative_ttl: None,
+            response_code: ResponseCode::NXDomain,
+            trusted: true,
+        });
+        assert_eq!(
+            Vec::<String>::new(),
+            no_records_or_error::<String>(
+                no_records,
+                "_kerberos.kingdom.hearts."
+            )
+            .unwrap()
+        );
+
+        let timeout = ResolveError::from(ResolveErrorKind::Timeout);
+        assert_eq!(
+            Error::NameResolutionError("_kerberos.kingdom.hearts.".to_string()),
+            no_records_or_error::<String>(timeout, "_kerberos.kingdom.hearts.")
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn locate_configured_kdcs() {
+        let mut locator = KdcLocator::new();
+        locator.set_resolver(Box::new(new_fake_resolver()));
+        locator.set_realm_kdcs(
+            "kingdom.hearts",
+            vec![
+                KdcEndpoint::parse("kdc2.kingdom.hearts:750").unwrap(),
+                KdcEndpoint::parse("unknown.kingdom.hearts").unwrap(),
+                KdcEndpoint::parse("tcp/[fe80::1]:8888").unwrap(),
+                KdcEndpoint::from(ip(4)),
+            ],
+        );
+
+        assert_eq!(
+            vec![
+                SocketAddr::new(ip(2), 750),
+                SocketAddr::new("fe80::1".parse().unwrap(), 8888),
+                SocketAddr::new(ip(4), DEFAULT_KERBEROS_PORT),
+            ],
+            locator
+                .locate_kdcs("KINGDOM.HEARTS", TransportProtocol::TCP)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn locate_kdcs_through_dns_unless_disabled() {
+        let mut locator = KdcLocator::new();
+        locator.set_resolver(Box::new(new_fake_resolver()));
+
+        assert_eq!(
+            vec![KdcEndpoint::new("10.0.0.3", DEFAULT_KERBEROS_PORT)],
+            locator
+                .kdc_endpoints("DISNEY.COM", TransportProtocol::UDP)
+                .unwrap()
+        );
+
+        locator.set_dns_lookup_kdc(false);
+        assert_eq!(
+            Error::NameResolutionError("DISNEY.COM".to_string()),
+            locator
+                .locate_kdcs("DISNEY.COM", TransportProtocol::UDP)
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn create_locator_from_krb5_config() {
+        let config = Krb5Config::parse(
+            "[realms]\nKINGDOM.HEARTS = {\nkdc = kdc1\nkdc = [::1]:750\n}\n",
+        )
+        .unwrap();
+
+        let locator = KdcLocator::from_krb5_config(&config).unwrap();
+        assert_eq!(
+            Some(&vec![
+                KdcEndpoint::new("kdc1", 88),
+                KdcEndpoint::new("::1", 750)
+            ]),
+            locator.realm_kdcs("KINGDOM.HEARTS")
+        );
+
+        let config = Krb5Config::parse(
+            "[realms]\nKINGDOM.HEARTS = {\nkdc = kdc1:port\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Error::ConfigError(
+                "invalid kdc of KINGDOM.HEARTS: kdc1:port".to_string()
+            ),
+            KdcLocator::from_krb5_config(&config).err().unwrap()
+        );
+    }
+
+    #[test]
+    fn display_kdc_endpoints() {
+        assert_eq!("kdc1:88", KdcEndpoint::new("kdc1", 88).to_string());
+        assert_eq!(
+            "[fe80::1]:750",
+            KdcEndpoint::parse("[fe80::1]:750").unwrap().to_string()
+        );
+        assert!(KdcEndpoint::parse(":88").is_err());
+        assert_eq!(
+            KdcEndpoint::new("kdc1", 464),
+            KdcEndpoint::parse_with_default_port("kdc1", 464).unwrap()
+        );
+    }
+
+    #[test]
+    fn split_server_addresses() {
+        assert_eq!(Some(("kdc", 88)), split_host_port("kdc", 88));
+        assert_eq!(Some(("kdc", 750)), split_host_port("udp/kdc:750", 88));
+        assert_eq!(Some(("::1", 88)), split_host_port("::1", 88));
+        assert_eq!(Some(("::1", 88)), split_host_port("[::1]", 88));
+        assert_eq!(Some(("::1", 464)), split_host_port("[::1]:464", 88));
+        assert_eq!(None, split_host_port("kdc:port", 88));
+    }
+
+    #[test]
+    fn sort_srv_records_by_weight() {
+        let records = vec![
+            SrvRecord::new(1, 0, 88, "c"),
+            SrvRecord::new(0, 0, 88, "b"),
+            SrvRecord::new(0, 65535, 88, "a"),
+        ];
+
+        for _ in 0..10 {
+            let sorted =
+                sort_srv_records(records.clone(), &mut rand::thread_rng());
+            assert_eq!("c", sorted[2].target);
+        }
+
+        let records =
+            vec![SrvRecord::new(0, 0, 88, "b"), SrvRecord::new(0, 1, 88, "a")];
+        let mut a_first = 0;
+        for _ in 0..100 {
+            let sorted =
+                sort_srv_records(records.clone(), &mut rand::thread_rng());
+            if sorted[0].target == "a" {
+                a_first += 1;
+            }
+        }
+        assert!(a_first > 0);
+    }
+}
@@ -1,7 +1,11 @@
 //! Errors raised by this library
 
-use crate::messages::{AsRep, KrbError};
+use crate::gssapi::GssError;
+use crate::messages::{
+    AsRep, KpasswdResultCode, KrbError, PasswordPolicy, TgsRep,
+};
 use ascii::FromAsciiError;
+use himmelblau_kerberos_constants::error_codes::error_code_to_string;
 use std::result;
 use std::string::FromUtf8Error;
 use thiserror::Error;
@@ -58,10 +62,24 @@ pub enum Error {
     #[error("Network error")]
     NetworkError,
 
+    /// The KDC proxy failed to forward the message or returned an invalid
+    /// response.
+    #[error("KDC proxy error: {}", _0)]
+    KdcProxyError(String),
+
+    /// The response is bigger than the maximum size allowed.
+    #[error("Response bigger than {} bytes", _0)]
+    ResponseTooLarge(usize),
+
     /// No key was provided in order to decrypt the KDC response.
     #[error("No key was provided")]
     NoKeyProvided,
 
+    /// The KRB-CRED was not encrypted, although a key was provided to
+    /// decrypt it.
+    #[error("The KRB-CRED is not encrypted")]
+    UnencryptedKrbCred,
+
     /// None cipher algorithm supported was specified.
     #[error("None cipher algorithm supported was specified")]
     NoProvidedSupportedCipherAlgorithm,
@@ -74,6 +92,95 @@ pub enum Error {
     #[error("Error parsing AsRep: {}", _1)]
     ParseAsRepError(AsRep, Box<Error>),
 
+    /// Error parsing TGS-REP message.
+    #[error("Error parsing TgsRep: {}", _1)]
+    ParseTgsRepError(TgsRep, Box<Error>),
+
+    /// The nonce of the response doesn't match with the one of the request.
+    #[error("Nonce mismatch: expected {} but received {}", _0, _1)]
+    NonceMismatch(u32, u32),
+
+    /// The PA-S4U-X509-USER returned by the KDC doesn't match with the
+    /// one sent in the S4U2Self request.
+    #[error("Invalid PA-S4U-X509-USER in KDC response: {}", _0)]
+    InvalidS4uX509UserReply(String),
+
+    /// The ticket cannot be renewed since it lacks the renewable flag.
+    #[error("The ticket is not renewable")]
+    TicketNotRenewable,
+
+    /// The ticket cannot be renewed since its renew-till time has passed.
+    #[error("The ticket renewable lifetime has expired")]
+    TicketRenewTillExpired,
+
+    /// The ticket cannot be renewed since it has already expired.
+    #[error("The ticket has expired")]
+    TicketExpired,
+
+    /// The ticket cannot be validated since it is not marked as invalid.
+    #[error("The ticket is already valid")]
+    TicketAlreadyValid,
+
+    /// The ticket cannot be validated before its starttime.
+    #[error("The ticket is not yet valid")]
+    TicketNotYetValid,
+
+    /// A forwarded TGT cannot be requested since the TGT is not forwardable.
+    #[error("The ticket is not forwardable")]
+    TicketNotForwardable,
+
+    /// The service is not trusted for delegation, since its ticket lacks
+    /// the ok-as-delegate flag.
+    #[error("Delegation to {} is not allowed", _0)]
+    DelegationNotAllowed(String),
+
+    /// The kpasswd server rejected the password change or set.
+    #[error("kpasswd error {:?}: {}", _0, _1)]
+    KpasswdError(KpasswdResultCode, String),
+
+    /// The new password does not satisfy the password policy.
+    #[error("The password does not satisfy the policy {:?}", _0)]
+    PasswordPolicyError(PasswordPolicy),
+
+    /// The AP-REP does not correspond to the authenticator sent in the
+    /// AP-REQ.
+    #[error("Mutual authentication failed")]
+    MutualAuthenticationFailed,
+
+    /// The AP-REQ was rejected by the acceptor, with the given
+    /// KRB_AP_ERR code.
+    #[error("AP-REQ rejected: {}", error_code_to_string(*_0))]
+    ApReqRejected(i32),
+
+    /// A KRB-SAFE or KRB-PRIV message was rejected, with the given
+    /// KRB_AP_ERR code.
+    #[error("Message rejected: {}", error_code_to_string(*_0))]
+    MessageRejected(i32),
+
+    /// Error of the GSS-API mechanism.
+    #[error("GSS-API error: {:?}", _0)]
+    GssapiError(GssError),
+
+    /// The SASL GSSAPI exchange failed.
+    #[error("SASL error: {}", _0)]
+    SaslError(String),
+
+    /// The signature of the PAC does not match its content.
+    #[error("Invalid PAC signature")]
+    InvalidPacSignature,
+
+    /// A referral pointed to a realm that was already visited.
+    #[error("Referral loop detected in realm {}", _0)]
+    ReferralLoop(String),
+
+    /// The number of referrals exceeds the maximum allowed.
+    #[error("Too many referrals, the maximum is {}", _0)]
+    TooManyReferrals(usize),
+
+    /// Error reading or parsing the Kerberos configuration.
+    #[error("Configuration error: {}", _0)]
+    ConfigError(String),
+
     /// The type of the principal name was not specified.
     #[error("Undefined type of principal name: {}", _0)]
     PrincipalNameTypeUndefined(String),
@@ -97,6 +204,12 @@ impl From<himmelblau_kerberos_crypto::Error> for Error {
     }
 }
 
+impl From<GssError> for Error {
+    fn from(error: GssError) -> Self {
+        return Self::GssapiError(error);
+    }
+}
+
 impl From<FromAsciiError<&str>> for Error {
     fn from(_error: FromAsciiError<&str>) -> Self {
         return Self::InvalidAscii;
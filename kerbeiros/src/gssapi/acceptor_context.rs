@@ -0,0 +1,339 @@
+use super::framing::{decode_krb5_token, encode_krb5_token};
+use super::{ChannelBindings, GssChecksum, GssError, SecurityContext};
+use crate::ap::{Acceptor, ApReqInfo};
+use crate::credentials::CredentialWarehouse;
+use crate::error::*;
+use himmelblau_kerberos_asn1::{ApReq, Asn1Object, EncryptionKey, KrbCred};
+use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+use himmelblau_kerberos_constants::gssapi::{
+    GSS_CHECKSUM_TYPE, GSS_C_MUTUAL_FLAG, KRB5_OID, MS_KRB5_OID, TOK_ID_AP_REP,
+    TOK_ID_AP_REQ,
+};
+use himmelblau_kerberos_crypto::Key;
+use rand::Rng;
+
+/// Security context of the Kerberos mechanism established from the side of
+/// the acceptor (the service), by accepting the initial context token of
+/// the initiator with the keys of an [Acceptor].
+///
+/// In case the initiator requests mutual authentication, the acceptor
+/// replies with the [output token](Self::output_token), which contains the
+/// AP-REP. With the AES keys, the AP-REP includes a subkey of the acceptor
+/// that protects the messages of the context.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # let initial_token: Vec<u8> = unimplemented!();
+///
+/// let acceptor = Acceptor::from_keytab_file("/etc/krb5.keytab").unwrap();
+/// let mut context =
+///     AcceptorContext::accept(&acceptor, &initial_token, None).unwrap();
+///
+/// println!("Authenticated {:?}", context.ap_req_info().cname);
+/// if let Some(output_token) = context.output_token() {
+///     // send output_token to the initiator
+/// }
+/// ```
+pub struct AcceptorContext {
+    ap_req_info: ApReqInfo,
+    flags: u32,
+    delegated_credentials: Option<CredentialWarehouse>,
+    output_token: Option<Vec<u8>>,
+    security_context: SecurityContext,
+}
+
+impl AcceptorContext {
+    /// Accepts the initial context token of the initiator. In case the
+    /// initiator includes channel bindings, they must match the given ones.
+    pub fn accept(
+        acceptor: &Acceptor,
+        token: &[u8],
+        channel_bindings: Option<&ChannelBindings>,
+    ) -> Result<Self> {
+        let (token_id, message) =
+            decode_krb5_token(&[KRB5_OID, MS_KRB5_OID], token)?;
+        if token_id != TOK_ID_AP_REQ {
+            return Err(GssError::DefectiveToken.into());
+        }
+        let (_, ap_req) =
+            ApReq::parse(message).map_err(|_| GssError::DefectiveToken)?;
+        let ap_req_info = acceptor.accept(&ap_req, None)?;
+
+        let checksum = match &ap_req_info.authenticator.cksum {
+            Some(checksum) if checksum.cksumtype == GSS_CHECKSUM_TYPE => {
+                GssChecksum::parse(&checksum.checksum)?
+            }
+            _ => return Err(GssError::DefectiveToken.into()),
+        };
+        if !checksum.matches_bindings(channel_bindings) {
+            return Err(GssError::BadBindings.into());
+        }
+
+        let delegated_credentials = match &checksum.delegation {
+            Some(raw_krb_cred) => {
+                let (_, krb_cred) = KrbCred::parse(raw_krb_cred)
+                    .map_err(|_| GssError::DefectiveToken)?;
+                Some(CredentialWarehouse::from_encrypted_krb_cred(
+                    krb_cred,
+                    &ap_req_info.session_key,
+                )?)
+            }
+            None => None,
+        };
+
+        let flags = checksum.flags;
+        let initiator_key = ap_req_info
+            .subkey
+            .as_ref()
+            .unwrap_or(&ap_req_info.session_key)
+            .clone();
+        let initiator_seq_number = ap_req_info.seq_number.unwrap_or_default();
+
+        if !ap_req_info.mutual_required && flags & GSS_C_MUTUAL_FLAG == 0 {
+            // Without AP-REP, both sides start from the sequence number of
+            // the initiator
+            return Ok(Self {
+                security_context: SecurityContext::new(
+                    initiator_key,
+                    false,
+                    false,
+                    flags,
+                    initiator_seq_number,
+                    initiator_seq_number,
+                ),
+                ap_req_info,
+                flags,
+                delegated_credentials,
+                output_token: None,
+            });
+        }
+
+        let subkey = if initiator_key.keytype == RC4_HMAC {
+            None
+        } else {
+            let key = Key::random(initiator_key.keytype)?;
+            Some(EncryptionKey::new(
+                initiator_key.keytype,
+                key.as_bytes().to_vec(),
+            ))
+        };
+        let seq_number = rand::thread_rng().gen::<u32>() & 0x3fffffff;
+        let ap_rep =
+            ap_req_info.build_ap_rep(subkey.clone(), Some(seq_number))?;
+
+        return Ok(Self {
+            security_context: SecurityContext::new(
+                subkey.clone().unwrap_or(initiator_key),
+                false,
+                subkey.is_some(),
+                flags,
+                seq_number,
+                initiator_seq_number,
+            ),
+            ap_req_info,
+            flags,
+            delegated_credentials,
+            output_token: Some(encode_krb5_token(
+                KRB5_OID,
+                TOK_ID_AP_REP,
+                &ap_rep.build(),
+            )),
+        });
+    }
+
+    /// Data of the accepted AP-REQ, such as the client and the
+    /// authorization data of the ticket.
+    pub fn ap_req_info(&self) -> &ApReqInfo {
+        return &self.ap_req_info;
+    }
+
+    /// Context flags (GSS_C_*_FLAG) requested by the initiator.
+    pub fn flags(&self) -> u32 {
+        return self.flags;
+    }
+
+    /// Credentials delegated by the initiator, in case of
+    /// GSS_C_DELEG_FLAG.
+    pub fn delegated_credentials(&self) -> Option<&CredentialWarehouse> {
+        return self.delegated_credentials.as_ref();
+    }
+
+    /// Token to send to the initiator, in case of mutual authentication.
+    pub fn output_token(&self) -> Option<&[u8]> {
+        return self.output_token.as_deref();
+    }
+
+    pub fn security_context(&mut self) -> &mut SecurityContext {
+        return &mut self.security_context;
+    }
+
+    pub fn into_security_context(self) -> SecurityContext {
+        return self.security_context;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::InitiatorContext;
+    use super::*;
+    use crate::ap::test::{
+        create_credential, new_credential, new_enc_ticket_part, new_keytab,
+        service_key, service_principal,
+    };
+    use himmelblau_kerberos_constants::gssapi::{
+        GSS_C_CONF_FLAG, GSS_C_DELEG_FLAG, GSS_C_INTEG_FLAG, GSS_C_REPLAY_FLAG,
+        GSS_C_SEQUENCE_FLAG,
+    };
+
+    fn new_initiator(flags: u32) -> InitiatorContext {
+        let credential = new_credential();
+        return InitiatorContext::new(credential, flags);
+    }
+
+    #[test]
+    fn establish_context_with_mutual_authentication() {
+        let flags = GSS_C_MUTUAL_FLAG
+            | GSS_C_REPLAY_FLAG
+            | GSS_C_SEQUENCE_FLAG
+            | GSS_C_INTEG_FLAG
+            | GSS_C_CONF_FLAG;
+        let mut initiator = new_initiator(flags);
+        let initial_token = initiator.step(None).unwrap().unwrap();
+        assert!(!initiator.is_established());
+
+        let acceptor = Acceptor::new(new_keytab());
+        let mut acceptor_context =
+            AcceptorContext::accept(&acceptor, &initial_token, None).unwrap();
+        assert_eq!(flags, acceptor_context.flags());
+        assert_eq!(
+            vec!["mickey".to_string()],
+            acceptor_context.ap_req_info().cname.name_string
+        );
+
+        let output_token = acceptor_context.output_token().unwrap().to_vec();
+        assert_eq!(None, initiator.step(Some(&output_token)).unwrap());
+
+        let initiator_context = initiator.security_context().unwrap();
+        let acceptor_context = acceptor_context.security_context();
+        assert_eq!(initiator_context.key(), acceptor_context.key());
+
+        let token = initiator_context.wrap(b"request", true).unwrap();
+        assert_eq!(
+            (b"request".to_vec(), true),
+            acceptor_context.unwrap(&token).unwrap()
+        );
+        let token = acceptor_context.get_mic(b"reply").unwrap();
+        initiator_context.verify_mic(b"reply", &token).unwrap();
+    }
+
+    #[test]
+    fn establish_context_without_mutual_authentication() {
+        let mut initiator = new_initiator(GSS_C_INTEG_FLAG);
+        let initial_token = initiator.step(None).unwrap().unwrap();
+        assert!(initiator.is_established());
+
+        let acceptor = Acceptor::new(new_keytab());
+        let acceptor_context =
+            AcceptorContext::accept(&acceptor, &initial_token, None).unwrap();
+        assert_eq!(None, acceptor_context.output_token());
+
+        let mut initiator_context = initiator.into_security_context().unwrap();
+        let mut acceptor_context = acceptor_context.into_security_context();
+        let token = acceptor_context.wrap(b"reply", false).unwrap();
+        assert_eq!(
+            (b"reply".to_vec(), false),
+            initiator_context.unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn accept_delegated_credentials() {
+        let mut initiator = new_initiator(GSS_C_MUTUAL_FLAG);
+        let enc_ticket_part = new_enc_ticket_part();
+        let tgt = create_credential(
+            service_principal("krbtgt"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        initiator.set_delegated_credentials(Some(CredentialWarehouse::new(
+            enc_ticket_part.crealm,
+            enc_ticket_part.cname.clone(),
+            vec![tgt],
+        )));
+        let initial_token = initiator.step(None).unwrap().unwrap();
+
+        let acceptor = Acceptor::new(new_keytab());
+        let acceptor_context =
+            AcceptorContext::accept(&acceptor, &initial_token, None).unwrap();
+        assert_ne!(0, acceptor_context.flags() & GSS_C_DELEG_FLAG);
+
+        let delegated_credentials =
+            acceptor_context.delegated_credentials().unwrap();
+        assert_eq!(&enc_ticket_part.cname, delegated_credentials.client());
+        assert_eq!(1, delegated_credentials.credentials().len());
+    }
+
+    #[test]
+    fn error_delegating_too_large_credentials() {
+        let mut initiator = new_initiator(GSS_C_MUTUAL_FLAG);
+        let enc_ticket_part = new_enc_ticket_part();
+        let tgt = create_credential(
+            service_principal("krbtgt"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        initiator.set_delegated_credentials(Some(CredentialWarehouse::new(
+            enc_ticket_part.crealm,
+            enc_ticket_part.cname.clone(),
+            vec![tgt; 1000],
+        )));
+
+        assert_eq!(
+            Error::GssapiError(GssError::Failure),
+            initiator.step(None).unwrap_err()
+        );
+        assert!(!initiator.is_established());
+    }
+
+    #[test]
+    fn reject_context_with_other_channel_bindings() {
+        let mut initiator = new_initiator(GSS_C_MUTUAL_FLAG);
+        initiator.set_channel_bindings(Some(ChannelBindings::new(
+            b"tls-server-end-point:1".to_vec(),
+        )));
+        let initial_token = initiator.step(None).unwrap().unwrap();
+
+        let acceptor = Acceptor::new(new_keytab());
+        assert_eq!(
+            Error::GssapiError(GssError::BadBindings),
+            AcceptorContext::accept(
+                &acceptor,
+                &initial_token,
+                Some(&ChannelBindings::new(b"tls-server-end-point:2".to_vec()))
+            )
+            .err()
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn error_processing_tampered_ap_rep() {
+        let mut initiator = new_initiator(GSS_C_MUTUAL_FLAG);
+        let initial_token = initiator.step(None).unwrap().unwrap();
+
+        let acceptor = Acceptor::new(new_keytab());
+        let acceptor_context =
+            AcceptorContext::accept(&acceptor, &initial_token, None).unwrap();
+        let mut output_token =
+            acceptor_context.output_token().unwrap().to_vec();
+        let last = output_token.len() - 1;
+        output_token[last] ^= 1;
+
+        assert!(initiator.step(Some(&output_token)).is_err());
+        assert!(!initiator.is_established());
+    }
+}
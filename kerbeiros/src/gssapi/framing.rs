@@ -0,0 +1,159 @@
+use super::GssError;
+use crate::error::*;
+
+/// Tag of the InitialContextToken framing, [APPLICATION 0]
+const APPLICATION_0_TAG: u8 = 0x60;
+
+/// Tag of the OIDs
+const OID_TAG: u8 = 0x06;
+
+/// Frames a token with the OID of the mechanism, as required for the
+/// initial context token (RFC 2743 section 3.1) and the tokens of RFC
+/// 1964. The OID must be DER encoded.
+pub fn encode_framed_token(mech_oid: &[u8], inner_token: &[u8]) -> Vec<u8> {
+    let length = mech_oid.len() + inner_token.len();
+    let mut token = vec![APPLICATION_0_TAG];
+    token.append(&mut encode_der_length(length));
+    token.extend_from_slice(mech_oid);
+    token.extend_from_slice(inner_token);
+    return token;
+}
+
+/// Parses a framed token, returning the DER encoded OID of the mechanism
+/// and the inner token.
+pub fn decode_framed_token(token: &[u8]) -> Result<(&[u8], &[u8])> {
+    if token.first() != Some(&APPLICATION_0_TAG) {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (length, content) = decode_der_length(&token[1..])?;
+    if length != content.len() {
+        return Err(GssError::DefectiveToken.into());
+    }
+
+    if content.first() != Some(&OID_TAG) {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (oid_length, oid_content) = decode_der_length(&content[1..])?;
+    if oid_length > oid_content.len() {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let oid_size = content.len() - oid_content.len() + oid_length;
+
+    return Ok(content.split_at(oid_size));
+}
+
+/// Frames a token of the Kerberos mechanism with its token identifier.
+pub fn encode_krb5_token(
+    mech_oid: &[u8],
+    token_id: u16,
+    message: &[u8],
+) -> Vec<u8> {
+    let mut inner_token = token_id.to_be_bytes().to_vec();
+    inner_token.extend_from_slice(message);
+    return encode_framed_token(mech_oid, &inner_token);
+}
+
+/// Parses a framed token of the Kerberos mechanism, returning its token
+/// identifier and message. The OID must be one of the given ones.
+pub fn decode_krb5_token<'a>(
+    mech_oids: &[&[u8]],
+    token: &'a [u8],
+) -> Result<(u16, &'a [u8])> {
+    let (mech_oid, inner_token) = decode_framed_token(token)?;
+    if !mech_oids.contains(&mech_oid) {
+        return Err(GssError::BadMech.into());
+    }
+    if inner_token.len() < 2 {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let token_id = u16::from_be_bytes([inner_token[0], inner_token[1]]);
+    return Ok((token_id, &inner_token[2..]));
+}
+
+/// Encodes a length in DER format.
+pub(crate) fn encode_der_length(length: usize) -> Vec<u8> {
+    if length < 0x80 {
+        return vec![length as u8];
+    }
+    let bytes: Vec<u8> = length
+        .to_be_bytes()
+        .iter()
+        .copied()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    let mut encoded = vec![0x80 | bytes.len() as u8];
+    encoded.extend_from_slice(&bytes);
+    return encoded;
+}
+
+/// Decodes a length in DER format, returning it along with the rest of the
+/// data.
+pub(crate) fn decode_der_length(data: &[u8]) -> Result<(usize, &[u8])> {
+    let first = *data.first().ok_or(GssError::DefectiveToken)?;
+    if first < 0x80 {
+        return Ok((first as usize, &data[1..]));
+    }
+
+    let size = (first & 0x7f) as usize;
+    if size == 0 || size > std::mem::size_of::<usize>() || data.len() <= size {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let length = data[1..=size]
+        .iter()
+        .fold(0, |length, byte| (length << 8) | *byte as usize);
+    return Ok((length, &data[size + 1..]));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::gssapi::{
+        KRB5_OID, MS_KRB5_OID, TOK_ID_AP_REQ,
+    };
+
+    #[test]
+    fn encode_and_decode_krb5_token() {
+        let token = encode_krb5_token(KRB5_OID, TOK_ID_AP_REQ, &[1, 2, 3]);
+        assert_eq!(
+            vec![
+                0x60, 0x10, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12,
+                0x01, 0x02, 0x02, 0x01, 0x00, 0x01, 0x02, 0x03
+            ],
+            token
+        );
+        assert_eq!(
+            (TOK_ID_AP_REQ, &[1_u8, 2, 3][..]),
+            decode_krb5_token(&[KRB5_OID, MS_KRB5_OID], &token).unwrap()
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_long_token() {
+        let message = vec![7; 300];
+        let token = encode_krb5_token(KRB5_OID, TOK_ID_AP_REQ, &message);
+        assert_eq!(&[0x60, 0x82, 0x01, 0x39], &token[..4]);
+        assert_eq!(
+            (TOK_ID_AP_REQ, &message[..]),
+            decode_krb5_token(&[KRB5_OID], &token).unwrap()
+        );
+    }
+
+    #[test]
+    fn error_decoding_token_of_another_mechanism() {
+        let token = encode_krb5_token(MS_KRB5_OID, TOK_ID_AP_REQ, &[1]);
+        assert_eq!(
+            Error::GssapiError(GssError::BadMech),
+            decode_krb5_token(&[KRB5_OID], &token).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_decoding_truncated_token() {
+        let token = encode_krb5_token(KRB5_OID, TOK_ID_AP_REQ, &[1, 2, 3]);
+        assert_eq!(
+            Error::GssapiError(GssError::DefectiveToken),
+            decode_krb5_token(&[KRB5_OID], &token[..token.len() - 1])
+                .unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use super::GssError;
+use crate::error::*;
+use himmelblau_kerberos_asn1::Checksum;
+use himmelblau_kerberos_constants::gssapi::{
+    GSS_CHECKSUM_TYPE, GSS_C_DELEG_FLAG,
+};
+use himmelblau_kerberos_crypto::md5;
+use std::convert::TryFrom;
+
+/// Size of the hash of the channel bindings
+pub const BINDINGS_HASH_SIZE: usize = 16;
+
+/// Option of the checksum that carries the delegated credentials
+const DELEGATION_OPTION: u16 = 1;
+
+/// Channel bindings (RFC 2744 section 3.11), which bind the security
+/// context to the underlying channel, such as a TLS connection.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChannelBindings {
+    pub initiator_addrtype: u32,
+    pub initiator_address: Vec<u8>,
+    pub acceptor_addrtype: u32,
+    pub acceptor_address: Vec<u8>,
+    pub application_data: Vec<u8>,
+}
+
+impl ChannelBindings {
+    /// Creates channel bindings with application data only, as the
+    /// tls-server-end-point bindings of RFC 5929.
+    pub fn new(application_data: Vec<u8>) -> Self {
+        return Self {
+            application_data,
+            ..Default::default()
+        };
+    }
+
+    /// MD5 hash of the channel bindings, included in the authenticator
+    /// checksum (RFC 4121 section 4.1.1.2).
+    pub fn hash(&self) -> [u8; BINDINGS_HASH_SIZE] {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.initiator_addrtype.to_le_bytes());
+        append_buffer(&mut data, &self.initiator_address);
+        data.extend_from_slice(&self.acceptor_addrtype.to_le_bytes());
+        append_buffer(&mut data, &self.acceptor_address);
+        append_buffer(&mut data, &self.application_data);
+
+        let mut hash = [0; BINDINGS_HASH_SIZE];
+        hash.copy_from_slice(&md5(&data));
+        return hash;
+    }
+}
+
+fn append_buffer(data: &mut Vec<u8>, buffer: &[u8]) {
+    data.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+    data.extend_from_slice(buffer);
+}
+
+/// Authenticator checksum of the Kerberos mechanism (RFC 4121 section
+/// 4.1.1), which carries the hash of the channel bindings, the context
+/// flags and the delegated credentials.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GssChecksum {
+    /// Hash of the channel bindings, all zeros if there are none.
+    pub bindings_hash: [u8; BINDINGS_HASH_SIZE],
+    pub flags: u32,
+    /// KRB-CRED with the delegated credentials, in case of
+    /// GSS_C_DELEG_FLAG.
+    pub delegation: Option<Vec<u8>>,
+    pub extensions: Vec<u8>,
+}
+
+impl GssChecksum {
+    pub fn new(
+        flags: u32,
+        channel_bindings: Option<&ChannelBindings>,
+        delegation: Option<Vec<u8>>,
+    ) -> Self {
+        let mut flags = flags;
+        if delegation.is_some() {
+            flags |= GSS_C_DELEG_FLAG;
+        } else {
+            flags &= !GSS_C_DELEG_FLAG;
+        }
+
+        return Self {
+            bindings_hash: channel_bindings
+                .map(ChannelBindings::hash)
+                .unwrap_or_default(),
+            flags,
+            delegation,
+            extensions: Vec::new(),
+        };
+    }
+
+    /// Encodes the checksum, failing in case the KRB-CRED of the delegated
+    /// credentials is too large for its 16 bits length.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        let mut data = (BINDINGS_HASH_SIZE as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&self.bindings_hash);
+        data.extend_from_slice(&self.flags.to_le_bytes());
+        if let Some(delegation) = &self.delegation {
+            let length = u16::try_from(delegation.len())
+                .map_err(|_| GssError::Failure)?;
+            data.extend_from_slice(&DELEGATION_OPTION.to_le_bytes());
+            data.extend_from_slice(&length.to_le_bytes());
+            data.extend_from_slice(delegation);
+        }
+        data.extend_from_slice(&self.extensions);
+        return Ok(data);
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 + BINDINGS_HASH_SIZE {
+            return Err(GssError::DefectiveToken.into());
+        }
+        let length = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        if length as usize != BINDINGS_HASH_SIZE {
+            return Err(GssError::BadBindings.into());
+        }
+
+        let mut bindings_hash = [0; BINDINGS_HASH_SIZE];
+        bindings_hash.copy_from_slice(&data[4..4 + BINDINGS_HASH_SIZE]);
+        let flags = &data[4 + BINDINGS_HASH_SIZE..8 + BINDINGS_HASH_SIZE];
+        let flags =
+            u32::from_le_bytes([flags[0], flags[1], flags[2], flags[3]]);
+
+        let mut rest = &data[8 + BINDINGS_HASH_SIZE..];
+        let mut delegation = None;
+        if flags & GSS_C_DELEG_FLAG != 0 && rest.len() >= 4 {
+            let option = u16::from_le_bytes([rest[0], rest[1]]);
+            let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            if option != DELEGATION_OPTION || rest.len() < 4 + length {
+                return Err(GssError::DefectiveToken.into());
+            }
+            delegation = Some(rest[4..4 + length].to_vec());
+            rest = &rest[4 + length..];
+        }
+
+        return Ok(Self {
+            bindings_hash,
+            flags,
+            delegation,
+            extensions: rest.to_vec(),
+        });
+    }
+
+    /// Whether the bindings of the checksum match the ones of the
+    /// acceptor. In case the initiator sent no bindings, they are accepted.
+    pub fn matches_bindings(
+        &self,
+        channel_bindings: Option<&ChannelBindings>,
+    ) -> bool {
+        if self.bindings_hash == [0; BINDINGS_HASH_SIZE] {
+            return true;
+        }
+        return match channel_bindings {
+            Some(channel_bindings) => {
+                channel_bindings.hash() == self.bindings_hash
+            }
+            None => false,
+        };
+    }
+
+    pub fn to_checksum(&self) -> Result<Checksum> {
+        return Ok(Checksum {
+            cksumtype: GSS_CHECKSUM_TYPE,
+            checksum: self.build()?,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::gssapi::{
+        GSS_C_INTEG_FLAG, GSS_C_MUTUAL_FLAG,
+    };
+
+    #[test]
+    fn build_and_parse_checksum_with_delegation() {
+        let checksum = GssChecksum::new(
+            GSS_C_MUTUAL_FLAG,
+            Some(&ChannelBindings::new(b"tls-server-end-point:1".to_vec())),
+            Some(vec![1, 2, 3]),
+        );
+        let raw_checksum = checksum.build().unwrap();
+
+        assert_eq!(&[16, 0, 0, 0], &raw_checksum[..4]);
+        assert_eq!(&[3, 0, 0, 0, 1, 0, 3, 0, 1, 2, 3], &raw_checksum[20..]);
+        assert_eq!(checksum, GssChecksum::parse(&raw_checksum).unwrap());
+    }
+
+    #[test]
+    fn error_building_checksum_with_too_large_delegation() {
+        let checksum =
+            GssChecksum::new(0, None, Some(vec![0; u16::MAX as usize + 1]));
+        assert_eq!(
+            Error::GssapiError(GssError::Failure),
+            checksum.build().unwrap_err()
+        );
+
+        let checksum =
+            GssChecksum::new(0, None, Some(vec![0; u16::MAX as usize]));
+        assert!(checksum.build().is_ok());
+    }
+
+    #[test]
+    fn parse_checksum_without_delegation() {
+        let mut raw_checksum = vec![16, 0, 0, 0];
+        raw_checksum.extend_from_slice(&[0; 16]);
+        raw_checksum.extend_from_slice(&[0x22, 0, 0, 0]);
+
+        let checksum = GssChecksum::parse(&raw_checksum).unwrap();
+        assert_eq!(GSS_C_MUTUAL_FLAG | GSS_C_INTEG_FLAG, checksum.flags);
+        assert_eq!(None, checksum.delegation);
+        assert!(checksum.matches_bindings(None));
+    }
+
+    #[test]
+    fn verify_channel_bindings() {
+        let channel_bindings = ChannelBindings::new(b"binding".to_vec());
+        let checksum = GssChecksum::new(0, Some(&channel_bindings), None);
+
+        assert!(checksum.matches_bindings(Some(&channel_bindings)));
+        assert!(!checksum.matches_bindings(None));
+        assert!(!checksum
+            .matches_bindings(Some(&ChannelBindings::new(b"other".to_vec()))));
+    }
+}
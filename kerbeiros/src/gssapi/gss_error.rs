@@ -0,0 +1,27 @@
+/// Errors of the GSS-API mechanism, named after the GSS-API major status
+/// codes of RFC 2743.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GssError {
+    /// The token is not of the Kerberos V5 mechanism.
+    BadMech,
+    /// The channel bindings of the initiator do not match the ones of the
+    /// acceptor.
+    BadBindings,
+    /// The checksum of the token is invalid, or it cannot be decrypted.
+    BadMic,
+    /// The token is malformed or of an unexpected type.
+    DefectiveToken,
+    /// The token was already received.
+    DuplicateToken,
+    /// The token is too old to check whether it is duplicated.
+    OldToken,
+    /// A later token was already received.
+    UnseqToken,
+    /// Some earlier tokens were not received.
+    GapToken,
+    /// The security context is not established yet.
+    NoContext,
+    /// The operation failed for a reason specific to the mechanism, such
+    /// as delegated credentials too large for the checksum.
+    Failure,
+}
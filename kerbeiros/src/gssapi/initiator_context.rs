@@ -0,0 +1,192 @@
+use super::framing::{decode_krb5_token, encode_krb5_token};
+use super::{ChannelBindings, GssChecksum, GssError, SecurityContext};
+use crate::ap::{ApReqBuilder, ApRequest};
+use crate::credentials::{Credential, CredentialWarehouse};
+use crate::error::*;
+use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+use himmelblau_kerberos_constants::gssapi::{
+    GSS_C_MUTUAL_FLAG, KRB5_OID, MS_KRB5_OID, TOK_ID_AP_REP, TOK_ID_AP_REQ,
+    TOK_ID_KRB_ERROR,
+};
+
+/// Establishes a security context of the Kerberos mechanism from the side
+/// of the initiator (the client), with a ticket for the service.
+///
+/// The first call to [step](Self::step) produces the initial context
+/// token, which contains the AP-REQ. In case of GSS_C_MUTUAL_FLAG, the
+/// context is established once the token of the acceptor, which contains
+/// the AP-REP, is passed to [step](Self::step). Otherwise, the context is
+/// established after the first call.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_constants::gssapi::*;
+/// # let credential: Credential = unimplemented!();
+/// # let acceptor_token: Vec<u8> = unimplemented!();
+///
+/// let mut context = InitiatorContext::new(
+///     credential,
+///     GSS_C_MUTUAL_FLAG | GSS_C_INTEG_FLAG | GSS_C_CONF_FLAG,
+/// );
+/// let initial_token = context.step(None).unwrap().unwrap();
+/// // send initial_token to the acceptor and receive acceptor_token
+/// context.step(Some(&acceptor_token)).unwrap();
+///
+/// let security_context = context.security_context().unwrap();
+/// let token = security_context.wrap(b"secret", true).unwrap();
+/// ```
+pub struct InitiatorContext {
+    credential: Credential,
+    flags: u32,
+    channel_bindings: Option<ChannelBindings>,
+    delegated_credentials: Option<CredentialWarehouse>,
+    ap_request: Option<ApRequest>,
+    security_context: Option<SecurityContext>,
+}
+
+impl InitiatorContext {
+    /// Creates a context for the service of the credential, requesting the
+    /// given context flags (GSS_C_*_FLAG).
+    pub fn new(credential: Credential, flags: u32) -> Self {
+        return Self {
+            credential,
+            flags,
+            channel_bindings: None,
+            delegated_credentials: None,
+            ap_request: None,
+            security_context: None,
+        };
+    }
+
+    pub fn flags(&self) -> u32 {
+        return self.flags;
+    }
+
+    pub fn set_channel_bindings(
+        &mut self,
+        channel_bindings: Option<ChannelBindings>,
+    ) {
+        self.channel_bindings = channel_bindings;
+    }
+
+    /// Sets the credentials delegated to the acceptor, usually a forwarded
+    /// TGT, which sets the GSS_C_DELEG_FLAG.
+    pub fn set_delegated_credentials(
+        &mut self,
+        delegated_credentials: Option<CredentialWarehouse>,
+    ) {
+        self.delegated_credentials = delegated_credentials;
+    }
+
+    /// Processes the token received from the acceptor, if any, and returns
+    /// the token to send to the acceptor, if any.
+    pub fn step(
+        &mut self,
+        input_token: Option<&[u8]>,
+    ) -> Result<Option<Vec<u8>>> {
+        if self.security_context.is_some() {
+            return Err(GssError::DefectiveToken.into());
+        }
+
+        match &self.ap_request {
+            None => {
+                return Ok(Some(self.initial_token()?));
+            }
+            Some(ap_request) => {
+                let input_token =
+                    input_token.ok_or(GssError::DefectiveToken)?;
+                let security_context =
+                    self.process_acceptor_token(ap_request, input_token)?;
+                self.security_context = Some(security_context);
+                return Ok(None);
+            }
+        }
+    }
+
+    pub fn is_established(&self) -> bool {
+        return self.security_context.is_some();
+    }
+
+    /// Security context, available once it is established.
+    pub fn security_context(&mut self) -> Option<&mut SecurityContext> {
+        return self.security_context.as_mut();
+    }
+
+    pub fn into_security_context(self) -> Option<SecurityContext> {
+        return self.security_context;
+    }
+
+    fn initial_token(&mut self) -> Result<Vec<u8>> {
+        let delegation = match &self.delegated_credentials {
+            Some(delegated_credentials) => Some(
+                delegated_credentials
+                    .into_encrypted_krb_cred(self.credential.key())?
+                    .build(),
+            ),
+            None => None,
+        };
+        let checksum = GssChecksum::new(
+            self.flags,
+            self.channel_bindings.as_ref(),
+            delegation,
+        );
+        self.flags = checksum.flags;
+        let mutual = self.flags & GSS_C_MUTUAL_FLAG != 0;
+
+        let mut builder = ApReqBuilder::new(&self.credential);
+        builder.generate_subkey()?;
+        builder.generate_seq_number();
+        builder.set_mutual_required(mutual);
+        builder.set_checksum(Some(checksum.to_checksum()?));
+        let ap_request = builder.build()?;
+
+        let token =
+            encode_krb5_token(KRB5_OID, TOK_ID_AP_REQ, &ap_request.build());
+
+        if !mutual {
+            // Without AP-REP, both sides start from the sequence number of
+            // the initiator
+            let seq_number = ap_request.seq_number().unwrap_or_default();
+            self.security_context = Some(SecurityContext::new(
+                ap_request.key().clone(),
+                true,
+                false,
+                self.flags,
+                seq_number,
+                seq_number,
+            ));
+        }
+        self.ap_request = Some(ap_request);
+        return Ok(token);
+    }
+
+    fn process_acceptor_token(
+        &self,
+        ap_request: &ApRequest,
+        token: &[u8],
+    ) -> Result<SecurityContext> {
+        let (token_id, message) =
+            decode_krb5_token(&[KRB5_OID, MS_KRB5_OID], token)?;
+        if token_id == TOK_ID_KRB_ERROR {
+            let (_, krb_error) = KrbError::parse(message)
+                .map_err(|_| GssError::DefectiveToken)?;
+            return Err(Error::KrbErrorResponse(krb_error));
+        }
+        if token_id != TOK_ID_AP_REP {
+            return Err(GssError::DefectiveToken.into());
+        }
+
+        let ap_rep_info = ap_request.verify_ap_rep(message)?;
+        let seq_number = ap_request.seq_number().unwrap_or_default();
+        return Ok(SecurityContext::new(
+            ap_rep_info.key,
+            true,
+            ap_rep_info.subkey.is_some(),
+            self.flags,
+            seq_number,
+            ap_rep_info.seq_number.unwrap_or(seq_number),
+        ));
+    }
+}
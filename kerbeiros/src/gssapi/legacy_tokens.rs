@@ -0,0 +1,321 @@
+//! MIC and Wrap tokens of RFC 1964, as specified for RC4-HMAC in RFC 4757
+//! section 7.
+
+use super::framing::{decode_krb5_token, encode_krb5_token};
+use super::GssError;
+use crate::error::*;
+use himmelblau_kerberos_asn1::EncryptionKey;
+use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+use himmelblau_kerberos_constants::gssapi::{
+    KRB5_OID, MS_KRB5_OID, SEAL_ALG_NONE, SEAL_ALG_RC4, SGN_ALG_HMAC_MD5,
+    TOK_ID_MIC_V1, TOK_ID_WRAP_V1,
+};
+use himmelblau_kerberos_crypto::{checksum_hmac_md5, hmac_md5, rc4_encrypt};
+use rand::Rng;
+
+/// Key usage of the checksum of the MIC tokens
+const MIC_SIGN_USAGE: i32 = 15;
+
+/// Key usage of the checksum of the Wrap tokens
+const WRAP_SIGN_USAGE: i32 = 13;
+
+/// Size of the fields of the tokens: header, encrypted sequence number,
+/// checksum and confounder
+const FIELD_SIZE: usize = 8;
+
+/// Builds a MIC token, sent by the initiator or by the acceptor.
+pub fn build_mic_token_v1(
+    key: &EncryptionKey,
+    sent_by_initiator: bool,
+    seq_number: u32,
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    check_key(key)?;
+    let header = header(TOK_ID_MIC_V1, SEAL_ALG_NONE);
+
+    let mut data = header.to_vec();
+    data.extend_from_slice(message);
+    let checksum = signature(key, MIC_SIGN_USAGE, &data);
+
+    let mut token = header[2..].to_vec();
+    token.extend_from_slice(&encrypt_seq_number(
+        key,
+        &checksum,
+        seq_number,
+        sent_by_initiator,
+    ));
+    token.extend_from_slice(&checksum);
+    return Ok(encode_krb5_token(KRB5_OID, TOK_ID_MIC_V1, &token));
+}
+
+/// Verifies a MIC token of the message, returning its sequence number.
+pub fn verify_mic_token_v1(
+    key: &EncryptionKey,
+    sent_by_initiator: bool,
+    token: &[u8],
+    message: &[u8],
+) -> Result<u32> {
+    check_key(key)?;
+    let header = header(TOK_ID_MIC_V1, SEAL_ALG_NONE);
+    let token = decode_token(TOK_ID_MIC_V1, token)?;
+    if token.len() != 3 * FIELD_SIZE || token[..FIELD_SIZE] != header {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let checksum = &token[2 * FIELD_SIZE..];
+
+    let mut data = header.to_vec();
+    data.extend_from_slice(message);
+    if signature(key, MIC_SIGN_USAGE, &data) != checksum {
+        return Err(GssError::BadMic.into());
+    }
+
+    return decrypt_seq_number(
+        key,
+        checksum,
+        &token[FIELD_SIZE..2 * FIELD_SIZE],
+        sent_by_initiator,
+    );
+}
+
+/// Builds a Wrap token, whose message is encrypted with RC4 in case of
+/// confidentiality.
+pub fn build_wrap_token_v1(
+    key: &EncryptionKey,
+    sent_by_initiator: bool,
+    seq_number: u32,
+    message: &[u8],
+    confidentiality: bool,
+) -> Result<Vec<u8>> {
+    check_key(key)?;
+    let seal_alg = if confidentiality {
+        SEAL_ALG_RC4
+    } else {
+        SEAL_ALG_NONE
+    };
+    let header = header(TOK_ID_WRAP_V1, seal_alg);
+
+    // The message is padded to the block size of RC4, which is 1
+    let mut data = rand::thread_rng().gen::<[u8; FIELD_SIZE]>().to_vec();
+    data.extend_from_slice(message);
+    data.push(1);
+
+    let mut signed_data = header.to_vec();
+    signed_data.extend_from_slice(&data);
+    let checksum = signature(key, WRAP_SIGN_USAGE, &signed_data);
+
+    if confidentiality {
+        data = rc4_encrypt(&data_key(key, seq_number), &data);
+    }
+
+    let mut token = header[2..].to_vec();
+    token.extend_from_slice(&encrypt_seq_number(
+        key,
+        &checksum,
+        seq_number,
+        sent_by_initiator,
+    ));
+    token.extend_from_slice(&checksum);
+    token.append(&mut data);
+    return Ok(encode_krb5_token(KRB5_OID, TOK_ID_WRAP_V1, &token));
+}
+
+/// Verifies a Wrap token, returning its sequence number, whether the
+/// message was encrypted and the message.
+pub fn unwrap_wrap_token_v1(
+    key: &EncryptionKey,
+    sent_by_initiator: bool,
+    token: &[u8],
+) -> Result<(u32, bool, Vec<u8>)> {
+    check_key(key)?;
+    let token = decode_token(TOK_ID_WRAP_V1, token)?;
+    if token.len() < 4 * FIELD_SIZE + 1 {
+        return Err(GssError::DefectiveToken.into());
+    }
+
+    let seal_alg = u16::from_be_bytes([token[4], token[5]]);
+    let confidentiality = seal_alg == SEAL_ALG_RC4;
+    let header = header(TOK_ID_WRAP_V1, seal_alg);
+    if token[..FIELD_SIZE] != header
+        || (!confidentiality && seal_alg != SEAL_ALG_NONE)
+    {
+        return Err(GssError::DefectiveToken.into());
+    }
+
+    let checksum = &token[2 * FIELD_SIZE..3 * FIELD_SIZE];
+    let seq_number = decrypt_seq_number(
+        key,
+        checksum,
+        &token[FIELD_SIZE..2 * FIELD_SIZE],
+        sent_by_initiator,
+    )?;
+
+    let mut data = token[3 * FIELD_SIZE..].to_vec();
+    if confidentiality {
+        data = rc4_encrypt(&data_key(key, seq_number), &data);
+    }
+
+    let mut signed_data = header.to_vec();
+    signed_data.extend_from_slice(&data);
+    if signature(key, WRAP_SIGN_USAGE, &signed_data) != checksum {
+        return Err(GssError::BadMic.into());
+    }
+
+    let padding = data[data.len() - 1] as usize;
+    if padding == 0 || padding > FIELD_SIZE || padding > data.len() - FIELD_SIZE
+    {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let message = data[FIELD_SIZE..data.len() - padding].to_vec();
+    return Ok((seq_number, confidentiality, message));
+}
+
+fn check_key(key: &EncryptionKey) -> Result<()> {
+    if key.keytype != RC4_HMAC {
+        return Err(himmelblau_kerberos_crypto::Error::UnsupportedAlgorithm(
+            key.keytype,
+        )
+        .into());
+    }
+    return Ok(());
+}
+
+/// Parses a framed token, returning the token without its identifier
+/// but with the rest of the header.
+fn decode_token(token_id: u16, token: &[u8]) -> Result<Vec<u8>> {
+    let (received_token_id, inner_token) =
+        decode_krb5_token(&[KRB5_OID, MS_KRB5_OID], token)?;
+    if received_token_id != token_id {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let mut token = token_id.to_be_bytes().to_vec();
+    token.extend_from_slice(inner_token);
+    return Ok(token);
+}
+
+/// First bytes of the tokens: identifier, algorithms and filler.
+fn header(token_id: u16, seal_alg: u16) -> [u8; FIELD_SIZE] {
+    let mut header = [0xff; FIELD_SIZE];
+    header[..2].copy_from_slice(&token_id.to_be_bytes());
+    header[2..4].copy_from_slice(&SGN_ALG_HMAC_MD5.to_be_bytes());
+    header[4..6].copy_from_slice(&seal_alg.to_be_bytes());
+    return header;
+}
+
+fn signature(key: &EncryptionKey, key_usage: i32, data: &[u8]) -> Vec<u8> {
+    return checksum_hmac_md5(&key.keyvalue, key_usage, data)[..FIELD_SIZE]
+        .to_vec();
+}
+
+/// Key to encrypt the sequence number, derived from the checksum.
+fn seq_number_key(key: &EncryptionKey, checksum: &[u8]) -> Vec<u8> {
+    return hmac_md5(&hmac_md5(&key.keyvalue, &[0; 4]), checksum);
+}
+
+fn encrypt_seq_number(
+    key: &EncryptionKey,
+    checksum: &[u8],
+    seq_number: u32,
+    sent_by_initiator: bool,
+) -> Vec<u8> {
+    let mut plain_seq_number = seq_number.to_be_bytes().to_vec();
+    plain_seq_number.extend_from_slice(&direction(sent_by_initiator));
+    return rc4_encrypt(&seq_number_key(key, checksum), &plain_seq_number);
+}
+
+fn decrypt_seq_number(
+    key: &EncryptionKey,
+    checksum: &[u8],
+    encrypted_seq_number: &[u8],
+    sent_by_initiator: bool,
+) -> Result<u32> {
+    let plain_seq_number =
+        rc4_encrypt(&seq_number_key(key, checksum), encrypted_seq_number);
+    if plain_seq_number[4..] != direction(sent_by_initiator) {
+        return Err(GssError::BadMic.into());
+    }
+    return Ok(u32::from_be_bytes([
+        plain_seq_number[0],
+        plain_seq_number[1],
+        plain_seq_number[2],
+        plain_seq_number[3],
+    ]));
+}
+
+/// Direction bytes that follow the sequence number.
+fn direction(sent_by_initiator: bool) -> [u8; 4] {
+    if sent_by_initiator {
+        return [0; 4];
+    }
+    return [0xff; 4];
+}
+
+/// Key to encrypt the data of a Wrap token, derived from the sequence
+/// number.
+fn data_key(key: &EncryptionKey, seq_number: u32) -> Vec<u8> {
+    let local_key: Vec<u8> =
+        key.keyvalue.iter().map(|byte| byte ^ 0xf0).collect();
+    return hmac_md5(&hmac_md5(&local_key, &[0; 4]), &seq_number.to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::AES128_CTS_HMAC_SHA1_96;
+
+    fn key() -> EncryptionKey {
+        return EncryptionKey::new(RC4_HMAC, vec![8; 16]);
+    }
+
+    #[test]
+    fn build_and_verify_mic_token() {
+        let token = build_mic_token_v1(&key(), true, 3, b"hello").unwrap();
+
+        assert_eq!(
+            3,
+            verify_mic_token_v1(&key(), true, &token, b"hello").unwrap()
+        );
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            verify_mic_token_v1(&key(), true, &token, b"bye").unwrap_err()
+        );
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            verify_mic_token_v1(&key(), false, &token, b"hello").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn wrap_and_unwrap_with_confidentiality() {
+        let token =
+            build_wrap_token_v1(&key(), false, 9, b"secret", true).unwrap();
+        assert!(!token.windows(6).any(|window| window == b"secret"));
+
+        assert_eq!(
+            (9, true, b"secret".to_vec()),
+            unwrap_wrap_token_v1(&key(), false, &token).unwrap()
+        );
+    }
+
+    #[test]
+    fn wrap_and_unwrap_without_confidentiality() {
+        let mut token =
+            build_wrap_token_v1(&key(), true, 1, b"clear", false).unwrap();
+        assert_eq!(
+            (1, false, b"clear".to_vec()),
+            unwrap_wrap_token_v1(&key(), true, &token).unwrap()
+        );
+
+        let last = token.len() - 2;
+        token[last] ^= 1;
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            unwrap_wrap_token_v1(&key(), true, &token).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_with_aes_key() {
+        let key = EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![8; 16]);
+        assert!(build_mic_token_v1(&key, true, 1, b"hello").is_err());
+    }
+}
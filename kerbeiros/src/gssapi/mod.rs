@@ -0,0 +1,33 @@
+//! Kerberos V5 mechanism of GSS-API (RFC 1964 and RFC 4121). The
+//! [InitiatorContext] and the [AcceptorContext] establish a security
+//! context with the AP exchange, carried in framed context tokens, and the
+//! established [SecurityContext] protects the messages with MIC and Wrap
+//! tokens.
+
+mod gss_error;
+pub use gss_error::GssError;
+
+mod framing;
+pub(crate) use framing::{decode_der_length, encode_der_length};
+pub use framing::{
+    decode_framed_token, decode_krb5_token, encode_framed_token,
+    encode_krb5_token,
+};
+
+mod gss_checksum;
+pub use gss_checksum::{ChannelBindings, GssChecksum, BINDINGS_HASH_SIZE};
+
+mod sequence_window;
+pub use sequence_window::SequenceWindow;
+
+mod legacy_tokens;
+mod tokens;
+
+mod security_context;
+pub use security_context::SecurityContext;
+
+mod initiator_context;
+pub use initiator_context::InitiatorContext;
+
+mod acceptor_context;
+pub use acceptor_context::AcceptorContext;
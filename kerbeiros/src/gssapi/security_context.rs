@@ -0,0 +1,312 @@
+use super::legacy_tokens::{
+    build_mic_token_v1, build_wrap_token_v1, unwrap_wrap_token_v1,
+    verify_mic_token_v1,
+};
+use super::tokens::{
+    build_mic_token, build_wrap_token, unwrap_wrap_token, verify_mic_token,
+    TokenInfo,
+};
+use super::{GssError, SequenceWindow};
+use crate::error::*;
+use himmelblau_kerberos_asn1::EncryptionKey;
+use himmelblau_kerberos_constants::etypes::RC4_HMAC;
+use himmelblau_kerberos_constants::gssapi::{
+    FLAG_ACCEPTOR_SUBKEY, FLAG_SEALED, FLAG_SENT_BY_ACCEPTOR,
+    GSS_C_REPLAY_FLAG, GSS_C_SEQUENCE_FLAG,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_ACCEPTOR_SEAL, KEY_USAGE_ACCEPTOR_SIGN, KEY_USAGE_INITIATOR_SEAL,
+    KEY_USAGE_INITIATOR_SIGN,
+};
+
+/// Established security context of the Kerberos mechanism, used to
+/// protect the messages exchanged by the initiator and the acceptor with
+/// MIC (integrity) and Wrap (integrity and, optionally, confidentiality)
+/// tokens.
+///
+/// The tokens of RFC 4121 are used with the AES keys, and the ones of RFC
+/// 1964 with the RC4-HMAC keys, as described in RFC 4757. The sequence
+/// numbers of the received tokens are verified according to the
+/// GSS_C_REPLAY_FLAG and GSS_C_SEQUENCE_FLAG flags of the context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityContext {
+    key: EncryptionKey,
+    initiator: bool,
+    acceptor_subkey: bool,
+    flags: u32,
+    send_seq_number: u64,
+    recv_window: SequenceWindow,
+}
+
+impl SecurityContext {
+    pub(crate) fn new(
+        key: EncryptionKey,
+        initiator: bool,
+        acceptor_subkey: bool,
+        flags: u32,
+        send_seq_number: u32,
+        recv_seq_number: u32,
+    ) -> Self {
+        let legacy = key.keytype == RC4_HMAC;
+        return Self {
+            key,
+            initiator,
+            acceptor_subkey,
+            flags,
+            send_seq_number: send_seq_number as u64,
+            recv_window: SequenceWindow::new(
+                recv_seq_number as u64,
+                !legacy,
+                flags & GSS_C_REPLAY_FLAG != 0,
+                flags & GSS_C_SEQUENCE_FLAG != 0,
+            ),
+        };
+    }
+
+    /// Key that protects the tokens: the subkey of the acceptor or, if
+    /// there is none, the subkey of the initiator or the session key.
+    pub fn key(&self) -> &EncryptionKey {
+        return &self.key;
+    }
+
+    pub fn is_initiator(&self) -> bool {
+        return self.initiator;
+    }
+
+    /// Context flags (GSS_C_*_FLAG) requested by the initiator.
+    pub fn flags(&self) -> u32 {
+        return self.flags;
+    }
+
+    /// Whether the tokens are the ones of RFC 1964 instead of RFC 4121.
+    pub fn is_legacy(&self) -> bool {
+        return self.key.keytype == RC4_HMAC;
+    }
+
+    /// Builds a MIC token of the message.
+    pub fn get_mic(&mut self, message: &[u8]) -> Result<Vec<u8>> {
+        let seq_number = self.next_send_seq_number();
+        if self.is_legacy() {
+            return build_mic_token_v1(
+                &self.key,
+                self.initiator,
+                seq_number as u32,
+                message,
+            );
+        }
+
+        let key_usage = if self.initiator {
+            KEY_USAGE_INITIATOR_SIGN
+        } else {
+            KEY_USAGE_ACCEPTOR_SIGN
+        };
+        return build_mic_token(
+            &self.key,
+            key_usage,
+            self.send_flags(),
+            seq_number,
+            message,
+        );
+    }
+
+    /// Verifies a MIC token of the message sent by the peer.
+    pub fn verify_mic(&mut self, message: &[u8], token: &[u8]) -> Result<()> {
+        if self.is_legacy() {
+            let seq_number = verify_mic_token_v1(
+                &self.key,
+                !self.initiator,
+                token,
+                message,
+            )?;
+            return self.recv_window.check(seq_number as u64);
+        }
+
+        let key_usage = if self.initiator {
+            KEY_USAGE_ACCEPTOR_SIGN
+        } else {
+            KEY_USAGE_INITIATOR_SIGN
+        };
+        let info = verify_mic_token(&self.key, key_usage, token, message)?;
+        return self.check_token_info(&info);
+    }
+
+    /// Builds a Wrap token of the message, encrypted in case of
+    /// confidentiality.
+    pub fn wrap(
+        &mut self,
+        message: &[u8],
+        confidentiality: bool,
+    ) -> Result<Vec<u8>> {
+        let seq_number = self.next_send_seq_number();
+        if self.is_legacy() {
+            return build_wrap_token_v1(
+                &self.key,
+                self.initiator,
+                seq_number as u32,
+                message,
+                confidentiality,
+            );
+        }
+
+        let key_usage = if self.initiator {
+            KEY_USAGE_INITIATOR_SEAL
+        } else {
+            KEY_USAGE_ACCEPTOR_SEAL
+        };
+        return build_wrap_token(
+            &self.key,
+            key_usage,
+            self.send_flags(),
+            seq_number,
+            message,
+            confidentiality,
+        );
+    }
+
+    /// Verifies a Wrap token sent by the peer, returning its message and
+    /// whether it was encrypted.
+    pub fn unwrap(&mut self, token: &[u8]) -> Result<(Vec<u8>, bool)> {
+        if self.is_legacy() {
+            let (seq_number, confidentiality, message) =
+                unwrap_wrap_token_v1(&self.key, !self.initiator, token)?;
+            self.recv_window.check(seq_number as u64)?;
+            return Ok((message, confidentiality));
+        }
+
+        let key_usage = if self.initiator {
+            KEY_USAGE_ACCEPTOR_SEAL
+        } else {
+            KEY_USAGE_INITIATOR_SEAL
+        };
+        let (info, message) = unwrap_wrap_token(&self.key, key_usage, token)?;
+        self.check_token_info(&info)?;
+        return Ok((message, info.flags & FLAG_SEALED != 0));
+    }
+
+    fn next_send_seq_number(&mut self) -> u64 {
+        let seq_number = self.send_seq_number;
+        self.send_seq_number = if self.is_legacy() {
+            (seq_number as u32).wrapping_add(1) as u64
+        } else {
+            seq_number.wrapping_add(1)
+        };
+        return seq_number;
+    }
+
+    fn send_flags(&self) -> u8 {
+        let mut flags = 0;
+        if !self.initiator {
+            flags |= FLAG_SENT_BY_ACCEPTOR;
+        }
+        if self.acceptor_subkey {
+            flags |= FLAG_ACCEPTOR_SUBKEY;
+        }
+        return flags;
+    }
+
+    /// Checks that the token was sent by the peer, with the same key, and
+    /// registers its sequence number.
+    fn check_token_info(&mut self, info: &TokenInfo) -> Result<()> {
+        let sent_by_acceptor = info.flags & FLAG_SENT_BY_ACCEPTOR != 0;
+        let acceptor_subkey = info.flags & FLAG_ACCEPTOR_SUBKEY != 0;
+        if sent_by_acceptor != self.initiator
+            || acceptor_subkey != self.acceptor_subkey
+        {
+            return Err(GssError::DefectiveToken.into());
+        }
+        return self.recv_window.check(info.seq_number);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::gssapi::GSS_C_MUTUAL_FLAG;
+
+    /// Contexts of the initiator and the acceptor that share the key.
+    pub fn new_security_contexts(
+        key: EncryptionKey,
+        flags: u32,
+    ) -> (SecurityContext, SecurityContext) {
+        let acceptor_subkey = key.keytype != RC4_HMAC;
+        return (
+            SecurityContext::new(
+                key.clone(),
+                true,
+                acceptor_subkey,
+                flags,
+                100,
+                200,
+            ),
+            SecurityContext::new(key, false, acceptor_subkey, flags, 200, 100),
+        );
+    }
+
+    fn flags() -> u32 {
+        return GSS_C_MUTUAL_FLAG | GSS_C_REPLAY_FLAG | GSS_C_SEQUENCE_FLAG;
+    }
+
+    #[test]
+    fn exchange_tokens_with_aes_key() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let (mut initiator, mut acceptor) = new_security_contexts(key, flags());
+        assert!(!initiator.is_legacy());
+
+        let token = initiator.wrap(b"request", true).unwrap();
+        assert_eq!(
+            (b"request".to_vec(), true),
+            acceptor.unwrap(&token).unwrap()
+        );
+
+        let token = acceptor.get_mic(b"reply").unwrap();
+        initiator.verify_mic(b"reply", &token).unwrap();
+
+        let token = acceptor.wrap(b"clear", false).unwrap();
+        assert_eq!(
+            (b"clear".to_vec(), false),
+            initiator.unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn exchange_tokens_with_rc4_key() {
+        let key = EncryptionKey::new(RC4_HMAC, vec![1; 16]);
+        let (mut initiator, mut acceptor) = new_security_contexts(key, flags());
+        assert!(initiator.is_legacy());
+
+        let token = initiator.get_mic(b"request").unwrap();
+        acceptor.verify_mic(b"request", &token).unwrap();
+
+        let token = acceptor.wrap(b"reply", true).unwrap();
+        assert_eq!(
+            (b"reply".to_vec(), true),
+            initiator.unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_replayed_token() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let (mut initiator, mut acceptor) = new_security_contexts(key, flags());
+
+        let token = initiator.wrap(b"request", true).unwrap();
+        acceptor.unwrap(&token).unwrap();
+        assert_eq!(
+            Error::GssapiError(GssError::DuplicateToken),
+            acceptor.unwrap(&token).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn reject_reflected_token() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let (mut initiator, _) = new_security_contexts(key, flags());
+
+        let token = initiator.get_mic(b"request").unwrap();
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            initiator.verify_mic(b"request", &token).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use super::GssError;
+use crate::error::*;
+
+/// Number of tokens before the expected one that are remembered in order
+/// to detect duplicates
+const WINDOW_SIZE: u64 = 64;
+
+/// Tracks the sequence numbers of the received tokens, in order to detect
+/// the duplicated tokens (GSS_C_REPLAY_FLAG) and the ones received out of
+/// order (GSS_C_SEQUENCE_FLAG), as described in RFC 2743 section 1.2.3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceWindow {
+    replay_detection: bool,
+    sequencing: bool,
+    mask: u64,
+    base: u64,
+    /// Next expected sequence number, relative to the base
+    next: u64,
+    /// Tokens received before the next one, the lowest bit being the
+    /// previous one
+    received: u64,
+}
+
+impl SequenceWindow {
+    /// Creates a window that expects the given sequence number. The
+    /// sequence numbers are of 64 bits in case of `wide`, or 32 bits
+    /// otherwise.
+    pub fn new(
+        initial_seq_number: u64,
+        wide: bool,
+        replay_detection: bool,
+        sequencing: bool,
+    ) -> Self {
+        return Self {
+            replay_detection,
+            sequencing,
+            mask: if wide { u64::MAX } else { u32::MAX as u64 },
+            base: initial_seq_number,
+            next: 0,
+            received: 0,
+        };
+    }
+
+    /// Next sequence number expected.
+    pub fn next_seq_number(&self) -> u64 {
+        return self.base.wrapping_add(self.next) & self.mask;
+    }
+
+    /// Registers the sequence number of a received token, failing in case
+    /// the token is duplicated or, if sequencing is required, out of order.
+    pub fn check(&mut self, seq_number: u64) -> Result<()> {
+        if !self.replay_detection && !self.sequencing {
+            return Ok(());
+        }
+
+        let relative = seq_number.wrapping_sub(self.base) & self.mask;
+        let ahead = relative.wrapping_sub(self.next) & self.mask;
+        if ahead == 0 {
+            self.received = (self.received << 1) | 1;
+            self.next = (self.next + 1) & self.mask;
+            return Ok(());
+        }
+
+        if ahead <= self.mask / 2 {
+            self.received = if ahead < WINDOW_SIZE - 1 {
+                (self.received << (ahead + 1)) | 1
+            } else {
+                1
+            };
+            self.next = (relative + 1) & self.mask;
+            return self.error_if_sequencing(GssError::GapToken);
+        }
+
+        let behind = self.next.wrapping_sub(relative) & self.mask;
+        if behind > WINDOW_SIZE {
+            if self.sequencing {
+                return Err(GssError::UnseqToken.into());
+            }
+            return Err(GssError::OldToken.into());
+        }
+
+        let bit = 1 << (behind - 1);
+        if self.received & bit != 0 {
+            if self.replay_detection {
+                return Err(GssError::DuplicateToken.into());
+            }
+            return Ok(());
+        }
+        self.received |= bit;
+        return self.error_if_sequencing(GssError::UnseqToken);
+    }
+
+    fn error_if_sequencing(&self, error: GssError) -> Result<()> {
+        if self.sequencing {
+            return Err(error.into());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accept_tokens_in_order() {
+        let mut window =
+            SequenceWindow::new(u32::MAX as u64 - 1, false, true, true);
+        for seq_number in [u32::MAX as u64 - 1, u32::MAX as u64, 0, 1] {
+            window.check(seq_number).unwrap();
+        }
+        assert_eq!(2, window.next_seq_number());
+    }
+
+    #[test]
+    fn detect_duplicated_tokens() {
+        let mut window = SequenceWindow::new(100, true, true, false);
+        window.check(100).unwrap();
+        window.check(102).unwrap();
+        window.check(101).unwrap();
+
+        assert_eq!(
+            Error::GssapiError(GssError::DuplicateToken),
+            window.check(101).unwrap_err()
+        );
+        assert_eq!(
+            Error::GssapiError(GssError::OldToken),
+            window.check(100 - WINDOW_SIZE).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn detect_tokens_out_of_order() {
+        let mut window = SequenceWindow::new(10, true, true, true);
+        window.check(10).unwrap();
+
+        assert_eq!(
+            Error::GssapiError(GssError::GapToken),
+            window.check(12).unwrap_err()
+        );
+        assert_eq!(
+            Error::GssapiError(GssError::UnseqToken),
+            window.check(11).unwrap_err()
+        );
+        window.check(13).unwrap();
+    }
+
+    #[test]
+    fn accept_any_token_without_detection() {
+        let mut window = SequenceWindow::new(10, true, false, false);
+        window.check(10).unwrap();
+        window.check(10).unwrap();
+        window.check(1).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! MIC and Wrap tokens of RFC 4121, used with the AES encryption types.
+
+use super::GssError;
+use crate::error::*;
+use himmelblau_kerberos_asn1::EncryptionKey;
+use himmelblau_kerberos_constants::gssapi::{
+    FLAG_SEALED, TOK_ID_MIC, TOK_ID_WRAP,
+};
+use himmelblau_kerberos_crypto::{
+    checksum_type_from_etype, keyed_checksum, new_kerberos_cipher,
+};
+
+/// Size of the header of the tokens
+pub const TOKEN_HEADER_SIZE: usize = 16;
+
+/// Fields of a received token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub flags: u8,
+    pub seq_number: u64,
+}
+
+/// Builds a MIC token, whose checksum covers the message and the header.
+pub fn build_mic_token(
+    key: &EncryptionKey,
+    key_usage: i32,
+    flags: u8,
+    seq_number: u64,
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    let mut token = TOK_ID_MIC.to_be_bytes().to_vec();
+    token.push(flags);
+    token.extend_from_slice(&[0xff; 5]);
+    token.extend_from_slice(&seq_number.to_be_bytes());
+
+    let mut checksum = checksum(key, key_usage, message, &token)?;
+    token.append(&mut checksum);
+    return Ok(token);
+}
+
+/// Verifies a MIC token of the message.
+pub fn verify_mic_token(
+    key: &EncryptionKey,
+    key_usage: i32,
+    token: &[u8],
+    message: &[u8],
+) -> Result<TokenInfo> {
+    if token.len() < TOKEN_HEADER_SIZE
+        || token[..2] != TOK_ID_MIC.to_be_bytes()
+        || token[3..8] != [0xff; 5]
+    {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (header, token_checksum) = token.split_at(TOKEN_HEADER_SIZE);
+
+    if checksum(key, key_usage, message, header)? != token_checksum {
+        return Err(GssError::BadMic.into());
+    }
+    return Ok(token_info(header));
+}
+
+/// Builds a Wrap token. In case of confidentiality, the message is
+/// encrypted along with a copy of the header. Otherwise, the message is
+/// followed by a checksum of the message and the header.
+pub fn build_wrap_token(
+    key: &EncryptionKey,
+    key_usage: i32,
+    flags: u8,
+    seq_number: u64,
+    message: &[u8],
+    confidentiality: bool,
+) -> Result<Vec<u8>> {
+    let flags = if confidentiality {
+        flags | FLAG_SEALED
+    } else {
+        flags & !FLAG_SEALED
+    };
+    let header = wrap_header(flags, 0, 0, seq_number);
+
+    if confidentiality {
+        let mut plaintext = message.to_vec();
+        plaintext.extend_from_slice(&header);
+        let cipher = new_kerberos_cipher(key.keytype)?;
+
+        let mut token = header.to_vec();
+        token.append(&mut cipher.encrypt(&key.keyvalue, key_usage, &plaintext));
+        return Ok(token);
+    }
+
+    let mut checksum = checksum(key, key_usage, message, &header)?;
+    let mut token =
+        wrap_header(flags, checksum.len() as u16, 0, seq_number).to_vec();
+    token.extend_from_slice(message);
+    token.append(&mut checksum);
+    return Ok(token);
+}
+
+/// Verifies a Wrap token and returns its message, decrypted in case of
+/// confidentiality. The data of the token may be rotated, as indicated by
+/// the RRC field.
+pub fn unwrap_wrap_token(
+    key: &EncryptionKey,
+    key_usage: i32,
+    token: &[u8],
+) -> Result<(TokenInfo, Vec<u8>)> {
+    if token.len() < TOKEN_HEADER_SIZE
+        || token[..2] != TOK_ID_WRAP.to_be_bytes()
+        || token[3] != 0xff
+    {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (header, data) = token.split_at(TOKEN_HEADER_SIZE);
+    let info = token_info(header);
+    let ec = u16::from_be_bytes([header[4], header[5]]) as usize;
+    let rrc = u16::from_be_bytes([header[6], header[7]]) as usize;
+
+    let mut data = data.to_vec();
+    if !data.is_empty() {
+        let rotation = rrc % data.len();
+        data.rotate_left(rotation);
+    }
+
+    if info.flags & FLAG_SEALED != 0 {
+        let cipher = new_kerberos_cipher(key.keytype)?;
+        let plaintext = cipher
+            .decrypt(&key.keyvalue, key_usage, &data)
+            .map_err(|_| GssError::BadMic)?;
+        if plaintext.len() < ec + TOKEN_HEADER_SIZE {
+            return Err(GssError::DefectiveToken.into());
+        }
+
+        let (message, trailer) =
+            plaintext.split_at(plaintext.len() - ec - TOKEN_HEADER_SIZE);
+        let encrypted_header = &trailer[ec..];
+        if encrypted_header[..6] != header[..6]
+            || encrypted_header[8..] != header[8..]
+        {
+            return Err(GssError::BadMic.into());
+        }
+        return Ok((info, message.to_vec()));
+    }
+
+    if data.len() < ec {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (message, token_checksum) = data.split_at(data.len() - ec);
+    let header = wrap_header(info.flags, 0, 0, info.seq_number);
+    if checksum(key, key_usage, message, &header)? != token_checksum {
+        return Err(GssError::BadMic.into());
+    }
+    return Ok((info, message.to_vec()));
+}
+
+fn wrap_header(
+    flags: u8,
+    ec: u16,
+    rrc: u16,
+    seq_number: u64,
+) -> [u8; TOKEN_HEADER_SIZE] {
+    let mut header = [0xff; TOKEN_HEADER_SIZE];
+    header[..2].copy_from_slice(&TOK_ID_WRAP.to_be_bytes());
+    header[2] = flags;
+    header[4..6].copy_from_slice(&ec.to_be_bytes());
+    header[6..8].copy_from_slice(&rrc.to_be_bytes());
+    header[8..].copy_from_slice(&seq_number.to_be_bytes());
+    return header;
+}
+
+fn token_info(header: &[u8]) -> TokenInfo {
+    let mut seq_number = [0; 8];
+    seq_number.copy_from_slice(&header[8..TOKEN_HEADER_SIZE]);
+    return TokenInfo {
+        flags: header[2],
+        seq_number: u64::from_be_bytes(seq_number),
+    };
+}
+
+/// Keyed checksum of the message followed by the header, of the type that
+/// goes along with the key.
+fn checksum(
+    key: &EncryptionKey,
+    key_usage: i32,
+    message: &[u8],
+    header: &[u8],
+) -> Result<Vec<u8>> {
+    let mut data = message.to_vec();
+    data.extend_from_slice(header);
+    return Ok(keyed_checksum(
+        checksum_type_from_etype(key.keytype)?,
+        &key.keyvalue,
+        key_usage,
+        &data,
+    )?);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::gssapi::FLAG_SENT_BY_ACCEPTOR;
+    use himmelblau_kerberos_constants::key_usages::{
+        KEY_USAGE_ACCEPTOR_SEAL, KEY_USAGE_INITIATOR_SIGN,
+    };
+
+    fn key() -> EncryptionKey {
+        return EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![4; 32]);
+    }
+
+    #[test]
+    fn build_and_verify_mic_token() {
+        let token =
+            build_mic_token(&key(), KEY_USAGE_INITIATOR_SIGN, 0, 5, b"hello")
+                .unwrap();
+
+        assert_eq!(TOKEN_HEADER_SIZE + 12, token.len());
+        assert_eq!(
+            TokenInfo {
+                flags: 0,
+                seq_number: 5
+            },
+            verify_mic_token(
+                &key(),
+                KEY_USAGE_INITIATOR_SIGN,
+                &token,
+                b"hello"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            verify_mic_token(&key(), KEY_USAGE_INITIATOR_SIGN, &token, b"bye")
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn wrap_and_unwrap_with_confidentiality() {
+        let token = build_wrap_token(
+            &key(),
+            KEY_USAGE_ACCEPTOR_SEAL,
+            FLAG_SENT_BY_ACCEPTOR,
+            7,
+            b"secret",
+            true,
+        )
+        .unwrap();
+
+        let (info, message) =
+            unwrap_wrap_token(&key(), KEY_USAGE_ACCEPTOR_SEAL, &token).unwrap();
+        assert_eq!(FLAG_SENT_BY_ACCEPTOR | FLAG_SEALED, info.flags);
+        assert_eq!(7, info.seq_number);
+        assert_eq!(b"secret".to_vec(), message);
+    }
+
+    #[test]
+    fn unwrap_rotated_token() {
+        let mut token = build_wrap_token(
+            &key(),
+            KEY_USAGE_ACCEPTOR_SEAL,
+            0,
+            7,
+            b"data",
+            true,
+        )
+        .unwrap();
+        token[6..8].copy_from_slice(&28_u16.to_be_bytes());
+        token[TOKEN_HEADER_SIZE..].rotate_right(28);
+
+        let (_, message) =
+            unwrap_wrap_token(&key(), KEY_USAGE_ACCEPTOR_SEAL, &token).unwrap();
+        assert_eq!(b"data".to_vec(), message);
+    }
+
+    #[test]
+    fn wrap_and_unwrap_without_confidentiality() {
+        let mut token = build_wrap_token(
+            &key(),
+            KEY_USAGE_ACCEPTOR_SEAL,
+            0,
+            1,
+            b"clear",
+            false,
+        )
+        .unwrap();
+        assert_eq!(b"clear", &token[TOKEN_HEADER_SIZE..TOKEN_HEADER_SIZE + 5]);
+
+        let (info, message) =
+            unwrap_wrap_token(&key(), KEY_USAGE_ACCEPTOR_SEAL, &token).unwrap();
+        assert_eq!(0, info.flags);
+        assert_eq!(b"clear".to_vec(), message);
+
+        token[TOKEN_HEADER_SIZE] = b'C';
+        assert_eq!(
+            Error::GssapiError(GssError::BadMic),
+            unwrap_wrap_token(&key(), KEY_USAGE_ACCEPTOR_SEAL, &token)
+                .unwrap_err()
+        );
+    }
+}
@@ -51,7 +51,26 @@ pub use credentials::*;
 pub mod requesters;
 pub use requesters::*;
 
+pub mod ap;
+pub use ap::*;
+
+pub mod gssapi;
+pub use gssapi::*;
+
+pub mod spnego;
+pub use spnego::*;
+
+pub mod sasl;
+pub use sasl::*;
+
+pub mod pac;
+pub use pac::*;
+
 pub mod utils;
 pub use utils::*;
 
+pub mod discovery;
+
+pub mod config;
+
 mod mappers;
@@ -1,17 +1,19 @@
 use super::options::AsReqOptions;
+use super::preauth_hints::PreauthHints;
 use super::timestamp_cipher::*;
 use crate::error::*;
+use crate::messages::requested_end_time;
 use ascii::AsciiString;
-use chrono::{Duration, Utc};
 use himmelblau_kerberos_asn1::{
     AsReq, Asn1Object, EncryptedData, KerbPaPacRequest, KerberosString, PaData,
     PrincipalName,
 };
+use himmelblau_kerberos_constants::kdc_options::{CANONICALIZE, POSTDATED};
 use himmelblau_kerberos_constants::pa_data_types::{
     PA_ENC_TIMESTAMP, PA_PAC_REQUEST,
 };
 use himmelblau_kerberos_constants::principal_names::{
-    NT_PRINCIPAL, NT_SRV_INST,
+    NT_ENTERPRISE, NT_SRV_INST,
 };
 use himmelblau_kerberos_crypto::Key;
 use rand::Rng;
@@ -20,6 +22,7 @@ pub(crate) struct AsReqBuilder<'a> {
     username: &'a AsciiString,
     user_key: Option<&'a Key>,
     options: &'a AsReqOptions,
+    hints: Option<&'a PreauthHints>,
 }
 
 impl<'a> AsReqBuilder<'a> {
@@ -27,11 +30,13 @@ impl<'a> AsReqBuilder<'a> {
         username: &'a AsciiString,
         user_key: Option<&'a Key>,
         options: &'a AsReqOptions,
+        hints: Option<&'a PreauthHints>,
     ) -> Self {
         return Self {
             username,
             user_key,
             options,
+            hints,
         };
     }
 
@@ -39,8 +44,9 @@ impl<'a> AsReqBuilder<'a> {
         username: &'a AsciiString,
         user_key: Option<&'a Key>,
         options: &'a AsReqOptions,
+        hints: Option<&'a PreauthHints>,
     ) -> Result<Vec<u8>> {
-        let builder = Self::new(username, user_key, options);
+        let builder = Self::new(username, user_key, options, hints);
         let as_req = builder.create_as_req_struct()?;
         return Ok(as_req.build());
     }
@@ -48,31 +54,40 @@ impl<'a> AsReqBuilder<'a> {
     fn create_as_req_struct(&self) -> Result<AsReq> {
         let mut as_req = AsReq::default();
         as_req.req_body.cname = Some(PrincipalName::new(
-            NT_PRINCIPAL,
+            self.options.name_type(),
             self.username.clone().into(),
         ));
         as_req.req_body.realm = self.options.realm().clone().into();
         as_req.req_body.kdc_options = self.options.kdc_options().into();
 
-        as_req.req_body.sname = Some(PrincipalName {
-            name_type: NT_SRV_INST,
-            name_string: vec![
-                KerberosString::from("krbtgt"),
-                self.options.realm().clone().into(),
-            ],
+        // Enterprise names must be canonicalized by the KDC (RFC 6806)
+        if self.options.name_type() == NT_ENTERPRISE {
+            *as_req.req_body.kdc_options |= CANONICALIZE;
+        }
+
+        as_req.req_body.sname = Some(match self.options.sname() {
+            Some(sname) => sname.clone(),
+            None => PrincipalName {
+                name_type: NT_SRV_INST,
+                name_string: vec![
+                    KerberosString::from("krbtgt"),
+                    self.options.realm().clone().into(),
+                ],
+            },
         });
 
+        let starttime = self.options.starttime();
         as_req.req_body.rtime = Some(
-            Utc::now()
-                .checked_add_signed(Duration::weeks(20 * 52))
-                .unwrap()
-                .into(),
+            requested_end_time(starttime, self.options.renew_lifetime()).into(),
         );
+        as_req.req_body.till =
+            requested_end_time(starttime, self.options.ticket_lifetime())
+                .into();
 
-        as_req.req_body.till = Utc::now()
-            .checked_add_signed(Duration::weeks(20 * 52))
-            .unwrap()
-            .into();
+        if let Some(starttime) = starttime {
+            as_req.req_body.from = Some((*starttime).into());
+            *as_req.req_body.kdc_options |= POSTDATED;
+        }
 
         as_req.req_body.nonce = rand::thread_rng().gen::<u32>();
 
@@ -116,6 +131,7 @@ impl<'a> AsReqBuilder<'a> {
             self.username,
             user_key,
             &self.options.sorted_etypes(),
+            self.hints,
         );
     }
 }
@@ -123,6 +139,7 @@ impl<'a> AsReqBuilder<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use chrono::{Duration, Utc};
     use himmelblau_kerberos_constants::etypes::*;
 
     #[test]
@@ -180,12 +197,79 @@ mod test {
         );
     }
 
+    #[test]
+    fn postdated_as_req_when_starttime_is_provided() {
+        let username = AsciiString::from_ascii("Mickey").unwrap();
+        let mut options = AsReqOptions::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+        );
+        let starttime = Utc::now() + Duration::hours(8);
+        options.set_starttime(Some(starttime));
+        options.set_ticket_lifetime(Some(Duration::hours(10)));
+
+        let builder = AsReqBuilder::new(&username, None, &options, None);
+        let as_req_struct = builder.create_as_req_struct().unwrap();
+
+        assert_eq!(
+            options.kdc_options() | POSTDATED,
+            as_req_struct.req_body.kdc_options.flags
+        );
+        assert_eq!(Some(starttime.into()), as_req_struct.req_body.from);
+        assert_eq!(
+            (starttime + Duration::hours(10)).timestamp(),
+            as_req_struct.req_body.till.time.time.timestamp()
+        );
+    }
+
+    #[test]
+    fn as_req_with_configured_lifetimes() {
+        let username = AsciiString::from_ascii("Mickey").unwrap();
+        let mut options = AsReqOptions::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+        );
+        options.set_ticket_lifetime(Some(Duration::hours(10)));
+        options.set_renew_lifetime(Some(Duration::days(7)));
+
+        let builder = AsReqBuilder::new(&username, None, &options, None);
+        let as_req_struct = builder.create_as_req_struct().unwrap();
+
+        let till = as_req_struct.req_body.till.time.time;
+        assert!(till > Utc::now() + Duration::hours(9));
+        assert!(till <= Utc::now() + Duration::hours(10));
+
+        let rtime = as_req_struct.req_body.rtime.unwrap().time.time;
+        assert!(rtime > Utc::now() + Duration::days(6));
+        assert!(rtime <= Utc::now() + Duration::days(7));
+    }
+
+    #[test]
+    fn canonicalized_as_req_with_enterprise_name() {
+        let username = AsciiString::from_ascii("mickey@disney.com").unwrap();
+        let mut options = AsReqOptions::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+        );
+        options.set_kdc_options(0);
+        options.set_name_type(NT_ENTERPRISE);
+
+        let builder = AsReqBuilder::new(&username, None, &options, None);
+        let as_req_struct = builder.create_as_req_struct().unwrap();
+
+        assert_eq!(
+            Some(PrincipalName::new(
+                NT_ENTERPRISE,
+                KerberosString::from("mickey@disney.com")
+            )),
+            as_req_struct.req_body.cname
+        );
+        assert_eq!(CANONICALIZE, as_req_struct.req_body.kdc_options.flags);
+    }
+
     fn create_as_req_struct_with_key(user_key: Option<&Key>) -> AsReq {
         let username = AsciiString::from_ascii("Mickey").unwrap();
         let options = AsReqOptions::new(
             AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
         );
-        let builder = AsReqBuilder::new(&username, user_key, &options);
+        let builder = AsReqBuilder::new(&username, user_key, &options, None);
 
         return builder.create_as_req_struct().unwrap();
     }
@@ -5,3 +5,6 @@ mod options;
 pub(crate) use options::AsReqOptions;
 
 mod timestamp_cipher;
+
+mod preauth_hints;
+pub use preauth_hints::{EtypeHint, PreauthHints};
@@ -1,26 +1,35 @@
 use crate::Result;
 use ascii::AsciiString;
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::PrincipalName;
 use himmelblau_kerberos_constants::etypes::{
     AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
 };
 use himmelblau_kerberos_constants::kdc_options::{
     CANONICALIZE, FORWARDABLE, RENEWABLE, RENEWABLE_OK,
 };
+use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
 use himmelblau_kerberos_crypto::is_supported_etype;
 use std::collections::HashSet;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) struct AsReqOptions {
     realm: AsciiString,
+    name_type: i32,
     etypes: HashSet<i32>,
     kdc_options: u32,
     pac: bool,
+    starttime: Option<DateTime<Utc>>,
+    ticket_lifetime: Option<Duration>,
+    renew_lifetime: Option<Duration>,
+    sname: Option<PrincipalName>,
 }
 
 impl AsReqOptions {
     pub fn new(realm: AsciiString) -> Self {
         return Self {
             realm,
+            name_type: NT_PRINCIPAL,
             kdc_options: FORWARDABLE | RENEWABLE | CANONICALIZE | RENEWABLE_OK,
             etypes: [
                 AES256_CTS_HMAC_SHA1_96,
@@ -31,6 +40,10 @@ impl AsReqOptions {
             .cloned()
             .collect(),
             pac: true,
+            starttime: None,
+            ticket_lifetime: None,
+            renew_lifetime: None,
+            sname: None,
         };
     }
 
@@ -38,6 +51,18 @@ impl AsReqOptions {
         return &self.realm;
     }
 
+    pub fn set_realm(&mut self, realm: AsciiString) {
+        self.realm = realm;
+    }
+
+    pub fn name_type(&self) -> i32 {
+        return self.name_type;
+    }
+
+    pub fn set_name_type(&mut self, name_type: i32) {
+        self.name_type = name_type;
+    }
+
     pub fn etypes(&self) -> &HashSet<i32> {
         return &self.etypes;
     }
@@ -90,6 +115,42 @@ impl AsReqOptions {
         return self.kdc_options;
     }
 
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    pub fn starttime(&self) -> Option<&DateTime<Utc>> {
+        return self.starttime.as_ref();
+    }
+
+    pub fn set_starttime(&mut self, starttime: Option<DateTime<Utc>>) {
+        self.starttime = starttime;
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.ticket_lifetime;
+    }
+
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.ticket_lifetime = ticket_lifetime;
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.renew_lifetime;
+    }
+
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.renew_lifetime = renew_lifetime;
+    }
+
+    pub fn sname(&self) -> Option<&PrincipalName> {
+        return self.sname.as_ref();
+    }
+
+    pub fn set_sname(&mut self, sname: Option<PrincipalName>) {
+        self.sname = sname;
+    }
+
     pub fn should_be_pac_included(&self) -> bool {
         return self.pac;
     }
@@ -0,0 +1,237 @@
+use himmelblau_kerberos_asn1::{
+    Asn1Object, EtypeInfo, EtypeInfo2, EtypeInfo2Entry, EtypeInfoEntry,
+    KrbError, MethodData, PaData,
+};
+use himmelblau_kerberos_constants::pa_data_types::{
+    PA_ETYPE_INFO, PA_ETYPE_INFO2, PA_PW_SALT,
+};
+
+/// Encryption type, salt and string-to-key parameters required to derive
+/// the user key, as indicated by the KDC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EtypeHint {
+    pub etype: i32,
+    pub salt: Option<Vec<u8>>,
+    pub s2kparams: Vec<u8>,
+}
+
+/// Pre-authentication hints supplied by the KDC, in the METHOD-DATA of a
+/// KDC_ERR_PREAUTH_REQUIRED error or in the padata of an AS-REP.
+///
+/// Malformed entries are ignored, since hints are advisory.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PreauthHints {
+    etype_info2: Vec<EtypeInfo2Entry>,
+    etype_info: Vec<EtypeInfoEntry>,
+    pw_salt: Option<Vec<u8>>,
+    padata_types: Vec<i32>,
+}
+
+impl PreauthHints {
+    /// Parses the hints from the e-data of a KRB-ERROR.
+    pub fn from_krb_error(krb_error: &KrbError) -> Self {
+        if let Some(e_data) = &krb_error.e_data {
+            if let Ok((_, method_data)) = MethodData::parse(e_data) {
+                return Self::from_padata(&method_data);
+            }
+        }
+        return Self::default();
+    }
+
+    pub fn from_padata(padata: &[PaData]) -> Self {
+        let mut hints = Self::default();
+
+        for entry in padata.iter() {
+            hints.padata_types.push(entry.padata_type);
+
+            match entry.padata_type {
+                PA_ETYPE_INFO2 => {
+                    if let Ok((_, etype_info2)) =
+                        EtypeInfo2::parse(&entry.padata_value)
+                    {
+                        hints.etype_info2 = etype_info2;
+                    }
+                }
+                PA_ETYPE_INFO => {
+                    if let Ok((_, etype_info)) =
+                        EtypeInfo::parse(&entry.padata_value)
+                    {
+                        hints.etype_info = etype_info;
+                    }
+                }
+                PA_PW_SALT => {
+                    hints.pw_salt = Some(entry.padata_value.clone());
+                }
+                _ => {}
+            }
+        }
+
+        return hints;
+    }
+
+    pub fn etype_info2(&self) -> &[EtypeInfo2Entry] {
+        return &self.etype_info2;
+    }
+
+    pub fn etype_info(&self) -> &[EtypeInfoEntry] {
+        return &self.etype_info;
+    }
+
+    pub fn pw_salt(&self) -> Option<&[u8]> {
+        return self.pw_salt.as_deref();
+    }
+
+    /// Types of the padata offered by the KDC.
+    pub fn padata_types(&self) -> &[i32] {
+        return &self.padata_types;
+    }
+
+    pub fn is_padata_type_offered(&self, padata_type: i32) -> bool {
+        return self.padata_types.contains(&padata_type);
+    }
+
+    /// Returns the hints of all the etypes, in the order of preference of
+    /// the KDC. ETYPE-INFO2 takes precedence over ETYPE-INFO.
+    pub fn etype_hints(&self) -> Vec<EtypeHint> {
+        if !self.etype_info2.is_empty() {
+            return self
+                .etype_info2
+                .iter()
+                .map(|entry| EtypeHint {
+                    etype: entry.etype,
+                    salt: entry
+                        .salt
+                        .as_ref()
+                        .map(|salt| salt.as_bytes().to_vec())
+                        .or_else(|| self.pw_salt.clone()),
+                    s2kparams: entry.s2kparams.clone().unwrap_or_default(),
+                })
+                .collect();
+        }
+
+        return self
+            .etype_info
+            .iter()
+            .map(|entry| EtypeHint {
+                etype: entry.etype,
+                salt: entry.salt.clone().or_else(|| self.pw_salt.clone()),
+                s2kparams: Vec::new(),
+            })
+            .collect();
+    }
+
+    /// Returns the hint of the given etype.
+    pub fn etype_hint(&self, etype: i32) -> Option<EtypeHint> {
+        return self
+            .etype_hints()
+            .into_iter()
+            .find(|hint| hint.etype == etype);
+    }
+
+    /// Selects the first etype preferred by the KDC that is included
+    /// in the etypes of the client.
+    pub fn select_etype(&self, etypes: &[i32]) -> Option<EtypeHint> {
+        return self
+            .etype_hints()
+            .into_iter()
+            .find(|hint| etypes.contains(&hint.etype));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::KerberosString;
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_PREAUTH_REQUIRED;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+    };
+    use himmelblau_kerberos_constants::pa_data_types::PA_ENC_TIMESTAMP;
+
+    fn create_krb_error(method_data: MethodData) -> KrbError {
+        return KrbError {
+            pvno: 5,
+            msg_type: 30,
+            error_code: KDC_ERR_PREAUTH_REQUIRED,
+            e_data: Some(method_data.build()),
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn parse_hints_from_krb_error() {
+        let etype_info2: EtypeInfo2 = vec![
+            EtypeInfo2Entry::new(
+                AES256_CTS_HMAC_SHA1_96,
+                Some(KerberosString::from("KINGDOM.HEARTSmickey.mouse")),
+                Some(vec![0, 0, 0x10, 0]),
+            ),
+            EtypeInfo2Entry::new(RC4_HMAC, None, None),
+        ];
+        let krb_error = create_krb_error(vec![
+            PaData::new(PA_ENC_TIMESTAMP, Vec::new()),
+            PaData::new(PA_ETYPE_INFO2, etype_info2.build()),
+        ]);
+
+        let hints = PreauthHints::from_krb_error(&krb_error);
+
+        assert_eq!(&etype_info2[..], hints.etype_info2());
+        assert!(hints.is_padata_type_offered(PA_ENC_TIMESTAMP));
+        assert!(!hints.is_padata_type_offered(PA_PW_SALT));
+        assert_eq!(
+            Some(EtypeHint {
+                etype: AES256_CTS_HMAC_SHA1_96,
+                salt: Some(b"KINGDOM.HEARTSmickey.mouse".to_vec()),
+                s2kparams: vec![0, 0, 0x10, 0],
+            }),
+            hints.select_etype(&[
+                AES256_CTS_HMAC_SHA1_96,
+                AES128_CTS_HMAC_SHA1_96,
+                RC4_HMAC
+            ])
+        );
+        assert_eq!(
+            Some(EtypeHint {
+                etype: RC4_HMAC,
+                salt: None,
+                s2kparams: Vec::new(),
+            }),
+            hints.select_etype(&[AES128_CTS_HMAC_SHA1_96, RC4_HMAC])
+        );
+        assert_eq!(None, hints.select_etype(&[AES128_CTS_HMAC_SHA1_96]));
+    }
+
+    #[test]
+    fn etype_info_and_pw_salt_hints() {
+        let etype_info: EtypeInfo = vec![EtypeInfoEntry {
+            etype: AES128_CTS_HMAC_SHA1_96,
+            salt: None,
+        }];
+        let hints = PreauthHints::from_padata(&[
+            PaData::new(PA_ETYPE_INFO, etype_info.build()),
+            PaData::new(PA_PW_SALT, b"custom-salt".to_vec()),
+        ]);
+
+        assert_eq!(
+            Some(EtypeHint {
+                etype: AES128_CTS_HMAC_SHA1_96,
+                salt: Some(b"custom-salt".to_vec()),
+                s2kparams: Vec::new(),
+            }),
+            hints.etype_hint(AES128_CTS_HMAC_SHA1_96)
+        );
+    }
+
+    #[test]
+    fn empty_hints_from_krb_error_without_method_data() {
+        let krb_error = KrbError {
+            e_data: Some(vec![0xff, 0x00]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PreauthHints::default(),
+            PreauthHints::from_krb_error(&krb_error)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::preauth_hints::PreauthHints;
 use crate::{Error, Result};
 use ascii::AsciiString;
 use chrono::Utc;
@@ -14,6 +15,7 @@ pub struct AsReqTimestampCipher<'a> {
     username: &'a AsciiString,
     user_key: &'a Key,
     etypes: &'a Vec<i32>,
+    hints: Option<&'a PreauthHints>,
     raw_timestamp: Vec<u8>,
 }
 
@@ -23,8 +25,10 @@ impl<'a> AsReqTimestampCipher<'a> {
         username: &'a AsciiString,
         user_key: &'a Key,
         etypes: &'a Vec<i32>,
+        hints: Option<&'a PreauthHints>,
     ) -> Result<(i32, Vec<u8>)> {
-        let timestamp_builder = Self::new(realm, username, user_key, etypes);
+        let timestamp_builder =
+            Self::new(realm, username, user_key, etypes, hints);
         return timestamp_builder.produce_encrypted_timestamp();
     }
 
@@ -33,12 +37,14 @@ impl<'a> AsReqTimestampCipher<'a> {
         username: &'a AsciiString,
         user_key: &'a Key,
         etypes: &'a Vec<i32>,
+        hints: Option<&'a PreauthHints>,
     ) -> Self {
         return Self {
             realm,
             username,
             user_key,
             etypes,
+            hints,
             raw_timestamp: Self::produce_raw_timestamp(),
         };
     }
@@ -77,6 +83,24 @@ impl<'a> AsReqTimestampCipher<'a> {
         &self,
         password: &str,
     ) -> Result<(i32, Vec<u8>)> {
+        if let Some(hints) = self.hints {
+            if let Some(hint) = hints.select_etype(self.etypes) {
+                let salt = match hint.salt {
+                    Some(salt) => salt,
+                    None if hint.etype == RC4_HMAC => Vec::new(),
+                    None => self.calculate_aes_salt(),
+                };
+                let cipher = new_kerberos_cipher(hint.etype)?;
+                let key = cipher.generate_key_from_string_with_params(
+                    password,
+                    &salt,
+                    &hint.s2kparams,
+                )?;
+                return self
+                    .encrypt_timestamp_with_cipher_and_key(hint.etype, &key);
+            }
+        }
+
         let etype;
         let salt;
 
@@ -149,6 +173,10 @@ impl<'a> AsReqTimestampCipher<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use himmelblau_kerberos_asn1::{
+        EtypeInfo2, EtypeInfo2Entry, KerberosString, PaData,
+    };
+    use himmelblau_kerberos_constants::pa_data_types::PA_ETYPE_INFO2;
 
     #[test]
     fn produce_encrypted_timestamp_with_rc4_key() {
@@ -164,6 +192,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::RC4Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -188,6 +217,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::AES128Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -213,6 +243,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::AES256Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -237,6 +268,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::RC4Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -262,6 +294,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::AES128Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -288,6 +321,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::AES256Key(key.clone()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -307,6 +341,7 @@ mod test {
             &AsciiString::from_ascii("Mickey").unwrap(),
             &Key::Secret("password".to_string()),
             &etypes,
+            None,
         )
         .unwrap();
     }
@@ -324,6 +359,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::Secret(password.to_string()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -351,6 +387,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::Secret(password.to_string()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -377,6 +414,7 @@ mod test {
                 &AsciiString::from_ascii("Mickey").unwrap(),
                 &Key::Secret(password.to_string()),
                 &etypes,
+                None,
             )
             .unwrap();
 
@@ -391,4 +429,45 @@ mod test {
             )
             .unwrap();
     }
+
+    #[test]
+    fn produce_encrypted_timestamp_with_password_following_kdc_hints() {
+        let etypes = vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96];
+        let password = "password";
+        let etype_info2: EtypeInfo2 = vec![
+            EtypeInfo2Entry::new(RC4_HMAC, None, None),
+            EtypeInfo2Entry::new(
+                AES128_CTS_HMAC_SHA1_96,
+                Some(KerberosString::from("ATHENA.MIT.EDUraeburn")),
+                Some(vec![0, 0, 0, 2]),
+            ),
+        ];
+        let hints = PreauthHints::from_padata(&[PaData::new(
+            PA_ETYPE_INFO2,
+            etype_info2.build(),
+        )]);
+
+        let (result_etype, timestamp) =
+            AsReqTimestampCipher::build_encrypted_timestamp(
+                &AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+                &AsciiString::from_ascii("Mickey").unwrap(),
+                &Key::Secret(password.to_string()),
+                &etypes,
+                Some(&hints),
+            )
+            .unwrap();
+
+        assert_eq!(AES128_CTS_HMAC_SHA1_96, result_etype);
+        let cipher = new_kerberos_cipher(AES128_CTS_HMAC_SHA1_96).unwrap();
+        let key = cipher
+            .generate_key_from_string_with_params(
+                password,
+                b"ATHENA.MIT.EDUraeburn",
+                &[0, 0, 0, 2],
+            )
+            .unwrap();
+        cipher
+            .decrypt(&key, KEY_USAGE_AS_REQ_TIMESTAMP, &timestamp)
+            .unwrap();
+    }
 }
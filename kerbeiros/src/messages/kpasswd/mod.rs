@@ -0,0 +1,7 @@
+mod request;
+#[cfg(test)]
+pub(crate) use request::test::{create_changepw_credential, kpasswd_reply};
+pub(crate) use request::KpasswdRequest;
+
+mod result;
+pub use result::{KpasswdResultCode, PasswordPolicy};
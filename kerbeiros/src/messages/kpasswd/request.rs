@@ -0,0 +1,358 @@
+use super::result::{kpasswd_result, KpasswdResultCode};
+use crate::ap::{ApReqBuilder, ApRequest, AuthContext};
+use crate::credentials::Credential;
+use crate::error::*;
+use himmelblau_kerberos_asn1::{
+    Asn1Object, ChangePasswdData, KrbError, KrbPriv, PrincipalName, Realm,
+};
+use himmelblau_kerberos_constants::kpasswd::{
+    KPASSWD_VERSION_CHANGE, KPASSWD_VERSION_SET,
+};
+
+/// Size of the header of the kpasswd messages: message length, version
+/// and AP-REQ or AP-REP length
+const KPASSWD_HEADER_SIZE: usize = 6;
+
+/// Builds the requests of the password change protocol (RFC 3244) and
+/// parses their replies. The requests are authenticated with a ticket for
+/// the kadmin/changepw service, and the password is encrypted in a
+/// KRB-PRIV message with a random subkey.
+pub(crate) struct KpasswdRequest {
+    ap_request: ApRequest,
+}
+
+impl KpasswdRequest {
+    pub fn new(credential: &Credential) -> Result<Self> {
+        let mut builder = ApReqBuilder::new(credential);
+        builder.generate_subkey()?;
+        builder.generate_seq_number();
+
+        return Ok(Self {
+            ap_request: builder.build()?,
+        });
+    }
+
+    /// Builds a change password request (version 1), whose user data is
+    /// the new password of the client.
+    pub fn build_change_password(&self, new_password: &str) -> Result<Vec<u8>> {
+        return self.build(KPASSWD_VERSION_CHANGE, new_password.as_bytes());
+    }
+
+    /// Builds a set password request (Microsoft version 0xff80), which
+    /// includes the principal whose password is set.
+    pub fn build_set_password(
+        &self,
+        new_password: &str,
+        targname: &PrincipalName,
+        targrealm: &Realm,
+    ) -> Result<Vec<u8>> {
+        let change_passwd_data = ChangePasswdData {
+            newpasswd: new_password.as_bytes().to_vec(),
+            targname: Some(targname.clone()),
+            targrealm: Some(targrealm.clone()),
+        };
+        return self.build(KPASSWD_VERSION_SET, &change_passwd_data.build());
+    }
+
+    fn build(&self, version: u16, user_data: &[u8]) -> Result<Vec<u8>> {
+        let ap_req = self.ap_request.build();
+        // The address used to reach the kpasswd server is not known, so
+        // the directional address of the initiator is sent
+        let krb_priv = AuthContext::from_ap_request(&self.ap_request, None)
+            .build_priv(user_data)?
+            .build();
+
+        let length = KPASSWD_HEADER_SIZE + ap_req.len() + krb_priv.len();
+        if length > u16::MAX as usize {
+            return Err(Error::KpasswdError(
+                KpasswdResultCode::Malformed,
+                format!("request of {} bytes is too large", length),
+            ));
+        }
+
+        let mut raw_request = Vec::with_capacity(length);
+        raw_request.extend_from_slice(&(length as u16).to_be_bytes());
+        raw_request.extend_from_slice(&version.to_be_bytes());
+        raw_request.extend_from_slice(&(ap_req.len() as u16).to_be_bytes());
+        raw_request.extend_from_slice(&ap_req);
+        raw_request.extend_from_slice(&krb_priv);
+        return Ok(raw_request);
+    }
+
+    /// Parses the reply of the kpasswd server, which contains an AP-REP
+    /// followed by a KRB-PRIV with the result, or a KRB-ERROR.
+    pub fn parse_reply(&self, raw_reply: &[u8]) -> Result<()> {
+        if raw_reply.len() < KPASSWD_HEADER_SIZE {
+            return Err(Error::BinaryParseError);
+        }
+        let length = u16::from_be_bytes([raw_reply[0], raw_reply[1]]);
+        let version = u16::from_be_bytes([raw_reply[2], raw_reply[3]]);
+        let ap_rep_length =
+            u16::from_be_bytes([raw_reply[4], raw_reply[5]]) as usize;
+
+        if length as usize != raw_reply.len()
+            || (version != KPASSWD_VERSION_CHANGE
+                && version != KPASSWD_VERSION_SET)
+        {
+            return Err(Error::BinaryParseError);
+        }
+
+        let raw_reply = &raw_reply[KPASSWD_HEADER_SIZE..];
+        if ap_rep_length == 0 {
+            let (_, krb_error) = KrbError::parse(raw_reply)?;
+            return Err(krb_error_result(krb_error));
+        }
+        if ap_rep_length > raw_reply.len() {
+            return Err(Error::BinaryParseError);
+        }
+
+        let ap_rep_info =
+            self.ap_request.verify_ap_rep(&raw_reply[..ap_rep_length])?;
+
+        let (_, krb_priv) = KrbPriv::parse(&raw_reply[ap_rep_length..])?;
+        // As other clients, only the sequence number of the reply is
+        // verified, since the kpasswd servers may not include a timestamp
+        let mut auth_context =
+            AuthContext::from_ap_request(&self.ap_request, Some(&ap_rep_info));
+        auth_context.set_use_timestamps(false);
+        let result = auth_context.decrypt_priv(&krb_priv)?;
+
+        if result.len() < 2 {
+            return Err(Error::BinaryParseError);
+        }
+        return kpasswd_result(
+            u16::from_be_bytes([result[0], result[1]]),
+            &result[2..],
+        );
+    }
+}
+
+/// Error of a KRB-ERROR reply, whose e-data may include the kpasswd
+/// result code and string.
+fn krb_error_result(krb_error: KrbError) -> Error {
+    if let Some(e_data) = &krb_error.e_data {
+        if e_data.len() >= 2 {
+            let result_code = u16::from_be_bytes([e_data[0], e_data[1]]);
+            if let Err(error) = kpasswd_result(result_code, &e_data[2..]) {
+                return error;
+            }
+        }
+    }
+    return Error::KrbErrorResponse(krb_error);
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::ap::test::{
+        create_credential, new_enc_ticket_part, service_key, session_key,
+    };
+    use crate::utils::{decrypt, encrypt};
+    use himmelblau_kerberos_asn1::{
+        ApRep, ApReq, Authenticator, EncApRepPart, EncKrbPrivPart,
+        EncryptionKey, HostAddress, KerberosString,
+    };
+    use himmelblau_kerberos_constants::address_types::DIRECTIONAL;
+    use himmelblau_kerberos_constants::key_usages::{
+        KEY_USAGE_AP_REP_ENC_PART, KEY_USAGE_AP_REQ_AUTHEN,
+        KEY_USAGE_KRB_PRIV_ENC_PART,
+    };
+    use himmelblau_kerberos_constants::kpasswd::*;
+    use himmelblau_kerberos_constants::message_types::KRB_PRIV;
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_PRINCIPAL, NT_SRV_INST,
+    };
+    use himmelblau_kerberos_constants::protocol_version::PVNO;
+
+    /// Credential with a ticket for the kadmin/changepw service
+    pub fn create_changepw_credential() -> Credential {
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("kadmin"));
+        sname.push(KerberosString::from("changepw"));
+        return create_credential(
+            sname,
+            &service_key(1),
+            Some(1),
+            &new_enc_ticket_part(),
+        );
+    }
+
+    /// Kpasswd server that decrypts the request with the session key and
+    /// replies with the given result. Returns the user data of the
+    /// request, along with the reply.
+    pub fn kpasswd_reply(
+        session_key: &EncryptionKey,
+        raw_request: &[u8],
+        result_code: u16,
+        result_string: &[u8],
+    ) -> (u16, Vec<u8>, Vec<u8>) {
+        let version = u16::from_be_bytes([raw_request[2], raw_request[3]]);
+        let ap_req_length =
+            u16::from_be_bytes([raw_request[4], raw_request[5]]) as usize;
+        let (_, ap_req) =
+            ApReq::parse(&raw_request[6..6 + ap_req_length]).unwrap();
+        let (_, authenticator) = Authenticator::parse(
+            &decrypt(
+                session_key,
+                KEY_USAGE_AP_REQ_AUTHEN,
+                &ap_req.authenticator,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let subkey = authenticator.subkey.unwrap();
+
+        let (_, krb_priv) =
+            KrbPriv::parse(&raw_request[6 + ap_req_length..]).unwrap();
+        let (_, enc_krb_priv_part) = EncKrbPrivPart::parse(
+            &decrypt(&subkey, KEY_USAGE_KRB_PRIV_ENC_PART, &krb_priv.enc_part)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(authenticator.seq_number, enc_krb_priv_part.seq_number);
+
+        let ap_rep = ApRep {
+            pvno: PVNO,
+            msg_type: 15,
+            enc_part: encrypt(
+                session_key,
+                KEY_USAGE_AP_REP_ENC_PART,
+                &EncApRepPart {
+                    ctime: authenticator.ctime,
+                    cusec: authenticator.cusec,
+                    subkey: None,
+                    seq_number: Some(1),
+                }
+                .build(),
+            )
+            .unwrap(),
+        }
+        .build();
+
+        let mut user_data = result_code.to_be_bytes().to_vec();
+        user_data.extend_from_slice(result_string);
+        let krb_priv = KrbPriv {
+            pvno: PVNO,
+            msg_type: KRB_PRIV,
+            enc_part: encrypt(
+                &subkey,
+                KEY_USAGE_KRB_PRIV_ENC_PART,
+                &EncKrbPrivPart {
+                    user_data,
+                    seq_number: Some(1),
+                    s_address: HostAddress::new(
+                        DIRECTIONAL,
+                        1_u32.to_be_bytes().to_vec(),
+                    ),
+                    ..Default::default()
+                }
+                .build(),
+            )
+            .unwrap(),
+        }
+        .build();
+
+        let length = 6 + ap_rep.len() + krb_priv.len();
+        let mut raw_reply = (length as u16).to_be_bytes().to_vec();
+        raw_reply.extend_from_slice(&KPASSWD_VERSION_CHANGE.to_be_bytes());
+        raw_reply.extend_from_slice(&(ap_rep.len() as u16).to_be_bytes());
+        raw_reply.extend_from_slice(&ap_rep);
+        raw_reply.extend_from_slice(&krb_priv);
+
+        return (version, enc_krb_priv_part.user_data, raw_reply);
+    }
+
+    #[test]
+    fn change_password() {
+        let credential = create_changepw_credential();
+        let request = KpasswdRequest::new(&credential).unwrap();
+
+        let (version, user_data, raw_reply) = kpasswd_reply(
+            &session_key(),
+            &request.build_change_password("N3wS3cr3t").unwrap(),
+            KRB5_KPASSWD_SUCCESS,
+            b"",
+        );
+
+        assert_eq!(KPASSWD_VERSION_CHANGE, version);
+        assert_eq!(b"N3wS3cr3t".to_vec(), user_data);
+        assert_eq!(Ok(()), request.parse_reply(&raw_reply));
+    }
+
+    #[test]
+    fn set_password_of_target_principal() {
+        let credential = create_changepw_credential();
+        let request = KpasswdRequest::new(&credential).unwrap();
+        let targname =
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("donald"));
+
+        let (version, user_data, raw_reply) = kpasswd_reply(
+            &session_key(),
+            &request
+                .build_set_password(
+                    "N3wS3cr3t",
+                    &targname,
+                    &Realm::from("KINGDOM.HEARTS"),
+                )
+                .unwrap(),
+            KRB5_KPASSWD_ACCESSDENIED,
+            b"Not allowed",
+        );
+
+        assert_eq!(KPASSWD_VERSION_SET, version);
+        assert_eq!(
+            ChangePasswdData {
+                newpasswd: b"N3wS3cr3t".to_vec(),
+                targname: Some(targname),
+                targrealm: Some(Realm::from("KINGDOM.HEARTS")),
+            },
+            ChangePasswdData::parse(&user_data).unwrap().1
+        );
+        assert_eq!(
+            Error::KpasswdError(
+                KpasswdResultCode::AccessDenied,
+                "Not allowed".to_string()
+            ),
+            request.parse_reply(&raw_reply).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn krb_error_reply_with_result_code() {
+        let credential = create_changepw_credential();
+        let request = KpasswdRequest::new(&credential).unwrap();
+
+        let mut e_data = KRB5_KPASSWD_AUTHERROR.to_be_bytes().to_vec();
+        e_data.extend_from_slice(b"Bad ticket");
+        let krb_error = KrbError {
+            pvno: 5,
+            msg_type: 30,
+            e_data: Some(e_data),
+            ..Default::default()
+        }
+        .build();
+        let mut raw_reply =
+            ((6 + krb_error.len()) as u16).to_be_bytes().to_vec();
+        raw_reply.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        raw_reply.extend_from_slice(&krb_error);
+
+        assert_eq!(
+            Error::KpasswdError(
+                KpasswdResultCode::AuthError,
+                "Bad ticket".to_string()
+            ),
+            request.parse_reply(&raw_reply).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_with_truncated_reply() {
+        let credential = create_changepw_credential();
+        let request = KpasswdRequest::new(&credential).unwrap();
+
+        assert_eq!(
+            Error::BinaryParseError,
+            request.parse_reply(&[0x00, 0x08, 0x00, 0x01]).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::error::*;
+use himmelblau_kerberos_constants::kpasswd::*;
+use std::time::Duration;
+
+/// Result codes returned by the kpasswd server when the password cannot
+/// be changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KpasswdResultCode {
+    /// The request was malformed.
+    Malformed,
+    /// The server failed to process the request.
+    HardError,
+    /// The authentication of the request failed.
+    AuthError,
+    /// The password change was rejected, like when the new password does
+    /// not satisfy the password policy.
+    SoftError,
+    /// The user is not allowed to change the password.
+    AccessDenied,
+    /// The version of the request is not supported.
+    BadVersion,
+    /// The ticket is not an initial ticket.
+    InitialFlagNeeded,
+    /// Result code not defined by RFC 3244.
+    Unknown(u16),
+}
+
+impl From<u16> for KpasswdResultCode {
+    fn from(result_code: u16) -> Self {
+        match result_code {
+            KRB5_KPASSWD_MALFORMED => Self::Malformed,
+            KRB5_KPASSWD_HARDERROR => Self::HardError,
+            KRB5_KPASSWD_AUTHERROR => Self::AuthError,
+            KRB5_KPASSWD_SOFTERROR => Self::SoftError,
+            KRB5_KPASSWD_ACCESSDENIED => Self::AccessDenied,
+            KRB5_KPASSWD_BAD_VERSION => Self::BadVersion,
+            KRB5_KPASSWD_INITIAL_FLAG_NEEDED => Self::InitialFlagNeeded,
+            _ => Self::Unknown(result_code),
+        }
+    }
+}
+
+/// Size of the password policy sent by Active Directory in the result
+/// string of a soft error
+const PASSWORD_POLICY_SIZE: usize = 30;
+
+/// Password policy returned by Active Directory when the new password
+/// does not satisfy it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: u32,
+    pub history_length: u32,
+    /// Flags of the policy, such as the requirement of complex passwords
+    pub properties: u32,
+    pub max_age: Duration,
+    pub min_age: Duration,
+}
+
+impl PasswordPolicy {
+    /// Parses the policy from the result string, which starts with two
+    /// zero bytes, followed by the big-endian fields of the policy. The
+    /// ages are given in intervals of 100 nanoseconds.
+    pub fn parse(raw: &[u8]) -> Option<Self> {
+        if raw.len() != PASSWORD_POLICY_SIZE || raw[0..2] != [0, 0] {
+            return None;
+        }
+
+        let u32_at = |i: usize| {
+            return u32::from_be_bytes([
+                raw[i],
+                raw[i + 1],
+                raw[i + 2],
+                raw[i + 3],
+            ]);
+        };
+        let age_at = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&raw[i..i + 8]);
+            return Duration::from_nanos(
+                u64::from_be_bytes(bytes).saturating_mul(100),
+            );
+        };
+
+        return Some(Self {
+            min_length: u32_at(2),
+            history_length: u32_at(6),
+            properties: u32_at(10),
+            max_age: age_at(14),
+            min_age: age_at(22),
+        });
+    }
+}
+
+/// Converts the result of the kpasswd reply into an error, unless the
+/// password was changed.
+pub(crate) fn kpasswd_result(
+    result_code: u16,
+    result_string: &[u8],
+) -> Result<()> {
+    if result_code == KRB5_KPASSWD_SUCCESS {
+        return Ok(());
+    }
+
+    if result_code == KRB5_KPASSWD_SOFTERROR {
+        if let Some(policy) = PasswordPolicy::parse(result_string) {
+            return Err(Error::PasswordPolicyError(policy));
+        }
+    }
+
+    return Err(Error::KpasswdError(
+        KpasswdResultCode::from(result_code),
+        String::from_utf8_lossy(result_string).to_string(),
+    ));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn success_result() {
+        assert_eq!(Ok(()), kpasswd_result(KRB5_KPASSWD_SUCCESS, b""));
+    }
+
+    #[test]
+    fn access_denied_result() {
+        assert_eq!(
+            Error::KpasswdError(
+                KpasswdResultCode::AccessDenied,
+                "Not allowed".to_string()
+            ),
+            kpasswd_result(KRB5_KPASSWD_ACCESSDENIED, b"Not allowed")
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn soft_error_with_password_policy() {
+        let raw_policy = vec![
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x18, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x00, 0x21, 0x00, 0xf5, 0x59, 0x80, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(
+            Error::PasswordPolicyError(PasswordPolicy {
+                min_length: 7,
+                history_length: 24,
+                properties: 1,
+                max_age: Duration::from_secs(42 * 24 * 60 * 60),
+                min_age: Duration::from_secs(0),
+            }),
+            kpasswd_result(KRB5_KPASSWD_SOFTERROR, &raw_policy).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn soft_error_with_message() {
+        assert_eq!(
+            Error::KpasswdError(
+                KpasswdResultCode::SoftError,
+                "Password too short".to_string()
+            ),
+            kpasswd_result(KRB5_KPASSWD_SOFTERROR, b"Password too short")
+                .unwrap_err()
+        );
+    }
+}
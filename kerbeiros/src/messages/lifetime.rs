@@ -0,0 +1,48 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+/// Lifetime requested when none is configured, which lets the KDC choose
+/// the maximum allowed
+const DEFAULT_LIFETIME_WEEKS: i64 = 20 * 52;
+
+/// Calculates the end time (till or rtime) of a requested ticket, from its
+/// starttime or now in case of not being postdated. The time is limited to
+/// the maximum KerberosTime, since the year is encoded with four digits.
+pub(crate) fn requested_end_time(
+    starttime: Option<&DateTime<Utc>>,
+    lifetime: Option<Duration>,
+) -> DateTime<Utc> {
+    let max_time = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+    let lifetime =
+        lifetime.unwrap_or_else(|| Duration::weeks(DEFAULT_LIFETIME_WEEKS));
+    let starttime = starttime.cloned().unwrap_or_else(Utc::now);
+    return starttime
+        .checked_add_signed(lifetime)
+        .map_or(max_time, |end_time| end_time.min(max_time));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn end_time_from_starttime() {
+        let starttime = Utc::now() + Duration::hours(8);
+        assert_eq!(
+            starttime + Duration::hours(10),
+            requested_end_time(Some(&starttime), Some(Duration::hours(10)))
+        );
+    }
+
+    #[test]
+    fn limit_end_time_of_huge_lifetimes() {
+        let max_time = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(
+            max_time,
+            requested_end_time(None, Some(Duration::days(999_999_999)))
+        );
+        assert_eq!(
+            max_time,
+            requested_end_time(None, Some(Duration::days(100 * 365 * 100)))
+        );
+    }
+}
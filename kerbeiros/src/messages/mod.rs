@@ -2,7 +2,21 @@
 
 mod asreq;
 pub(crate) use asreq::*;
+pub use asreq::{EtypeHint, PreauthHints};
+
+mod tgsreq;
+pub use tgsreq::S4uUser;
+pub(crate) use tgsreq::*;
+
+mod lifetime;
+pub(crate) use lifetime::requested_end_time;
+
+mod kpasswd;
+pub(crate) use kpasswd::*;
+pub use kpasswd::{KpasswdResultCode, PasswordPolicy};
 
 pub use himmelblau_kerberos_asn1::AsRep;
 pub use himmelblau_kerberos_asn1::AsReq;
 pub use himmelblau_kerberos_asn1::KrbError;
+pub use himmelblau_kerberos_asn1::TgsRep;
+pub use himmelblau_kerberos_asn1::TgsReq;
@@ -0,0 +1,431 @@
+use super::options::TgsReqOptions;
+use super::s4u::S4uUser;
+use crate::credentials::Credential;
+use crate::error::*;
+use crate::messages::requested_end_time;
+use crate::utils::encrypt;
+use himmelblau_kerberos_asn1::{
+    ApReq, Asn1Object, Authenticator, AuthorizationData, Checksum,
+    EncryptedData, EncryptionKey, KdcReqBody, PaData, PaPacOptions,
+    PrincipalName, Realm, TgsReq, Ticket,
+};
+use himmelblau_kerberos_constants::kdc_options::{
+    POSTDATED, RENEW, RENEWABLE, VALIDATE,
+};
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_TGS_REP_ENC_PART_AUTHEN_SUBKEY,
+    KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY, KEY_USAGE_TGS_REQ_AUTHEN,
+    KEY_USAGE_TGS_REQ_AUTHEN_CKSUM, KEY_USAGE_TGS_REQ_AUTH_DATA_AUTHEN_SUBKEY,
+    KEY_USAGE_TGS_REQ_AUTH_DATA_SESSION_KEY,
+};
+use himmelblau_kerberos_constants::pa_data_types::{
+    PA_PAC_OPTIONS, PA_TGS_REQ,
+};
+use himmelblau_kerberos_crypto::{
+    checksum_type_from_etype, keyed_checksum, Key,
+};
+use rand::Rng;
+
+/// Builds a TGS-REQ by using a TGT to authenticate the client against the KDC
+pub(crate) struct TgsReqBuilder<'a> {
+    tgt: &'a Credential,
+    sname: &'a PrincipalName,
+    options: &'a TgsReqOptions,
+    nonce: u32,
+    subkey: Option<EncryptionKey>,
+    s4u_user: Option<&'a S4uUser>,
+    additional_ticket: Option<&'a Ticket>,
+    kdc_options: u32,
+    pac_options: Option<u32>,
+}
+
+impl<'a> TgsReqBuilder<'a> {
+    pub fn new(
+        tgt: &'a Credential,
+        sname: &'a PrincipalName,
+        options: &'a TgsReqOptions,
+    ) -> Result<Self> {
+        let mut subkey = None;
+        if options.should_subkey_be_used() {
+            subkey = Some(Self::generate_subkey(tgt.key().keytype)?);
+        }
+
+        return Ok(Self {
+            tgt,
+            sname,
+            options,
+            nonce: rand::thread_rng().gen::<u32>(),
+            subkey,
+            s4u_user: None,
+            additional_ticket: None,
+            kdc_options: options.kdc_options(),
+            pac_options: None,
+        });
+    }
+
+    fn generate_subkey(etype: i32) -> Result<EncryptionKey> {
+        let key = Key::random(etype)?;
+        return Ok(EncryptionKey::new(etype, key.as_bytes().to_vec()));
+    }
+
+    pub fn options(&self) -> &TgsReqOptions {
+        return self.options;
+    }
+
+    pub fn nonce(&self) -> u32 {
+        return self.nonce;
+    }
+
+    /// Key used by the KDC to encrypt the reply, along with its key usage.
+    pub fn reply_key(&self) -> (&EncryptionKey, i32) {
+        match &self.subkey {
+            Some(subkey) => {
+                return (subkey, KEY_USAGE_TGS_REP_ENC_PART_AUTHEN_SUBKEY);
+            }
+            None => {
+                return (
+                    self.tgt.key(),
+                    KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY,
+                );
+            }
+        }
+    }
+
+    /// Sets the user to impersonate by using S4U2Self.
+    pub fn set_s4u_user(&mut self, s4u_user: &'a S4uUser) {
+        self.s4u_user = Some(s4u_user);
+    }
+
+    /// Sets the ticket to be sent in the additional-tickets field.
+    pub fn set_additional_ticket(&mut self, ticket: &'a Ticket) {
+        self.additional_ticket = Some(ticket);
+    }
+
+    /// Adds KDC options to the ones specified in the request options.
+    pub fn add_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options |= kdc_options;
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.kdc_options;
+    }
+
+    /// Replaces the KDC options specified in the request options.
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    /// Sets the options to be sent in PA-PAC-OPTIONS.
+    pub fn set_pac_options(&mut self, pac_options: u32) {
+        self.pac_options = Some(pac_options);
+    }
+
+    pub fn build_tgs_req(&self) -> Result<TgsReq> {
+        let req_body = self.create_req_body()?;
+        let ap_req = self.create_ap_req(&req_body)?;
+
+        let mut padata = vec![PaData::new(PA_TGS_REQ, ap_req.build())];
+        if let Some(s4u_user) = self.s4u_user {
+            padata.append(&mut s4u_user.padata(
+                self.nonce,
+                self.tgt.key(),
+                self.reply_key().0,
+            )?);
+        }
+
+        if let Some(pac_options) = self.pac_options {
+            let pa_pac_options = PaPacOptions {
+                kerberos_flags: pac_options.into(),
+            };
+            padata.push(PaData::new(PA_PAC_OPTIONS, pa_pac_options.build()));
+        }
+
+        return Ok(TgsReq {
+            padata: Some(padata),
+            req_body,
+            ..Default::default()
+        });
+    }
+
+    fn create_req_body(&self) -> Result<KdcReqBody> {
+        // Renewed and validated tickets keep the starttime of the original
+        let starttime = if self.kdc_options() & (RENEW | VALIDATE) == 0 {
+            self.options.starttime()
+        } else {
+            None
+        };
+
+        let mut req_body = KdcReqBody {
+            kdc_options: self.kdc_options().into(),
+            realm: tgs_realm(self.tgt),
+            sname: Some(self.sname.clone()),
+            till: requested_end_time(starttime, self.options.ticket_lifetime())
+                .into(),
+            nonce: self.nonce,
+            etypes: self.options.sorted_etypes(),
+            ..Default::default()
+        };
+
+        if let Some(starttime) = starttime {
+            req_body.from = Some((*starttime).into());
+            *req_body.kdc_options |= POSTDATED;
+        }
+
+        if self.kdc_options() & RENEWABLE != 0 {
+            req_body.rtime = Some(
+                requested_end_time(starttime, self.options.renew_lifetime())
+                    .into(),
+            );
+        }
+
+        if let Some(authorization_data) = self.options.authorization_data() {
+            req_body.enc_authorization_data =
+                Some(self.encrypt_authorization_data(authorization_data)?);
+        }
+
+        if let Some(ticket) = self.additional_ticket {
+            req_body.additional_tickets = Some(vec![ticket.clone()]);
+        }
+
+        return Ok(req_body);
+    }
+
+    fn encrypt_authorization_data(
+        &self,
+        authorization_data: &AuthorizationData,
+    ) -> Result<EncryptedData> {
+        let raw_authorization_data = authorization_data.build();
+
+        let (key, key_usage) = match &self.subkey {
+            Some(subkey) => (subkey, KEY_USAGE_TGS_REQ_AUTH_DATA_AUTHEN_SUBKEY),
+            None => (self.tgt.key(), KEY_USAGE_TGS_REQ_AUTH_DATA_SESSION_KEY),
+        };
+
+        return encrypt(key, key_usage, &raw_authorization_data);
+    }
+
+    fn create_ap_req(&self, req_body: &KdcReqBody) -> Result<ApReq> {
+        let authenticator = self.create_authenticator(req_body)?;
+
+        return Ok(ApReq {
+            ticket: self.tgt.ticket().clone(),
+            authenticator: encrypt(
+                self.tgt.key(),
+                KEY_USAGE_TGS_REQ_AUTHEN,
+                &authenticator.build(),
+            )?,
+            ..Default::default()
+        });
+    }
+
+    fn create_authenticator(
+        &self,
+        req_body: &KdcReqBody,
+    ) -> Result<Authenticator> {
+        let session_key = self.tgt.key();
+        let cksumtype = checksum_type_from_etype(session_key.keytype)?;
+
+        return Ok(Authenticator {
+            crealm: self.tgt.crealm().clone(),
+            cname: self.tgt.cname().clone(),
+            cksum: Some(Checksum {
+                cksumtype,
+                checksum: keyed_checksum(
+                    cksumtype,
+                    &session_key.keyvalue,
+                    KEY_USAGE_TGS_REQ_AUTHEN_CKSUM,
+                    &req_body.build(),
+                )?,
+            }),
+            subkey: self.subkey.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+/// Realm served by the KDC that issued the TGT, which is the
+/// realm where the TGS-REQ must be sent.
+pub(crate) fn tgs_realm(tgt: &Credential) -> Realm {
+    let sname = tgt.sname();
+    if sname.name_string.len() == 2
+        && sname.name_string[0].to_lowercase() == "krbtgt"
+    {
+        return sname.name_string[1].clone();
+    }
+    return tgt.srealm().clone();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ap::test::service_principal;
+    use crate::requesters::create_tgt;
+    use crate::utils::decrypt;
+    use chrono::{Duration, Utc};
+    use himmelblau_kerberos_asn1::{AuthorizationDataEntry, Ticket};
+    use himmelblau_kerberos_constants::etypes::*;
+    use himmelblau_kerberos_constants::kdc_options::CONSTRAINED_DELEGATION;
+    use himmelblau_kerberos_constants::pa_pac_options::RESOURCE_BASED_CONSTRAINED_DELEGATION;
+
+    fn decrypt_authenticator(
+        session_key: &EncryptionKey,
+        tgs_req: &TgsReq,
+    ) -> (ApReq, Authenticator) {
+        let padata = &tgs_req.padata.as_ref().unwrap()[0];
+        assert_eq!(PA_TGS_REQ, padata.padata_type);
+
+        let ap_req = ApReq::parse(&padata.padata_value).unwrap().1;
+        let raw_authenticator = decrypt(
+            session_key,
+            KEY_USAGE_TGS_REQ_AUTHEN,
+            &ap_req.authenticator,
+        )
+        .unwrap();
+
+        return (ap_req, Authenticator::parse(&raw_authenticator).unwrap().1);
+    }
+
+    #[test]
+    fn tgs_req_with_authenticator_encrypted_with_session_key() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let sname = service_principal("web.kingdom.hearts");
+        let options = TgsReqOptions::new();
+
+        let builder = TgsReqBuilder::new(&tgt, &sname, &options).unwrap();
+        let tgs_req = builder.build_tgs_req().unwrap();
+
+        assert_eq!(Realm::from("KINGDOM.HEARTS"), tgs_req.req_body.realm);
+        assert_eq!(Some(sname.clone()), tgs_req.req_body.sname);
+        assert_eq!(builder.nonce(), tgs_req.req_body.nonce);
+        assert_eq!(
+            vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            tgs_req.req_body.etypes
+        );
+
+        let (ap_req, authenticator) =
+            decrypt_authenticator(&session_key, &tgs_req);
+
+        assert_eq!(tgt.ticket(), &ap_req.ticket);
+        assert_eq!(tgt.cname(), &authenticator.cname);
+        assert_eq!(None, authenticator.subkey);
+
+        let cksum = authenticator.cksum.unwrap();
+        assert_eq!(
+            keyed_checksum(
+                cksum.cksumtype,
+                &session_key.keyvalue,
+                KEY_USAGE_TGS_REQ_AUTHEN_CKSUM,
+                &tgs_req.req_body.build()
+            )
+            .unwrap(),
+            cksum.checksum
+        );
+    }
+
+    #[test]
+    fn tgs_req_with_additional_ticket_and_pac_options() {
+        let session_key =
+            EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![7; 16]);
+        let tgt = create_tgt(session_key);
+        let sname = service_principal("web.kingdom.hearts");
+        let options = TgsReqOptions::new();
+        let ticket = Ticket::new(
+            Realm::from("KINGDOM.HEARTS"),
+            service_principal("web.kingdom.hearts"),
+            EncryptedData::default(),
+        );
+
+        let mut builder = TgsReqBuilder::new(&tgt, &sname, &options).unwrap();
+        builder.set_additional_ticket(&ticket);
+        builder.add_kdc_options(CONSTRAINED_DELEGATION);
+        builder.set_pac_options(RESOURCE_BASED_CONSTRAINED_DELEGATION);
+        let tgs_req = builder.build_tgs_req().unwrap();
+
+        assert_eq!(
+            options.kdc_options() | CONSTRAINED_DELEGATION,
+            tgs_req.req_body.kdc_options.flags
+        );
+        assert_eq!(Some(vec![ticket]), tgs_req.req_body.additional_tickets);
+
+        let padata = tgs_req.padata.unwrap();
+        assert_eq!(PA_PAC_OPTIONS, padata[1].padata_type);
+        assert_eq!(
+            RESOURCE_BASED_CONSTRAINED_DELEGATION,
+            PaPacOptions::parse(&padata[1].padata_value)
+                .unwrap()
+                .1
+                .kerberos_flags
+                .flags
+        );
+    }
+
+    #[test]
+    fn postdated_tgs_req_when_starttime_is_provided() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![7; 32]);
+        let tgt = create_tgt(session_key);
+        let sname = service_principal("web.kingdom.hearts");
+        let mut options = TgsReqOptions::new();
+        let starttime = Utc::now() + Duration::hours(8);
+        options.set_starttime(Some(starttime));
+        options.set_ticket_lifetime(Some(Duration::hours(10)));
+
+        let builder = TgsReqBuilder::new(&tgt, &sname, &options).unwrap();
+        let tgs_req = builder.build_tgs_req().unwrap();
+
+        assert_eq!(
+            options.kdc_options() | POSTDATED,
+            tgs_req.req_body.kdc_options.flags
+        );
+        assert_eq!(Some(starttime.into()), tgs_req.req_body.from);
+        assert_eq!(
+            (starttime + Duration::hours(10)).timestamp(),
+            tgs_req.req_body.till.time.time.timestamp()
+        );
+
+        let mut builder = TgsReqBuilder::new(&tgt, &sname, &options).unwrap();
+        builder.set_kdc_options(VALIDATE);
+        let tgs_req = builder.build_tgs_req().unwrap();
+
+        assert_eq!(VALIDATE, tgs_req.req_body.kdc_options.flags);
+        assert_eq!(None, tgs_req.req_body.from);
+    }
+
+    #[test]
+    fn tgs_req_with_subkey_and_authorization_data() {
+        let session_key = EncryptionKey::new(RC4_HMAC, vec![7; 16]);
+        let tgt = create_tgt(session_key.clone());
+        let sname = service_principal("web.kingdom.hearts");
+        let mut options = TgsReqOptions::new();
+        options.set_subkey(true);
+
+        let authorization_data = vec![AuthorizationDataEntry {
+            ad_type: 1,
+            ad_data: vec![1, 2, 3],
+        }];
+        options.set_authorization_data(Some(authorization_data.clone()));
+
+        let builder = TgsReqBuilder::new(&tgt, &sname, &options).unwrap();
+        let tgs_req = builder.build_tgs_req().unwrap();
+        let subkey = builder.reply_key().0.clone();
+
+        let (_, authenticator) = decrypt_authenticator(&session_key, &tgs_req);
+        assert_eq!(Some(subkey.clone()), authenticator.subkey);
+
+        let enc_authorization_data =
+            tgs_req.req_body.enc_authorization_data.unwrap();
+        let raw_authorization_data = decrypt(
+            &subkey,
+            KEY_USAGE_TGS_REQ_AUTH_DATA_AUTHEN_SUBKEY,
+            &enc_authorization_data,
+        )
+        .unwrap();
+
+        assert_eq!(
+            authorization_data,
+            AuthorizationData::parse(&raw_authorization_data).unwrap().1
+        );
+    }
+}
@@ -0,0 +1,8 @@
+mod builder;
+pub(crate) use builder::{tgs_realm, TgsReqBuilder};
+
+mod options;
+pub(crate) use options::TgsReqOptions;
+
+mod s4u;
+pub use s4u::S4uUser;
@@ -0,0 +1,224 @@
+use crate::Result;
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::AuthorizationData;
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+};
+use himmelblau_kerberos_constants::kdc_options::{
+    CANONICALIZE, FORWARDABLE, RENEWABLE, RENEWABLE_OK,
+};
+use himmelblau_kerberos_crypto::is_supported_etype;
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct TgsReqOptions {
+    etypes: HashSet<i32>,
+    kdc_options: u32,
+    authorization_data: Option<AuthorizationData>,
+    subkey: bool,
+    rbcd: bool,
+    starttime: Option<DateTime<Utc>>,
+    ok_as_delegate: bool,
+    ticket_lifetime: Option<Duration>,
+    renew_lifetime: Option<Duration>,
+    s4u_reply_required: bool,
+}
+
+impl TgsReqOptions {
+    pub fn new() -> Self {
+        return Self {
+            kdc_options: FORWARDABLE | RENEWABLE | CANONICALIZE | RENEWABLE_OK,
+            etypes: [
+                AES256_CTS_HMAC_SHA1_96,
+                AES128_CTS_HMAC_SHA1_96,
+                RC4_HMAC,
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            authorization_data: None,
+            subkey: false,
+            rbcd: false,
+            starttime: None,
+            ok_as_delegate: true,
+            ticket_lifetime: None,
+            renew_lifetime: None,
+            s4u_reply_required: false,
+        };
+    }
+
+    pub fn etypes(&self) -> &HashSet<i32> {
+        return &self.etypes;
+    }
+
+    pub fn set_etype(&mut self, etype: i32) -> Result<()> {
+        return self.set_etypes([etype].iter().cloned().collect());
+    }
+
+    pub fn set_etypes(&mut self, etypes: HashSet<i32>) -> Result<()> {
+        self.error_if_unsupported_etypes(&etypes)?;
+        self.etypes = etypes;
+        return Ok(());
+    }
+
+    fn error_if_unsupported_etypes(&self, etypes: &HashSet<i32>) -> Result<()> {
+        for etype in etypes.iter() {
+            if !is_supported_etype(*etype) {
+                return Err(
+                    himmelblau_kerberos_crypto::Error::UnsupportedAlgorithm(
+                        *etype,
+                    ),
+                )?;
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn sorted_etypes(&self) -> Vec<i32> {
+        let mut etypes_vec: Vec<i32> = Vec::new();
+
+        for etype in
+            [AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC]
+        {
+            if self.etypes.contains(&etype) {
+                etypes_vec.push(etype);
+            }
+        }
+
+        return etypes_vec;
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.kdc_options;
+    }
+
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.kdc_options = kdc_options;
+    }
+
+    pub fn starttime(&self) -> Option<&DateTime<Utc>> {
+        return self.starttime.as_ref();
+    }
+
+    pub fn set_starttime(&mut self, starttime: Option<DateTime<Utc>>) {
+        self.starttime = starttime;
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.ticket_lifetime;
+    }
+
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.ticket_lifetime = ticket_lifetime;
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.renew_lifetime;
+    }
+
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.renew_lifetime = renew_lifetime;
+    }
+
+    pub fn is_ok_as_delegate_honoured(&self) -> bool {
+        return self.ok_as_delegate;
+    }
+
+    pub fn set_ok_as_delegate_honoured(&mut self, ok_as_delegate: bool) {
+        self.ok_as_delegate = ok_as_delegate;
+    }
+
+    pub fn authorization_data(&self) -> Option<&AuthorizationData> {
+        return self.authorization_data.as_ref();
+    }
+
+    pub fn set_authorization_data(
+        &mut self,
+        authorization_data: Option<AuthorizationData>,
+    ) {
+        self.authorization_data = authorization_data;
+    }
+
+    pub fn should_subkey_be_used(&self) -> bool {
+        return self.subkey;
+    }
+
+    pub fn set_subkey(&mut self, subkey: bool) {
+        self.subkey = subkey;
+    }
+
+    pub fn is_rbcd_requested(&self) -> bool {
+        return self.rbcd;
+    }
+
+    pub fn set_rbcd(&mut self, rbcd: bool) {
+        self.rbcd = rbcd;
+    }
+
+    pub fn is_s4u_reply_required(&self) -> bool {
+        return self.s4u_reply_required;
+    }
+
+    pub fn set_s4u_reply_required(&mut self, s4u_reply_required: bool) {
+        self.s4u_reply_required = s4u_reply_required;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::etypes::DES_CBC_MD5;
+
+    #[test]
+    fn default_etypes() {
+        let options = TgsReqOptions::new();
+        let etypes: HashSet<i32> =
+            [AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC]
+                .iter()
+                .cloned()
+                .collect();
+
+        assert_eq!(&etypes, options.etypes());
+    }
+
+    #[test]
+    fn default_kdc_options() {
+        let options = TgsReqOptions::new();
+
+        assert_eq!(
+            FORWARDABLE | RENEWABLE | CANONICALIZE | RENEWABLE_OK,
+            options.kdc_options()
+        );
+    }
+
+    #[test]
+    fn sorted_etypes_by_strength() {
+        let mut options = TgsReqOptions::new();
+
+        assert_eq!(
+            vec![AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            options.sorted_etypes()
+        );
+
+        options
+            .set_etypes(
+                [RC4_HMAC, AES128_CTS_HMAC_SHA1_96]
+                    .iter()
+                    .cloned()
+                    .collect(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            vec![AES128_CTS_HMAC_SHA1_96, RC4_HMAC],
+            options.sorted_etypes()
+        );
+    }
+
+    #[should_panic(expected = "UnsupportedAlgorithm(3)")]
+    #[test]
+    fn error_setting_unsupported_etype() {
+        let mut options = TgsReqOptions::new();
+        options.set_etype(DES_CBC_MD5).unwrap();
+    }
+}
@@ -0,0 +1,446 @@
+use crate::error::*;
+use himmelblau_kerberos_asn1::{
+    Asn1Object, BitString, Checksum, EncryptionKey, KerberosString, PaData,
+    PaForUser, PaS4uX509User, PrincipalName, Realm, S4uUserId,
+};
+use himmelblau_kerberos_constants::checksum_types::HMAC_MD5;
+use himmelblau_kerberos_constants::key_usages::{
+    KEY_USAGE_KERB_NON_KERB_CKSUM_SALT, KEY_USAGE_PA_S4U_X509_USER_REPLY,
+    KEY_USAGE_PA_S4U_X509_USER_REQUEST,
+};
+use himmelblau_kerberos_constants::pa_data_types::{
+    PA_FOR_USER, PA_S4U_X509_USER,
+};
+use himmelblau_kerberos_constants::s4u_options::SIGNED_WITH_KUN_27;
+use himmelblau_kerberos_crypto::{
+    checksum_hmac_md5, checksum_type_from_etype, keyed_checksum,
+};
+use subtle::ConstantTimeEq;
+
+/// Authentication package specified in PA-FOR-USER, as required by MS-SFU.
+const AUTH_PACKAGE: &str = "Kerberos";
+
+/// User impersonated by a service in a S4U2Self request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum S4uUser {
+    /// User identified by its principal name and realm.
+    Name(PrincipalName, Realm),
+
+    /// User identified by its X.509 certificate (DER encoded) and realm.
+    Certificate(Vec<u8>, Realm),
+}
+
+impl S4uUser {
+    pub fn realm(&self) -> &Realm {
+        match self {
+            S4uUser::Name(_, realm) => return realm,
+            S4uUser::Certificate(_, realm) => return realm,
+        }
+    }
+
+    /// Builds the S4UUserID sent in PA-S4U-X509-USER, which must include
+    /// the nonce of the request.
+    pub(crate) fn user_id(&self, nonce: u32) -> S4uUserId {
+        let mut user_id = S4uUserId {
+            nonce,
+            crealm: self.realm().clone(),
+            options: Some(BitString::new(
+                SIGNED_WITH_KUN_27.to_be_bytes().to_vec(),
+                0,
+            )),
+            ..Default::default()
+        };
+
+        match self {
+            S4uUser::Name(name, _) => {
+                user_id.cname = Some(name.clone());
+            }
+            S4uUser::Certificate(certificate, _) => {
+                user_id.subject_certificate = Some(certificate.clone());
+            }
+        }
+
+        return user_id;
+    }
+
+    /// Builds the padata required to request a S4U2Self ticket. The
+    /// PA-FOR-USER is only included when the user is specified by name.
+    pub(crate) fn padata(
+        &self,
+        nonce: u32,
+        session_key: &EncryptionKey,
+        reply_key: &EncryptionKey,
+    ) -> Result<Vec<PaData>> {
+        let mut padata = Vec::new();
+
+        if let S4uUser::Name(name, realm) = self {
+            let pa_for_user = build_pa_for_user(name, realm, session_key);
+            padata.push(PaData::new(PA_FOR_USER, pa_for_user.build()));
+        }
+
+        let pa_s4u_x509_user =
+            build_pa_s4u_x509_user(self.user_id(nonce), reply_key)?;
+        padata.push(PaData::new(PA_S4U_X509_USER, pa_s4u_x509_user.build()));
+
+        return Ok(padata);
+    }
+
+    /// Checks the PA-S4U-X509-USER echoed by the KDC in the reply. In case
+    /// the user was specified by certificate, the KDC must echo it, since
+    /// the request could not be understood otherwise. In case of the user
+    /// specified by name, the reply without PA-S4U-X509-USER of the KDCs
+    /// that only understand PA-FOR-USER is accepted, unless `required`,
+    /// since then the user of the ticket cannot be verified.
+    pub(crate) fn verify_reply(
+        &self,
+        nonce: u32,
+        padata: &[PaData],
+        reply_key: &EncryptionKey,
+        required: bool,
+    ) -> Result<()> {
+        let raw_pa_s4u_x509_user = padata
+            .iter()
+            .find(|padata| padata.padata_type == PA_S4U_X509_USER);
+
+        match raw_pa_s4u_x509_user {
+            Some(padata) => {
+                let pa_s4u_x509_user =
+                    PaS4uX509User::parse(&padata.padata_value)?.1;
+                return verify_pa_s4u_x509_user(
+                    &self.user_id(nonce),
+                    &pa_s4u_x509_user,
+                    reply_key,
+                );
+            }
+            None => {
+                if required || matches!(self, S4uUser::Certificate(_, _)) {
+                    return Err(Error::InvalidS4uX509UserReply(
+                        "not found".to_string(),
+                    ));
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Builds the PA-FOR-USER, whose checksum is always an HMAC-MD5 keyed
+/// with the TGT session key, regardless of its type.
+pub(crate) fn build_pa_for_user(
+    name: &PrincipalName,
+    realm: &Realm,
+    session_key: &EncryptionKey,
+) -> PaForUser {
+    let mut plaintext = name.name_type.to_le_bytes().to_vec();
+    for component in name.name_string.iter() {
+        plaintext.extend_from_slice(component.as_bytes());
+    }
+    plaintext.extend_from_slice(realm.as_bytes());
+    plaintext.extend_from_slice(AUTH_PACKAGE.as_bytes());
+
+    return PaForUser {
+        username: name.clone(),
+        userrealm: realm.clone(),
+        cksum: Checksum {
+            cksumtype: HMAC_MD5,
+            checksum: checksum_hmac_md5(
+                &session_key.keyvalue,
+                KEY_USAGE_KERB_NON_KERB_CKSUM_SALT,
+                &plaintext,
+            ),
+        },
+        auth_package: KerberosString::from(AUTH_PACKAGE),
+    };
+}
+
+/// Builds the PA-S4U-X509-USER, whose checksum is keyed with the key
+/// that the KDC uses to encrypt the reply.
+pub(crate) fn build_pa_s4u_x509_user(
+    user_id: S4uUserId,
+    key: &EncryptionKey,
+) -> Result<PaS4uX509User> {
+    let cksumtype = checksum_type_from_etype(key.keytype)?;
+    let checksum = keyed_checksum(
+        cksumtype,
+        &key.keyvalue,
+        KEY_USAGE_PA_S4U_X509_USER_REQUEST,
+        &user_id.build(),
+    )?;
+
+    return Ok(PaS4uX509User {
+        user_id,
+        checksum: Checksum {
+            cksumtype,
+            checksum,
+        },
+    });
+}
+
+fn verify_pa_s4u_x509_user(
+    sent_user_id: &S4uUserId,
+    pa_s4u_x509_user: &PaS4uX509User,
+    key: &EncryptionKey,
+) -> Result<()> {
+    let user_id = &pa_s4u_x509_user.user_id;
+    let options = s4u_options(user_id);
+
+    let key_usage = if options & SIGNED_WITH_KUN_27 != 0 {
+        KEY_USAGE_PA_S4U_X509_USER_REPLY
+    } else {
+        KEY_USAGE_PA_S4U_X509_USER_REQUEST
+    };
+
+    // The checksum must be of the type of the reply key, since other types
+    // may require keys of other sizes
+    let cksumtype = checksum_type_from_etype(key.keytype)?;
+    if pa_s4u_x509_user.checksum.cksumtype != cksumtype {
+        return Err(Error::InvalidS4uX509UserReply(
+            "invalid checksum type".to_string(),
+        ));
+    }
+
+    let checksum =
+        keyed_checksum(cksumtype, &key.keyvalue, key_usage, &user_id.build())?;
+
+    if !bool::from(checksum.ct_eq(&pa_s4u_x509_user.checksum.checksum)) {
+        return Err(Error::InvalidS4uX509UserReply(
+            "invalid checksum".to_string(),
+        ));
+    }
+
+    if user_id.nonce != sent_user_id.nonce {
+        return Err(Error::NonceMismatch(sent_user_id.nonce, user_id.nonce));
+    }
+
+    if options != s4u_options(sent_user_id) {
+        return Err(Error::InvalidS4uX509UserReply(format!(
+            "options {:#x} do not match",
+            options
+        )));
+    }
+
+    if user_id.crealm.to_uppercase() != sent_user_id.crealm.to_uppercase() {
+        return Err(Error::InvalidS4uX509UserReply(format!(
+            "realm {} does not match",
+            user_id.crealm
+        )));
+    }
+
+    if sent_user_id.cname.is_some() && user_id.cname != sent_user_id.cname {
+        return Err(Error::InvalidS4uX509UserReply(
+            "user name does not match".to_string(),
+        ));
+    }
+
+    if user_id.subject_certificate != sent_user_id.subject_certificate {
+        return Err(Error::InvalidS4uX509UserReply(
+            "certificate does not match".to_string(),
+        ));
+    }
+
+    return Ok(());
+}
+
+fn s4u_options(user_id: &S4uUserId) -> u32 {
+    let mut bytes = [0; 4];
+    if let Some(options) = &user_id.options {
+        for (i, byte) in options.bytes.iter().take(4).enumerate() {
+            bytes[i] = *byte;
+        }
+    }
+    return u32::from_be_bytes(bytes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+    use himmelblau_kerberos_constants::etypes::*;
+    use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
+
+    fn create_user() -> S4uUser {
+        return S4uUser::Name(
+            PrincipalName::new(
+                NT_PRINCIPAL,
+                KerberosString::from("administrador"),
+            ),
+            Realm::from("kingdom.hearts"),
+        );
+    }
+
+    /// Builds the PA-S4U-X509-USER that a KDC would echo, signed with the
+    /// key usage 27
+    fn create_reply(user_id: S4uUserId, key: &EncryptionKey) -> PaData {
+        let cksumtype = checksum_type_from_etype(key.keytype).unwrap();
+        let pa_s4u_x509_user = PaS4uX509User {
+            checksum: Checksum {
+                cksumtype,
+                checksum: keyed_checksum(
+                    cksumtype,
+                    &key.keyvalue,
+                    KEY_USAGE_PA_S4U_X509_USER_REPLY,
+                    &user_id.build(),
+                )
+                .unwrap(),
+            },
+            user_id,
+        };
+        return PaData::new(PA_S4U_X509_USER, pa_s4u_x509_user.build());
+    }
+
+    #[test]
+    fn pa_for_user_checksum() {
+        let session_key = EncryptionKey::new(
+            AES256_CTS_HMAC_SHA1_96,
+            vec![
+                0x72, 0xC3, 0x90, 0xC6, 0x5D, 0x71, 0x89, 0xAB, 0x9A, 0x39,
+                0xC3, 0xFB, 0xFC, 0xBA, 0x41, 0xB8, 0x5A, 0x5F, 0x72, 0x6E,
+                0xD6, 0x4C, 0x0C, 0x8A, 0x5C, 0xAB, 0x5B, 0xEB, 0x64, 0x8C,
+                0xB8, 0x74,
+            ],
+        );
+
+        let pa_for_user = build_pa_for_user(
+            &PrincipalName::new(
+                NT_PRINCIPAL,
+                KerberosString::from("administrador"),
+            ),
+            &Realm::from("kingdom.hearts"),
+            &session_key,
+        );
+
+        assert_eq!(HMAC_MD5, pa_for_user.cksum.cksumtype);
+        assert_eq!(
+            vec![
+                0x85, 0xBF, 0xBD, 0x75, 0xEE, 0x4A, 0x87, 0x92, 0x9C, 0x28,
+                0xBC, 0x07, 0x24, 0x95, 0x2F, 0x8D,
+            ],
+            pa_for_user.cksum.checksum
+        );
+        assert_eq!("Kerberos", pa_for_user.auth_package.as_str());
+    }
+
+    #[test]
+    fn padata_of_user_by_name() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let padata = create_user().padata(1234, &key, &key).unwrap();
+
+        assert_eq!(2, padata.len());
+        assert_eq!(PA_FOR_USER, padata[0].padata_type);
+        assert_eq!(PA_S4U_X509_USER, padata[1].padata_type);
+
+        let pa_s4u_x509_user =
+            PaS4uX509User::parse(&padata[1].padata_value).unwrap().1;
+        assert_eq!(1234, pa_s4u_x509_user.user_id.nonce);
+        assert_eq!(
+            keyed_checksum(
+                HMAC_SHA1_96_AES256,
+                &key.keyvalue,
+                KEY_USAGE_PA_S4U_X509_USER_REQUEST,
+                &pa_s4u_x509_user.user_id.build()
+            )
+            .unwrap(),
+            pa_s4u_x509_user.checksum.checksum
+        );
+    }
+
+    #[test]
+    fn padata_of_user_by_certificate() {
+        let key = EncryptionKey::new(RC4_HMAC, vec![1; 16]);
+        let user =
+            S4uUser::Certificate(vec![0x30, 0x00], Realm::from("KINGDOM"));
+        let padata = user.padata(1234, &key, &key).unwrap();
+
+        assert_eq!(1, padata.len());
+        let pa_s4u_x509_user =
+            PaS4uX509User::parse(&padata[0].padata_value).unwrap().1;
+        assert_eq!(None, pa_s4u_x509_user.user_id.cname);
+        assert_eq!(
+            Some(vec![0x30, 0x00]),
+            pa_s4u_x509_user.user_id.subject_certificate
+        );
+        assert_eq!(HMAC_MD5, pa_s4u_x509_user.checksum.cksumtype);
+    }
+
+    #[test]
+    fn verify_valid_reply() {
+        let key = EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]);
+        let user = create_user();
+        let reply = create_reply(user.user_id(1234), &key);
+
+        user.verify_reply(1234, &[reply], &key, true).unwrap();
+    }
+
+    #[test]
+    fn verify_missing_reply_of_user_by_name() {
+        let key = EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]);
+        create_user().verify_reply(1234, &[], &key, false).unwrap();
+    }
+
+    #[should_panic(expected = "InvalidS4uX509UserReply(\"not found\")")]
+    #[test]
+    fn verify_required_missing_reply_of_user_by_name() {
+        let key = EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]);
+        create_user().verify_reply(1234, &[], &key, true).unwrap();
+    }
+
+    #[should_panic(expected = "invalid checksum type")]
+    #[test]
+    fn verify_reply_with_checksum_of_another_type() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let user = create_user();
+        let reply = create_reply(
+            user.user_id(1234),
+            &EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]),
+        );
+
+        user.verify_reply(1234, &[reply], &key, false).unwrap();
+    }
+
+    #[should_panic(expected = "InvalidS4uX509UserReply(\"not found\")")]
+    #[test]
+    fn verify_missing_reply_of_user_by_certificate() {
+        let key = EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]);
+        let user =
+            S4uUser::Certificate(vec![0x30, 0x00], Realm::from("KINGDOM"));
+        user.verify_reply(1234, &[], &key, false).unwrap();
+    }
+
+    #[should_panic(expected = "InvalidS4uX509UserReply(\"invalid checksum\")")]
+    #[test]
+    fn verify_reply_with_invalid_checksum() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let user = create_user();
+        let reply = create_reply(
+            user.user_id(1234),
+            &EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![2; 32]),
+        );
+
+        user.verify_reply(1234, &[reply], &key, false).unwrap();
+    }
+
+    #[should_panic(expected = "NonceMismatch(1234, 4321)")]
+    #[test]
+    fn verify_reply_with_invalid_nonce() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let user = create_user();
+        let reply = create_reply(user.user_id(4321), &key);
+
+        user.verify_reply(1234, &[reply], &key, false).unwrap();
+    }
+
+    #[should_panic(expected = "user name does not match")]
+    #[test]
+    fn verify_reply_with_other_user() {
+        let key = EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let user = create_user();
+        let mut user_id = user.user_id(1234);
+        user_id.cname = Some(PrincipalName::new(
+            NT_PRINCIPAL,
+            KerberosString::from("mickey"),
+        ));
+        let reply = create_reply(user_id, &key);
+
+        user.verify_reply(1234, &[reply], &key, false).unwrap();
+    }
+}
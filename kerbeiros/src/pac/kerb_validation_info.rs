@@ -0,0 +1,398 @@
+use super::ndr_reader::NdrReader;
+use super::Sid;
+use crate::error::*;
+
+/// Group of the domain of the user, identified by its relative identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupMembership {
+    pub relative_id: u32,
+    pub attributes: u32,
+}
+
+/// SID of a group of other domains, such as universal groups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SidAndAttributes {
+    pub sid: Sid,
+    pub attributes: u32,
+}
+
+/// (*KERB_VALIDATION_INFO*) Logon information of the user, included in
+/// the PAC_LOGON_INFO buffer encoded with NDR. The times are FILETIMEs,
+/// the number of 100 ns intervals since January 1, 1601 (UTC).
+/// Defined in MS-PAC, section 2.5.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KerbValidationInfo {
+    pub logon_time: u64,
+    pub logoff_time: u64,
+    pub kick_off_time: u64,
+    pub password_last_set: u64,
+    pub password_can_change: u64,
+    pub password_must_change: u64,
+    pub effective_name: String,
+    pub full_name: String,
+    pub logon_script: String,
+    pub profile_path: String,
+    pub home_directory: String,
+    pub home_directory_drive: String,
+    pub logon_count: u16,
+    pub bad_password_count: u16,
+    pub user_id: u32,
+    pub primary_group_id: u32,
+    pub group_ids: Vec<GroupMembership>,
+    pub user_flags: u32,
+    pub user_session_key: Vec<u8>,
+    pub logon_server: String,
+    pub logon_domain_name: String,
+    pub logon_domain_id: Option<Sid>,
+    pub user_account_control: u32,
+    pub sub_auth_status: u32,
+    pub last_successful_i_logon: u64,
+    pub last_failed_i_logon: u64,
+    pub failed_i_logon_count: u32,
+    pub extra_sids: Vec<SidAndAttributes>,
+    pub resource_group_domain_sid: Option<Sid>,
+    pub resource_group_ids: Vec<GroupMembership>,
+}
+
+impl KerbValidationInfo {
+    /// Parses the content of a PAC_LOGON_INFO buffer.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        reader.read_type_header()?;
+        if reader.read_u32()? == 0 {
+            return Err(Error::BinaryParseError);
+        }
+
+        let mut info = Self {
+            logon_time: reader.read_filetime()?,
+            logoff_time: reader.read_filetime()?,
+            kick_off_time: reader.read_filetime()?,
+            password_last_set: reader.read_filetime()?,
+            password_can_change: reader.read_filetime()?,
+            password_must_change: reader.read_filetime()?,
+            ..Default::default()
+        };
+        let effective_name = reader.read_unicode_string_header()?;
+        let full_name = reader.read_unicode_string_header()?;
+        let logon_script = reader.read_unicode_string_header()?;
+        let profile_path = reader.read_unicode_string_header()?;
+        let home_directory = reader.read_unicode_string_header()?;
+        let home_directory_drive = reader.read_unicode_string_header()?;
+        info.logon_count = reader.read_u16()?;
+        info.bad_password_count = reader.read_u16()?;
+        info.user_id = reader.read_u32()?;
+        info.primary_group_id = reader.read_u32()?;
+        let group_count = reader.read_u32()?;
+        let group_ids_pointer = reader.read_u32()?;
+        info.user_flags = reader.read_u32()?;
+        info.user_session_key = reader.read_bytes(16)?.to_vec();
+        let logon_server = reader.read_unicode_string_header()?;
+        let logon_domain_name = reader.read_unicode_string_header()?;
+        let logon_domain_id_pointer = reader.read_u32()?;
+        let _reserved1 = reader.read_bytes(8)?;
+        info.user_account_control = reader.read_u32()?;
+        info.sub_auth_status = reader.read_u32()?;
+        info.last_successful_i_logon = reader.read_filetime()?;
+        info.last_failed_i_logon = reader.read_filetime()?;
+        info.failed_i_logon_count = reader.read_u32()?;
+        let _reserved3 = reader.read_u32()?;
+        let sid_count = reader.read_u32()?;
+        let extra_sids_pointer = reader.read_u32()?;
+        let resource_group_domain_sid_pointer = reader.read_u32()?;
+        let resource_group_count = reader.read_u32()?;
+        let resource_group_ids_pointer = reader.read_u32()?;
+
+        // The referents of the pointers follow the structure, in the same
+        // order as the pointers
+        info.effective_name = reader.read_unicode_string(&effective_name)?;
+        info.full_name = reader.read_unicode_string(&full_name)?;
+        info.logon_script = reader.read_unicode_string(&logon_script)?;
+        info.profile_path = reader.read_unicode_string(&profile_path)?;
+        info.home_directory = reader.read_unicode_string(&home_directory)?;
+        info.home_directory_drive =
+            reader.read_unicode_string(&home_directory_drive)?;
+        if group_ids_pointer != 0 {
+            info.group_ids = read_group_ids(&mut reader, group_count)?;
+        }
+        info.logon_server = reader.read_unicode_string(&logon_server)?;
+        info.logon_domain_name =
+            reader.read_unicode_string(&logon_domain_name)?;
+        if logon_domain_id_pointer != 0 {
+            info.logon_domain_id = Some(reader.read_rpc_sid()?);
+        }
+        if extra_sids_pointer != 0 {
+            info.extra_sids = read_extra_sids(&mut reader, sid_count)?;
+        }
+        if resource_group_domain_sid_pointer != 0 {
+            info.resource_group_domain_sid = Some(reader.read_rpc_sid()?);
+        }
+        if resource_group_ids_pointer != 0 {
+            info.resource_group_ids =
+                read_group_ids(&mut reader, resource_group_count)?;
+        }
+
+        return Ok(info);
+    }
+
+    /// SID of the user, composed by the SID of its domain and its RID.
+    pub fn user_sid(&self) -> Option<Sid> {
+        return self
+            .logon_domain_id
+            .as_ref()
+            .map(|domain_sid| domain_sid.with_rid(self.user_id));
+    }
+
+    /// SIDs of all the groups of the user: the primary group, the groups
+    /// of its domain, the extra SIDs and the resource groups.
+    pub fn group_sids(&self) -> Vec<Sid> {
+        let mut sids = Vec::new();
+        if let Some(domain_sid) = &self.logon_domain_id {
+            sids.push(domain_sid.with_rid(self.primary_group_id));
+            for group in self.group_ids.iter() {
+                let sid = domain_sid.with_rid(group.relative_id);
+                if !sids.contains(&sid) {
+                    sids.push(sid);
+                }
+            }
+        }
+        for extra_sid in self.extra_sids.iter() {
+            sids.push(extra_sid.sid.clone());
+        }
+        if let Some(domain_sid) = &self.resource_group_domain_sid {
+            for group in self.resource_group_ids.iter() {
+                sids.push(domain_sid.with_rid(group.relative_id));
+            }
+        }
+        return sids;
+    }
+
+    /// Whether the user is member of the group identified by the SID.
+    pub fn is_member_of(&self, group_sid: &Sid) -> bool {
+        return self.group_sids().contains(group_sid);
+    }
+}
+
+fn read_group_ids(
+    reader: &mut NdrReader,
+    count: u32,
+) -> Result<Vec<GroupMembership>> {
+    reader.read_array_count(count, 8)?;
+    let mut group_ids = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        group_ids.push(GroupMembership {
+            relative_id: reader.read_u32()?,
+            attributes: reader.read_u32()?,
+        });
+    }
+    return Ok(group_ids);
+}
+
+fn read_extra_sids(
+    reader: &mut NdrReader,
+    count: u32,
+) -> Result<Vec<SidAndAttributes>> {
+    reader.read_array_count(count, 8)?;
+    let mut pointers = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let pointer = reader.read_u32()?;
+        let attributes = reader.read_u32()?;
+        pointers.push((pointer, attributes));
+    }
+
+    let mut extra_sids = Vec::with_capacity(count as usize);
+    for (pointer, attributes) in pointers {
+        if pointer == 0 {
+            return Err(Error::BinaryParseError);
+        }
+        extra_sids.push(SidAndAttributes {
+            sid: reader.read_rpc_sid()?,
+            attributes,
+        });
+    }
+    return Ok(extra_sids);
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::super::ndr_reader::test::NdrWriter;
+    use super::*;
+    use himmelblau_kerberos_constants::pac::{
+        SE_GROUP_ENABLED, SE_GROUP_ENABLED_BY_DEFAULT, SE_GROUP_MANDATORY,
+        SE_GROUP_RESOURCE,
+    };
+
+    const GROUP_ATTRIBUTES: u32 =
+        SE_GROUP_MANDATORY | SE_GROUP_ENABLED_BY_DEFAULT | SE_GROUP_ENABLED;
+
+    pub fn domain_sid() -> Sid {
+        return Sid::new(1, [0, 0, 0, 0, 0, 5], vec![21, 1, 2, 3]);
+    }
+
+    pub fn new_kerb_validation_info() -> KerbValidationInfo {
+        return KerbValidationInfo {
+            logon_time: 0x01d9000000000001,
+            logoff_time: 0x7fffffffffffffff,
+            kick_off_time: 0x7fffffffffffffff,
+            password_last_set: 0x01d8000000000002,
+            password_can_change: 0x01d8000000000003,
+            password_must_change: 0x7fffffffffffffff,
+            effective_name: "mickey".to_string(),
+            full_name: "Mickey Mouse".to_string(),
+            logon_count: 12,
+            user_id: 1105,
+            primary_group_id: 513,
+            group_ids: vec![
+                GroupMembership {
+                    relative_id: 513,
+                    attributes: GROUP_ATTRIBUTES,
+                },
+                GroupMembership {
+                    relative_id: 512,
+                    attributes: GROUP_ATTRIBUTES,
+                },
+            ],
+            user_flags: 0x20,
+            user_session_key: vec![0; 16],
+            logon_server: "DC01".to_string(),
+            logon_domain_name: "KINGDOM".to_string(),
+            logon_domain_id: Some(domain_sid()),
+            user_account_control: 0x210,
+            extra_sids: vec![SidAndAttributes {
+                sid: Sid::new(1, [0, 0, 0, 0, 0, 5], vec![21, 4, 5, 6, 519]),
+                attributes: GROUP_ATTRIBUTES,
+            }],
+            resource_group_domain_sid: Some(Sid::new(
+                1,
+                [0, 0, 0, 0, 0, 5],
+                vec![21, 7, 8, 9],
+            )),
+            resource_group_ids: vec![GroupMembership {
+                relative_id: 1200,
+                attributes: GROUP_ATTRIBUTES | SE_GROUP_RESOURCE,
+            }],
+            ..Default::default()
+        };
+    }
+
+    /// Encodes the logon information with NDR, as the KDCs do.
+    pub fn build_kerb_validation_info(info: &KerbValidationInfo) -> Vec<u8> {
+        let mut writer = NdrWriter::new();
+        writer.filetime(info.logon_time);
+        writer.filetime(info.logoff_time);
+        writer.filetime(info.kick_off_time);
+        writer.filetime(info.password_last_set);
+        writer.filetime(info.password_can_change);
+        writer.filetime(info.password_must_change);
+        writer.unicode_string_header(&info.effective_name);
+        writer.unicode_string_header(&info.full_name);
+        writer.unicode_string_header(&info.logon_script);
+        writer.unicode_string_header(&info.profile_path);
+        writer.unicode_string_header(&info.home_directory);
+        writer.unicode_string_header(&info.home_directory_drive);
+        writer.u16(info.logon_count);
+        writer.u16(info.bad_password_count);
+        writer.u32(info.user_id);
+        writer.u32(info.primary_group_id);
+        writer.u32(info.group_ids.len() as u32);
+        writer.pointer(!info.group_ids.is_empty());
+        writer.u32(info.user_flags);
+        writer.data.extend_from_slice(&info.user_session_key);
+        writer.unicode_string_header(&info.logon_server);
+        writer.unicode_string_header(&info.logon_domain_name);
+        writer.pointer(info.logon_domain_id.is_some());
+        writer.u32(0);
+        writer.u32(0);
+        writer.u32(info.user_account_control);
+        writer.u32(info.sub_auth_status);
+        writer.filetime(info.last_successful_i_logon);
+        writer.filetime(info.last_failed_i_logon);
+        writer.u32(info.failed_i_logon_count);
+        writer.u32(0);
+        writer.u32(info.extra_sids.len() as u32);
+        writer.pointer(!info.extra_sids.is_empty());
+        writer.pointer(info.resource_group_domain_sid.is_some());
+        writer.u32(info.resource_group_ids.len() as u32);
+        writer.pointer(!info.resource_group_ids.is_empty());
+
+        writer.unicode_string(&info.effective_name);
+        writer.unicode_string(&info.full_name);
+        writer.unicode_string(&info.logon_script);
+        writer.unicode_string(&info.profile_path);
+        writer.unicode_string(&info.home_directory);
+        writer.unicode_string(&info.home_directory_drive);
+        write_group_ids(&mut writer, &info.group_ids);
+        writer.unicode_string(&info.logon_server);
+        writer.unicode_string(&info.logon_domain_name);
+        if let Some(sid) = &info.logon_domain_id {
+            writer.rpc_sid(sid);
+        }
+        if !info.extra_sids.is_empty() {
+            writer.u32(info.extra_sids.len() as u32);
+            for extra_sid in info.extra_sids.iter() {
+                writer.pointer(true);
+                writer.u32(extra_sid.attributes);
+            }
+            for extra_sid in info.extra_sids.iter() {
+                writer.rpc_sid(&extra_sid.sid);
+            }
+        }
+        if let Some(sid) = &info.resource_group_domain_sid {
+            writer.rpc_sid(sid);
+        }
+        write_group_ids(&mut writer, &info.resource_group_ids);
+        return writer.data;
+    }
+
+    fn write_group_ids(writer: &mut NdrWriter, group_ids: &[GroupMembership]) {
+        if group_ids.is_empty() {
+            return;
+        }
+        writer.u32(group_ids.len() as u32);
+        for group in group_ids.iter() {
+            writer.u32(group.relative_id);
+            writer.u32(group.attributes);
+        }
+    }
+
+    #[test]
+    fn parse_kerb_validation_info() {
+        let info = new_kerb_validation_info();
+        assert_eq!(
+            info,
+            KerbValidationInfo::parse(&build_kerb_validation_info(&info))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn retrieve_sids_of_user_and_groups() {
+        let info = new_kerb_validation_info();
+        assert_eq!("S-1-5-21-1-2-3-1105", info.user_sid().unwrap().to_string());
+
+        let group_sids: Vec<String> = info
+            .group_sids()
+            .iter()
+            .map(|sid| sid.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "S-1-5-21-1-2-3-513",
+                "S-1-5-21-1-2-3-512",
+                "S-1-5-21-4-5-6-519",
+                "S-1-5-21-7-8-9-1200",
+            ],
+            group_sids
+        );
+        assert!(info.is_member_of(&domain_sid().with_rid(512)));
+        assert!(!info.is_member_of(&domain_sid().with_rid(518)));
+    }
+
+    #[test]
+    fn error_parsing_truncated_kerb_validation_info() {
+        let raw = build_kerb_validation_info(&new_kerb_validation_info());
+        assert_eq!(
+            Error::BinaryParseError,
+            KerbValidationInfo::parse(&raw[..raw.len() - 4]).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Privilege Attribute Certificate (MS-PAC), included by the Active
+//! Directory KDCs in the tickets, which allows the services to authorize
+//! the clients by their groups.
+
+mod ndr_reader;
+
+mod sid;
+pub use sid::Sid;
+
+mod kerb_validation_info;
+pub use kerb_validation_info::{
+    GroupMembership, KerbValidationInfo, SidAndAttributes,
+};
+
+mod pac_client_info;
+pub use pac_client_info::PacClientInfo;
+
+mod upn_dns_info;
+pub use upn_dns_info::UpnDnsInfo;
+
+mod s4u_delegation_info;
+pub use s4u_delegation_info::S4uDelegationInfo;
+
+mod pac_attributes_info;
+pub use pac_attributes_info::PacAttributesInfo;
+
+mod pac_signature;
+pub use pac_signature::PacSignature;
+
+mod pac_type;
+pub use pac_type::{Pac, PacInfoBuffer};
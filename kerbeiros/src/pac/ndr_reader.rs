@@ -0,0 +1,304 @@
+use super::Sid;
+use crate::error::*;
+
+/// Size of the common and private headers of the type serialization
+/// version 1, that precede the NDR data of the PAC buffers
+const TYPE_HEADER_SIZE: usize = 16;
+
+/// Header of a RPC_UNICODE_STRING, whose characters are deferred
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct UnicodeStringHeader {
+    pub length: u16,
+    pub pointer: u32,
+}
+
+/// Reads the little-endian data of the PAC buffers. The primitive values
+/// are aligned to their size, as required by NDR (MS-RPCE section 2.2.5).
+pub(crate) struct NdrReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> NdrReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        return Self { data, position: 0 };
+    }
+
+    pub fn position(&self) -> usize {
+        return self.position;
+    }
+
+    pub fn align(&mut self, alignment: usize) {
+        let remainder = self.position % alignment;
+        if remainder != 0 {
+            self.position += alignment - remainder;
+        }
+    }
+
+    pub fn read_bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(size)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(Error::BinaryParseError)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        return Ok(bytes);
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        self.align(2);
+        let bytes = self.read_bytes(2)?;
+        return Ok(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        self.align(4);
+        let bytes = self.read_bytes(4)?;
+        return Ok(u32::from_le_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3],
+        ]));
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        self.align(8);
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        return Ok(u64::from_le_bytes(bytes));
+    }
+
+    /// Reads a FILETIME, which is composed by two 32 bits values.
+    pub fn read_filetime(&mut self) -> Result<u64> {
+        let low = self.read_u32()? as u64;
+        let high = self.read_u32()? as u64;
+        return Ok((high << 32) | low);
+    }
+
+    /// Reads an UTF-16 string of the given size in bytes.
+    pub fn read_utf16(&mut self, size: usize) -> Result<String> {
+        return utf16_to_string(self.read_bytes(size)?);
+    }
+
+    /// Reads a SID in binary format (MS-DTYP section 2.4.2.2).
+    pub fn read_sid(&mut self) -> Result<Sid> {
+        let revision = self.read_u8()?;
+        let sub_authority_count = self.read_u8()?;
+        let mut identifier_authority = [0; 6];
+        identifier_authority.copy_from_slice(self.read_bytes(6)?);
+
+        let mut sub_authorities =
+            Vec::with_capacity(sub_authority_count as usize);
+        for _ in 0..sub_authority_count {
+            sub_authorities.push(self.read_u32()?);
+        }
+        return Ok(Sid::new(revision, identifier_authority, sub_authorities));
+    }
+
+    /// Reads the headers of the type serialization version 1 (MS-RPCE
+    /// section 2.2.6), that precede the NDR data.
+    pub fn read_type_header(&mut self) -> Result<()> {
+        let header = self.read_bytes(TYPE_HEADER_SIZE)?;
+        // Version 1, little-endian and header length of 8
+        if header[..4] != [0x01, 0x10, 0x08, 0x00] {
+            return Err(Error::BinaryParseError);
+        }
+        return Ok(());
+    }
+
+    /// Reads the header of a RPC_UNICODE_STRING.
+    pub fn read_unicode_string_header(
+        &mut self,
+    ) -> Result<UnicodeStringHeader> {
+        let length = self.read_u16()?;
+        let _maximum_length = self.read_u16()?;
+        let pointer = self.read_u32()?;
+        return Ok(UnicodeStringHeader { length, pointer });
+    }
+
+    /// Reads the deferred characters of a RPC_UNICODE_STRING, as a
+    /// conformant and varying array.
+    pub fn read_unicode_string(
+        &mut self,
+        header: &UnicodeStringHeader,
+    ) -> Result<String> {
+        if header.pointer == 0 {
+            return Ok(String::new());
+        }
+        let _max_count = self.read_u32()?;
+        let offset = self.read_u32()?;
+        let actual_count = self.read_u32()? as usize;
+        if offset != 0 || actual_count * 2 != header.length as usize {
+            return Err(Error::BinaryParseError);
+        }
+        return self.read_utf16(actual_count * 2);
+    }
+
+    /// Reads a deferred RPC_SID, which is a conformant structure.
+    pub fn read_rpc_sid(&mut self) -> Result<Sid> {
+        let max_count = self.read_u32()?;
+        let sid = self.read_sid()?;
+        if max_count as usize != sid.sub_authorities.len() {
+            return Err(Error::BinaryParseError);
+        }
+        return Ok(sid);
+    }
+
+    /// Reads the maximum count of a conformant array, which must match the
+    /// number of elements specified in the structure. The remaining data
+    /// must be enough for the elements of the given size, so the count can
+    /// be used to allocate them.
+    pub fn read_array_count(
+        &mut self,
+        count: u32,
+        element_size: usize,
+    ) -> Result<()> {
+        if self.read_u32()? != count {
+            return Err(Error::BinaryParseError);
+        }
+        let remaining = self.data.len().saturating_sub(self.position);
+        if (count as usize).saturating_mul(element_size) > remaining {
+            return Err(Error::BinaryParseError);
+        }
+        return Ok(());
+    }
+}
+
+/// Decodes an UTF-16 little-endian string.
+pub(crate) fn utf16_to_string(bytes: &[u8]) -> Result<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(Error::BinaryParseError);
+    }
+    let chars: Vec<u16> = bytes
+        .chunks(2)
+        .map(|char| u16::from_le_bytes([char[0], char[1]]))
+        .collect();
+    return String::from_utf16(&chars).map_err(|_| Error::InvalidUtf8);
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// Writes NDR data, to build the buffers of the tests.
+    #[derive(Default)]
+    pub struct NdrWriter {
+        pub data: Vec<u8>,
+        next_pointer: u32,
+    }
+
+    impl NdrWriter {
+        /// Writes the type headers and the pointer to the structure.
+        pub fn new() -> Self {
+            let mut writer = Self::default();
+            writer.data.extend_from_slice(&[
+                0x01, 0x10, 0x08, 0x00, 0xcc, 0xcc, 0xcc, 0xcc,
+            ]);
+            writer.data.extend_from_slice(&[0; 8]);
+            writer.pointer(true);
+            return writer;
+        }
+
+        pub fn align(&mut self, alignment: usize) {
+            while !self.data.len().is_multiple_of(alignment) {
+                self.data.push(0);
+            }
+        }
+
+        pub fn u16(&mut self, value: u16) {
+            self.align(2);
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        pub fn u32(&mut self, value: u32) {
+            self.align(4);
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        pub fn filetime(&mut self, value: u64) {
+            self.u32(value as u32);
+            self.u32((value >> 32) as u32);
+        }
+
+        /// Writes a pointer, with a new referent id if it is not null.
+        pub fn pointer(&mut self, present: bool) {
+            if present {
+                self.next_pointer += 4;
+                self.u32(0x00020000 + self.next_pointer);
+            } else {
+                self.u32(0);
+            }
+        }
+
+        pub fn unicode_string_header(&mut self, string: &str) {
+            let length = (string.encode_utf16().count() * 2) as u16;
+            self.u16(length);
+            self.u16(length);
+            self.pointer(!string.is_empty());
+        }
+
+        pub fn unicode_string(&mut self, string: &str) {
+            if string.is_empty() {
+                return;
+            }
+            let chars: Vec<u16> = string.encode_utf16().collect();
+            self.u32(chars.len() as u32);
+            self.u32(0);
+            self.u32(chars.len() as u32);
+            for char in chars {
+                self.u16(char);
+            }
+        }
+
+        pub fn rpc_sid(&mut self, sid: &Sid) {
+            self.u32(sid.sub_authorities.len() as u32);
+            self.data.push(sid.revision);
+            self.data.push(sid.sub_authorities.len() as u8);
+            self.data.extend_from_slice(&sid.identifier_authority);
+            for sub_authority in sid.sub_authorities.iter() {
+                self.u32(*sub_authority);
+            }
+        }
+    }
+
+    #[test]
+    fn read_aligned_values() {
+        let mut reader = NdrReader::new(&[
+            0x01, 0xff, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00,
+            0x00, 0x05, 0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(1, reader.read_u8().unwrap());
+        assert_eq!(2, reader.read_u16().unwrap());
+        assert_eq!(3, reader.read_u32().unwrap());
+        assert_eq!(0x0000000500000004, reader.read_filetime().unwrap());
+        assert_eq!(Error::BinaryParseError, reader.read_u8().unwrap_err());
+    }
+
+    #[test]
+    fn read_unicode_string() {
+        let mut reader = NdrReader::new(&[
+            0x04, 0x00, 0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x61, 0x00,
+            0x62, 0x00,
+        ]);
+        let header = reader.read_unicode_string_header().unwrap();
+        assert_eq!("ab", reader.read_unicode_string(&header).unwrap());
+    }
+
+    #[test]
+    fn error_reading_array_count_beyond_data() {
+        let mut reader =
+            NdrReader::new(&[0xff, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            Error::BinaryParseError,
+            reader.read_array_count(u32::MAX, 8).unwrap_err()
+        );
+
+        let mut reader =
+            NdrReader::new(&[0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        reader.read_array_count(1, 4).unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+use super::ndr_reader::NdrReader;
+use crate::error::*;
+use himmelblau_kerberos_constants::pac::PAC_WAS_REQUESTED;
+
+/// (*PAC_ATTRIBUTES_INFO*) Attributes of the PAC, that indicate whether it
+/// was requested by the client.
+/// Defined in MS-PAC, section 2.14.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacAttributesInfo {
+    /// Number of bits of the flags
+    pub flags_length: u32,
+    pub flags: Vec<u32>,
+}
+
+impl PacAttributesInfo {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let flags_length = reader.read_u32()?;
+        let mut flags = Vec::new();
+        for _ in 0..flags_length.div_ceil(32) {
+            flags.push(reader.read_u32()?);
+        }
+        return Ok(Self {
+            flags_length,
+            flags,
+        });
+    }
+
+    /// Whether the client requested the PAC (PAC_WAS_REQUESTED).
+    pub fn was_requested(&self) -> bool {
+        return self
+            .flags
+            .first()
+            .map(|flags| flags & PAC_WAS_REQUESTED != 0)
+            .unwrap_or(false);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pac_attributes_info() {
+        let info = PacAttributesInfo::parse(&[
+            0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ])
+        .unwrap();
+
+        assert_eq!(2, info.flags_length);
+        assert_eq!(vec![PAC_WAS_REQUESTED], info.flags);
+        assert!(info.was_requested());
+    }
+}
@@ -0,0 +1,43 @@
+use super::ndr_reader::NdrReader;
+use crate::error::*;
+
+/// (*PAC_CLIENT_INFO*) Client of the ticket, used to verify that the PAC
+/// corresponds to the ticket.
+/// Defined in MS-PAC, section 2.7.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacClientInfo {
+    /// Authentication time of the ticket, as FILETIME
+    pub client_id: u64,
+    pub name: String,
+}
+
+impl PacClientInfo {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let client_id = reader.read_filetime()?;
+        let name_length = reader.read_u16()?;
+        let name = reader.read_utf16(name_length as usize)?;
+        return Ok(Self { client_id, name });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pac_client_info() {
+        assert_eq!(
+            PacClientInfo {
+                client_id: 0x01d0000000000001,
+                name: "mickey".to_string(),
+            },
+            PacClientInfo::parse(&[
+                0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xd0, 0x01, 0x0c, 0x00,
+                0x6d, 0x00, 0x69, 0x00, 0x63, 0x00, 0x6b, 0x00, 0x65, 0x00,
+                0x79, 0x00,
+            ])
+            .unwrap()
+        );
+    }
+}
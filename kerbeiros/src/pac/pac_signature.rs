@@ -0,0 +1,74 @@
+use super::ndr_reader::NdrReader;
+use crate::error::*;
+use himmelblau_kerberos_constants::checksum_types::{
+    HMAC_MD5, HMAC_SHA1_96_AES128, HMAC_SHA1_96_AES256,
+};
+
+/// Offset of the signature in the PAC_SIGNATURE_DATA
+pub(crate) const SIGNATURE_OFFSET: usize = 4;
+
+/// (*PAC_SIGNATURE_DATA*) Signature of the PAC, such as the server and
+/// the KDC checksums.
+/// Defined in MS-PAC, section 2.8.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacSignature {
+    pub signature_type: i32,
+    pub signature: Vec<u8>,
+    /// Identifier of the RODC that issued the ticket, if any
+    pub rodc_identifier: Option<u16>,
+}
+
+impl PacSignature {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let signature_type = reader.read_u32()? as i32;
+        let signature_size = match signature_type {
+            HMAC_MD5 => 16,
+            HMAC_SHA1_96_AES128 | HMAC_SHA1_96_AES256 => 12,
+            _ => raw.len() - SIGNATURE_OFFSET,
+        };
+        let signature = reader.read_bytes(signature_size)?.to_vec();
+
+        let rodc_identifier = if reader.position() < raw.len() {
+            Some(reader.read_u16()?)
+        } else {
+            None
+        };
+        return Ok(Self {
+            signature_type,
+            signature,
+            rodc_identifier,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pac_signature() {
+        let mut raw = vec![0x10, 0x00, 0x00, 0x00];
+        raw.extend_from_slice(&[0xaa; 12]);
+        assert_eq!(
+            PacSignature {
+                signature_type: HMAC_SHA1_96_AES256,
+                signature: vec![0xaa; 12],
+                rodc_identifier: None,
+            },
+            PacSignature::parse(&raw).unwrap()
+        );
+
+        let mut raw = vec![0x76, 0xff, 0xff, 0xff];
+        raw.extend_from_slice(&[0xbb; 16]);
+        raw.extend_from_slice(&[0x07, 0x00]);
+        assert_eq!(
+            PacSignature {
+                signature_type: HMAC_MD5,
+                signature: vec![0xbb; 16],
+                rodc_identifier: Some(7),
+            },
+            PacSignature::parse(&raw).unwrap()
+        );
+    }
+}
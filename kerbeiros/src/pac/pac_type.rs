@@ -0,0 +1,423 @@
+use super::ndr_reader::NdrReader;
+use super::pac_signature::SIGNATURE_OFFSET;
+use super::{
+    KerbValidationInfo, PacAttributesInfo, PacClientInfo, PacSignature,
+    S4uDelegationInfo, Sid, UpnDnsInfo,
+};
+use crate::error::*;
+use himmelblau_kerberos_asn1::{
+    AdIfRelevant, Asn1Object, AuthorizationData, EncTicketPart, EncryptionKey,
+};
+use himmelblau_kerberos_constants::ad_types::{AD_IF_RELEVANT, AD_WIN2K_PACK};
+use himmelblau_kerberos_constants::key_usages::KEY_USAGE_KERB_NON_KERB_CKSUM_SALT;
+use himmelblau_kerberos_constants::pac::{
+    PAC_ATTRIBUTES_INFO, PAC_CLIENT_INFO, PAC_DELEGATION_INFO,
+    PAC_FULL_CHECKSUM, PAC_LOGON_INFO, PAC_PRIVSVR_CHECKSUM, PAC_REQUESTOR,
+    PAC_SERVER_CHECKSUM, PAC_TICKET_CHECKSUM, PAC_UPN_DNS_INFO,
+};
+use himmelblau_kerberos_crypto::{checksum_type_from_etype, keyed_checksum};
+use std::convert::TryFrom;
+use subtle::ConstantTimeEq;
+
+/// Buffer of the PAC, as specified by its PAC_INFO_BUFFER.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacInfoBuffer {
+    pub buffer_type: u32,
+    /// Offset of the buffer from the start of the PAC
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// (*PACTYPE*) Privilege Attribute Certificate, included by the Active
+/// Directory KDCs in the authorization data of the tickets, which
+/// contains the logon information and groups of the client.
+///
+/// The known buffers are parsed, whereas all of them, including the
+/// unknown ones, are available in `buffers`.
+/// Defined in MS-PAC, section 2.3.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # let ap_req_info: ApReqInfo = unimplemented!();
+/// # let admins_sid: Sid = unimplemented!();
+///
+/// let pac = Pac::from_enc_ticket_part(&ap_req_info.enc_ticket_part)
+///     .unwrap()
+///     .unwrap();
+/// let logon_info = pac.logon_info.unwrap();
+/// if logon_info.is_member_of(&admins_sid) {
+///     println!("{} is an administrator", logon_info.effective_name);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pac {
+    pub version: u32,
+    pub buffers: Vec<PacInfoBuffer>,
+    pub logon_info: Option<KerbValidationInfo>,
+    pub client_info: Option<PacClientInfo>,
+    pub upn_dns_info: Option<UpnDnsInfo>,
+    pub delegation_info: Option<S4uDelegationInfo>,
+    pub requestor: Option<Sid>,
+    pub attributes_info: Option<PacAttributesInfo>,
+    pub server_checksum: Option<PacSignature>,
+    pub kdc_checksum: Option<PacSignature>,
+    pub ticket_checksum: Option<PacSignature>,
+    pub full_checksum: Option<PacSignature>,
+    raw: Vec<u8>,
+}
+
+impl Pac {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let buffer_count = reader.read_u32()?;
+        let version = reader.read_u32()?;
+
+        let mut pac = Self {
+            version,
+            buffers: Vec::new(),
+            logon_info: None,
+            client_info: None,
+            upn_dns_info: None,
+            delegation_info: None,
+            requestor: None,
+            attributes_info: None,
+            server_checksum: None,
+            kdc_checksum: None,
+            ticket_checksum: None,
+            full_checksum: None,
+            raw: raw.to_vec(),
+        };
+
+        for _ in 0..buffer_count {
+            let buffer_type = reader.read_u32()?;
+            let size = reader.read_u32()? as usize;
+            let offset = reader.read_u64()?;
+            let data = usize::try_from(offset)
+                .ok()
+                .and_then(|start| raw.get(start..start.checked_add(size)?))
+                .ok_or(Error::BinaryParseError)?;
+            pac.parse_buffer(buffer_type, data)?;
+            pac.buffers.push(PacInfoBuffer {
+                buffer_type,
+                offset,
+                data: data.to_vec(),
+            });
+        }
+
+        return Ok(pac);
+    }
+
+    /// Retrieves the PAC of the authorization data of a ticket, where it
+    /// is included as AD-WIN2K-PAC inside an AD-IF-RELEVANT element.
+    pub fn from_authorization_data(
+        authorization_data: &AuthorizationData,
+    ) -> Result<Option<Self>> {
+        for entry in authorization_data.iter() {
+            match entry.ad_type {
+                AD_WIN2K_PACK => return Ok(Some(Self::parse(&entry.ad_data)?)),
+                AD_IF_RELEVANT => {
+                    let (_, if_relevant) = AdIfRelevant::parse(&entry.ad_data)?;
+                    if let Some(pac) =
+                        Self::from_authorization_data(&if_relevant)?
+                    {
+                        return Ok(Some(pac));
+                    }
+                }
+                _ => {}
+            }
+        }
+        return Ok(None);
+    }
+
+    /// Retrieves the PAC of a decrypted ticket, if any.
+    pub fn from_enc_ticket_part(
+        enc_ticket_part: &EncTicketPart,
+    ) -> Result<Option<Self>> {
+        match &enc_ticket_part.authorization_data {
+            Some(authorization_data) => {
+                return Self::from_authorization_data(authorization_data);
+            }
+            None => return Ok(None),
+        }
+    }
+
+    /// Verifies the server checksum with the key of the service that
+    /// decrypted the ticket, which proves that the PAC was issued along
+    /// with the ticket.
+    pub fn verify_server_checksum(&self, key: &EncryptionKey) -> Result<()> {
+        let server_checksum = self
+            .server_checksum
+            .as_ref()
+            .ok_or(Error::InvalidPacSignature)?;
+        return verify_signature(server_checksum, key, &self.zeroed_raw()?);
+    }
+
+    /// Verifies the KDC checksum, which signs the server checksum, with the
+    /// key of the krbtgt account.
+    pub fn verify_kdc_checksum(&self, key: &EncryptionKey) -> Result<()> {
+        let server_checksum = self
+            .server_checksum
+            .as_ref()
+            .ok_or(Error::InvalidPacSignature)?;
+        let kdc_checksum = self
+            .kdc_checksum
+            .as_ref()
+            .ok_or(Error::InvalidPacSignature)?;
+        return verify_signature(kdc_checksum, key, &server_checksum.signature);
+    }
+
+    fn parse_buffer(&mut self, buffer_type: u32, data: &[u8]) -> Result<()> {
+        match buffer_type {
+            PAC_LOGON_INFO => {
+                self.logon_info = Some(KerbValidationInfo::parse(data)?);
+            }
+            PAC_CLIENT_INFO => {
+                self.client_info = Some(PacClientInfo::parse(data)?);
+            }
+            PAC_UPN_DNS_INFO => {
+                self.upn_dns_info = Some(UpnDnsInfo::parse(data)?);
+            }
+            PAC_DELEGATION_INFO => {
+                self.delegation_info = Some(S4uDelegationInfo::parse(data)?);
+            }
+            PAC_REQUESTOR => {
+                self.requestor = Some(Sid::parse(data)?);
+            }
+            PAC_ATTRIBUTES_INFO => {
+                self.attributes_info = Some(PacAttributesInfo::parse(data)?);
+            }
+            PAC_SERVER_CHECKSUM => {
+                self.server_checksum = Some(PacSignature::parse(data)?);
+            }
+            PAC_PRIVSVR_CHECKSUM => {
+                self.kdc_checksum = Some(PacSignature::parse(data)?);
+            }
+            PAC_TICKET_CHECKSUM => {
+                self.ticket_checksum = Some(PacSignature::parse(data)?);
+            }
+            PAC_FULL_CHECKSUM => {
+                self.full_checksum = Some(PacSignature::parse(data)?);
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    /// Raw PAC with the signatures of the server and KDC checksums set to
+    /// zero, as it was when the server checksum was calculated.
+    fn zeroed_raw(&self) -> Result<Vec<u8>> {
+        let mut raw = self.raw.clone();
+        for buffer in self.buffers.iter() {
+            let signature = match buffer.buffer_type {
+                PAC_SERVER_CHECKSUM => &self.server_checksum,
+                PAC_PRIVSVR_CHECKSUM => &self.kdc_checksum,
+                _ => continue,
+            };
+            if let Some(signature) = signature {
+                let start = buffer.offset as usize + SIGNATURE_OFFSET;
+                let end = start + signature.signature.len();
+                raw.get_mut(start..end)
+                    .ok_or(Error::BinaryParseError)?
+                    .fill(0);
+            }
+        }
+        return Ok(raw);
+    }
+}
+
+fn verify_signature(
+    signature: &PacSignature,
+    key: &EncryptionKey,
+    data: &[u8],
+) -> Result<()> {
+    // The signature must be of the type of the key, since other types may
+    // require keys of other sizes
+    if signature.signature_type != checksum_type_from_etype(key.keytype)? {
+        return Err(Error::InvalidPacSignature);
+    }
+    let checksum = keyed_checksum(
+        signature.signature_type,
+        &key.keyvalue,
+        KEY_USAGE_KERB_NON_KERB_CKSUM_SALT,
+        data,
+    )?;
+    if !bool::from(checksum[..].ct_eq(&signature.signature[..])) {
+        return Err(Error::InvalidPacSignature);
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::kerb_validation_info::test::{
+        build_kerb_validation_info, new_kerb_validation_info,
+    };
+    use super::*;
+    use crate::ap::test::{new_enc_ticket_part, service_key};
+    use himmelblau_kerberos_asn1::AuthorizationDataEntry;
+    use himmelblau_kerberos_constants::checksum_types::HMAC_SHA1_96_AES256;
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, RC4_HMAC,
+    };
+
+    const SIGNATURE_SIZE: usize = 12;
+
+    /// Builds a PAC with the logon information, signed with the given
+    /// server and KDC keys.
+    fn build_pac(
+        server_key: &EncryptionKey,
+        kdc_key: &EncryptionKey,
+    ) -> Vec<u8> {
+        let mut signature = HMAC_SHA1_96_AES256.to_le_bytes().to_vec();
+        signature.extend_from_slice(&[0; SIGNATURE_SIZE]);
+        let buffers = [
+            (
+                PAC_LOGON_INFO,
+                build_kerb_validation_info(&new_kerb_validation_info()),
+            ),
+            (PAC_SERVER_CHECKSUM, signature.clone()),
+            (PAC_PRIVSVR_CHECKSUM, signature),
+        ];
+
+        let mut raw = Vec::new();
+        raw.extend_from_slice(&(buffers.len() as u32).to_le_bytes());
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        let mut offset = 8 + 16 * buffers.len();
+        let mut signature_offsets = Vec::new();
+        for (buffer_type, data) in buffers.iter() {
+            raw.extend_from_slice(&buffer_type.to_le_bytes());
+            raw.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            raw.extend_from_slice(&(offset as u64).to_le_bytes());
+            if *buffer_type != PAC_LOGON_INFO {
+                signature_offsets.push(offset + SIGNATURE_OFFSET);
+            }
+            offset += data.len().div_ceil(8) * 8;
+        }
+        for (_, data) in buffers.iter() {
+            raw.extend_from_slice(data);
+            raw.resize(raw.len().div_ceil(8) * 8, 0);
+        }
+
+        let server_signature = sign(server_key, &raw);
+        let server_offset = signature_offsets[0];
+        raw[server_offset..server_offset + SIGNATURE_SIZE]
+            .copy_from_slice(&server_signature);
+        let kdc_offset = signature_offsets[1];
+        raw[kdc_offset..kdc_offset + SIGNATURE_SIZE]
+            .copy_from_slice(&sign(kdc_key, &server_signature));
+        return raw;
+    }
+
+    fn sign(key: &EncryptionKey, data: &[u8]) -> Vec<u8> {
+        return keyed_checksum(
+            HMAC_SHA1_96_AES256,
+            &key.keyvalue,
+            KEY_USAGE_KERB_NON_KERB_CKSUM_SALT,
+            data,
+        )
+        .unwrap();
+    }
+
+    fn new_pac() -> Pac {
+        return Pac::parse(&build_pac(&service_key(2), &service_key(9)))
+            .unwrap();
+    }
+
+    #[test]
+    fn parse_pac() {
+        let pac = new_pac();
+        assert_eq!(0, pac.version);
+        assert_eq!(3, pac.buffers.len());
+        assert_eq!(Some(new_kerb_validation_info()), pac.logon_info);
+        assert_eq!(
+            HMAC_SHA1_96_AES256,
+            pac.server_checksum.unwrap().signature_type
+        );
+        assert_eq!(None, pac.client_info);
+        assert_eq!(None, pac.ticket_checksum);
+    }
+
+    #[test]
+    fn error_parsing_pac_with_buffer_out_of_bounds() {
+        let raw = build_pac(&service_key(2), &service_key(9));
+        assert_eq!(
+            Error::BinaryParseError,
+            Pac::parse(&raw[..raw.len() - 16]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn retrieve_pac_of_enc_ticket_part() {
+        let raw_pac = build_pac(&service_key(2), &service_key(9));
+        let if_relevant: AdIfRelevant = vec![AuthorizationDataEntry {
+            ad_type: AD_WIN2K_PACK,
+            ad_data: raw_pac,
+        }];
+        let mut enc_ticket_part = new_enc_ticket_part();
+        enc_ticket_part.authorization_data =
+            Some(vec![AuthorizationDataEntry {
+                ad_type: AD_IF_RELEVANT,
+                ad_data: if_relevant.build(),
+            }]);
+
+        assert_eq!(
+            Some(new_pac()),
+            Pac::from_enc_ticket_part(&enc_ticket_part).unwrap()
+        );
+
+        enc_ticket_part.authorization_data = None;
+        assert_eq!(None, Pac::from_enc_ticket_part(&enc_ticket_part).unwrap());
+    }
+
+    #[test]
+    fn verify_pac_checksums() {
+        let pac = new_pac();
+        pac.verify_server_checksum(&service_key(2)).unwrap();
+        pac.verify_kdc_checksum(&service_key(9)).unwrap();
+
+        assert_eq!(
+            Error::InvalidPacSignature,
+            pac.verify_server_checksum(&service_key(1)).unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidPacSignature,
+            pac.verify_kdc_checksum(&service_key(2)).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_verifying_pac_checksums_with_key_of_another_type() {
+        let pac = new_pac();
+        assert_eq!(
+            Error::InvalidPacSignature,
+            pac.verify_server_checksum(&EncryptionKey::new(
+                RC4_HMAC,
+                vec![2; 16]
+            ))
+            .unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidPacSignature,
+            pac.verify_kdc_checksum(&EncryptionKey::new(
+                AES128_CTS_HMAC_SHA1_96,
+                vec![9; 16]
+            ))
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn detect_tampered_pac() {
+        let mut raw = build_pac(&service_key(2), &service_key(9));
+        // The user id of the logon information
+        raw[8 + 16 * 3 + 20 + 6 * 8 + 6 * 8 + 4] ^= 1;
+        let pac = Pac::parse(&raw).unwrap();
+        assert_eq!(1104, pac.logon_info.as_ref().unwrap().user_id);
+        assert_eq!(
+            Error::InvalidPacSignature,
+            pac.verify_server_checksum(&service_key(2)).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use super::ndr_reader::NdrReader;
+use crate::error::*;
+
+/// (*S4U_DELEGATION_INFO*) Services that the client was delegated through
+/// with S4U2proxy, included in the PAC_DELEGATION_INFO buffer encoded with
+/// NDR.
+/// Defined in MS-PAC, section 2.9.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct S4uDelegationInfo {
+    pub s4u2proxy_target: String,
+    pub transited_services: Vec<String>,
+}
+
+impl S4uDelegationInfo {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        reader.read_type_header()?;
+        if reader.read_u32()? == 0 {
+            return Err(Error::BinaryParseError);
+        }
+
+        let s4u2proxy_target = reader.read_unicode_string_header()?;
+        let transited_list_size = reader.read_u32()?;
+        let transited_services_pointer = reader.read_u32()?;
+
+        let mut info = Self {
+            s4u2proxy_target: reader.read_unicode_string(&s4u2proxy_target)?,
+            transited_services: Vec::new(),
+        };
+        if transited_services_pointer != 0 {
+            reader.read_array_count(transited_list_size, 8)?;
+            let mut headers = Vec::with_capacity(transited_list_size as usize);
+            for _ in 0..transited_list_size {
+                headers.push(reader.read_unicode_string_header()?);
+            }
+            for header in headers.iter() {
+                info.transited_services
+                    .push(reader.read_unicode_string(header)?);
+            }
+        }
+        return Ok(info);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ndr_reader::test::NdrWriter;
+    use super::*;
+
+    #[test]
+    fn parse_s4u_delegation_info() {
+        let mut writer = NdrWriter::new();
+        writer.unicode_string_header("cifs/fs");
+        writer.u32(2);
+        writer.pointer(true);
+        writer.unicode_string("cifs/fs");
+        writer.u32(2);
+        writer.unicode_string_header("http/web");
+        writer.unicode_string_header("http/app");
+        writer.unicode_string("http/web");
+        writer.unicode_string("http/app");
+
+        assert_eq!(
+            S4uDelegationInfo {
+                s4u2proxy_target: "cifs/fs".to_string(),
+                transited_services: vec![
+                    "http/web".to_string(),
+                    "http/app".to_string()
+                ],
+            },
+            S4uDelegationInfo::parse(&writer.data).unwrap()
+        );
+    }
+}
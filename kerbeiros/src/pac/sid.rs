@@ -0,0 +1,90 @@
+use super::ndr_reader::NdrReader;
+use crate::error::*;
+use std::fmt;
+
+/// Security identifier of a user or group, defined in MS-DTYP section
+/// 2.4.2.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sid {
+    pub revision: u8,
+    pub identifier_authority: [u8; 6],
+    pub sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    pub fn new(
+        revision: u8,
+        identifier_authority: [u8; 6],
+        sub_authorities: Vec<u32>,
+    ) -> Self {
+        return Self {
+            revision,
+            identifier_authority,
+            sub_authorities,
+        };
+    }
+
+    /// Parses a SID in binary format.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let sid = reader.read_sid()?;
+        if reader.position() != raw.len() {
+            return Err(Error::BinaryParseError);
+        }
+        return Ok(sid);
+    }
+
+    /// Creates the SID of an account of a domain, by appending its
+    /// relative identifier (RID) to the SID of the domain.
+    pub fn with_rid(&self, rid: u32) -> Self {
+        let mut sid = self.clone();
+        sid.sub_authorities.push(rid);
+        return sid;
+    }
+
+    /// Relative identifier, the last sub-authority.
+    pub fn rid(&self) -> Option<u32> {
+        return self.sub_authorities.last().copied();
+    }
+}
+
+impl fmt::Display for Sid {
+    /// Displays the SID in the S-1-5-21-... string format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut authority = [0; 8];
+        authority[2..].copy_from_slice(&self.identifier_authority);
+        write!(f, "S-{}-{}", self.revision, u64::from_be_bytes(authority))?;
+        for sub_authority in self.sub_authorities.iter() {
+            write!(f, "-{}", sub_authority)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_sid() {
+        let sid = Sid::parse(&[
+            0x01, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x15, 0x00, 0x00,
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x00,
+        ])
+        .unwrap();
+
+        assert_eq!("S-1-5-21-1-2-3", sid.to_string());
+        assert_eq!("S-1-5-21-1-2-3-513", sid.with_rid(513).to_string());
+        assert_eq!(Some(3), sid.rid());
+    }
+
+    #[test]
+    fn error_parsing_truncated_sid() {
+        assert_eq!(
+            Error::BinaryParseError,
+            Sid::parse(&[0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05])
+                .unwrap_err()
+        );
+    }
+}
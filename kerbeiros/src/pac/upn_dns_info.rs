@@ -0,0 +1,102 @@
+use super::ndr_reader::{utf16_to_string, NdrReader};
+use super::Sid;
+use crate::error::*;
+use himmelblau_kerberos_constants::pac::UPN_DNS_INFO_EXTENDED;
+
+/// (*UPN_DNS_INFO*) UPN and DNS domain of the client. In case of
+/// UPN_DNS_INFO_EXTENDED, it also includes the SAM name and the SID of the
+/// client.
+/// Defined in MS-PAC, section 2.10.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpnDnsInfo {
+    pub upn: String,
+    pub dns_domain_name: String,
+    pub flags: u32,
+    pub sam_name: Option<String>,
+    pub sid: Option<Sid>,
+}
+
+impl UpnDnsInfo {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = NdrReader::new(raw);
+        let upn_length = reader.read_u16()?;
+        let upn_offset = reader.read_u16()?;
+        let dns_domain_name_length = reader.read_u16()?;
+        let dns_domain_name_offset = reader.read_u16()?;
+        let flags = reader.read_u32()?;
+
+        let mut info = Self {
+            upn: utf16_to_string(field(raw, upn_offset, upn_length)?)?,
+            dns_domain_name: utf16_to_string(field(
+                raw,
+                dns_domain_name_offset,
+                dns_domain_name_length,
+            )?)?,
+            flags,
+            sam_name: None,
+            sid: None,
+        };
+
+        if flags & UPN_DNS_INFO_EXTENDED != 0 {
+            let sam_name_length = reader.read_u16()?;
+            let sam_name_offset = reader.read_u16()?;
+            let sid_length = reader.read_u16()?;
+            let sid_offset = reader.read_u16()?;
+            info.sam_name = Some(utf16_to_string(field(
+                raw,
+                sam_name_offset,
+                sam_name_length,
+            )?)?);
+            info.sid = Some(Sid::parse(field(raw, sid_offset, sid_length)?)?);
+        }
+
+        return Ok(info);
+    }
+}
+
+/// Field of the buffer specified by its offset and length.
+fn field(raw: &[u8], offset: u16, length: u16) -> Result<&[u8]> {
+    let start = offset as usize;
+    let end = start + length as usize;
+    return raw.get(start..end).ok_or(Error::BinaryParseError);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_upn_dns_info() {
+        let raw = [
+            0x04, 0x00, 0x10, 0x00, 0x04, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x6d, 0x00, 0x40, 0x00, 0x4b, 0x00,
+            0x48, 0x00,
+        ];
+        assert_eq!(
+            UpnDnsInfo {
+                upn: "m@".to_string(),
+                dns_domain_name: "KH".to_string(),
+                flags: 0,
+                sam_name: None,
+                sid: None,
+            },
+            UpnDnsInfo::parse(&raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_extended_upn_dns_info() {
+        let raw = [
+            0x02, 0x00, 0x18, 0x00, 0x02, 0x00, 0x1a, 0x00, 0x02, 0x00, 0x00,
+            0x00, 0x02, 0x00, 0x1c, 0x00, 0x0c, 0x00, 0x20, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x6d, 0x00, 0x4b, 0x00, 0x6d, 0x00, 0x00, 0x00, 0x01,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x12, 0x00, 0x00, 0x00,
+        ];
+        let info = UpnDnsInfo::parse(&raw).unwrap();
+
+        assert_eq!("m", info.upn);
+        assert_eq!("K", info.dns_domain_name);
+        assert_eq!(Some("m".to_string()), info.sam_name);
+        assert_eq!("S-1-5-18", info.sid.unwrap().to_string());
+    }
+}
@@ -1,11 +1,14 @@
+use crate::discovery::{DnsResolver, KdcEndpoint, SystemDnsResolver};
 use crate::error::*;
 use crate::messages::*;
 use crate::transporter::*;
 use ascii::AsciiString;
-use himmelblau_kerberos_asn1::{AsRep, Asn1Object, KrbError};
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{AsRep, Asn1Object, KrbError, PrincipalName};
 use himmelblau_kerberos_crypto::Key;
 use std::collections::HashSet;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 
 /// Encapsule the possible responses to an AS-REQ request
 #[derive(Debug, PartialEq)]
@@ -52,15 +55,33 @@ pub enum AsReqResponse {
 pub struct AsRequester {
     as_options: AsReqOptions,
     transporter: Box<dyn Transporter>,
-    kdc_address: IpAddr,
+    kdcs: Vec<KdcEndpoint>,
+    transport_protocol: TransportProtocol,
+    transport_settings: TransportSettings,
+    resolver: Arc<dyn DnsResolver>,
 }
 
 impl AsRequester {
     pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::with_kdcs(realm, vec![KdcEndpoint::from(kdc_address)]);
+    }
+
+    /// Creates a requester that sends the requests to the first KDC of the
+    /// list that responds.
+    pub fn with_kdcs(realm: AsciiString, kdcs: Vec<KdcEndpoint>) -> Self {
+        let resolver: Arc<dyn DnsResolver> = Arc::new(SystemDnsResolver::new());
         return Self {
-            kdc_address,
-            transporter: new_transporter(kdc_address, TransportProtocol::TCP),
+            transporter: Box::new(FailoverTransporter::new(
+                kdcs.clone(),
+                resolver.clone(),
+                TransportProtocol::TCP,
+                TransportSettings::default(),
+            )),
+            kdcs,
             as_options: AsReqOptions::new(realm),
+            transport_protocol: TransportProtocol::TCP,
+            transport_settings: TransportSettings::default(),
+            resolver,
         };
     }
 
@@ -73,10 +94,49 @@ impl AsRequester {
             username,
             user_key,
             &self.as_options,
+            None,
             &self.transporter,
         );
     }
 
+    /// Sends the AS-REQ choosing the etype, salt and string-to-key
+    /// parameters of the user key from the hints supplied by the KDC.
+    pub fn request_with_preauth_hints(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        hints: &PreauthHints,
+    ) -> Result<AsReqResponse> {
+        return AsRequest::request(
+            username,
+            user_key,
+            &self.as_options,
+            Some(hints),
+            &self.transporter,
+        );
+    }
+
+    /// Sends the AS-REQ to the KDC of another realm, with the same options.
+    #[allow(clippy::borrowed_box)]
+    pub(crate) fn request_to_realm(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        hints: Option<&PreauthHints>,
+        realm: &AsciiString,
+        transporter: &Box<dyn Transporter>,
+    ) -> Result<AsReqResponse> {
+        let mut as_options = self.as_options.clone();
+        as_options.set_realm(realm.clone());
+        return AsRequest::request(
+            username,
+            user_key,
+            &as_options,
+            hints,
+            transporter,
+        );
+    }
+
     pub fn etypes(&self) -> &HashSet<i32> {
         return self.as_options.etypes();
     }
@@ -93,16 +153,140 @@ impl AsRequester {
         return self.as_options.kdc_options();
     }
 
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.as_options.set_kdc_options(kdc_options);
+    }
+
+    pub fn starttime(&self) -> Option<&DateTime<Utc>> {
+        return self.as_options.starttime();
+    }
+
+    /// Sets the time from which the requested tickets should be valid,
+    /// requesting postdated tickets.
+    pub fn set_starttime(&mut self, starttime: Option<DateTime<Utc>>) {
+        self.as_options.set_starttime(starttime);
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.as_options.ticket_lifetime();
+    }
+
+    /// Sets the lifetime requested for the ticket. By default, the maximum
+    /// lifetime allowed by the KDC is requested.
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.as_options.set_ticket_lifetime(ticket_lifetime);
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.as_options.renew_lifetime();
+    }
+
+    /// Sets the renewable lifetime requested for the ticket. By default,
+    /// the maximum renewable lifetime allowed by the KDC is requested.
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.as_options.set_renew_lifetime(renew_lifetime);
+    }
+
     pub fn realm(&self) -> &AsciiString {
         return self.as_options.realm();
     }
 
+    pub fn sname(&self) -> Option<&PrincipalName> {
+        return self.as_options.sname();
+    }
+
+    /// Sets the service of the requested ticket, such as kadmin/changepw.
+    /// By default, the TGT of the realm is requested.
+    pub fn set_sname(&mut self, sname: Option<PrincipalName>) {
+        self.as_options.set_sname(sname);
+    }
+
+    pub fn name_type(&self) -> i32 {
+        return self.as_options.name_type();
+    }
+
+    /// Sets the type of the client name, such as NT_ENTERPRISE to request
+    /// tickets for user principal names like bob@contoso.com.
+    pub fn set_name_type(&mut self, name_type: i32) {
+        self.as_options.set_name_type(name_type);
+    }
+
+    pub fn transport_protocol(&self) -> TransportProtocol {
+        return self.transport_protocol;
+    }
+
     pub fn set_transport_protocol(
         &mut self,
         transport_protocol: TransportProtocol,
     ) {
-        self.transporter =
-            new_transporter(self.kdc_address, transport_protocol);
+        self.transport_protocol = transport_protocol;
+        self.reset_transporter();
+    }
+
+    /// Sets the maximum size of the messages sent over UDP, since bigger
+    /// messages are sent over TCP.
+    pub fn set_udp_preference_limit(&mut self, udp_preference_limit: usize) {
+        self.transport_settings.udp_preference_limit = udp_preference_limit;
+        self.reset_transporter();
+    }
+
+    pub fn transport_settings(&self) -> &TransportSettings {
+        return &self.transport_settings;
+    }
+
+    /// Sets the timeouts and retries of the connections with the KDCs.
+    pub fn set_transport_settings(
+        &mut self,
+        transport_settings: TransportSettings,
+    ) {
+        self.transport_settings = transport_settings;
+        self.reset_transporter();
+    }
+
+    pub fn kdcs(&self) -> &[KdcEndpoint] {
+        return &self.kdcs;
+    }
+
+    /// Sets the KDCs of the realm, which are tried in order until one
+    /// responds.
+    pub fn set_kdcs(&mut self, kdcs: Vec<KdcEndpoint>) {
+        self.kdcs = kdcs;
+        self.reset_transporter();
+    }
+
+    /// Sets the resolver of the host names of the KDCs.
+    pub fn set_resolver(&mut self, resolver: Box<dyn DnsResolver>) {
+        self.share_resolver(Arc::from(resolver));
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn resolver(&self) -> Arc<dyn DnsResolver> {
+        return self.resolver.clone();
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn as_options(&self) -> &AsReqOptions {
+        return &self.as_options;
+    }
+
+    pub(crate) fn share_resolver(&mut self, resolver: Arc<dyn DnsResolver>) {
+        self.resolver = resolver;
+        self.reset_transporter();
+    }
+
+    /// Retrieves the address of the KDC that answered the last request.
+    /// The following requests are sent to this KDC first.
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.transporter.last_kdc_address();
+    }
+
+    fn reset_transporter(&mut self) {
+        self.transporter = new_kdcs_transporter(
+            self.kdcs.clone(),
+            self.resolver.clone(),
+            self.transport_protocol,
+            self.transport_settings.clone(),
+        );
     }
 
     #[cfg(test)]
@@ -111,7 +295,7 @@ impl AsRequester {
     }
 }
 
-struct AsRequest {}
+pub(crate) struct AsRequest {}
 
 impl AsRequest {
     #[allow(clippy::borrowed_box)]
@@ -119,15 +303,18 @@ impl AsRequest {
         username: &AsciiString,
         user_key: Option<&Key>,
         options: &AsReqOptions,
+        hints: Option<&PreauthHints>,
         transporter: &Box<dyn Transporter>,
     ) -> Result<AsReqResponse> {
         let raw_as_req =
-            AsReqBuilder::build_as_req(username, user_key, options).unwrap();
+            AsReqBuilder::build_as_req(username, user_key, options, hints)?;
         let raw_response = transporter.request_and_response(&raw_as_req)?;
         return Self::parse_as_request_response(&raw_response);
     }
 
-    fn parse_as_request_response(raw_response: &[u8]) -> Result<AsReqResponse> {
+    pub(crate) fn parse_as_request_response(
+        raw_response: &[u8],
+    ) -> Result<AsReqResponse> {
         match KrbError::parse(raw_response) {
             Ok((_, krb_error)) => {
                 return Ok(AsReqResponse::KrbError(krb_error));
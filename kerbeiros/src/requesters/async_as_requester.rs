@@ -0,0 +1,181 @@
+use super::as_requester::{AsReqResponse, AsRequest, AsRequester};
+use crate::discovery::KdcEndpoint;
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use himmelblau_kerberos_crypto::Key;
+use std::net::{IpAddr, SocketAddr};
+
+/// Sends the AS-REQ requests asynchronously and retrieves the response.
+/// The options are the ones of the [AsRequester] it is created from.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request_as_rep() -> Result<AsReqResponse> {
+///     let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+///     let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     let user_key = Key::Secret("S3cr3t".to_string());
+///
+///     let mut as_requester = AsRequester::new(realm, kdc_address);
+///     as_requester.set_etype(AES256_CTS_HMAC_SHA1_96)?;
+///
+///     let as_requester = AsyncAsRequester::from(as_requester);
+///     return as_requester.request(&username, Some(&user_key)).await;
+/// }
+/// ```
+///
+pub struct AsyncAsRequester {
+    requester: AsRequester,
+    transporter: Box<dyn AsyncTransporter>,
+}
+
+impl AsyncAsRequester {
+    pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::from(AsRequester::new(realm, kdc_address));
+    }
+
+    /// Creates a requester that sends the requests to the first KDC of the
+    /// list that responds.
+    pub fn with_kdcs(realm: AsciiString, kdcs: Vec<KdcEndpoint>) -> Self {
+        return Self::from(AsRequester::with_kdcs(realm, kdcs));
+    }
+
+    /// Synchronous requester which holds the options of the requests.
+    pub fn requester(&self) -> &AsRequester {
+        return &self.requester;
+    }
+
+    pub async fn request(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<AsReqResponse> {
+        return AsyncAsRequest::request(
+            username,
+            user_key,
+            self.requester.as_options(),
+            None,
+            &self.transporter,
+        )
+        .await;
+    }
+
+    /// Sends the AS-REQ choosing the etype, salt and string-to-key
+    /// parameters of the user key from the hints supplied by the KDC.
+    pub async fn request_with_preauth_hints(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        hints: &PreauthHints,
+    ) -> Result<AsReqResponse> {
+        return AsyncAsRequest::request(
+            username,
+            user_key,
+            self.requester.as_options(),
+            Some(hints),
+            &self.transporter,
+        )
+        .await;
+    }
+
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.transporter.last_kdc_address();
+    }
+
+    #[cfg(test)]
+    pub fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.transporter = transporter;
+    }
+}
+
+impl From<AsRequester> for AsyncAsRequester {
+    fn from(requester: AsRequester) -> Self {
+        let transporter = new_async_kdcs_transporter(
+            requester.kdcs().to_vec(),
+            requester.resolver(),
+            requester.transport_protocol(),
+            requester.transport_settings().clone(),
+        );
+        return Self {
+            requester,
+            transporter,
+        };
+    }
+}
+
+pub(crate) struct AsyncAsRequest {}
+
+impl AsyncAsRequest {
+    #[allow(clippy::borrowed_box)]
+    pub async fn request(
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        options: &AsReqOptions,
+        hints: Option<&PreauthHints>,
+        transporter: &Box<dyn AsyncTransporter>,
+    ) -> Result<AsReqResponse> {
+        let raw_as_req =
+            AsReqBuilder::build_as_req(username, user_key, options, hints)?;
+        let raw_response =
+            transporter.request_and_response(&raw_as_req).await?;
+        return AsRequest::parse_as_request_response(&raw_response);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_trait::async_trait;
+    use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_PREAUTH_REQUIRED;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn receive_krb_error() {
+        struct FakeTransporter {}
+
+        #[async_trait]
+        impl AsyncTransporter for FakeTransporter {
+            async fn request_and_response(
+                &self,
+                _raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let krb_error = KrbError {
+                    pvno: 5,
+                    msg_type: 30,
+                    error_code: KDC_ERR_PREAUTH_REQUIRED,
+                    ..Default::default()
+                };
+                return Ok(krb_error.build());
+            }
+        }
+
+        let mut as_requester = AsyncAsRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        );
+        as_requester.set_transporter(Box::new(FakeTransporter {}));
+
+        match as_requester
+            .request(&AsciiString::from_ascii("Mickey").unwrap(), None)
+            .await
+            .unwrap()
+        {
+            AsReqResponse::KrbError(krb_error) => {
+                assert_eq!(KDC_ERR_PREAUTH_REQUIRED, krb_error.error_code);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+}
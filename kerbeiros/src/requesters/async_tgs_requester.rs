@@ -0,0 +1,304 @@
+use super::tgs_requester::{
+    referral_realm, service_to_principal_name, visit_referral_realm,
+    TgsRequest, TgsRequester,
+};
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::discovery::KdcEndpoint;
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use himmelblau_kerberos_asn1::{Asn1Object, PrincipalName};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Gets service tickets from the KDC asynchronously by sending TGS-REQ
+/// requests authenticated with a TGT. The options are the ones of the
+/// [TgsRequester] it is created from.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+///
+/// async fn request_service_ticket(
+///     tgs_requester: &AsyncTgsRequester,
+///     tgt: &Credential,
+/// ) -> Result<Credential> {
+///     let service = AsciiString::from_ascii("HTTP/web.contoso.com").unwrap();
+///     return tgs_requester.request(tgt, &service).await;
+/// }
+/// ```
+///
+pub struct AsyncTgsRequester {
+    requester: TgsRequester,
+    transporter: Box<dyn AsyncTransporter>,
+    realm_transporters: HashMap<String, Box<dyn AsyncTransporter>>,
+}
+
+impl AsyncTgsRequester {
+    pub fn new(kdc_address: IpAddr) -> Self {
+        return Self::from(TgsRequester::new(kdc_address));
+    }
+
+    /// Creates a requester which sends the requests to the given KDCs,
+    /// trying them in order until one of them responds.
+    pub fn with_kdcs(kdcs: Vec<KdcEndpoint>) -> Self {
+        return Self::from(TgsRequester::with_kdcs(kdcs));
+    }
+
+    /// Creates a requester for the default realm of the configuration, as
+    /// [TgsRequester::from_krb5_config] does.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        return Ok(Self::from(TgsRequester::from_krb5_config(config)?));
+    }
+
+    /// Synchronous requester which holds the options of the requests.
+    pub fn requester(&self) -> &TgsRequester {
+        return &self.requester;
+    }
+
+    /// Requests a ticket for the service, in the form `service/host`.
+    pub async fn request(
+        &self,
+        tgt: &Credential,
+        service: &AsciiString,
+    ) -> Result<Credential> {
+        return self
+            .request_principal(tgt, &service_to_principal_name(service))
+            .await;
+    }
+
+    /// Requests a ticket for the specified principal name, following the
+    /// referral TGTs returned by the KDCs.
+    pub async fn request_principal(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+    ) -> Result<Credential> {
+        let home_realm = tgs_realm(tgt).to_uppercase();
+        let mut visited_realms = vec![home_realm.clone()];
+        let mut credential =
+            self.request_in_realm(tgt, sname, &home_realm).await?;
+
+        while let Some(next_realm) = referral_realm(&credential, sname) {
+            visit_referral_realm(
+                &mut visited_realms,
+                next_realm,
+                self.requester.max_referrals(),
+            )?;
+            credential = self
+                .request_in_realm(&credential, sname, &home_realm)
+                .await?;
+        }
+
+        return Ok(credential);
+    }
+
+    /// Renews a renewable ticket, as [TgsRequester::renew] does.
+    pub async fn renew(&self, credential: &Credential) -> Result<Credential> {
+        let builder = self.requester.renew_builder(credential)?;
+        return AsyncTgsRequest::request(&builder, &self.transporter).await;
+    }
+
+    /// Validates a postdated ticket, as [TgsRequester::validate] does.
+    pub async fn validate(
+        &self,
+        credential: &Credential,
+    ) -> Result<Credential> {
+        let builder = self.requester.validate_builder(credential)?;
+        return AsyncTgsRequest::request(&builder, &self.transporter).await;
+    }
+
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.transporter.last_kdc_address();
+    }
+
+    /// Sends the TGS-REQ to the KDC of the realm that issued the TGT.
+    async fn request_in_realm(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+        home_realm: &str,
+    ) -> Result<Credential> {
+        let builder =
+            TgsReqBuilder::new(tgt, sname, self.requester.tgs_options())?;
+        let realm = tgs_realm(tgt).to_uppercase();
+
+        if realm == home_realm {
+            return AsyncTgsRequest::request(&builder, &self.transporter).await;
+        }
+
+        if let Some(transporter) = self.realm_transporters.get(&realm) {
+            return AsyncTgsRequest::request(&builder, transporter).await;
+        }
+
+        let kdc_locator = self.requester.kdc_locator();
+        let kdcs = kdc_locator
+            .async_kdc_endpoints(&realm, self.requester.transport_protocol())
+            .await?;
+        let transporter = new_async_kdcs_transporter(
+            kdcs,
+            kdc_locator.resolver(),
+            self.requester.transport_protocol(),
+            self.requester.transport_settings().clone(),
+        );
+        return AsyncTgsRequest::request(&builder, &transporter).await;
+    }
+
+    #[cfg(test)]
+    fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.transporter = transporter;
+    }
+
+    #[cfg(test)]
+    fn set_realm_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn AsyncTransporter>,
+    ) {
+        self.realm_transporters
+            .insert(realm.to_uppercase(), transporter);
+    }
+}
+
+impl From<TgsRequester> for AsyncTgsRequester {
+    fn from(requester: TgsRequester) -> Self {
+        let transporter = new_async_kdcs_transporter(
+            requester.kdcs().to_vec(),
+            requester.kdc_locator().resolver(),
+            requester.transport_protocol(),
+            requester.transport_settings().clone(),
+        );
+        return Self {
+            requester,
+            transporter,
+            realm_transporters: HashMap::new(),
+        };
+    }
+}
+
+struct AsyncTgsRequest {}
+
+impl AsyncTgsRequest {
+    #[allow(clippy::borrowed_box)]
+    async fn request(
+        builder: &TgsReqBuilder<'_>,
+        transporter: &Box<dyn AsyncTransporter>,
+    ) -> Result<Credential> {
+        let raw_tgs_req = builder.build_tgs_req()?.build();
+        let raw_response =
+            transporter.request_and_response(&raw_tgs_req).await?;
+        let tgs_rep = TgsRequest::parse_tgs_rep(&raw_response)?;
+        return TgsRequest::extract_credential_from_tgs_rep(builder, tgs_rep);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::tgs_requester::test::{create_tgt, FakeKdc, ReferralKdc};
+    use super::*;
+    use himmelblau_kerberos_asn1::{EncryptionKey, KerberosString, Realm};
+    use himmelblau_kerberos_constants::etypes::AES256_CTS_HMAC_SHA1_96;
+    use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+    use std::net::Ipv4Addr;
+
+    fn new_tgs_requester() -> AsyncTgsRequester {
+        return AsyncTgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+    }
+
+    #[tokio::test]
+    async fn request_service_ticket() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_tgs_requester();
+        tgs_requester.set_transporter(Box::new(SyncTransporterAdapter(
+            Box::new(FakeKdc {
+                session_key,
+                nonce_delta: 0,
+            }),
+        )));
+
+        let credential = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.kingdom.hearts").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("HTTP"));
+        sname.push(KerberosString::from("web.kingdom.hearts"));
+
+        assert_eq!(&sname, credential.sname());
+        assert_eq!(&Realm::from("KINGDOM.HEARTS"), credential.srealm());
+    }
+
+    #[tokio::test]
+    async fn error_with_invalid_nonce() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_tgs_requester();
+        tgs_requester.set_transporter(Box::new(SyncTransporterAdapter(
+            Box::new(FakeKdc {
+                session_key,
+                nonce_delta: 1,
+            }),
+        )));
+
+        let error = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.kingdom.hearts").unwrap(),
+            )
+            .await
+            .unwrap_err();
+
+        match error {
+            Error::ParseTgsRepError(_, error) => match *error {
+                Error::NonceMismatch(_, _) => {}
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn follow_referrals_to_service_realm() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let issued_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]);
+        let tgt = create_tgt(session_key.clone());
+
+        let mut tgs_requester = new_tgs_requester();
+        tgs_requester.set_transporter(Box::new(SyncTransporterAdapter(
+            Box::new(ReferralKdc {
+                session_key,
+                referral: Some("DARK.WORLD"),
+            }),
+        )));
+        tgs_requester.set_realm_transporter(
+            "DARK.WORLD",
+            Box::new(SyncTransporterAdapter(Box::new(ReferralKdc {
+                session_key: issued_key,
+                referral: None,
+            }))),
+        );
+
+        let credential = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!("DARK.WORLD", credential.srealm().to_string());
+    }
+}
@@ -0,0 +1,306 @@
+use super::as_requester::AsReqResponse;
+use super::async_as_requester::AsyncAsRequest;
+use super::tgt_requester::{
+    extract_credential_from_as_rep, follow_wrong_realm_referral,
+    preauth_key_and_hints, TgtRequester,
+};
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::discovery::KdcEndpoint;
+use crate::messages::PreauthHints;
+use crate::transporter::*;
+use crate::{Error, Result};
+use ascii::AsciiString;
+use himmelblau_kerberos_constants::error_codes::KDC_ERR_WRONG_REALM;
+use himmelblau_kerberos_crypto::Key;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Gets a TGT from KDC asynchronously by sending one or more AS-REQ
+/// requests. The options are the ones of the [TgtRequester] it is
+/// created from.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerbeiros::config::Krb5Config;
+/// use ascii::AsciiString;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// async fn request_tgt() -> Result<Credential> {
+///     let config = Krb5Config::load()?;
+///     let username = AsciiString::from_ascii("Bob").unwrap();
+///     let user_key = Key::Secret("S3cr3t".to_string());
+///
+///     let tgt_requester = AsyncTgtRequester::from_krb5_config(&config)?;
+///     return tgt_requester.request(&username, Some(&user_key)).await;
+/// }
+/// ```
+///
+pub struct AsyncTgtRequester {
+    requester: TgtRequester,
+    transporter: Box<dyn AsyncTransporter>,
+    realm_transporters: HashMap<String, Box<dyn AsyncTransporter>>,
+}
+
+impl AsyncTgtRequester {
+    pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::from(TgtRequester::new(realm, kdc_address));
+    }
+
+    /// Creates a requester which sends the requests to the given KDCs,
+    /// trying them in order until one of them responds.
+    pub fn with_kdcs(realm: AsciiString, kdcs: Vec<KdcEndpoint>) -> Self {
+        return Self::from(TgtRequester::with_kdcs(realm, kdcs));
+    }
+
+    /// Creates a requester for the default realm of the configuration, as
+    /// [TgtRequester::from_krb5_config] does.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        return Ok(Self::from(TgtRequester::from_krb5_config(config)?));
+    }
+
+    /// Synchronous requester which holds the options of the requests.
+    pub fn requester(&self) -> &TgtRequester {
+        return &self.requester;
+    }
+
+    /// Requests a TGT for the user. In case the KDC responds with a
+    /// KDC_ERR_WRONG_REALM referral, the request is retried against the
+    /// realm indicated by the KDC.
+    pub async fn request(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+    ) -> Result<Credential> {
+        let mut realm = self.requester.realm().clone();
+        let mut visited_realms = vec![realm.to_string().to_uppercase()];
+
+        loop {
+            match self.request_tgt(username, user_key, &realm).await {
+                Err(Error::KrbErrorResponse(krb_error))
+                    if krb_error.error_code == KDC_ERR_WRONG_REALM =>
+                {
+                    realm = follow_wrong_realm_referral(
+                        krb_error,
+                        &mut visited_realms,
+                        self.requester.max_referrals(),
+                    )?;
+                }
+                result => {
+                    return result;
+                }
+            }
+        }
+    }
+
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.transporter.last_kdc_address();
+    }
+
+    async fn request_tgt(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        realm: &AsciiString,
+    ) -> Result<Credential> {
+        let krb_error = match self
+            .request_to_realm(username, None, None, realm)
+            .await?
+        {
+            AsReqResponse::KrbError(krb_error) => krb_error,
+            AsReqResponse::AsRep(as_rep) => {
+                return extract_credential_from_as_rep(user_key, as_rep, None);
+            }
+        };
+
+        let (user_key, hints) = preauth_key_and_hints(krb_error, user_key)?;
+        match self
+            .request_to_realm(username, Some(user_key), Some(&hints), realm)
+            .await?
+        {
+            AsReqResponse::KrbError(krb_error) => {
+                return Err(Error::KrbErrorResponse(krb_error));
+            }
+            AsReqResponse::AsRep(as_rep) => {
+                return extract_credential_from_as_rep(
+                    Some(user_key),
+                    as_rep,
+                    Some(&hints),
+                );
+            }
+        }
+    }
+
+    /// Sends an AS-REQ to the KDC of the given realm.
+    async fn request_to_realm(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        hints: Option<&PreauthHints>,
+        realm: &AsciiString,
+    ) -> Result<AsReqResponse> {
+        let realm_key = realm.to_string().to_uppercase();
+        if realm_key == self.requester.realm().to_string().to_uppercase() {
+            return AsyncAsRequest::request(
+                username,
+                user_key,
+                self.requester.as_options(),
+                hints,
+                &self.transporter,
+            )
+            .await;
+        }
+
+        let mut as_options = self.requester.as_options().clone();
+        as_options.set_realm(realm.clone());
+
+        if let Some(transporter) = self.realm_transporters.get(&realm_key) {
+            return AsyncAsRequest::request(
+                username,
+                user_key,
+                &as_options,
+                hints,
+                transporter,
+            )
+            .await;
+        }
+
+        let kdc_locator = self.requester.kdc_locator();
+        let kdcs = kdc_locator
+            .async_kdc_endpoints(
+                realm.as_str(),
+                self.requester.transport_protocol(),
+            )
+            .await?;
+        let transporter = new_async_kdcs_transporter(
+            kdcs,
+            kdc_locator.resolver(),
+            self.requester.transport_protocol(),
+            self.requester.transport_settings().clone(),
+        );
+        return AsyncAsRequest::request(
+            username,
+            user_key,
+            &as_options,
+            hints,
+            &transporter,
+        )
+        .await;
+    }
+
+    #[cfg(test)]
+    fn set_transporter(&mut self, transporter: Box<dyn AsyncTransporter>) {
+        self.transporter = transporter;
+    }
+
+    #[cfg(test)]
+    fn set_realm_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn AsyncTransporter>,
+    ) {
+        self.realm_transporters
+            .insert(realm.to_uppercase(), transporter);
+    }
+}
+
+impl From<TgtRequester> for AsyncTgtRequester {
+    fn from(requester: TgtRequester) -> Self {
+        let transporter = new_async_kdcs_transporter(
+            requester.kdcs().to_vec(),
+            requester.kdc_locator().resolver(),
+            requester.transport_protocol(),
+            requester.transport_settings().clone(),
+        );
+        return Self {
+            requester,
+            transporter,
+            realm_transporters: HashMap::new(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::tgt_requester::test::{CanonicalKdc, WrongRealmKdc};
+    use super::*;
+    use himmelblau_kerberos_asn1::{KerberosString, PrincipalName};
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_ENTERPRISE, NT_PRINCIPAL,
+    };
+    use std::net::Ipv4Addr;
+
+    fn new_enterprise_tgt_requester() -> AsyncTgtRequester {
+        let mut tgt_requester = TgtRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        );
+        tgt_requester.set_name_type(NT_ENTERPRISE);
+        return AsyncTgtRequester::from(tgt_requester);
+    }
+
+    #[tokio::test]
+    async fn request_tgt_following_wrong_realm_referral() {
+        let mut tgt_requester = new_enterprise_tgt_requester();
+        tgt_requester.set_transporter(Box::new(SyncTransporterAdapter(
+            Box::new(WrongRealmKdc {
+                referral: "DISNEY.COM",
+            }),
+        )));
+        tgt_requester.set_realm_transporter(
+            "DISNEY.COM",
+            Box::new(SyncTransporterAdapter(Box::new(CanonicalKdc {}))),
+        );
+
+        let credential = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey@disney.com").unwrap(),
+                Some(&Key::AES256Key([5; 32])),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!("DISNEY.COM", credential.crealm().to_string());
+        assert_eq!(
+            &PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
+            credential.cname()
+        );
+    }
+
+    #[tokio::test]
+    async fn error_on_wrong_realm_referral_loop() {
+        let mut tgt_requester = new_enterprise_tgt_requester();
+        tgt_requester.set_transporter(Box::new(SyncTransporterAdapter(
+            Box::new(WrongRealmKdc {
+                referral: "DISNEY.COM",
+            }),
+        )));
+        tgt_requester.set_realm_transporter(
+            "DISNEY.COM",
+            Box::new(SyncTransporterAdapter(Box::new(WrongRealmKdc {
+                referral: "KINGDOM.HEARTS",
+            }))),
+        );
+
+        let error = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey@disney.com").unwrap(),
+                Some(&Key::AES256Key([5; 32])),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(Error::ReferralLoop("KINGDOM.HEARTS".to_string()), error);
+    }
+
+    #[test]
+    fn requests_can_be_spawned() {
+        fn assert_send<T: Send>(_: T) {}
+
+        let tgt_requester = new_enterprise_tgt_requester();
+        let username = AsciiString::from_ascii("mickey").unwrap();
+        assert_send(tgt_requester.request(&username, None));
+    }
+}
@@ -0,0 +1,554 @@
+use super::tgt_requester::*;
+use crate::config::Krb5Config;
+use crate::credentials::*;
+use crate::discovery::{
+    discover_kpasswd_servers, DnsResolver, KdcEndpoint, KdcLocator,
+    DEFAULT_KPASSWD_PORT,
+};
+use crate::messages::KpasswdRequest;
+use crate::transporter::*;
+use crate::{Error, Result};
+use ascii::AsciiString;
+use chrono::Duration;
+use himmelblau_kerberos_asn1::{KerberosString, PrincipalName, Realm};
+use himmelblau_kerberos_constants::principal_names::NT_SRV_INST;
+use himmelblau_kerberos_crypto::Key;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Lifetime of the tickets requested for the kadmin/changepw service
+const CHANGEPW_TICKET_LIFETIME_MINUTES: i64 = 5;
+
+/// Changes and sets passwords through the kpasswd protocol (RFC 3244).
+///
+/// The requests are authenticated with a ticket for the kadmin/changepw
+/// service, which is requested to the KDC with an AS-REQ, since the
+/// kpasswd servers only accept initial tickets.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// // Prepare the arguments
+/// let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+/// let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// let user_key = Key::Secret("S3cr3t".to_string());
+///
+/// // Change the password of the user
+/// let kpasswd_requester = KpasswdRequester::new(realm, kdc_address);
+/// kpasswd_requester
+///     .change_password(&username, &user_key, "N3wS3cr3t")
+///     .unwrap();
+/// ```
+///
+pub struct KpasswdRequester {
+    tgt_requester: TgtRequester,
+    servers: Vec<KdcEndpoint>,
+    resolver: Arc<dyn DnsResolver>,
+    transport_protocol: TransportProtocol,
+    transport_settings: TransportSettings,
+    transporter: Box<dyn Transporter>,
+}
+
+impl KpasswdRequester {
+    /// Creates a requester for the realm, where the KDC and the kpasswd
+    /// server are in the same host.
+    pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::with_servers(
+            TgtRequester::new(realm, kdc_address),
+            vec![KdcEndpoint::from(SocketAddr::new(
+                kdc_address,
+                DEFAULT_KPASSWD_PORT,
+            ))],
+        );
+    }
+
+    /// Creates a requester which gets the kadmin/changepw tickets with the
+    /// given TGT requester and sends the kpasswd requests to the given
+    /// servers, trying them in order until one of them responds. The
+    /// transport protocol, settings and resolver of the TGT requester are
+    /// used to communicate with the servers.
+    pub fn with_servers(
+        mut tgt_requester: TgtRequester,
+        servers: Vec<KdcEndpoint>,
+    ) -> Self {
+        tgt_requester.set_sname(Some(changepw_principal_name()));
+        tgt_requester.set_kdc_options(0);
+        tgt_requester.set_ticket_lifetime(Some(Duration::minutes(
+            CHANGEPW_TICKET_LIFETIME_MINUTES,
+        )));
+        tgt_requester.set_renew_lifetime(None);
+
+        let resolver = tgt_requester.kdc_locator().resolver();
+        let transport_protocol = tgt_requester.transport_protocol();
+        let transport_settings = tgt_requester.transport_settings().clone();
+        return Self {
+            transporter: new_kdcs_transporter(
+                servers.clone(),
+                resolver.clone(),
+                transport_protocol,
+                transport_settings.clone(),
+            ),
+            tgt_requester,
+            servers,
+            resolver,
+            transport_protocol,
+            transport_settings,
+        };
+    }
+
+    /// Creates a requester for the default realm of the configuration.
+    /// The kpasswd servers are the `kpasswd_server` entries of the realm
+    /// or, if there are none, the hosts of the `admin_server` entries.
+    /// Otherwise, they are discovered through DNS or, if DNS lookups are
+    /// disabled, the hosts of the KDCs are used.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        let realm = config.default_realm().ok_or_else(|| {
+            Error::ConfigError("default_realm is not defined".to_string())
+        })?;
+        let tgt_requester = TgtRequester::from_krb5_config(config)?;
+        let servers = kpasswd_servers(
+            config,
+            realm,
+            tgt_requester.kdc_locator(),
+            tgt_requester.transport_protocol(),
+        )?;
+        return Ok(Self::with_servers(tgt_requester, servers));
+    }
+
+    /// Requests a ticket for the kadmin/changepw service with the key of
+    /// the user.
+    pub fn request_ticket(
+        &self,
+        username: &AsciiString,
+        user_key: &Key,
+    ) -> Result<Credential> {
+        return self.tgt_requester.request(username, Some(user_key));
+    }
+
+    /// Changes the password of the user, which must be expired or known
+    /// by the user, since the key of the user is required.
+    pub fn change_password(
+        &self,
+        username: &AsciiString,
+        user_key: &Key,
+        new_password: &str,
+    ) -> Result<()> {
+        let credential = self.request_ticket(username, user_key)?;
+        return self.change_password_with_ticket(&credential, new_password);
+    }
+
+    /// Changes the password of the client of the kadmin/changepw ticket.
+    pub fn change_password_with_ticket(
+        &self,
+        credential: &Credential,
+        new_password: &str,
+    ) -> Result<()> {
+        let request = KpasswdRequest::new(credential)?;
+        let raw_reply = self.transporter.request_and_response(
+            &request.build_change_password(new_password)?,
+        )?;
+        return request.parse_reply(&raw_reply);
+    }
+
+    /// Sets the password of the target principal (Microsoft set password
+    /// extension), which requires the client of the kadmin/changepw ticket
+    /// to have permissions to reset the password of the target.
+    pub fn set_password(
+        &self,
+        credential: &Credential,
+        target: &PrincipalName,
+        target_realm: &Realm,
+        new_password: &str,
+    ) -> Result<()> {
+        let request = KpasswdRequest::new(credential)?;
+        let raw_reply = self.transporter.request_and_response(
+            &request.build_set_password(new_password, target, target_realm)?,
+        )?;
+        return request.parse_reply(&raw_reply);
+    }
+
+    pub fn tgt_requester(&self) -> &TgtRequester {
+        return &self.tgt_requester;
+    }
+
+    pub fn servers(&self) -> &[KdcEndpoint] {
+        return &self.servers;
+    }
+
+    /// Sets the kpasswd servers, which are tried in order until one of
+    /// them responds.
+    pub fn set_servers(&mut self, servers: Vec<KdcEndpoint>) {
+        self.servers = servers;
+        self.update_transporter();
+    }
+
+    pub fn transport_protocol(&self) -> TransportProtocol {
+        return self.transport_protocol;
+    }
+
+    pub fn set_transport_protocol(
+        &mut self,
+        transport_protocol: TransportProtocol,
+    ) {
+        self.transport_protocol = transport_protocol;
+        self.tgt_requester
+            .set_transport_protocol(transport_protocol);
+        self.update_transporter();
+    }
+
+    pub fn transport_settings(&self) -> &TransportSettings {
+        return &self.transport_settings;
+    }
+
+    /// Sets the timeouts, retries, UDP preference limit and KDC proxy used
+    /// to communicate with the KDCs and the kpasswd servers.
+    pub fn set_transport_settings(
+        &mut self,
+        transport_settings: TransportSettings,
+    ) {
+        self.tgt_requester
+            .set_transport_settings(transport_settings.clone());
+        self.transport_settings = transport_settings;
+        self.update_transporter();
+    }
+
+    /// Sets the resolver used to resolve the names of the KDCs and the
+    /// kpasswd servers.
+    pub fn set_resolver(&mut self, resolver: Box<dyn DnsResolver>) {
+        self.tgt_requester.set_resolver(resolver);
+        self.resolver = self.tgt_requester.kdc_locator().resolver();
+        self.update_transporter();
+    }
+
+    fn update_transporter(&mut self) {
+        self.transporter = new_kdcs_transporter(
+            self.servers.clone(),
+            self.resolver.clone(),
+            self.transport_protocol,
+            self.transport_settings.clone(),
+        );
+    }
+
+    #[cfg(test)]
+    fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
+        self.transporter = transporter;
+    }
+}
+
+/// Principal name of the password change service, kadmin/changepw
+fn changepw_principal_name() -> PrincipalName {
+    let mut sname =
+        PrincipalName::new(NT_SRV_INST, KerberosString::from("kadmin"));
+    sname.push(KerberosString::from("changepw"));
+    return sname;
+}
+
+/// Retrieves the kpasswd servers of the realm from the configuration or
+/// through DNS. The KDC proxy locates the servers by itself.
+fn kpasswd_servers(
+    config: &Krb5Config,
+    realm: &str,
+    kdc_locator: &KdcLocator,
+    transport_protocol: TransportProtocol,
+) -> Result<Vec<KdcEndpoint>> {
+    if transport_protocol == TransportProtocol::KdcProxy {
+        return Ok(Vec::new());
+    }
+
+    let servers = parse_servers(config.kpasswd_servers(realm), realm, true)?;
+    if !servers.is_empty() {
+        return Ok(servers);
+    }
+
+    // The kpasswd service runs in the admin servers, but not in the port
+    // of the kadmin service
+    let servers = parse_servers(config.admin_servers(realm), realm, false)?;
+    if !servers.is_empty() {
+        return Ok(servers);
+    }
+
+    if kdc_locator.dns_lookup_kdc() {
+        return Ok(discover_kpasswd_servers(
+            &*kdc_locator.resolver(),
+            realm,
+            transport_protocol,
+        )?
+        .into_iter()
+        .map(KdcEndpoint::from)
+        .collect());
+    }
+
+    return Ok(kdc_locator
+        .kdc_endpoints(realm, transport_protocol)?
+        .into_iter()
+        .map(|kdc| KdcEndpoint::new(&kdc.host, DEFAULT_KPASSWD_PORT))
+        .collect());
+}
+
+fn parse_servers(
+    servers: Vec<&str>,
+    realm: &str,
+    keep_port: bool,
+) -> Result<Vec<KdcEndpoint>> {
+    return servers
+        .into_iter()
+        .map(|server| {
+            let mut endpoint = KdcEndpoint::parse_with_default_port(
+                server,
+                DEFAULT_KPASSWD_PORT,
+            )
+            .map_err(|_| {
+                Error::ConfigError(format!(
+                    "invalid kpasswd server of {}: {}",
+                    realm, server
+                ))
+            })?;
+            if !keep_port {
+                endpoint.port = DEFAULT_KPASSWD_PORT;
+            }
+            return Ok(endpoint);
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ap::test::session_key;
+    use crate::discovery::test::FakeDnsResolver;
+    use crate::discovery::SrvRecord;
+    use crate::messages::{
+        create_changepw_credential, kpasswd_reply, KpasswdResultCode,
+    };
+    use himmelblau_kerberos_asn1::{Asn1Object, ChangePasswdData};
+    use himmelblau_kerberos_constants::kpasswd::*;
+    use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
+
+    use std::net::Ipv4Addr;
+    use std::sync::Mutex;
+
+    /// Version and user data of the requests received by the server
+    type KpasswdRequests = Arc<Mutex<Vec<(u16, Vec<u8>)>>>;
+
+    /// Kpasswd server that replies with the given result code and saves
+    /// the version and user data of the requests
+    struct FakeKpasswdServer {
+        result_code: u16,
+        result_string: Vec<u8>,
+        requests: KpasswdRequests,
+    }
+
+    impl Transporter for FakeKpasswdServer {
+        fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+            let (version, user_data, raw_reply) = kpasswd_reply(
+                &session_key(),
+                raw_request,
+                self.result_code,
+                &self.result_string,
+            );
+            self.requests.lock().unwrap().push((version, user_data));
+            return Ok(raw_reply);
+        }
+    }
+
+    fn new_requester(
+        result_code: u16,
+        result_string: &[u8],
+    ) -> (KpasswdRequester, KpasswdRequests) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let mut requester = KpasswdRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        );
+        requester.set_transporter(Box::new(FakeKpasswdServer {
+            result_code,
+            result_string: result_string.to_vec(),
+            requests: requests.clone(),
+        }));
+        return (requester, requests);
+    }
+
+    #[test]
+    fn create_kpasswd_requester() {
+        let requester = KpasswdRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        );
+
+        assert_eq!(&[KdcEndpoint::new("10.0.0.1", 464)], requester.servers());
+        assert_eq!(
+            Some(&changepw_principal_name()),
+            requester.tgt_requester().sname()
+        );
+        assert_eq!(0, requester.tgt_requester().kdc_options());
+        assert_eq!(
+            Some(Duration::minutes(5)),
+            requester.tgt_requester().ticket_lifetime()
+        );
+        assert_eq!(None, requester.tgt_requester().renew_lifetime());
+    }
+
+    #[test]
+    fn change_password_with_ticket() {
+        let (requester, requests) = new_requester(KRB5_KPASSWD_SUCCESS, b"");
+        let credential = create_changepw_credential();
+
+        requester
+            .change_password_with_ticket(&credential, "N3wP4ssw0rd")
+            .unwrap();
+
+        assert_eq!(
+            vec![(KPASSWD_VERSION_CHANGE, b"N3wP4ssw0rd".to_vec())],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn set_password_of_target() {
+        let (requester, requests) = new_requester(KRB5_KPASSWD_SUCCESS, b"");
+        let credential = create_changepw_credential();
+        let target =
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("goofy"));
+
+        requester
+            .set_password(
+                &credential,
+                &target,
+                &Realm::from("KINGDOM.HEARTS"),
+                "N3wP4ssw0rd",
+            )
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(KPASSWD_VERSION_SET, requests[0].0);
+        let (_, change_passwd_data) =
+            ChangePasswdData::parse(&requests[0].1).unwrap();
+        assert_eq!(Some(target), change_passwd_data.targname);
+    }
+
+    #[test]
+    fn error_when_password_is_rejected() {
+        let (requester, _) =
+            new_requester(KRB5_KPASSWD_SOFTERROR, b"Password too short");
+        let credential = create_changepw_credential();
+
+        assert_eq!(
+            Error::KpasswdError(
+                KpasswdResultCode::SoftError,
+                "Password too short".to_string()
+            ),
+            requester
+                .change_password_with_ticket(&credential, "short")
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn kpasswd_servers_from_krb5_config() {
+        let config = Krb5Config::parse(
+            "[libdefaults]
+    default_realm = KINGDOM.HEARTS
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kpasswd_server = kpasswd1.kingdom.hearts
+        kpasswd_server = kpasswd2.kingdom.hearts:1464
+        admin_server = admin.kingdom.hearts:749
+    }
+",
+        )
+        .unwrap();
+
+        let requester = KpasswdRequester::from_krb5_config(&config).unwrap();
+
+        assert_eq!(
+            &[
+                KdcEndpoint::new("kpasswd1.kingdom.hearts", 464),
+                KdcEndpoint::new("kpasswd2.kingdom.hearts", 1464),
+            ],
+            requester.servers()
+        );
+    }
+
+    #[test]
+    fn kpasswd_servers_from_admin_servers() {
+        let config = Krb5Config::parse(
+            "[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        admin_server = admin.kingdom.hearts:749
+    }
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![KdcEndpoint::new("admin.kingdom.hearts", 464)],
+            kpasswd_servers(
+                &config,
+                "KINGDOM.HEARTS",
+                &KdcLocator::from_krb5_config(&config).unwrap(),
+                TransportProtocol::UDP
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn kpasswd_servers_discovered_through_dns() {
+        let config = Krb5Config::parse("").unwrap();
+        let mut resolver = FakeDnsResolver::default();
+        resolver.srv_records.insert(
+            "_kpasswd._tcp.KINGDOM.HEARTS.".to_string(),
+            vec![SrvRecord::new(0, 0, 464, "kpasswd.kingdom.hearts.")],
+        );
+        resolver.ip_records.insert(
+            "kpasswd.kingdom.hearts".to_string(),
+            vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7))],
+        );
+        let mut kdc_locator = KdcLocator::new();
+        kdc_locator.set_resolver(Box::new(resolver));
+
+        assert_eq!(
+            vec![KdcEndpoint::new("10.0.0.7", 464)],
+            kpasswd_servers(
+                &config,
+                "KINGDOM.HEARTS",
+                &kdc_locator,
+                TransportProtocol::TCP
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn kpasswd_servers_in_kdc_hosts_without_dns_lookup() {
+        let config = Krb5Config::parse(
+            "[libdefaults]
+    dns_lookup_kdc = false
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts:750
+    }
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![KdcEndpoint::new("kdc1.kingdom.hearts", 464)],
+            kpasswd_servers(
+                &config,
+                "KINGDOM.HEARTS",
+                &KdcLocator::from_krb5_config(&config).unwrap(),
+                TransportProtocol::UDP
+            )
+            .unwrap()
+        );
+    }
+}
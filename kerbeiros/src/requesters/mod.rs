@@ -6,4 +6,27 @@ pub use as_requester::*;
 mod tgt_requester;
 pub use tgt_requester::*;
 
-pub use crate::transporter::TransportProtocol;
+mod tgs_requester;
+#[cfg(test)]
+pub(crate) use tgs_requester::test::create_tgt;
+pub use tgs_requester::*;
+
+mod kpasswd_requester;
+pub use kpasswd_requester::*;
+
+pub use crate::transporter::{TransportProtocol, TransportSettings};
+
+#[cfg(feature = "async")]
+mod async_as_requester;
+#[cfg(feature = "async")]
+pub use async_as_requester::*;
+
+#[cfg(feature = "async")]
+mod async_tgt_requester;
+#[cfg(feature = "async")]
+pub use async_tgt_requester::*;
+
+#[cfg(feature = "async")]
+mod async_tgs_requester;
+#[cfg(feature = "async")]
+pub use async_tgs_requester::*;
@@ -0,0 +1,1952 @@
+use crate::config::{Krb5Config, DEFAULT_CLOCKSKEW_SECONDS};
+use crate::credentials::*;
+use crate::discovery::{DnsResolver, KdcEndpoint, KdcLocator};
+use crate::error::*;
+use crate::messages::*;
+use crate::transporter::*;
+use ascii::AsciiString;
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{
+    Asn1Object, AuthorizationData, KerberosString, KrbError, PrincipalName,
+    TgsRep, Ticket,
+};
+use himmelblau_kerberos_constants::kdc_options::{
+    CONSTRAINED_DELEGATION, ENC_TKT_IN_SKEY, FORWARDABLE, FORWARDED, PROXIABLE,
+    RENEW, RENEWABLE, RENEWABLE_OK, VALIDATE,
+};
+use himmelblau_kerberos_constants::pa_pac_options::RESOURCE_BASED_CONSTRAINED_DELEGATION;
+use himmelblau_kerberos_constants::principal_names::{
+    NT_PRINCIPAL, NT_SRV_INST,
+};
+use himmelblau_kerberos_constants::ticket_flags;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+
+/// Gets service tickets from the KDC by sending TGS-REQ requests
+/// authenticated with a TGT
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use ascii::AsciiString;
+/// use std::net::*;
+/// use himmelblau_kerberos_crypto::Key;
+///
+/// // Prepare the arguments
+/// let realm = AsciiString::from_ascii("CONTOSO.COM").unwrap();
+/// let kdc_address = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+/// let username = AsciiString::from_ascii("Bob").unwrap();
+/// let user_key = Key::Secret("S3cr3t".to_string());
+///
+/// // Request the TGT
+/// let tgt_requester = TgtRequester::new(realm, kdc_address);
+/// let tgt = tgt_requester.request(&username, Some(&user_key)).unwrap();
+///
+/// // Request a ticket for the service
+/// let service = AsciiString::from_ascii("HTTP/web.contoso.com").unwrap();
+/// let tgs_requester = TgsRequester::new(kdc_address);
+/// let credential = tgs_requester.request(&tgt, &service).unwrap();
+///
+/// // Save the ticket into a Linux format file
+/// credential.save_into_ccache_file("bob_http.ccache").unwrap();
+/// ```
+///
+pub struct TgsRequester {
+    tgs_options: TgsReqOptions,
+    transporter: Box<dyn Transporter>,
+    kdcs: Vec<KdcEndpoint>,
+    transport_protocol: TransportProtocol,
+    transport_settings: TransportSettings,
+    realm_transporters: HashMap<String, Box<dyn Transporter>>,
+    kdc_locator: KdcLocator,
+    capaths: HashMap<(String, String), Vec<String>>,
+    max_referrals: usize,
+    clockskew: Duration,
+}
+
+impl TgsRequester {
+    pub fn new(kdc_address: IpAddr) -> Self {
+        return Self::with_kdcs(vec![KdcEndpoint::from(kdc_address)]);
+    }
+
+    /// Creates a requester which sends the requests to the given KDCs,
+    /// trying them in order until one of them responds.
+    pub fn with_kdcs(kdcs: Vec<KdcEndpoint>) -> Self {
+        let kdc_locator = KdcLocator::new();
+        return Self {
+            transporter: Box::new(FailoverTransporter::new(
+                kdcs.clone(),
+                kdc_locator.resolver(),
+                TransportProtocol::TCP,
+                TransportSettings::default(),
+            )),
+            kdcs,
+            tgs_options: TgsReqOptions::new(),
+            transport_protocol: TransportProtocol::TCP,
+            transport_settings: TransportSettings::default(),
+            realm_transporters: HashMap::new(),
+            kdc_locator,
+            capaths: HashMap::new(),
+            max_referrals: DEFAULT_MAX_REFERRALS,
+            clockskew: Duration::seconds(DEFAULT_CLOCKSKEW_SECONDS),
+        };
+    }
+
+    /// Creates a requester that sends the requests to the KDCs of the
+    /// default realm of the configuration, either configured or discovered
+    /// through DNS. The KDCs of other realms, capaths, etypes, lifetimes,
+    /// clockskew and UDP preference limit are also taken from the
+    /// configuration.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        let realm = config.default_realm().ok_or_else(|| {
+            Error::ConfigError("default_realm is not defined".to_string())
+        })?;
+        let kdc_locator = KdcLocator::from_krb5_config(config)?;
+        let transport_protocol = match config.kdc_proxy_url(realm) {
+            Some(_) => TransportProtocol::KdcProxy,
+            None => TransportProtocol::UDP,
+        };
+        let kdcs = kdc_locator.kdc_endpoints(realm, transport_protocol)?;
+
+        let mut requester = Self::with_kdcs(kdcs);
+        requester.kdc_locator = kdc_locator;
+        requester.transport_settings.udp_preference_limit =
+            config.udp_preference_limit()?;
+        if let Some(kdc_proxy_url) = config.kdc_proxy_url(realm) {
+            let mut transport_settings = requester.transport_settings().clone();
+            transport_settings.kdc_proxy_url = Some(kdc_proxy_url.to_string());
+            requester.set_transport_settings(transport_settings);
+        }
+        requester.set_transport_protocol(transport_protocol);
+
+        if let Some(etypes) = config.supported_tgs_etypes() {
+            if etypes.is_empty() {
+                return Err(Error::NoProvidedSupportedCipherAlgorithm);
+            }
+            requester.set_etypes(etypes)?;
+        }
+
+        requester.set_ticket_lifetime(config.ticket_lifetime()?);
+        if let Some(renew_lifetime) = config.renew_lifetime()? {
+            if renew_lifetime.is_zero() {
+                requester.set_kdc_options(
+                    requester.kdc_options() & !(RENEWABLE | RENEWABLE_OK),
+                );
+            } else {
+                requester.set_renew_lifetime(Some(renew_lifetime));
+            }
+        }
+        requester.set_clockskew(config.clockskew()?);
+
+        for (client_realm, service_realm, path) in config.capaths() {
+            requester.set_capath(&client_realm, &service_realm, path);
+        }
+
+        return Ok(requester);
+    }
+
+    /// Requests a ticket for the service, in the form `service/host`.
+    pub fn request(
+        &self,
+        tgt: &Credential,
+        service: &AsciiString,
+    ) -> Result<Credential> {
+        return self
+            .request_principal(tgt, &service_to_principal_name(service));
+    }
+
+    /// Requests a ticket for the specified principal name. In case the
+    /// service belongs to another realm, the referral TGTs returned by the
+    /// KDCs are followed until the service ticket is retrieved (RFC 6806).
+    pub fn request_principal(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+    ) -> Result<Credential> {
+        return self.request_following_referrals(
+            tgt,
+            sname,
+            &tgs_realm(tgt).to_uppercase(),
+        );
+    }
+
+    /// Requests a ticket for the specified principal name of the given
+    /// realm. The cross-realm TGTs are requested through the intermediate
+    /// realms configured with [set_capath](Self::set_capath), or directly
+    /// to the service realm otherwise.
+    pub fn request_principal_in_realm(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+        realm: &str,
+    ) -> Result<Credential> {
+        let home_realm = tgs_realm(tgt).to_uppercase();
+        let service_realm = realm.to_uppercase();
+
+        let mut path = self
+            .capaths
+            .get(&(home_realm.clone(), service_realm.clone()))
+            .cloned()
+            .unwrap_or_default();
+        path.push(service_realm);
+
+        let mut current_tgt = tgt.clone();
+        for hop_realm in path.iter() {
+            if tgs_realm(&current_tgt).to_uppercase() == *hop_realm {
+                continue;
+            }
+
+            current_tgt = self.request_following_referrals(
+                &current_tgt,
+                &krbtgt_principal_name(hop_realm),
+                &home_realm,
+            )?;
+        }
+
+        return self.request_following_referrals(
+            &current_tgt,
+            sname,
+            &home_realm,
+        );
+    }
+
+    fn request_following_referrals(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+        home_realm: &str,
+    ) -> Result<Credential> {
+        let mut visited_realms = vec![tgs_realm(tgt).to_uppercase()];
+        let mut credential = self.request_in_realm(tgt, sname, home_realm)?;
+
+        while let Some(next_realm) = referral_realm(&credential, sname) {
+            visit_referral_realm(
+                &mut visited_realms,
+                next_realm,
+                self.max_referrals,
+            )?;
+            credential =
+                self.request_in_realm(&credential, sname, home_realm)?;
+        }
+
+        return Ok(credential);
+    }
+
+    /// Sends the TGS-REQ to the KDC of the realm that issued the TGT.
+    fn request_in_realm(
+        &self,
+        tgt: &Credential,
+        sname: &PrincipalName,
+        home_realm: &str,
+    ) -> Result<Credential> {
+        let builder = TgsReqBuilder::new(tgt, sname, &self.tgs_options)?;
+        let realm = tgs_realm(tgt).to_uppercase();
+
+        if realm == home_realm {
+            return TgsRequest::request(&builder, &self.transporter);
+        }
+
+        if let Some(transporter) = self.realm_transporters.get(&realm) {
+            return TgsRequest::request(&builder, transporter);
+        }
+
+        let kdcs = self.realm_kdcs(&realm)?;
+        return TgsRequest::request(&builder, &self.new_kdc_transporter(kdcs));
+    }
+
+    /// Retrieves the KDCs of a foreign realm, configured or discovered
+    /// through DNS.
+    pub(crate) fn realm_kdcs(&self, realm: &str) -> Result<Vec<KdcEndpoint>> {
+        return self
+            .kdc_locator
+            .kdc_endpoints(realm, self.transport_protocol);
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn kdc_locator(&self) -> &KdcLocator {
+        return &self.kdc_locator;
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn tgs_options(&self) -> &TgsReqOptions {
+        return &self.tgs_options;
+    }
+
+    fn new_kdc_transporter(
+        &self,
+        kdcs: Vec<KdcEndpoint>,
+    ) -> Box<dyn Transporter> {
+        return new_kdcs_transporter(
+            kdcs,
+            self.kdc_locator.resolver(),
+            self.transport_protocol,
+            self.transport_settings.clone(),
+        );
+    }
+
+    /// Requests a ticket to itself on behalf of the user (S4U2Self), by
+    /// using the TGT of the service. The returned ticket can be used for
+    /// protocol transition, as well as evidence ticket for S4U2Proxy.
+    pub fn request_s4u2self(
+        &self,
+        tgt: &Credential,
+        s4u_user: &S4uUser,
+    ) -> Result<Credential> {
+        let mut builder =
+            TgsReqBuilder::new(tgt, tgt.cname(), &self.tgs_options)?;
+        builder.set_s4u_user(s4u_user);
+        return TgsRequest::request_s4u2self(
+            &builder,
+            s4u_user,
+            &self.transporter,
+        );
+    }
+
+    /// Requests a ticket for the service on behalf of the client of the
+    /// evidence ticket (S4U2Proxy), which is usually retrieved with
+    /// [request_s4u2self](Self::request_s4u2self). The returned credential
+    /// belongs to the impersonated user.
+    pub fn request_s4u2proxy(
+        &self,
+        tgt: &Credential,
+        evidence_ticket: &Credential,
+        sname: &PrincipalName,
+    ) -> Result<Credential> {
+        let mut builder = TgsReqBuilder::new(tgt, sname, &self.tgs_options)?;
+        builder.set_additional_ticket(evidence_ticket.ticket());
+        builder.add_kdc_options(CONSTRAINED_DELEGATION);
+
+        if self.tgs_options.is_rbcd_requested() {
+            builder.set_pac_options(RESOURCE_BASED_CONSTRAINED_DELEGATION);
+        }
+
+        return TgsRequest::request(&builder, &self.transporter);
+    }
+
+    /// Requests a ticket for the peer principal that is encrypted with the
+    /// session key of its TGT instead of a long-term key (user-to-user).
+    pub fn request_u2u(
+        &self,
+        tgt: &Credential,
+        peer_tgt: &Ticket,
+        sname: &PrincipalName,
+    ) -> Result<Credential> {
+        let mut builder = TgsReqBuilder::new(tgt, sname, &self.tgs_options)?;
+        builder.set_additional_ticket(peer_tgt);
+        builder.add_kdc_options(ENC_TKT_IN_SKEY);
+
+        let mut credential = TgsRequest::request(&builder, &self.transporter)?;
+        credential.set_second_ticket(Some(peer_tgt.clone()));
+        return Ok(credential);
+    }
+
+    /// Renews a renewable ticket, TGT or service ticket, by presenting it
+    /// to the KDC with the RENEW option. The ticket must be renewed before
+    /// its end and renew-till times, allowing for the clock skew.
+    pub fn renew(&self, credential: &Credential) -> Result<Credential> {
+        return TgsRequest::request(
+            &self.renew_builder(credential)?,
+            &self.transporter,
+        );
+    }
+
+    pub(crate) fn renew_builder<'a>(
+        &'a self,
+        credential: &'a Credential,
+    ) -> Result<TgsReqBuilder<'a>> {
+        if credential.flags().flags & ticket_flags::RENEWABLE == 0 {
+            return Err(Error::TicketNotRenewable);
+        }
+
+        match credential.renew_till() {
+            Some(renew_till)
+                if renew_till.time.time + self.clockskew > Utc::now() => {}
+            _ => {
+                return Err(Error::TicketRenewTillExpired);
+            }
+        }
+
+        // The KDC does not renew expired tickets (RFC 4120 section 3.3.3.1)
+        if credential.endtime().time.time + self.clockskew < Utc::now() {
+            return Err(Error::TicketExpired);
+        }
+
+        let mut builder = TgsReqBuilder::new(
+            credential,
+            credential.sname(),
+            &self.tgs_options,
+        )?;
+        builder.set_kdc_options(
+            RENEW
+                | (credential.flags().flags
+                    & (FORWARDABLE | PROXIABLE | RENEWABLE)),
+        );
+
+        return Ok(builder);
+    }
+
+    /// Requests a forwarded TGT, which can be delegated to a service in
+    /// order to allow it to act on behalf of the client.
+    pub fn request_forwarded_tgt(
+        &self,
+        tgt: &Credential,
+    ) -> Result<Credential> {
+        if tgt.flags().flags & ticket_flags::FORWARDABLE == 0 {
+            return Err(Error::TicketNotForwardable);
+        }
+
+        let mut builder =
+            TgsReqBuilder::new(tgt, tgt.sname(), &self.tgs_options)?;
+        builder.add_kdc_options(FORWARDED);
+
+        return TgsRequest::request(&builder, &self.transporter);
+    }
+
+    /// Requests a forwarded TGT to be delegated to the service of the given
+    /// ticket. Unless disabled with
+    /// [set_ok_as_delegate_honoured](Self::set_ok_as_delegate_honoured),
+    /// the delegation is only allowed if the service ticket has the
+    /// ok-as-delegate flag, which indicates that the realm policy trusts
+    /// the service for delegation.
+    pub fn request_delegated_tgt(
+        &self,
+        tgt: &Credential,
+        service_credential: &Credential,
+    ) -> Result<Credential> {
+        if self.tgs_options.is_ok_as_delegate_honoured()
+            && service_credential.flags().flags & ticket_flags::OK_AS_DELEGATE
+                == 0
+        {
+            return Err(Error::DelegationNotAllowed(
+                service_credential.sname().to_string(),
+            ));
+        }
+
+        return self.request_forwarded_tgt(tgt);
+    }
+
+    /// Validates a postdated ticket, which is issued as invalid, by
+    /// presenting it to the KDC with the VALIDATE option once its starttime
+    /// has been reached, allowing for the clock skew.
+    pub fn validate(&self, credential: &Credential) -> Result<Credential> {
+        return TgsRequest::request(
+            &self.validate_builder(credential)?,
+            &self.transporter,
+        );
+    }
+
+    pub(crate) fn validate_builder<'a>(
+        &'a self,
+        credential: &'a Credential,
+    ) -> Result<TgsReqBuilder<'a>> {
+        if credential.flags().flags & ticket_flags::INVALID == 0 {
+            return Err(Error::TicketAlreadyValid);
+        }
+
+        if let Some(starttime) = credential.starttime() {
+            if starttime.time.time > Utc::now() + self.clockskew {
+                return Err(Error::TicketNotYetValid);
+            }
+        }
+
+        let mut builder = TgsReqBuilder::new(
+            credential,
+            credential.sname(),
+            &self.tgs_options,
+        )?;
+        builder.set_kdc_options(VALIDATE);
+
+        return Ok(builder);
+    }
+
+    pub fn etypes(&self) -> &HashSet<i32> {
+        return self.tgs_options.etypes();
+    }
+
+    pub fn set_etype(&mut self, etype: i32) -> Result<()> {
+        return self.tgs_options.set_etype(etype);
+    }
+
+    pub fn set_etypes(&mut self, etypes: HashSet<i32>) -> Result<()> {
+        return self.tgs_options.set_etypes(etypes);
+    }
+
+    pub fn kdc_options(&self) -> u32 {
+        return self.tgs_options.kdc_options();
+    }
+
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.tgs_options.set_kdc_options(kdc_options);
+    }
+
+    pub fn starttime(&self) -> Option<&DateTime<Utc>> {
+        return self.tgs_options.starttime();
+    }
+
+    /// Sets the time from which the requested tickets should be valid,
+    /// requesting postdated tickets. The TGT must have the may-postdate
+    /// flag, requested with the ALLOW_POSTDATE option.
+    pub fn set_starttime(&mut self, starttime: Option<DateTime<Utc>>) {
+        self.tgs_options.set_starttime(starttime);
+    }
+
+    pub fn authorization_data(&self) -> Option<&AuthorizationData> {
+        return self.tgs_options.authorization_data();
+    }
+
+    /// Sets the authorization data to be sent in the
+    /// enc-authorization-data field of the request.
+    pub fn set_authorization_data(
+        &mut self,
+        authorization_data: Option<AuthorizationData>,
+    ) {
+        self.tgs_options.set_authorization_data(authorization_data);
+    }
+
+    /// Indicates whether S4U2Proxy requests ask for resource-based
+    /// constrained delegation, by including PA-PAC-OPTIONS.
+    pub fn set_rbcd(&mut self, rbcd: bool) {
+        self.tgs_options.set_rbcd(rbcd);
+    }
+
+    /// Indicates whether the KDC must echo the PA-S4U-X509-USER in the
+    /// S4U2Self replies of users specified by name. Disabled by default,
+    /// since the KDCs previous to Windows 2008 only understand PA-FOR-USER,
+    /// but then the user of the ticket is not verified.
+    pub fn set_s4u_reply_required(&mut self, s4u_reply_required: bool) {
+        self.tgs_options.set_s4u_reply_required(s4u_reply_required);
+    }
+
+    /// Indicates whether the ok-as-delegate flag of service tickets is
+    /// required to delegate credentials to them. Enabled by default.
+    pub fn set_ok_as_delegate_honoured(&mut self, ok_as_delegate: bool) {
+        self.tgs_options.set_ok_as_delegate_honoured(ok_as_delegate);
+    }
+
+    /// Indicates whether a random subkey is included in the authenticator,
+    /// in which case the KDC uses it to encrypt the response.
+    pub fn set_subkey(&mut self, subkey: bool) {
+        self.tgs_options.set_subkey(subkey);
+    }
+
+    pub fn transport_protocol(&self) -> TransportProtocol {
+        return self.transport_protocol;
+    }
+
+    pub fn set_transport_protocol(
+        &mut self,
+        transport_protocol: TransportProtocol,
+    ) {
+        self.transport_protocol = transport_protocol;
+        self.reset_transporter();
+    }
+
+    /// Sets the maximum size of the messages sent over UDP, since bigger
+    /// messages are sent over TCP.
+    pub fn set_udp_preference_limit(&mut self, udp_preference_limit: usize) {
+        self.transport_settings.udp_preference_limit = udp_preference_limit;
+        self.reset_transporter();
+    }
+
+    pub fn transport_settings(&self) -> &TransportSettings {
+        return &self.transport_settings;
+    }
+
+    /// Sets the timeouts, retries, UDP preference limit and KDC proxy used
+    /// to communicate with the KDCs.
+    pub fn set_transport_settings(
+        &mut self,
+        transport_settings: TransportSettings,
+    ) {
+        self.transport_settings = transport_settings;
+        self.reset_transporter();
+    }
+
+    pub fn kdcs(&self) -> &[KdcEndpoint] {
+        return &self.kdcs;
+    }
+
+    /// Sets the KDCs of the realm of the TGT, which are tried in order
+    /// until one of them responds.
+    pub fn set_kdcs(&mut self, kdcs: Vec<KdcEndpoint>) {
+        self.kdcs = kdcs;
+        self.reset_transporter();
+    }
+
+    /// Returns the address of the last KDC of the realm of the TGT that
+    /// responded.
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.transporter.last_kdc_address();
+    }
+
+    fn reset_transporter(&mut self) {
+        self.transporter = self.new_kdc_transporter(self.kdcs.clone());
+    }
+
+    /// Sets the address of the KDC of a foreign realm, used to follow
+    /// referrals. Otherwise, the address is located from the configured
+    /// KDCs or the realm name.
+    pub fn set_realm_kdc(&mut self, realm: &str, kdc_address: IpAddr) {
+        self.set_realm_kdcs(realm, vec![KdcEndpoint::from(kdc_address)]);
+    }
+
+    /// Sets the KDCs of a foreign realm, used to follow referrals.
+    pub fn set_realm_kdcs(&mut self, realm: &str, kdcs: Vec<KdcEndpoint>) {
+        self.kdc_locator.set_realm_kdcs(realm, kdcs);
+    }
+
+    /// Sets the intermediate realms to traverse from the client realm to
+    /// the service realm, like the capaths section of krb5.conf.
+    pub fn set_capath(
+        &mut self,
+        client_realm: &str,
+        service_realm: &str,
+        intermediate_realms: Vec<String>,
+    ) {
+        self.capaths.insert(
+            (client_realm.to_uppercase(), service_realm.to_uppercase()),
+            intermediate_realms
+                .iter()
+                .map(|realm| realm.to_uppercase())
+                .collect(),
+        );
+    }
+
+    /// Sets the resolver used to discover the KDCs and resolve their names.
+    pub fn set_resolver(&mut self, resolver: Box<dyn DnsResolver>) {
+        self.kdc_locator.set_resolver(resolver);
+        self.reset_transporter();
+    }
+
+    /// Sets whether the KDCs of other realms are discovered through DNS,
+    /// when they are not configured.
+    pub fn set_dns_lookup_kdc(&mut self, dns_lookup_kdc: bool) {
+        self.kdc_locator.set_dns_lookup_kdc(dns_lookup_kdc);
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.tgs_options.ticket_lifetime();
+    }
+
+    /// Sets the lifetime requested for the tickets. By default, the
+    /// maximum lifetime allowed by the KDC is requested.
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.tgs_options.set_ticket_lifetime(ticket_lifetime);
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.tgs_options.renew_lifetime();
+    }
+
+    /// Sets the renewable lifetime requested for the tickets. By default,
+    /// the maximum renewable lifetime allowed by the KDC is requested.
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.tgs_options.set_renew_lifetime(renew_lifetime);
+    }
+
+    pub fn clockskew(&self) -> Duration {
+        return self.clockskew;
+    }
+
+    /// Sets the maximum clock skew with the KDC, tolerated when checking
+    /// the ticket times before renewal and validation.
+    pub fn set_clockskew(&mut self, clockskew: Duration) {
+        self.clockskew = clockskew;
+    }
+
+    pub fn max_referrals(&self) -> usize {
+        return self.max_referrals;
+    }
+
+    /// Sets the maximum number of referrals followed in a request.
+    pub fn set_max_referrals(&mut self, max_referrals: usize) {
+        self.max_referrals = max_referrals;
+    }
+
+    #[cfg(test)]
+    pub fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
+        self.transporter = transporter;
+    }
+
+    #[cfg(test)]
+    pub fn set_realm_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn Transporter>,
+    ) {
+        self.realm_transporters
+            .insert(realm.to_uppercase(), transporter);
+    }
+}
+
+/// Default maximum number of referrals followed in a request, as in MIT.
+pub(crate) const DEFAULT_MAX_REFERRALS: usize = 10;
+
+fn krbtgt_principal_name(realm: &str) -> PrincipalName {
+    let mut sname =
+        PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
+    sname.push(KerberosString::from(realm));
+    return sname;
+}
+
+/// Adds the realm of a referral to the visited realms, checking that it was
+/// not visited before and that the maximum of referrals is not exceeded.
+pub(crate) fn visit_referral_realm(
+    visited_realms: &mut Vec<String>,
+    next_realm: String,
+    max_referrals: usize,
+) -> Result<()> {
+    if visited_realms.contains(&next_realm) {
+        return Err(Error::ReferralLoop(next_realm));
+    }
+
+    // The first visited realm is the one of the initial TGT
+    let followed_referrals = visited_realms.len().saturating_sub(1);
+    if followed_referrals >= max_referrals {
+        return Err(Error::TooManyReferrals(max_referrals));
+    }
+
+    visited_realms.push(next_realm);
+    return Ok(());
+}
+
+/// Returns the realm of the next KDC in case the credential is a referral
+/// TGT (krbtgt/NEXT@CURRENT) instead of a ticket for the requested service.
+pub(crate) fn referral_realm(
+    credential: &Credential,
+    requested_sname: &PrincipalName,
+) -> Option<String> {
+    let sname = credential.sname();
+    if sname.name_string.len() != 2
+        || sname.name_string[0].to_lowercase() != "krbtgt"
+    {
+        return None;
+    }
+
+    let next_realm = sname.name_string[1].to_uppercase();
+    if next_realm == credential.srealm().to_uppercase() {
+        return None;
+    }
+
+    let requested = &requested_sname.name_string;
+    if requested.len() == 2
+        && requested[0].to_lowercase() == "krbtgt"
+        && requested[1].to_uppercase() == next_realm
+    {
+        return None;
+    }
+
+    return Some(next_realm);
+}
+
+pub(crate) fn service_to_principal_name(
+    service: &AsciiString,
+) -> PrincipalName {
+    let mut components = service.as_str().split('/');
+    let name_type = if service.as_str().contains('/') {
+        NT_SRV_INST
+    } else {
+        NT_PRINCIPAL
+    };
+
+    let mut sname = PrincipalName::new(
+        name_type,
+        KerberosString::from(components.next().unwrap_or_default()),
+    );
+    for component in components {
+        sname.push(KerberosString::from(component));
+    }
+
+    return sname;
+}
+
+/// Encapsule the possible responses to a TGS-REQ request
+enum TgsReqResponse {
+    KrbError(KrbError),
+    TgsRep(TgsRep),
+}
+
+pub(crate) struct TgsRequest {}
+
+impl TgsRequest {
+    #[allow(clippy::borrowed_box)]
+    pub fn request(
+        builder: &TgsReqBuilder,
+        transporter: &Box<dyn Transporter>,
+    ) -> Result<Credential> {
+        let tgs_rep = Self::request_tgs_rep(builder, transporter)?;
+        return Self::extract_credential_from_tgs_rep(builder, tgs_rep);
+    }
+
+    #[allow(clippy::borrowed_box)]
+    pub fn request_s4u2self(
+        builder: &TgsReqBuilder,
+        s4u_user: &S4uUser,
+        transporter: &Box<dyn Transporter>,
+    ) -> Result<Credential> {
+        let tgs_rep = Self::request_tgs_rep(builder, transporter)?;
+        let credential =
+            Self::extract_credential_from_tgs_rep(builder, tgs_rep.clone())?;
+
+        let padata = match (&tgs_rep.padata, credential.encrypted_pa_data()) {
+            (Some(padata), _) => padata,
+            (None, Some(encrypted_pa_data)) => encrypted_pa_data,
+            (None, None) => &Vec::new(),
+        };
+
+        if let Err(error) = s4u_user.verify_reply(
+            builder.nonce(),
+            padata,
+            builder.reply_key().0,
+            builder.options().is_s4u_reply_required(),
+        ) {
+            return Err(Error::ParseTgsRepError(tgs_rep, Box::new(error)));
+        }
+
+        return Ok(credential);
+    }
+
+    #[allow(clippy::borrowed_box)]
+    fn request_tgs_rep(
+        builder: &TgsReqBuilder,
+        transporter: &Box<dyn Transporter>,
+    ) -> Result<TgsRep> {
+        let raw_tgs_req = builder.build_tgs_req()?.build();
+        let raw_response = transporter.request_and_response(&raw_tgs_req)?;
+        return Self::parse_tgs_rep(&raw_response);
+    }
+
+    /// Parses the response of the KDC, which is an error in case of
+    /// KRB-ERROR.
+    pub(crate) fn parse_tgs_rep(raw_response: &[u8]) -> Result<TgsRep> {
+        match Self::parse_tgs_request_response(raw_response)? {
+            TgsReqResponse::KrbError(krb_error) => {
+                return Err(Error::KrbErrorResponse(krb_error));
+            }
+            TgsReqResponse::TgsRep(tgs_rep) => {
+                return Ok(tgs_rep);
+            }
+        }
+    }
+
+    fn parse_tgs_request_response(
+        raw_response: &[u8],
+    ) -> Result<TgsReqResponse> {
+        match KrbError::parse(raw_response) {
+            Ok((_, krb_error)) => {
+                return Ok(TgsReqResponse::KrbError(krb_error));
+            }
+            Err(_) => {
+                let tgs_rep = TgsRep::parse(raw_response)?.1;
+                return Ok(TgsReqResponse::TgsRep(tgs_rep));
+            }
+        }
+    }
+
+    pub(crate) fn extract_credential_from_tgs_rep(
+        builder: &TgsReqBuilder,
+        tgs_rep: TgsRep,
+    ) -> Result<Credential> {
+        let (key, key_usage) = builder.reply_key();
+
+        match CredentialKrbInfoMapper::tgs_rep_to_credential(
+            key,
+            key_usage,
+            builder.nonce(),
+            tgs_rep.clone(),
+        ) {
+            Ok(credential) => {
+                return Ok(credential);
+            }
+            Err(error) => {
+                return Err(Error::ParseTgsRepError(tgs_rep, Box::new(error)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use chrono::Duration;
+    use himmelblau_kerberos_asn1::{
+        ApReq, Authenticator, Checksum, EncAsRepPart, EncTgsRepPart,
+        EncryptedData, EncryptionKey, PaData, PaPacOptions, PaS4uX509User,
+        Realm, TgsReq, Ticket,
+    };
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_S_PRINCIPAL_UNKNOWN;
+    use himmelblau_kerberos_constants::etypes::*;
+    use himmelblau_kerberos_constants::key_usages::{
+        KEY_USAGE_PA_S4U_X509_USER_REPLY,
+        KEY_USAGE_TGS_REP_ENC_PART_AUTHEN_SUBKEY,
+        KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY, KEY_USAGE_TGS_REQ_AUTHEN,
+    };
+    use himmelblau_kerberos_constants::pa_data_types::{
+        PA_PAC_OPTIONS, PA_S4U_X509_USER,
+    };
+    use himmelblau_kerberos_crypto::{keyed_checksum, new_kerberos_cipher};
+    use std::net::Ipv4Addr;
+
+    pub fn create_tgt(session_key: EncryptionKey) -> Credential {
+        return create_tgt_with_flags(session_key, 0, None);
+    }
+
+    pub fn create_tgt_with_flags(
+        session_key: EncryptionKey,
+        flags: u32,
+        renew_till: Option<DateTime<Utc>>,
+    ) -> Credential {
+        return create_tgt_with_times(
+            session_key,
+            flags,
+            Utc::now(),
+            renew_till,
+        );
+    }
+
+    pub fn create_tgt_with_times(
+        session_key: EncryptionKey,
+        flags: u32,
+        endtime: DateTime<Utc>,
+        renew_till: Option<DateTime<Utc>>,
+    ) -> Credential {
+        let realm = Realm::from("KINGDOM.HEARTS");
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
+        sname.push(realm.clone());
+
+        let enc_part = EncAsRepPart {
+            key: session_key,
+            flags: flags.into(),
+            authtime: Utc::now().into(),
+            endtime: endtime.into(),
+            renew_till: renew_till.map(|renew_till| renew_till.into()),
+            srealm: realm.clone(),
+            sname: sname.clone(),
+            ..Default::default()
+        };
+
+        return Credential::new(
+            realm.clone(),
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
+            Ticket::new(realm, sname, EncryptedData::default()),
+            enc_part,
+        );
+    }
+
+    /// Decrypts the authenticator of a TGS-REQ
+    pub fn decrypt_tgs_req_authenticator(
+        session_key: &EncryptionKey,
+        tgs_req: &TgsReq,
+    ) -> Authenticator {
+        let padata = &tgs_req.padata.as_ref().unwrap()[0];
+        let ap_req = ApReq::parse(&padata.padata_value).unwrap().1;
+        let raw_authenticator = new_kerberos_cipher(session_key.keytype)
+            .unwrap()
+            .decrypt(
+                &session_key.keyvalue,
+                KEY_USAGE_TGS_REQ_AUTHEN,
+                &ap_req.authenticator.cipher,
+            )
+            .unwrap();
+        return Authenticator::parse(&raw_authenticator).unwrap().1;
+    }
+
+    /// Builds the TGS-REP that a KDC would return for the given TGS-REQ
+    pub fn produce_tgs_rep(
+        session_key: &EncryptionKey,
+        raw_tgs_req: &[u8],
+        nonce_delta: u32,
+    ) -> Vec<u8> {
+        return produce_tgs_rep_for_sname(
+            session_key,
+            raw_tgs_req,
+            nonce_delta,
+            None,
+        );
+    }
+
+    /// Produces a TGS-REP for the given sname, or for the requested one
+    /// if no sname is provided.
+    pub fn produce_tgs_rep_for_sname(
+        session_key: &EncryptionKey,
+        raw_tgs_req: &[u8],
+        nonce_delta: u32,
+        sname: Option<PrincipalName>,
+    ) -> Vec<u8> {
+        let tgs_req = TgsReq::parse(raw_tgs_req).unwrap().1;
+        let authenticator =
+            decrypt_tgs_req_authenticator(session_key, &tgs_req);
+
+        let (reply_key, key_usage) = match &authenticator.subkey {
+            Some(subkey) => {
+                (subkey.clone(), KEY_USAGE_TGS_REP_ENC_PART_AUTHEN_SUBKEY)
+            }
+            None => {
+                (session_key.clone(), KEY_USAGE_TGS_REP_ENC_PART_SESSION_KEY)
+            }
+        };
+
+        let sname =
+            sname.unwrap_or_else(|| tgs_req.req_body.sname.clone().unwrap());
+        let enc_tgs_rep_part = EncTgsRepPart {
+            key: EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]),
+            nonce: tgs_req.req_body.nonce.wrapping_add(nonce_delta),
+            authtime: Utc::now().into(),
+            endtime: Utc::now().into(),
+            srealm: tgs_req.req_body.realm.clone(),
+            sname: sname.clone(),
+            ..Default::default()
+        };
+
+        let cipher = new_kerberos_cipher(reply_key.keytype).unwrap();
+        let mut tgs_rep = TgsRep {
+            crealm: authenticator.crealm,
+            cname: authenticator.cname,
+            ticket: Ticket::new(
+                tgs_req.req_body.realm.clone(),
+                sname,
+                EncryptedData::default(),
+            ),
+            enc_part: EncryptedData::new(
+                reply_key.keytype,
+                None,
+                cipher.encrypt(
+                    &reply_key.keyvalue,
+                    key_usage,
+                    &enc_tgs_rep_part.build(),
+                ),
+            ),
+            ..Default::default()
+        };
+
+        let s4u_padata = tgs_req
+            .padata
+            .iter()
+            .flatten()
+            .find(|padata| padata.padata_type == PA_S4U_X509_USER);
+        if let Some(padata) = s4u_padata {
+            let mut pa_s4u_x509_user =
+                PaS4uX509User::parse(&padata.padata_value).unwrap().1;
+            let user_id = &pa_s4u_x509_user.user_id;
+            pa_s4u_x509_user.checksum = Checksum {
+                cksumtype: pa_s4u_x509_user.checksum.cksumtype,
+                checksum: keyed_checksum(
+                    pa_s4u_x509_user.checksum.cksumtype,
+                    &reply_key.keyvalue,
+                    KEY_USAGE_PA_S4U_X509_USER_REPLY,
+                    &user_id.build(),
+                )
+                .unwrap(),
+            };
+
+            if let Some(cname) = &user_id.cname {
+                tgs_rep.cname = cname.clone();
+            }
+            tgs_rep.crealm = user_id.crealm.clone();
+            tgs_rep.padata = Some(vec![PaData::new(
+                PA_S4U_X509_USER,
+                pa_s4u_x509_user.build(),
+            )]);
+        }
+
+        return tgs_rep.build();
+    }
+
+    pub struct FakeKdc {
+        pub session_key: EncryptionKey,
+        pub nonce_delta: u32,
+    }
+
+    impl Transporter for FakeKdc {
+        fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+            return Ok(produce_tgs_rep(
+                &self.session_key,
+                raw_request,
+                self.nonce_delta,
+            ));
+        }
+    }
+
+    /// KDC that refers the requests of services to another realm.
+    pub struct ReferralKdc {
+        pub session_key: EncryptionKey,
+        pub referral: Option<&'static str>,
+    }
+
+    impl Transporter for ReferralKdc {
+        fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+            let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+            let requested_sname = tgs_req.req_body.sname.unwrap();
+
+            let sname = match self.referral {
+                Some(next_realm)
+                    if requested_sname.name_string[0] != "krbtgt" =>
+                {
+                    Some(krbtgt_principal_name(next_realm))
+                }
+                _ => None,
+            };
+
+            return Ok(produce_tgs_rep_for_sname(
+                &self.session_key,
+                raw_request,
+                0,
+                sname,
+            ));
+        }
+    }
+
+    fn new_referral_tgs_requester(
+        session_key: &EncryptionKey,
+        referrals: &[(&str, Option<&'static str>)],
+    ) -> TgsRequester {
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        let issued_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]);
+
+        for (realm, referral) in referrals.iter() {
+            let kdc = Box::new(ReferralKdc {
+                session_key: if *realm == "KINGDOM.HEARTS" {
+                    session_key.clone()
+                } else {
+                    issued_key.clone()
+                },
+                referral: *referral,
+            });
+
+            if *realm == "KINGDOM.HEARTS" {
+                tgs_requester.set_transporter(kdc);
+            } else {
+                tgs_requester.set_realm_transporter(realm, kdc);
+            }
+        }
+
+        return tgs_requester;
+    }
+
+    fn new_tgs_requester(
+        session_key: &EncryptionKey,
+        nonce_delta: u32,
+    ) -> TgsRequester {
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester.set_transporter(Box::new(FakeKdc {
+            session_key: session_key.clone(),
+            nonce_delta,
+        }));
+        return tgs_requester;
+    }
+
+    #[test]
+    fn request_service_ticket() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let tgs_requester = new_tgs_requester(&session_key, 0);
+
+        let credential = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.kingdom.hearts").unwrap(),
+            )
+            .unwrap();
+
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("HTTP"));
+        sname.push(KerberosString::from("web.kingdom.hearts"));
+
+        assert_eq!(&sname, credential.sname());
+        assert_eq!(tgt.cname(), credential.cname());
+        assert_eq!(&Realm::from("KINGDOM.HEARTS"), credential.srealm());
+        assert_eq!(
+            &EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]),
+            credential.key()
+        );
+    }
+
+    #[test]
+    fn request_service_ticket_with_subkey() {
+        let session_key = EncryptionKey::new(RC4_HMAC, vec![1; 16]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_tgs_requester(&session_key, 0);
+        tgs_requester.set_subkey(true);
+
+        tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("cifs/fs01.kingdom.hearts").unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[should_panic(expected = "NonceMismatch")]
+    #[test]
+    fn request_service_ticket_with_invalid_nonce() {
+        let session_key =
+            EncryptionKey::new(AES128_CTS_HMAC_SHA1_96, vec![1; 16]);
+        let tgt = create_tgt(session_key.clone());
+        let tgs_requester = new_tgs_requester(&session_key, 1);
+
+        tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.kingdom.hearts").unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[should_panic(expected = "KrbErrorResponse")]
+    #[test]
+    fn request_service_ticket_receiving_krb_error() {
+        struct FakeTransporter {}
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                _raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let krb_error = KrbError {
+                    pvno: 5,
+                    msg_type: 30,
+                    error_code: KDC_ERR_S_PRINCIPAL_UNKNOWN,
+                    ..Default::default()
+                };
+                return Ok(krb_error.build());
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key);
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester.set_transporter(Box::new(FakeTransporter {}));
+
+        tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/unknown.kingdom.hearts")
+                    .unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn request_s4u2self_ticket() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let tgs_requester = new_tgs_requester(&session_key, 0);
+        let user = PrincipalName::new(
+            NT_PRINCIPAL,
+            KerberosString::from("administrador"),
+        );
+
+        let credential = tgs_requester
+            .request_s4u2self(
+                &tgt,
+                &S4uUser::Name(user.clone(), Realm::from("KINGDOM.HEARTS")),
+            )
+            .unwrap();
+
+        assert_eq!(&user, credential.cname());
+        assert_eq!(tgt.cname(), credential.sname());
+    }
+
+    #[test]
+    fn request_s4u2self_ticket_by_certificate_with_subkey() {
+        let session_key = EncryptionKey::new(RC4_HMAC, vec![1; 16]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_tgs_requester(&session_key, 0);
+        tgs_requester.set_subkey(true);
+
+        tgs_requester
+            .request_s4u2self(
+                &tgt,
+                &S4uUser::Certificate(
+                    vec![0x30, 0x00],
+                    Realm::from("KINGDOM.HEARTS"),
+                ),
+            )
+            .unwrap();
+    }
+
+    #[should_panic(expected = "InvalidS4uX509UserReply")]
+    #[test]
+    fn request_s4u2self_ticket_without_echoed_certificate() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let raw_tgs_rep =
+                    produce_tgs_rep(&self.session_key, raw_request, 0);
+                let mut tgs_rep = TgsRep::parse(&raw_tgs_rep).unwrap().1;
+                tgs_rep.padata = None;
+                return Ok(tgs_rep.build());
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester
+            .set_transporter(Box::new(FakeTransporter { session_key }));
+
+        tgs_requester
+            .request_s4u2self(
+                &tgt,
+                &S4uUser::Certificate(
+                    vec![0x30, 0x00],
+                    Realm::from("KINGDOM.HEARTS"),
+                ),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn request_s4u2proxy_ticket() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+            evidence_ticket: Ticket,
+            user: PrincipalName,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+                assert_ne!(
+                    0,
+                    tgs_req.req_body.kdc_options.flags & CONSTRAINED_DELEGATION
+                );
+                assert_eq!(
+                    Some(vec![self.evidence_ticket.clone()]),
+                    tgs_req.req_body.additional_tickets
+                );
+
+                let padata = tgs_req.padata.unwrap();
+                assert_eq!(PA_PAC_OPTIONS, padata[1].padata_type);
+                assert_eq!(
+                    RESOURCE_BASED_CONSTRAINED_DELEGATION,
+                    PaPacOptions::parse(&padata[1].padata_value)
+                        .unwrap()
+                        .1
+                        .kerberos_flags
+                        .flags
+                );
+
+                let raw_tgs_rep =
+                    produce_tgs_rep(&self.session_key, raw_request, 0);
+                let mut tgs_rep = TgsRep::parse(&raw_tgs_rep).unwrap().1;
+                tgs_rep.cname = self.user.clone();
+                return Ok(tgs_rep.build());
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let user = PrincipalName::new(
+            NT_PRINCIPAL,
+            KerberosString::from("administrador"),
+        );
+        let evidence_ticket = Credential::new(
+            Realm::from("KINGDOM.HEARTS"),
+            user.clone(),
+            Ticket::new(
+                Realm::from("KINGDOM.HEARTS"),
+                tgt.cname().clone(),
+                EncryptedData::new(AES256_CTS_HMAC_SHA1_96, None, vec![1; 8]),
+            ),
+            EncAsRepPart::default(),
+        );
+
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester.set_rbcd(true);
+        tgs_requester.set_transporter(Box::new(FakeTransporter {
+            session_key,
+            evidence_ticket: evidence_ticket.ticket().clone(),
+            user: user.clone(),
+        }));
+
+        let credential = tgs_requester
+            .request_s4u2proxy(
+                &tgt,
+                &evidence_ticket,
+                &service_to_principal_name(
+                    &AsciiString::from_ascii("cifs/fs01.kingdom.hearts")
+                        .unwrap(),
+                ),
+            )
+            .unwrap();
+
+        assert_eq!(&user, credential.cname());
+    }
+
+    #[test]
+    fn request_u2u_ticket() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+            peer_tgt: Ticket,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+                assert_ne!(
+                    0,
+                    tgs_req.req_body.kdc_options.flags & ENC_TKT_IN_SKEY
+                );
+                assert_eq!(
+                    Some(vec![self.peer_tgt.clone()]),
+                    tgs_req.req_body.additional_tickets
+                );
+                return Ok(produce_tgs_rep(&self.session_key, raw_request, 0));
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let peer_tgt = create_tgt(EncryptionKey::new(RC4_HMAC, vec![2; 16]))
+            .ticket()
+            .clone();
+
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester.set_transporter(Box::new(FakeTransporter {
+            session_key,
+            peer_tgt: peer_tgt.clone(),
+        }));
+
+        let credential = tgs_requester
+            .request_u2u(
+                &tgt,
+                &peer_tgt,
+                &PrincipalName::new(
+                    NT_PRINCIPAL,
+                    KerberosString::from("donald"),
+                ),
+            )
+            .unwrap();
+
+        assert!(credential.is_skey());
+        assert_eq!(Some(&peer_tgt), credential.second_ticket());
+    }
+
+    #[test]
+    fn renew_ticket() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+            ticket: Ticket,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+                assert_eq!(
+                    RENEW | RENEWABLE | FORWARDABLE,
+                    tgs_req.req_body.kdc_options.flags
+                );
+                assert_eq!(
+                    Some(self.ticket.sname.clone()),
+                    tgs_req.req_body.sname
+                );
+
+                let padata = &tgs_req.padata.as_ref().unwrap()[0];
+                let ap_req = ApReq::parse(&padata.padata_value).unwrap().1;
+                assert_eq!(self.ticket, ap_req.ticket);
+
+                return Ok(produce_tgs_rep(&self.session_key, raw_request, 0));
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::RENEWABLE | ticket_flags::FORWARDABLE,
+            Some(Utc::now() + Duration::days(1)),
+        );
+
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester.set_transporter(Box::new(FakeTransporter {
+            session_key,
+            ticket: tgt.ticket().clone(),
+        }));
+
+        let credential = tgs_requester.renew(&tgt).unwrap();
+        assert_eq!(tgt.sname(), credential.sname());
+        assert_eq!(tgt.cname(), credential.cname());
+    }
+
+    #[should_panic(expected = "TicketNotRenewable")]
+    #[test]
+    fn renew_not_renewable_ticket() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::FORWARDABLE,
+            Some(Utc::now() + Duration::days(1)),
+        );
+
+        new_tgs_requester(&session_key, 0).renew(&tgt).unwrap();
+    }
+
+    #[test]
+    fn renew_ticket_within_clockskew_after_renew_till() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::RENEWABLE,
+            Some(Utc::now() - Duration::minutes(1)),
+        );
+
+        let mut tgs_requester = new_tgs_requester(&session_key, 0);
+        tgs_requester.renew(&tgt).unwrap();
+
+        tgs_requester.set_clockskew(Duration::zero());
+        assert_eq!(
+            Error::TicketRenewTillExpired,
+            tgs_requester.renew(&tgt).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn renew_ticket_within_clockskew_after_endtime() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_times(
+            session_key.clone(),
+            ticket_flags::RENEWABLE,
+            Utc::now() - Duration::minutes(1),
+            Some(Utc::now() + Duration::days(1)),
+        );
+
+        let mut tgs_requester = new_tgs_requester(&session_key, 0);
+        tgs_requester.renew(&tgt).unwrap();
+
+        tgs_requester.set_clockskew(Duration::zero());
+        assert_eq!(
+            Error::TicketExpired,
+            tgs_requester.renew(&tgt).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn create_tgs_requester_from_krb5_config() {
+        let config = Krb5Config::parse(
+            r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tgs_enctypes = aes256-cts rc4-hmac
+    permitted_enctypes = aes256-cts aes128-cts
+    clockskew = 1m
+    ticket_lifetime = 8h
+    renew_lifetime = 0
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = 127.0.0.1:8888
+    }
+
+[capaths]
+    KINGDOM.HEARTS = {
+        PIXAR.COM = DISNEY.COM
+    }
+"#,
+        )
+        .unwrap();
+
+        let tgs_requester = TgsRequester::from_krb5_config(&config).unwrap();
+
+        assert_eq!(
+            &[KdcEndpoint::new("127.0.0.1", 8888)],
+            tgs_requester.kdcs()
+        );
+        assert_eq!(TransportProtocol::UDP, tgs_requester.transport_protocol);
+        assert_eq!(
+            &[AES256_CTS_HMAC_SHA1_96]
+                .iter()
+                .cloned()
+                .collect::<HashSet<i32>>(),
+            tgs_requester.etypes()
+        );
+        assert_eq!(Duration::minutes(1), tgs_requester.clockskew());
+        assert_eq!(Some(Duration::hours(8)), tgs_requester.ticket_lifetime());
+        assert_eq!(0, tgs_requester.kdc_options() & RENEWABLE);
+        assert_eq!(
+            Some(&vec!["DISNEY.COM".to_string()]),
+            tgs_requester
+                .capaths
+                .get(&("KINGDOM.HEARTS".to_string(), "PIXAR.COM".to_string()))
+        );
+    }
+
+    #[test]
+    fn error_creating_tgs_requester_without_default_realm() {
+        let config = Krb5Config::parse("[libdefaults]\n").unwrap();
+
+        match TgsRequester::from_krb5_config(&config) {
+            Err(error) => assert_eq!(
+                Error::ConfigError("default_realm is not defined".to_string()),
+                error
+            ),
+            Ok(_) => panic!("requester created without default realm"),
+        }
+    }
+
+    #[should_panic(expected = "TicketRenewTillExpired")]
+    #[test]
+    fn renew_ticket_after_renew_till() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::RENEWABLE,
+            Some(Utc::now() - Duration::days(1)),
+        );
+
+        new_tgs_requester(&session_key, 0).renew(&tgt).unwrap();
+    }
+
+    #[test]
+    fn validate_postdated_ticket() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+                assert_eq!(VALIDATE, tgs_req.req_body.kdc_options.flags);
+                return Ok(produce_tgs_rep(&self.session_key, raw_request, 0));
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::INVALID | ticket_flags::POSTDATE,
+            None,
+        );
+
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester
+            .set_transporter(Box::new(FakeTransporter { session_key }));
+
+        let credential = tgs_requester.validate(&tgt).unwrap();
+        assert_eq!(0, credential.flags().flags & ticket_flags::INVALID);
+    }
+
+    #[should_panic(expected = "TicketAlreadyValid")]
+    #[test]
+    fn validate_valid_ticket() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+
+        new_tgs_requester(&session_key, 0).validate(&tgt).unwrap();
+    }
+
+    #[test]
+    fn request_delegated_tgt() {
+        struct FakeTransporter {
+            session_key: EncryptionKey,
+        }
+
+        impl Transporter for FakeTransporter {
+            fn request_and_response(
+                &self,
+                raw_request: &[u8],
+            ) -> Result<Vec<u8>> {
+                let tgs_req = TgsReq::parse(raw_request).unwrap().1;
+                assert_ne!(0, tgs_req.req_body.kdc_options.flags & FORWARDED);
+                return Ok(produce_tgs_rep(&self.session_key, raw_request, 0));
+            }
+        }
+
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::FORWARDABLE,
+            None,
+        );
+        let service_credential = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::OK_AS_DELEGATE,
+            None,
+        );
+
+        let mut tgs_requester =
+            TgsRequester::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)));
+        tgs_requester
+            .set_transporter(Box::new(FakeTransporter { session_key }));
+
+        let credential = tgs_requester
+            .request_delegated_tgt(&tgt, &service_credential)
+            .unwrap();
+        assert_eq!(tgt.sname(), credential.sname());
+    }
+
+    #[should_panic(expected = "DelegationNotAllowed")]
+    #[test]
+    fn request_delegated_tgt_for_service_not_ok_as_delegate() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::FORWARDABLE,
+            None,
+        );
+        let service_credential = create_tgt(session_key.clone());
+
+        new_tgs_requester(&session_key, 0)
+            .request_delegated_tgt(&tgt, &service_credential)
+            .unwrap();
+    }
+
+    #[test]
+    fn request_delegated_tgt_ignoring_ok_as_delegate() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt_with_flags(
+            session_key.clone(),
+            ticket_flags::FORWARDABLE,
+            None,
+        );
+        let service_credential = create_tgt(session_key.clone());
+
+        let mut tgs_requester = new_tgs_requester(&session_key, 0);
+        tgs_requester.set_ok_as_delegate_honoured(false);
+        tgs_requester
+            .request_delegated_tgt(&tgt, &service_credential)
+            .unwrap();
+    }
+
+    #[should_panic(expected = "TicketNotForwardable")]
+    #[test]
+    fn request_forwarded_tgt_with_not_forwardable_tgt() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+
+        new_tgs_requester(&session_key, 0)
+            .request_forwarded_tgt(&tgt)
+            .unwrap();
+    }
+
+    #[test]
+    fn service_name_to_principal_name() {
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("cifs"));
+        sname.push(KerberosString::from("fs01"));
+
+        assert_eq!(
+            sname,
+            service_to_principal_name(
+                &AsciiString::from_ascii("cifs/fs01").unwrap()
+            )
+        );
+        assert_eq!(
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
+            service_to_principal_name(
+                &AsciiString::from_ascii("mickey").unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn follow_referrals_to_service_realm() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let tgs_requester = new_referral_tgs_requester(
+            &session_key,
+            &[
+                ("KINGDOM.HEARTS", Some("TWILIGHT.TOWN")),
+                ("TWILIGHT.TOWN", Some("DARK.WORLD")),
+                ("DARK.WORLD", None),
+            ],
+        );
+
+        let credential = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+            )
+            .unwrap();
+
+        let mut sname =
+            PrincipalName::new(NT_SRV_INST, KerberosString::from("HTTP"));
+        sname.push(KerberosString::from("web.dark.world"));
+
+        assert_eq!(&sname, credential.sname());
+        assert_eq!("DARK.WORLD", credential.srealm().to_string());
+    }
+
+    #[test]
+    fn error_on_referral_loop() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let tgs_requester = new_referral_tgs_requester(
+            &session_key,
+            &[
+                ("KINGDOM.HEARTS", Some("TWILIGHT.TOWN")),
+                ("TWILIGHT.TOWN", Some("KINGDOM.HEARTS")),
+            ],
+        );
+
+        let error = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+            )
+            .unwrap_err();
+
+        assert_eq!(Error::ReferralLoop("KINGDOM.HEARTS".to_string()), error);
+    }
+
+    #[test]
+    fn error_on_too_many_referrals() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_referral_tgs_requester(
+            &session_key,
+            &[
+                ("KINGDOM.HEARTS", Some("TWILIGHT.TOWN")),
+                ("TWILIGHT.TOWN", Some("DARK.WORLD")),
+                ("DARK.WORLD", None),
+            ],
+        );
+        tgs_requester.set_max_referrals(1);
+
+        let error = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+            )
+            .unwrap_err();
+
+        assert_eq!(Error::TooManyReferrals(1), error);
+    }
+
+    #[test]
+    fn follow_referrals_up_to_the_maximum() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_referral_tgs_requester(
+            &session_key,
+            &[
+                ("KINGDOM.HEARTS", Some("TWILIGHT.TOWN")),
+                ("TWILIGHT.TOWN", Some("DARK.WORLD")),
+                ("DARK.WORLD", None),
+            ],
+        );
+        tgs_requester.set_max_referrals(2);
+
+        let credential = tgs_requester
+            .request(
+                &tgt,
+                &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!("DARK.WORLD", credential.srealm().to_string());
+    }
+
+    #[test]
+    fn request_service_through_capath() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let mut tgs_requester = new_referral_tgs_requester(
+            &session_key,
+            &[
+                ("KINGDOM.HEARTS", None),
+                ("TWILIGHT.TOWN", None),
+                ("DARK.WORLD", None),
+            ],
+        );
+        tgs_requester.set_capath(
+            "kingdom.hearts",
+            "dark.world",
+            vec!["twilight.town".to_string()],
+        );
+
+        let sname = service_to_principal_name(
+            &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+        );
+        let credential = tgs_requester
+            .request_principal_in_realm(&tgt, &sname, "DARK.WORLD")
+            .unwrap();
+
+        assert_eq!(&sname, credential.sname());
+        assert_eq!("DARK.WORLD", credential.srealm().to_string());
+    }
+
+    #[test]
+    fn referral_realm_of_credential() {
+        let session_key =
+            EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![1; 32]);
+        let tgt = create_tgt(session_key.clone());
+        let service = service_to_principal_name(
+            &AsciiString::from_ascii("HTTP/web.dark.world").unwrap(),
+        );
+
+        assert_eq!(None, referral_realm(&tgt, &service));
+
+        let realm = Realm::from("KINGDOM.HEARTS");
+        let sname = krbtgt_principal_name("DARK.WORLD");
+        let tgt = Credential::new(
+            realm.clone(),
+            PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
+            Ticket::new(realm.clone(), sname.clone(), EncryptedData::default()),
+            EncAsRepPart {
+                key: session_key,
+                srealm: realm,
+                sname,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            Some("DARK.WORLD".to_string()),
+            referral_realm(&tgt, &service)
+        );
+        assert_eq!(
+            None,
+            referral_realm(&tgt, &krbtgt_principal_name("dark.world"))
+        );
+    }
+}
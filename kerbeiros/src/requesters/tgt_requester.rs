@@ -1,13 +1,21 @@
 use super::as_requester::*;
+use super::tgs_requester::{visit_referral_realm, DEFAULT_MAX_REFERRALS};
+use crate::config::Krb5Config;
 use crate::credentials::*;
+use crate::discovery::{DnsResolver, KdcEndpoint, KdcLocator};
+use crate::messages::PreauthHints;
 use crate::transporter::*;
 use crate::{Error, Result};
 use ascii::AsciiString;
-use himmelblau_kerberos_asn1::{AsRep, KrbError};
-use himmelblau_kerberos_constants::error_codes::KDC_ERR_PREAUTH_REQUIRED;
+use chrono::{DateTime, Duration, Utc};
+use himmelblau_kerberos_asn1::{AsRep, KrbError, PrincipalName};
+use himmelblau_kerberos_constants::error_codes::{
+    KDC_ERR_PREAUTH_REQUIRED, KDC_ERR_WRONG_REALM,
+};
+use himmelblau_kerberos_constants::kdc_options::{RENEWABLE, RENEWABLE_OK};
 use himmelblau_kerberos_crypto::Key;
-use std::collections::HashSet;
-use std::net::IpAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 
 /// Gets a TGT from KDC by sending one or more AS-REQ requests
 /// # Examples
@@ -38,21 +46,154 @@ use std::net::IpAddr;
 ///
 pub struct TgtRequester {
     as_requester: AsRequester,
+    transport_protocol: TransportProtocol,
+    realm_transporters: HashMap<String, Box<dyn Transporter>>,
+    kdc_locator: KdcLocator,
+    transport_settings: TransportSettings,
+    max_referrals: usize,
 }
 
 impl TgtRequester {
     pub fn new(realm: AsciiString, kdc_address: IpAddr) -> Self {
+        return Self::with_kdcs(realm, vec![KdcEndpoint::from(kdc_address)]);
+    }
+
+    /// Creates a requester which sends the requests to the given KDCs,
+    /// trying them in order until one of them responds.
+    pub fn with_kdcs(realm: AsciiString, kdcs: Vec<KdcEndpoint>) -> Self {
+        let kdc_locator = KdcLocator::new();
+        let mut as_requester = AsRequester::with_kdcs(realm, kdcs);
+        as_requester.share_resolver(kdc_locator.resolver());
         return Self {
-            as_requester: AsRequester::new(realm, kdc_address),
+            as_requester,
+            transport_protocol: TransportProtocol::TCP,
+            realm_transporters: HashMap::new(),
+            kdc_locator,
+            transport_settings: TransportSettings::default(),
+            max_referrals: DEFAULT_MAX_REFERRALS,
         };
     }
 
+    /// Creates a requester for the default realm of the configuration,
+    /// which sends the requests to the KDCs of the realm, either
+    /// configured or discovered through DNS. The etypes, lifetimes and
+    /// UDP preference limit are also taken from the configuration.
+    pub fn from_krb5_config(config: &Krb5Config) -> Result<Self> {
+        let realm = config.default_realm().ok_or_else(|| {
+            Error::ConfigError("default_realm is not defined".to_string())
+        })?;
+        let kdc_locator = KdcLocator::from_krb5_config(config)?;
+        let transport_protocol = match config.kdc_proxy_url(realm) {
+            Some(_) => TransportProtocol::KdcProxy,
+            None => TransportProtocol::UDP,
+        };
+        let kdcs = kdc_locator.kdc_endpoints(realm, transport_protocol)?;
+
+        let mut requester =
+            Self::with_kdcs(AsciiString::from_ascii(realm)?, kdcs);
+        requester
+            .as_requester
+            .share_resolver(kdc_locator.resolver());
+        requester.kdc_locator = kdc_locator;
+        requester.set_udp_preference_limit(config.udp_preference_limit()?);
+        if let Some(kdc_proxy_url) = config.kdc_proxy_url(realm) {
+            let mut transport_settings = requester.transport_settings().clone();
+            transport_settings.kdc_proxy_url = Some(kdc_proxy_url.to_string());
+            requester.set_transport_settings(transport_settings);
+        }
+        requester.set_transport_protocol(transport_protocol);
+
+        if let Some(etypes) = config.supported_tkt_etypes() {
+            if etypes.is_empty() {
+                return Err(Error::NoProvidedSupportedCipherAlgorithm);
+            }
+            requester.set_etypes(etypes)?;
+        }
+
+        requester.set_ticket_lifetime(config.ticket_lifetime()?);
+        if let Some(renew_lifetime) = config.renew_lifetime()? {
+            if renew_lifetime.is_zero() {
+                requester.set_kdc_options(
+                    requester.kdc_options() & !(RENEWABLE | RENEWABLE_OK),
+                );
+            } else {
+                requester.set_renew_lifetime(Some(renew_lifetime));
+            }
+        }
+
+        return Ok(requester);
+    }
+
+    /// Requests a TGT for the user. In case the KDC responds with a
+    /// KDC_ERR_WRONG_REALM referral, the request is retried against the
+    /// realm indicated by the KDC. The returned credential contains the
+    /// canonical client name.
     pub fn request(
         &self,
         username: &AsciiString,
         user_key: Option<&Key>,
     ) -> Result<Credential> {
-        return TGTRequest::request(username, user_key, &self.as_requester);
+        return TGTRequest::request(username, user_key, self);
+    }
+
+    /// Sends an AS-REQ to the KDC of the given realm.
+    fn request_to_realm(
+        &self,
+        username: &AsciiString,
+        user_key: Option<&Key>,
+        hints: Option<&PreauthHints>,
+        realm: &AsciiString,
+    ) -> Result<AsReqResponse> {
+        let realm_key = realm.to_string().to_uppercase();
+        if realm_key == self.realm().to_string().to_uppercase() {
+            if let Some(hints) = hints {
+                return self
+                    .as_requester
+                    .request_with_preauth_hints(username, user_key, hints);
+            }
+            return self.as_requester.request(username, user_key);
+        }
+
+        if let Some(transporter) = self.realm_transporters.get(&realm_key) {
+            return self.as_requester.request_to_realm(
+                username,
+                user_key,
+                hints,
+                realm,
+                transporter,
+            );
+        }
+
+        let transporter = new_kdcs_transporter(
+            self.realm_kdcs(realm.as_str())?,
+            self.kdc_locator.resolver(),
+            self.transport_protocol,
+            self.transport_settings.clone(),
+        );
+        return self.as_requester.request_to_realm(
+            username,
+            user_key,
+            hints,
+            realm,
+            &transporter,
+        );
+    }
+
+    /// Retrieves the KDCs of another realm, configured or discovered
+    /// through DNS.
+    pub(crate) fn realm_kdcs(&self, realm: &str) -> Result<Vec<KdcEndpoint>> {
+        return self
+            .kdc_locator
+            .kdc_endpoints(realm, self.transport_protocol);
+    }
+
+    pub(crate) fn kdc_locator(&self) -> &KdcLocator {
+        return &self.kdc_locator;
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn as_options(&self) -> &crate::messages::AsReqOptions {
+        return self.as_requester.as_options();
     }
 
     pub fn etypes(&self) -> &HashSet<i32> {
@@ -71,114 +212,615 @@ impl TgtRequester {
         return self.as_requester.kdc_options();
     }
 
+    pub fn set_kdc_options(&mut self, kdc_options: u32) {
+        self.as_requester.set_kdc_options(kdc_options);
+    }
+
+    pub fn starttime(&self) -> Option<&DateTime<Utc>> {
+        return self.as_requester.starttime();
+    }
+
+    /// Sets the time from which the requested tickets should be valid,
+    /// requesting postdated tickets.
+    pub fn set_starttime(&mut self, starttime: Option<DateTime<Utc>>) {
+        self.as_requester.set_starttime(starttime);
+    }
+
+    pub fn ticket_lifetime(&self) -> Option<Duration> {
+        return self.as_requester.ticket_lifetime();
+    }
+
+    /// Sets the lifetime requested for the TGT. By default, the maximum
+    /// lifetime allowed by the KDC is requested.
+    pub fn set_ticket_lifetime(&mut self, ticket_lifetime: Option<Duration>) {
+        self.as_requester.set_ticket_lifetime(ticket_lifetime);
+    }
+
+    pub fn renew_lifetime(&self) -> Option<Duration> {
+        return self.as_requester.renew_lifetime();
+    }
+
+    /// Sets the renewable lifetime requested for the TGT. By default, the
+    /// maximum renewable lifetime allowed by the KDC is requested.
+    pub fn set_renew_lifetime(&mut self, renew_lifetime: Option<Duration>) {
+        self.as_requester.set_renew_lifetime(renew_lifetime);
+    }
+
     pub fn realm(&self) -> &AsciiString {
         return self.as_requester.realm();
     }
 
+    pub fn name_type(&self) -> i32 {
+        return self.as_requester.name_type();
+    }
+
+    /// Sets the type of the client name, such as NT_ENTERPRISE to request
+    /// tickets for user principal names like bob@contoso.com.
+    pub fn set_name_type(&mut self, name_type: i32) {
+        self.as_requester.set_name_type(name_type);
+    }
+
+    pub fn sname(&self) -> Option<&PrincipalName> {
+        return self.as_requester.sname();
+    }
+
+    /// Sets the service of the requested ticket, such as kadmin/changepw,
+    /// which is then an initial ticket for the service. By default, the
+    /// TGT of the realm is requested.
+    pub fn set_sname(&mut self, sname: Option<PrincipalName>) {
+        self.as_requester.set_sname(sname);
+    }
+
+    pub fn transport_protocol(&self) -> TransportProtocol {
+        return self.transport_protocol;
+    }
+
     pub fn set_transport_protocol(
         &mut self,
         transport_protocol: TransportProtocol,
     ) {
+        self.transport_protocol = transport_protocol;
         self.as_requester.set_transport_protocol(transport_protocol);
     }
 
+    /// Sets the maximum size of the messages sent over UDP, since bigger
+    /// messages are sent over TCP.
+    pub fn set_udp_preference_limit(&mut self, udp_preference_limit: usize) {
+        self.transport_settings.udp_preference_limit = udp_preference_limit;
+        self.as_requester
+            .set_udp_preference_limit(udp_preference_limit);
+    }
+
+    pub fn transport_settings(&self) -> &TransportSettings {
+        return &self.transport_settings;
+    }
+
+    /// Sets the timeouts, retries, UDP preference limit and KDC proxy used
+    /// to communicate with the KDCs.
+    pub fn set_transport_settings(
+        &mut self,
+        transport_settings: TransportSettings,
+    ) {
+        self.as_requester
+            .set_transport_settings(transport_settings.clone());
+        self.transport_settings = transport_settings;
+    }
+
+    pub fn kdcs(&self) -> &[KdcEndpoint] {
+        return self.as_requester.kdcs();
+    }
+
+    /// Sets the KDCs of the realm, which are tried in order until one of
+    /// them responds.
+    pub fn set_kdcs(&mut self, kdcs: Vec<KdcEndpoint>) {
+        self.as_requester.set_kdcs(kdcs);
+    }
+
+    /// Returns the address of the last KDC of the realm that responded.
+    pub fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return self.as_requester.last_kdc_address();
+    }
+
+    /// Sets the address of the KDC of another realm, used when the KDC
+    /// refers the client to that realm. Otherwise, the address is located
+    /// from the configured KDCs or the realm name.
+    pub fn set_realm_kdc(&mut self, realm: &str, kdc_address: IpAddr) {
+        self.set_realm_kdcs(realm, vec![KdcEndpoint::from(kdc_address)]);
+    }
+
+    /// Sets the KDCs of another realm, used when the KDC refers the
+    /// client to that realm.
+    pub fn set_realm_kdcs(&mut self, realm: &str, kdcs: Vec<KdcEndpoint>) {
+        self.kdc_locator.set_realm_kdcs(realm, kdcs);
+    }
+
+    /// Sets the resolver used to discover the KDCs and resolve their names.
+    pub fn set_resolver(&mut self, resolver: Box<dyn DnsResolver>) {
+        self.kdc_locator.set_resolver(resolver);
+        self.as_requester
+            .share_resolver(self.kdc_locator.resolver());
+    }
+
+    /// Sets whether the KDCs of other realms are discovered through DNS,
+    /// when they are not configured.
+    pub fn set_dns_lookup_kdc(&mut self, dns_lookup_kdc: bool) {
+        self.kdc_locator.set_dns_lookup_kdc(dns_lookup_kdc);
+    }
+
+    pub fn max_referrals(&self) -> usize {
+        return self.max_referrals;
+    }
+
+    /// Sets the maximum number of client referrals followed in a request.
+    pub fn set_max_referrals(&mut self, max_referrals: usize) {
+        self.max_referrals = max_referrals;
+    }
+
     #[cfg(test)]
     fn set_transporter(&mut self, transporter: Box<dyn Transporter>) {
         self.as_requester.set_transporter(transporter);
     }
+
+    #[cfg(test)]
+    fn set_realm_transporter(
+        &mut self,
+        realm: &str,
+        transporter: Box<dyn Transporter>,
+    ) {
+        self.realm_transporters
+            .insert(realm.to_uppercase(), transporter);
+    }
 }
 
 struct TGTRequest<'a> {
     username: &'a AsciiString,
     user_key: Option<&'a Key>,
-    as_requester: &'a AsRequester,
+    requester: &'a TgtRequester,
+    realm: AsciiString,
 }
 
 impl<'a> TGTRequest<'a> {
     pub fn request(
         username: &'a AsciiString,
         user_key: Option<&'a Key>,
-        as_requester: &'a AsRequester,
+        requester: &'a TgtRequester,
     ) -> Result<Credential> {
-        let request = Self {
+        let mut request = Self {
             username,
             user_key,
-            as_requester,
+            requester,
+            realm: requester.realm().clone(),
         };
-        return request.request_tgt();
+        return request.request_tgt_following_referrals();
     }
 
-    fn request_tgt(&self) -> Result<Credential> {
-        match self.as_requester.request(self.username, None)? {
-            AsReqResponse::KrbError(krb_error) => {
-                return self.process_1st_krb_error(krb_error);
-            }
-            AsReqResponse::AsRep(as_rep) => {
-                return self.extract_credential_from_as_rep(as_rep);
+    fn request_tgt_following_referrals(&mut self) -> Result<Credential> {
+        let mut visited_realms = vec![self.realm.to_string().to_uppercase()];
+
+        loop {
+            match self.request_tgt() {
+                Err(Error::KrbErrorResponse(krb_error))
+                    if krb_error.error_code == KDC_ERR_WRONG_REALM =>
+                {
+                    self.realm = follow_wrong_realm_referral(
+                        krb_error,
+                        &mut visited_realms,
+                        self.requester.max_referrals,
+                    )?;
+                }
+                result => {
+                    return result;
+                }
             }
         }
     }
 
-    fn process_1st_krb_error(&self, krb_error: KrbError) -> Result<Credential> {
-        if krb_error.error_code != KDC_ERR_PREAUTH_REQUIRED {
-            return Err(Error::KrbErrorResponse(krb_error))?;
-        }
+    fn request_as_req(
+        &self,
+        user_key: Option<&Key>,
+        hints: Option<&PreauthHints>,
+    ) -> Result<AsReqResponse> {
+        return self.requester.request_to_realm(
+            self.username,
+            user_key,
+            hints,
+            &self.realm,
+        );
+    }
 
-        if let Some(user_key) = self.user_key {
-            return self.request_2nd_as_req(user_key);
+    fn request_tgt(&self) -> Result<Credential> {
+        match self.request_as_req(None, None)? {
+            AsReqResponse::KrbError(krb_error) => {
+                let (user_key, hints) =
+                    preauth_key_and_hints(krb_error, self.user_key)?;
+                return self.request_2nd_as_req(user_key, &hints);
+            }
+            AsReqResponse::AsRep(as_rep) => {
+                return extract_credential_from_as_rep(
+                    self.user_key,
+                    as_rep,
+                    None,
+                );
+            }
         }
-
-        return Err(Error::KrbErrorResponse(krb_error))?;
     }
 
-    fn request_2nd_as_req(&self, user_key: &Key) -> Result<Credential> {
-        match self.as_requester.request(self.username, Some(user_key))? {
+    fn request_2nd_as_req(
+        &self,
+        user_key: &Key,
+        hints: &PreauthHints,
+    ) -> Result<Credential> {
+        match self.request_as_req(Some(user_key), Some(hints))? {
             AsReqResponse::KrbError(krb_error) => {
                 return Err(Error::KrbErrorResponse(krb_error))?;
             }
             AsReqResponse::AsRep(as_rep) => {
-                return self.extract_credential_from_as_rep(as_rep);
+                return extract_credential_from_as_rep(
+                    Some(user_key),
+                    as_rep,
+                    Some(hints),
+                );
             }
         }
     }
+}
 
-    fn extract_credential_from_as_rep(
-        &self,
-        as_rep: AsRep,
-    ) -> Result<Credential> {
-        let user_key;
+/// Retrieves the realm indicated by a KDC_ERR_WRONG_REALM referral, which
+/// is added to the visited realms.
+pub(crate) fn follow_wrong_realm_referral(
+    krb_error: KrbError,
+    visited_realms: &mut Vec<String>,
+    max_referrals: usize,
+) -> Result<AsciiString> {
+    let next_realm = match &krb_error.crealm {
+        Some(crealm) => crealm.to_string().to_uppercase(),
+        None => {
+            return Err(Error::KrbErrorResponse(krb_error));
+        }
+    };
+
+    let realm = AsciiString::from_ascii(next_realm.as_str())?;
+    visit_referral_realm(visited_realms, next_realm, max_referrals)?;
+    return Ok(realm);
+}
+
+/// Retrieves the user key and the hints to send the pre-authenticated
+/// AS-REQ required by the KDC in the first KRB-ERROR.
+pub(crate) fn preauth_key_and_hints(
+    krb_error: KrbError,
+    user_key: Option<&Key>,
+) -> Result<(&Key, PreauthHints)> {
+    if krb_error.error_code != KDC_ERR_PREAUTH_REQUIRED {
+        return Err(Error::KrbErrorResponse(krb_error));
+    }
 
-        if let Some(key) = &self.user_key {
-            user_key = key;
-        } else {
+    if let Some(user_key) = user_key {
+        let hints = PreauthHints::from_krb_error(&krb_error);
+        return Ok((user_key, hints));
+    }
+
+    return Err(Error::KrbErrorResponse(krb_error));
+}
+
+/// Decrypts the AS-REP with the user key, retrieving the TGT.
+pub(crate) fn extract_credential_from_as_rep(
+    user_key: Option<&Key>,
+    as_rep: AsRep,
+    hints: Option<&PreauthHints>,
+) -> Result<Credential> {
+    let user_key = match user_key {
+        Some(user_key) => user_key,
+        None => {
             return Err(Error::ParseAsRepError(
                 as_rep,
                 Box::new(Error::NoKeyProvided),
-            ))?;
+            ));
         }
+    };
 
-        match CredentialKrbInfoMapper::kdc_rep_to_credential(
-            user_key,
-            as_rep.clone(),
-        ) {
-            Ok(credential) => {
-                return Ok(credential);
-            }
-            Err(error) => {
-                return Err(Error::ParseAsRepError(
-                    as_rep,
-                    Box::new(error.clone()),
-                ))?;
-            }
+    match CredentialKrbInfoMapper::kdc_rep_to_credential(
+        user_key,
+        as_rep.clone(),
+        hints,
+    ) {
+        Ok(credential) => {
+            return Ok(credential);
+        }
+        Err(error) => {
+            return Err(Error::ParseAsRepError(as_rep, Box::new(error)));
         }
     }
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
+    use himmelblau_kerberos_asn1::{
+        Asn1Object, EncAsRepPart, EncryptedData, EncryptionKey, KerberosString,
+        PrincipalName, Realm, Ticket,
+    };
+    use himmelblau_kerberos_constants::etypes::{
+        AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96,
+    };
+    use himmelblau_kerberos_constants::key_usages::KEY_USAGE_AS_REP_ENC_PART;
+    use himmelblau_kerberos_constants::principal_names::{
+        NT_ENTERPRISE, NT_PRINCIPAL, NT_SRV_INST,
+    };
+    use himmelblau_kerberos_crypto::new_kerberos_cipher;
     use std::net::Ipv4Addr;
 
+    /// KDC that refers the client to another realm with KDC_ERR_WRONG_REALM
+    pub struct WrongRealmKdc {
+        pub referral: &'static str,
+    }
+
+    impl Transporter for WrongRealmKdc {
+        fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+            let krb_error = KrbError {
+                pvno: 5,
+                msg_type: 30,
+                error_code: KDC_ERR_WRONG_REALM,
+                crealm: Some(Realm::from(self.referral)),
+                ..Default::default()
+            };
+            return Ok(krb_error.build());
+        }
+    }
+
+    /// KDC that issues a TGT for the canonical name of the user
+    pub struct CanonicalKdc {}
+
+    impl Transporter for CanonicalKdc {
+        fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+            let realm = Realm::from("DISNEY.COM");
+            let mut sname =
+                PrincipalName::new(NT_SRV_INST, KerberosString::from("krbtgt"));
+            sname.push(realm.clone());
+
+            let enc_as_rep_part = EncAsRepPart {
+                key: EncryptionKey::new(AES256_CTS_HMAC_SHA1_96, vec![9; 32]),
+                srealm: realm.clone(),
+                sname: sname.clone(),
+                ..Default::default()
+            };
+
+            let cipher = new_kerberos_cipher(AES256_CTS_HMAC_SHA1_96).unwrap();
+            let as_rep = AsRep {
+                crealm: realm.clone(),
+                cname: PrincipalName::new(
+                    NT_PRINCIPAL,
+                    KerberosString::from("mickey"),
+                ),
+                ticket: Ticket::new(realm, sname, EncryptedData::default()),
+                enc_part: EncryptedData::new(
+                    AES256_CTS_HMAC_SHA1_96,
+                    None,
+                    cipher.encrypt(
+                        &[5; 32],
+                        KEY_USAGE_AS_REP_ENC_PART,
+                        &enc_as_rep_part.build(),
+                    ),
+                ),
+                ..Default::default()
+            };
+            return Ok(as_rep.build());
+        }
+    }
+
+    fn new_enterprise_tgt_requester() -> TgtRequester {
+        let mut tgt_requester = TgtRequester::new(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+        );
+        tgt_requester.set_name_type(NT_ENTERPRISE);
+        return tgt_requester;
+    }
+
+    #[test]
+    fn request_tgt_following_wrong_realm_referral() {
+        let mut tgt_requester = new_enterprise_tgt_requester();
+        tgt_requester.set_transporter(Box::new(WrongRealmKdc {
+            referral: "DISNEY.COM",
+        }));
+        tgt_requester
+            .set_realm_transporter("DISNEY.COM", Box::new(CanonicalKdc {}));
+
+        let credential = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey@disney.com").unwrap(),
+                Some(&Key::AES256Key([5; 32])),
+            )
+            .unwrap();
+
+        assert_eq!("DISNEY.COM", credential.crealm().to_string());
+        assert_eq!(
+            &PrincipalName::new(NT_PRINCIPAL, KerberosString::from("mickey")),
+            credential.cname()
+        );
+    }
+
+    #[test]
+    fn error_on_wrong_realm_referral_loop() {
+        let mut tgt_requester = new_enterprise_tgt_requester();
+        tgt_requester.set_transporter(Box::new(WrongRealmKdc {
+            referral: "DISNEY.COM",
+        }));
+        tgt_requester.set_realm_transporter(
+            "DISNEY.COM",
+            Box::new(WrongRealmKdc {
+                referral: "KINGDOM.HEARTS",
+            }),
+        );
+
+        let error = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey@disney.com").unwrap(),
+                Some(&Key::AES256Key([5; 32])),
+            )
+            .unwrap_err();
+
+        assert_eq!(Error::ReferralLoop("KINGDOM.HEARTS".to_string()), error);
+    }
+
+    #[test]
+    fn error_on_wrong_realm_referral_without_dns_lookup_kdc() {
+        let mut tgt_requester = new_enterprise_tgt_requester();
+        tgt_requester.set_transporter(Box::new(WrongRealmKdc {
+            referral: "DISNEY.COM",
+        }));
+        tgt_requester.set_dns_lookup_kdc(false);
+
+        let error = tgt_requester
+            .request(
+                &AsciiString::from_ascii("mickey@disney.com").unwrap(),
+                Some(&Key::AES256Key([5; 32])),
+            )
+            .unwrap_err();
+
+        assert_eq!(Error::NameResolutionError("DISNEY.COM".to_string()), error);
+    }
+
+    #[test]
+    fn create_tgt_requester_with_multiple_kdcs_from_krb5_config() {
+        let config = Krb5Config::parse(
+            r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = kdc1.kingdom.hearts
+        kdc = kdc2.kingdom.hearts:750
+        kdc = [::1]:8888
+    }
+"#,
+        )
+        .unwrap();
+
+        let tgt_requester = TgtRequester::from_krb5_config(&config).unwrap();
+
+        assert_eq!(
+            &[
+                KdcEndpoint::new("kdc1.kingdom.hearts", 88),
+                KdcEndpoint::new("kdc2.kingdom.hearts", 750),
+                KdcEndpoint::new("::1", 8888),
+            ],
+            tgt_requester.kdcs()
+        );
+        assert_eq!(None, tgt_requester.last_kdc_address());
+    }
+
+    #[test]
+    fn create_tgt_requester_with_kdc_proxy_from_krb5_config() {
+        let config = Krb5Config::parse(
+            r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = https://kdcproxy.kingdom.hearts/KdcProxy
+    }
+"#,
+        )
+        .unwrap();
+
+        let tgt_requester = TgtRequester::from_krb5_config(&config).unwrap();
+
+        assert_eq!(
+            TransportProtocol::KdcProxy,
+            tgt_requester.transport_protocol()
+        );
+        assert_eq!(
+            Some("https://kdcproxy.kingdom.hearts/KdcProxy".to_string()),
+            tgt_requester.transport_settings().kdc_proxy_url
+        );
+        assert!(tgt_requester.kdcs().is_empty());
+    }
+
+    #[test]
+    fn request_tgt_through_kdc_proxy() {
+        let url = spawn_kdc_proxy("KINGDOM.HEARTS", None);
+        let mut tgt_requester = TgtRequester::with_kdcs(
+            AsciiString::from_ascii("KINGDOM.HEARTS").unwrap(),
+            Vec::new(),
+        );
+        tgt_requester.set_transport_settings(TransportSettings {
+            kdc_proxy_url: Some(url),
+            ..Default::default()
+        });
+        tgt_requester.set_transport_protocol(TransportProtocol::KdcProxy);
+
+        match tgt_requester
+            .request(&AsciiString::from_ascii("mickey").unwrap(), None)
+        {
+            Err(Error::KrbErrorResponse(krb_error)) => {
+                assert_eq!(30, krb_error.msg_type);
+            }
+            result => {
+                panic!("Unexpected result {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn create_tgt_requester_from_krb5_config() {
+        let config = Krb5Config::parse(
+            r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tkt_enctypes = aes256-cts-hmac-sha1-96 aes128-cts-hmac-sha1-96
+    udp_preference_limit = 1
+    ticket_lifetime = 10h
+    renew_lifetime = 7d
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = 127.0.0.1
+    }
+"#,
+        )
+        .unwrap();
+
+        let tgt_requester = TgtRequester::from_krb5_config(&config).unwrap();
+
+        assert_eq!("KINGDOM.HEARTS", tgt_requester.realm().as_str());
+        assert_eq!(
+            &[AES256_CTS_HMAC_SHA1_96, AES128_CTS_HMAC_SHA1_96]
+                .iter()
+                .cloned()
+                .collect::<HashSet<i32>>(),
+            tgt_requester.etypes()
+        );
+        assert_eq!(1, tgt_requester.transport_settings().udp_preference_limit);
+        assert_eq!(Some(Duration::hours(10)), tgt_requester.ticket_lifetime());
+        assert_eq!(Some(Duration::days(7)), tgt_requester.renew_lifetime());
+    }
+
+    #[test]
+    fn error_creating_tgt_requester_without_supported_etypes() {
+        let config = Krb5Config::parse(
+            r#"
+[libdefaults]
+    default_realm = KINGDOM.HEARTS
+    default_tkt_enctypes = des-cbc-md5
+
+[realms]
+    KINGDOM.HEARTS = {
+        kdc = 127.0.0.1
+    }
+"#,
+        )
+        .unwrap();
+
+        match TgtRequester::from_krb5_config(&config) {
+            Err(error) => {
+                assert_eq!(Error::NoProvidedSupportedCipherAlgorithm, error)
+            }
+            Ok(_) => panic!("requester created without supported etypes"),
+        }
+    }
+
     #[should_panic(expected = "KrbErrorResponse")]
     #[test]
     fn request_tgt_receiving_krb_error() {
@@ -0,0 +1,7 @@
+//! GSSAPI mechanism of SASL (RFC 4752), used to bind to the LDAP servers,
+//! such as Active Directory or OpenLDAP, with a Kerberos ticket.
+
+mod sasl_gssapi_client;
+pub use sasl_gssapi_client::{
+    SaslGssapiClient, DEFAULT_SASL_MAX_BUFFER_SIZE, MAX_SASL_BUFFER_SIZE,
+};
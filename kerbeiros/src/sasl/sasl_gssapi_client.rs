@@ -0,0 +1,384 @@
+use crate::credentials::Credential;
+use crate::error::*;
+use crate::gssapi::{InitiatorContext, SecurityContext};
+use himmelblau_kerberos_constants::gssapi::{
+    GSS_C_CONF_FLAG, GSS_C_INTEG_FLAG, GSS_C_MUTUAL_FLAG, GSS_C_REPLAY_FLAG,
+    GSS_C_SEQUENCE_FLAG,
+};
+use himmelblau_kerberos_constants::sasl::{
+    SECURITY_LAYER_CONFIDENTIALITY, SECURITY_LAYER_INTEGRITY,
+    SECURITY_LAYER_NONE,
+};
+
+/// Maximum size of the buffers, since it is encoded in 3 bytes
+pub const MAX_SASL_BUFFER_SIZE: u32 = 0xffffff;
+
+/// Maximum size of the wrapped messages that the client accepts by default
+pub const DEFAULT_SASL_MAX_BUFFER_SIZE: u32 = 0x10000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SaslState {
+    Initial,
+    EstablishingContext,
+    NegotiatingSecurityLayer,
+    Completed,
+}
+
+/// Client of the GSSAPI mechanism of SASL (RFC 4752), which authenticates
+/// with a Kerberos ticket for the service, such as `ldap/host`.
+///
+/// The [step](Self::step) function produces the responses to the
+/// challenges of the server, starting with the initial response, until the
+/// exchange is completed. Then, in case a security layer was negotiated,
+/// the messages are protected with [wrap](Self::wrap) and
+/// [unwrap](Self::unwrap). In LDAP, each wrapped message is preceded by
+/// its length, in 4 bytes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # let credential: Credential = unimplemented!();
+/// # fn sasl_bind(mechanism: &str, credentials: &[u8]) -> Option<Vec<u8>> {
+/// #     unimplemented!()
+/// # }
+///
+/// let mut client = SaslGssapiClient::new(credential);
+/// let mut challenge = None;
+/// while !client.is_complete() {
+///     let response = client.step(challenge.as_deref()).unwrap();
+///     // send the response in a bind request with the GSSAPI mechanism
+///     challenge = sasl_bind("GSSAPI", &response);
+/// }
+///
+/// let request = client.wrap(b"LDAP message").unwrap();
+/// ```
+pub struct SaslGssapiClient {
+    context: InitiatorContext,
+    authzid: Option<String>,
+    security_layers: u8,
+    max_buffer_size: u32,
+    state: SaslState,
+    security_layer: u8,
+    peer_max_buffer_size: u32,
+}
+
+impl SaslGssapiClient {
+    /// Creates a client that accepts any security layer, preferring
+    /// confidentiality over integrity.
+    pub fn new(credential: Credential) -> Self {
+        return Self {
+            context: InitiatorContext::new(
+                credential,
+                GSS_C_MUTUAL_FLAG
+                    | GSS_C_REPLAY_FLAG
+                    | GSS_C_SEQUENCE_FLAG
+                    | GSS_C_INTEG_FLAG
+                    | GSS_C_CONF_FLAG,
+            ),
+            authzid: None,
+            security_layers: SECURITY_LAYER_NONE
+                | SECURITY_LAYER_INTEGRITY
+                | SECURITY_LAYER_CONFIDENTIALITY,
+            max_buffer_size: DEFAULT_SASL_MAX_BUFFER_SIZE,
+            state: SaslState::Initial,
+            security_layer: SECURITY_LAYER_NONE,
+            peer_max_buffer_size: 0,
+        };
+    }
+
+    /// Sets the identity to act as, if it is different from the client
+    /// of the ticket.
+    pub fn set_authzid(&mut self, authzid: Option<String>) {
+        self.authzid = authzid;
+    }
+
+    /// Sets the security layers (SECURITY_LAYER_*) accepted by the client.
+    pub fn set_security_layers(&mut self, security_layers: u8) {
+        self.security_layers = security_layers;
+    }
+
+    /// Sets the maximum size of the wrapped messages that the client can
+    /// receive, up to [MAX_SASL_BUFFER_SIZE].
+    pub fn set_max_buffer_size(&mut self, max_buffer_size: u32) {
+        self.max_buffer_size = max_buffer_size.min(MAX_SASL_BUFFER_SIZE);
+    }
+
+    /// Processes the challenge of the server, if any, and returns the
+    /// response to send to the server, which may be empty.
+    pub fn step(&mut self, challenge: Option<&[u8]>) -> Result<Vec<u8>> {
+        let challenge = challenge.unwrap_or_default();
+        match self.state {
+            SaslState::Initial => {
+                let token = self.context.step(None)?.unwrap_or_default();
+                self.state = if self.context.is_established() {
+                    SaslState::NegotiatingSecurityLayer
+                } else {
+                    SaslState::EstablishingContext
+                };
+                return Ok(token);
+            }
+            SaslState::EstablishingContext => {
+                if challenge.is_empty() {
+                    return Err(Error::SaslError(
+                        "Empty challenge before the context is established"
+                            .to_string(),
+                    ));
+                }
+                let token = self.context.step(Some(challenge))?;
+                self.state = SaslState::NegotiatingSecurityLayer;
+                return Ok(token.unwrap_or_default());
+            }
+            SaslState::NegotiatingSecurityLayer => {
+                // The server may need more round trips to complete its
+                // side of the context
+                if challenge.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let response = self.negotiate_security_layer(challenge)?;
+                self.state = SaslState::Completed;
+                return Ok(response);
+            }
+            SaslState::Completed => {
+                return Err(Error::SaslError(
+                    "The exchange is already completed".to_string(),
+                ));
+            }
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        return self.state == SaslState::Completed;
+    }
+
+    /// Security layer (SECURITY_LAYER_*) negotiated with the server.
+    pub fn security_layer(&self) -> u8 {
+        return self.security_layer;
+    }
+
+    /// Maximum size of the wrapped messages that the server can receive.
+    pub fn peer_max_buffer_size(&self) -> u32 {
+        return self.peer_max_buffer_size;
+    }
+
+    /// Security context, available once it is established.
+    pub fn security_context(&mut self) -> Option<&mut SecurityContext> {
+        return self.context.security_context();
+    }
+
+    /// Protects a message according to the negotiated security layer.
+    /// Without security layer, the message is returned as is.
+    pub fn wrap(&mut self, message: &[u8]) -> Result<Vec<u8>> {
+        let confidentiality = self.check_security_layer()?;
+        if self.security_layer == SECURITY_LAYER_NONE {
+            return Ok(message.to_vec());
+        }
+
+        let token = self
+            .security_context()
+            .ok_or_else(not_completed)?
+            .wrap(message, confidentiality)?;
+        if token.len() > self.peer_max_buffer_size as usize {
+            return Err(Error::SaslError(format!(
+                "Wrapped message of {} bytes exceeds the maximum of {}",
+                token.len(),
+                self.peer_max_buffer_size
+            )));
+        }
+        return Ok(token);
+    }
+
+    /// Verifies a message protected according to the negotiated security
+    /// layer. Without security layer, the message is returned as is.
+    pub fn unwrap(&mut self, token: &[u8]) -> Result<Vec<u8>> {
+        let confidentiality = self.check_security_layer()?;
+        if self.security_layer == SECURITY_LAYER_NONE {
+            return Ok(token.to_vec());
+        }
+
+        let (message, encrypted) = self
+            .security_context()
+            .ok_or_else(not_completed)?
+            .unwrap(token)?;
+        if confidentiality && !encrypted {
+            return Err(Error::SaslError(
+                "Received a message without confidentiality".to_string(),
+            ));
+        }
+        return Ok(message);
+    }
+
+    /// Unwraps the security layers offered by the server and replies with
+    /// the chosen one, the maximum buffer size and the authzid.
+    fn negotiate_security_layer(
+        &mut self,
+        challenge: &[u8],
+    ) -> Result<Vec<u8>> {
+        let security_context =
+            self.context.security_context().ok_or_else(not_completed)?;
+        let (offer, _) = security_context.unwrap(challenge)?;
+        if offer.len() != 4 {
+            return Err(Error::SaslError(format!(
+                "Invalid security layer message of {} bytes",
+                offer.len()
+            )));
+        }
+
+        let offered_layers = offer[0] & self.security_layers;
+        let security_layer = [
+            SECURITY_LAYER_CONFIDENTIALITY,
+            SECURITY_LAYER_INTEGRITY,
+            SECURITY_LAYER_NONE,
+        ]
+        .iter()
+        .copied()
+        .find(|layer| offered_layers & layer != 0)
+        .ok_or_else(|| {
+            Error::SaslError(format!(
+                "No acceptable security layer in {:#04x}",
+                offer[0]
+            ))
+        })?;
+
+        let max_buffer_size = if security_layer == SECURITY_LAYER_NONE {
+            0
+        } else {
+            self.max_buffer_size
+        };
+        let mut response = max_buffer_size.to_be_bytes();
+        response[0] = security_layer;
+        let mut response = response.to_vec();
+        if let Some(authzid) = &self.authzid {
+            response.extend_from_slice(authzid.as_bytes());
+        }
+
+        let token = security_context.wrap(&response, false)?;
+        self.security_layer = security_layer;
+        self.peer_max_buffer_size =
+            u32::from_be_bytes([0, offer[1], offer[2], offer[3]]);
+        return Ok(token);
+    }
+
+    /// Returns whether the negotiated security layer requires
+    /// confidentiality, failing in case the exchange is not completed.
+    fn check_security_layer(&self) -> Result<bool> {
+        if self.state != SaslState::Completed {
+            return Err(not_completed());
+        }
+        return Ok(self.security_layer == SECURITY_LAYER_CONFIDENTIALITY);
+    }
+}
+
+fn not_completed() -> Error {
+    return Error::SaslError("The exchange is not completed".to_string());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ap::test::{new_credential, new_keytab};
+    use crate::ap::Acceptor;
+    use crate::gssapi::AcceptorContext;
+
+    fn new_client() -> SaslGssapiClient {
+        let credential = new_credential();
+        return SaslGssapiClient::new(credential);
+    }
+
+    /// Authenticates the client until the server offers its security
+    /// layers, returning the context of the server and the offer.
+    fn authenticate(
+        client: &mut SaslGssapiClient,
+        security_layers: u8,
+    ) -> (SecurityContext, Vec<u8>) {
+        let initial_response = client.step(None).unwrap();
+        let acceptor = Acceptor::new(new_keytab());
+        let server_context =
+            AcceptorContext::accept(&acceptor, &initial_response, None)
+                .unwrap();
+
+        let challenge = server_context.output_token().unwrap().to_vec();
+        assert_eq!(Vec::<u8>::new(), client.step(Some(&challenge)).unwrap());
+
+        let mut server_context = server_context.into_security_context();
+        let offer = server_context
+            .wrap(&[security_layers, 0x00, 0x10, 0x00], false)
+            .unwrap();
+        return (server_context, offer);
+    }
+
+    #[test]
+    fn bind_with_confidentiality() {
+        let mut client = new_client();
+        client.set_authzid(Some("u:mickey".to_string()));
+        let (mut server_context, offer) = authenticate(
+            &mut client,
+            SECURITY_LAYER_NONE
+                | SECURITY_LAYER_INTEGRITY
+                | SECURITY_LAYER_CONFIDENTIALITY,
+        );
+
+        let response = client.step(Some(&offer)).unwrap();
+        assert!(client.is_complete());
+        assert_eq!(SECURITY_LAYER_CONFIDENTIALITY, client.security_layer());
+        assert_eq!(0x1000, client.peer_max_buffer_size());
+
+        let (response, _) = server_context.unwrap(&response).unwrap();
+        assert_eq!(b"\x04\x01\x00\x00u:mickey".to_vec(), response);
+
+        let token = client.wrap(b"search").unwrap();
+        assert_eq!(
+            (b"search".to_vec(), true),
+            server_context.unwrap(&token).unwrap()
+        );
+        let token = server_context.wrap(b"entry", true).unwrap();
+        assert_eq!(b"entry".to_vec(), client.unwrap(&token).unwrap());
+
+        let token = server_context.wrap(b"entry", false).unwrap();
+        assert!(client.unwrap(&token).is_err());
+    }
+
+    #[test]
+    fn bind_without_security_layer() {
+        let mut client = new_client();
+        let (mut server_context, offer) =
+            authenticate(&mut client, SECURITY_LAYER_NONE);
+
+        // The server may send empty challenges before its offer
+        assert_eq!(Vec::<u8>::new(), client.step(Some(&[])).unwrap());
+        assert!(!client.is_complete());
+
+        let response = client.step(Some(&offer)).unwrap();
+        assert_eq!(SECURITY_LAYER_NONE, client.security_layer());
+        assert_eq!(
+            (vec![0x01, 0x00, 0x00, 0x00], false),
+            server_context.unwrap(&response).unwrap()
+        );
+        assert_eq!(b"search".to_vec(), client.wrap(b"search").unwrap());
+    }
+
+    #[test]
+    fn error_without_acceptable_security_layer() {
+        let mut client = new_client();
+        client.set_security_layers(SECURITY_LAYER_CONFIDENTIALITY);
+        let (_, offer) = authenticate(
+            &mut client,
+            SECURITY_LAYER_NONE | SECURITY_LAYER_INTEGRITY,
+        );
+
+        assert_eq!(
+            Error::SaslError(
+                "No acceptable security layer in 0x03".to_string()
+            ),
+            client.step(Some(&offer)).unwrap_err()
+        );
+        assert!(!client.is_complete());
+    }
+
+    #[test]
+    fn error_wrapping_before_completion() {
+        let mut client = new_client();
+        client.step(None).unwrap();
+        assert!(client.wrap(b"search").is_err());
+    }
+}
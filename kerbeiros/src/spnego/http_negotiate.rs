@@ -0,0 +1,242 @@
+use super::SpnegoInitiator;
+use crate::credentials::CredentialWarehouse;
+use crate::error::*;
+use crate::gssapi::{GssError, InitiatorContext, SecurityContext};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use himmelblau_kerberos_constants::gssapi::{
+    GSS_C_CONF_FLAG, GSS_C_INTEG_FLAG, GSS_C_MUTUAL_FLAG, GSS_C_REPLAY_FLAG,
+    GSS_C_SEQUENCE_FLAG,
+};
+
+/// Authentication scheme of the HTTP headers, defined in RFC 4559
+pub const NEGOTIATE_SCHEME: &str = "Negotiate";
+
+/// Context flags requested by [HttpNegotiator::new]
+pub const DEFAULT_HTTP_NEGOTIATE_FLAGS: u32 = GSS_C_MUTUAL_FLAG
+    | GSS_C_REPLAY_FLAG
+    | GSS_C_SEQUENCE_FLAG
+    | GSS_C_INTEG_FLAG
+    | GSS_C_CONF_FLAG;
+
+/// Produces the value of a Negotiate header, such as `Authorization`, that
+/// carries the token encoded in base64.
+pub fn encode_negotiate_header(token: &[u8]) -> String {
+    return format!("{} {}", NEGOTIATE_SCHEME, STANDARD.encode(token));
+}
+
+/// Retrieves the token of the value of a Negotiate header, such as
+/// `WWW-Authenticate`. A header without token, sent by the servers to
+/// request the authentication, returns `None`.
+pub fn decode_negotiate_header(header: &str) -> Result<Option<Vec<u8>>> {
+    let header = header.trim();
+    let (scheme, token) = header.split_once(' ').unwrap_or((header, ""));
+    if !scheme.eq_ignore_ascii_case(NEGOTIATE_SCHEME) {
+        return Err(GssError::BadMech.into());
+    }
+
+    let token = token.trim();
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let token = STANDARD
+        .decode(token)
+        .map_err(|_| GssError::DefectiveToken)?;
+    return Ok(Some(token));
+}
+
+/// Authenticates against a HTTP service with the Negotiate scheme (RFC
+/// 4559), by using the ticket for `HTTP/host` of a [CredentialWarehouse].
+///
+/// The first [authorization header](Self::authorization_header) is sent
+/// in the request. Then, the `WWW-Authenticate` header of the final
+/// response must be passed to
+/// [process_www_authenticate](Self::process_www_authenticate), in order to
+/// verify the server in case of mutual authentication.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// # let credentials: CredentialWarehouse = unimplemented!();
+/// # let www_authenticate: Option<&str> = unimplemented!();
+///
+/// let mut negotiator =
+///     HttpNegotiator::new(&credentials, "web.contoso.com").unwrap();
+/// let authorization = negotiator.authorization_header().unwrap();
+/// // send the request with the Authorization header and retrieve the
+/// // WWW-Authenticate header of the response
+/// negotiator.process_www_authenticate(www_authenticate).unwrap();
+/// ```
+pub struct HttpNegotiator {
+    spnego: SpnegoInitiator,
+}
+
+impl HttpNegotiator {
+    /// Creates a negotiator with the ticket for `HTTP/host` included in
+    /// the credentials, requesting mutual authentication.
+    pub fn new(credentials: &CredentialWarehouse, host: &str) -> Result<Self> {
+        return Self::new_with_flags(
+            credentials,
+            host,
+            DEFAULT_HTTP_NEGOTIATE_FLAGS,
+        );
+    }
+
+    /// Creates a negotiator with the ticket for `HTTP/host` included in
+    /// the credentials, requesting the given context flags (GSS_C_*_FLAG).
+    pub fn new_with_flags(
+        credentials: &CredentialWarehouse,
+        host: &str,
+        flags: u32,
+    ) -> Result<Self> {
+        let service = format!("HTTP/{}", host);
+        let credential = credentials
+            .credentials()
+            .iter()
+            .find(|credential| {
+                credential
+                    .sname()
+                    .to_string()
+                    .eq_ignore_ascii_case(&service)
+            })
+            .ok_or_else(|| {
+                Error::NotAvailableData(format!("Ticket for {}", service))
+            })?;
+
+        return Ok(Self {
+            spnego: SpnegoInitiator::new(InitiatorContext::new(
+                credential.clone(),
+                flags,
+            )),
+        });
+    }
+
+    /// Value of the `Authorization` header of the first request.
+    pub fn authorization_header(&mut self) -> Result<String> {
+        let token = self.spnego.step(None)?.ok_or(GssError::NoContext)?;
+        return Ok(encode_negotiate_header(&token));
+    }
+
+    /// Processes the `WWW-Authenticate` header of a response, if any,
+    /// returning the value of the `Authorization` header to send in the
+    /// next request, in case the server asks for it. In case of mutual
+    /// authentication, the final response must include the token of the
+    /// server.
+    pub fn process_www_authenticate(
+        &mut self,
+        header: Option<&str>,
+    ) -> Result<Option<String>> {
+        let token = match header {
+            Some(header) => decode_negotiate_header(header)?,
+            None => None,
+        };
+
+        let token = match token {
+            Some(token) => token,
+            None => {
+                if self.spnego.context().flags() & GSS_C_MUTUAL_FLAG != 0 {
+                    return Err(Error::MutualAuthenticationFailed);
+                }
+                return Ok(None);
+            }
+        };
+
+        return Ok(self
+            .spnego
+            .step(Some(&token))?
+            .map(|token| encode_negotiate_header(&token)));
+    }
+
+    pub fn is_established(&self) -> bool {
+        return self.spnego.is_established();
+    }
+
+    /// Security context, available once it is established.
+    pub fn security_context(&mut self) -> Option<&mut SecurityContext> {
+        return self.spnego.security_context();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ap::test::{
+        create_credential, new_enc_ticket_part, service_key, service_principal,
+    };
+    use crate::spnego::spnego_initiator::test::accept;
+    use himmelblau_kerberos_constants::spnego::ACCEPT_COMPLETED;
+
+    fn new_credentials() -> CredentialWarehouse {
+        let enc_ticket_part = new_enc_ticket_part();
+        let credential = create_credential(
+            service_principal("web.kingdom.hearts"),
+            &service_key(2),
+            Some(2),
+            &enc_ticket_part,
+        );
+        return CredentialWarehouse::new(
+            enc_ticket_part.crealm,
+            enc_ticket_part.cname,
+            vec![credential],
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_negotiate_header() {
+        assert_eq!("Negotiate AQID", encode_negotiate_header(&[1, 2, 3]));
+        assert_eq!(
+            Some(vec![1, 2, 3]),
+            decode_negotiate_header(" negotiate  AQID ").unwrap()
+        );
+        assert_eq!(None, decode_negotiate_header("Negotiate").unwrap());
+        assert_eq!(
+            Error::GssapiError(GssError::BadMech),
+            decode_negotiate_header("Basic AQID").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn authenticate_with_negotiate_headers() {
+        let mut negotiator =
+            HttpNegotiator::new(&new_credentials(), "WEB.kingdom.hearts")
+                .unwrap();
+        let authorization = negotiator.authorization_header().unwrap();
+        let token = decode_negotiate_header(&authorization).unwrap().unwrap();
+
+        let (_, token) = accept(&token, ACCEPT_COMPLETED, false);
+        let www_authenticate = encode_negotiate_header(&token);
+        assert_eq!(
+            None,
+            negotiator
+                .process_www_authenticate(Some(&www_authenticate))
+                .unwrap()
+        );
+        assert!(negotiator.is_established());
+    }
+
+    #[test]
+    fn error_without_www_authenticate_in_final_response() {
+        let mut negotiator =
+            HttpNegotiator::new(&new_credentials(), "web.kingdom.hearts")
+                .unwrap();
+        negotiator.authorization_header().unwrap();
+
+        assert_eq!(
+            Error::MutualAuthenticationFailed,
+            negotiator.process_www_authenticate(None).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_without_ticket_for_host() {
+        assert_eq!(
+            Error::NotAvailableData(
+                "Ticket for HTTP/other.kingdom.hearts".to_string()
+            ),
+            HttpNegotiator::new(&new_credentials(), "other.kingdom.hearts")
+                .err()
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! SPNEGO (RFC 4178), used to negotiate the Kerberos mechanism of GSS-API
+//! with the services, and the Negotiate scheme of HTTP (RFC 4559) built on
+//! top of it.
+
+mod negotiation_token;
+pub use negotiation_token::NegotiationToken;
+
+mod spnego_initiator;
+pub use spnego_initiator::SpnegoInitiator;
+
+mod http_negotiate;
+pub use http_negotiate::{
+    decode_negotiate_header, encode_negotiate_header, HttpNegotiator,
+    DEFAULT_HTTP_NEGOTIATE_FLAGS, NEGOTIATE_SCHEME,
+};
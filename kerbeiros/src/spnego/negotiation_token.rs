@@ -0,0 +1,132 @@
+use crate::error::*;
+use crate::gssapi::{
+    decode_der_length, decode_framed_token, encode_der_length,
+    encode_framed_token, GssError,
+};
+use himmelblau_kerberos_asn1::{Asn1Object, NegTokenInit, NegTokenResp};
+use himmelblau_kerberos_constants::spnego::SPNEGO_OID;
+
+/// Tag of the NegTokenInit choice, [0]
+const NEG_TOKEN_INIT_TAG: u8 = 0xa0;
+
+/// Tag of the NegTokenResp choice, [1]
+const NEG_TOKEN_RESP_TAG: u8 = 0xa1;
+
+/// Token of SPNEGO. The NegTokenInit is framed with the OID of SPNEGO, as
+/// an initial context token, whereas the NegTokenResp is sent as is.
+/// Defined in RFC4178, section 4.2.
+/// ```asn1
+/// NegotiationToken ::= CHOICE {
+///     negTokenInit    [0] NegTokenInit,
+///     negTokenResp    [1] NegTokenResp
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum NegotiationToken {
+    Init(NegTokenInit),
+    Resp(NegTokenResp),
+}
+
+impl NegotiationToken {
+    pub fn build(&self) -> Vec<u8> {
+        match self {
+            NegotiationToken::Init(neg_token_init) => {
+                return encode_framed_token(
+                    SPNEGO_OID,
+                    &tagged(NEG_TOKEN_INIT_TAG, &neg_token_init.build()),
+                );
+            }
+            NegotiationToken::Resp(neg_token_resp) => {
+                return tagged(NEG_TOKEN_RESP_TAG, &neg_token_resp.build());
+            }
+        }
+    }
+
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        if raw.first() == Some(&NEG_TOKEN_RESP_TAG) {
+            let (_, neg_token_resp) =
+                NegTokenResp::parse(untagged(NEG_TOKEN_RESP_TAG, raw)?)
+                    .map_err(|_| GssError::DefectiveToken)?;
+            return Ok(NegotiationToken::Resp(neg_token_resp));
+        }
+
+        let (mech_oid, inner_token) = decode_framed_token(raw)?;
+        if mech_oid != SPNEGO_OID {
+            return Err(GssError::BadMech.into());
+        }
+        let (_, neg_token_init) =
+            NegTokenInit::parse(untagged(NEG_TOKEN_INIT_TAG, inner_token)?)
+                .map_err(|_| GssError::DefectiveToken)?;
+        return Ok(NegotiationToken::Init(neg_token_init));
+    }
+}
+
+fn tagged(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut raw = vec![tag];
+    raw.append(&mut encode_der_length(value.len()));
+    raw.extend_from_slice(value);
+    return raw;
+}
+
+fn untagged(tag: u8, raw: &[u8]) -> Result<&[u8]> {
+    if raw.first() != Some(&tag) {
+        return Err(GssError::DefectiveToken.into());
+    }
+    let (length, value) = decode_der_length(&raw[1..])?;
+    if length != value.len() {
+        return Err(GssError::DefectiveToken.into());
+    }
+    return Ok(value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{Enumerated, Oid};
+    use himmelblau_kerberos_constants::spnego::ACCEPT_COMPLETED;
+
+    #[test]
+    fn build_and_parse_neg_token_init() {
+        let token = NegotiationToken::Init(NegTokenInit::new(
+            vec![Oid::new(1, 2, vec![840, 113554, 1, 2, 2])],
+            Some(vec![0x01, 0x02]),
+        ));
+        let raw = token.build();
+
+        assert_eq!(
+            vec![
+                0x60, 0x21, 0x06, 0x06, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x02,
+                0xa0, 0x17, 0x30, 0x15, 0xa0, 0x0d, 0x30, 0x0b, 0x06, 0x09,
+                0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02, 0xa2,
+                0x04, 0x04, 0x02, 0x01, 0x02,
+            ],
+            raw
+        );
+        assert_eq!(token, NegotiationToken::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn build_and_parse_neg_token_resp() {
+        let token = NegotiationToken::Resp(NegTokenResp {
+            neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+            ..Default::default()
+        });
+        let raw = token.build();
+
+        assert_eq!(
+            vec![0xa1, 0x07, 0x30, 0x05, 0xa0, 0x03, 0x0a, 0x01, 0x00],
+            raw
+        );
+        assert_eq!(token, NegotiationToken::parse(&raw).unwrap());
+    }
+
+    #[test]
+    fn error_parsing_token_of_other_mechanism() {
+        let raw =
+            encode_framed_token(&[0x06, 0x01, 0x00], &[0xa0, 0x02, 0x30, 0x00]);
+        assert_eq!(
+            Error::GssapiError(GssError::BadMech),
+            NegotiationToken::parse(&raw).unwrap_err()
+        );
+    }
+}
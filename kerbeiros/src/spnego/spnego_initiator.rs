@@ -0,0 +1,299 @@
+use super::NegotiationToken;
+use crate::error::*;
+use crate::gssapi::{GssError, InitiatorContext, SecurityContext};
+use himmelblau_kerberos_asn1::{
+    Asn1Object, Enumerated, NegTokenInit, NegTokenResp, Oid,
+};
+use himmelblau_kerberos_constants::spnego::{
+    ACCEPT_COMPLETED, ACCEPT_INCOMPLETE, REJECT, REQUEST_MIC,
+};
+
+/// Negotiates the Kerberos mechanism with SPNEGO (RFC 4178) from the side
+/// of the initiator, wrapping the tokens of an [InitiatorContext].
+///
+/// Both the OID of Kerberos used by Microsoft and the standard one are
+/// offered, as Windows does. The mechListMIC sent by the acceptor is
+/// verified, and the one of the initiator is sent when requested.
+///
+/// # Examples
+///
+/// ```no_run
+/// use himmelblau_kerbeiros::*;
+/// use himmelblau_kerberos_constants::gssapi::*;
+/// # let credential: Credential = unimplemented!();
+/// # let acceptor_token: Vec<u8> = unimplemented!();
+///
+/// let mut spnego =
+///     SpnegoInitiator::new(InitiatorContext::new(credential, GSS_C_MUTUAL_FLAG));
+/// let initial_token = spnego.step(None).unwrap().unwrap();
+/// // send initial_token to the acceptor and receive acceptor_token
+/// spnego.step(Some(&acceptor_token)).unwrap();
+/// assert!(spnego.is_established());
+/// ```
+pub struct SpnegoInitiator {
+    context: InitiatorContext,
+    mech_types: Vec<Oid>,
+    initial_token_sent: bool,
+    completed: bool,
+}
+
+impl SpnegoInitiator {
+    pub fn new(context: InitiatorContext) -> Self {
+        return Self {
+            context,
+            mech_types: vec![
+                Oid::new(1, 2, vec![840, 48018, 1, 2, 2]),
+                Oid::new(1, 2, vec![840, 113554, 1, 2, 2]),
+            ],
+            initial_token_sent: false,
+            completed: false,
+        };
+    }
+
+    /// Mechanisms offered to the acceptor, ordered by preference.
+    pub fn mech_types(&self) -> &[Oid] {
+        return &self.mech_types;
+    }
+
+    /// Processes the token received from the acceptor, if any, and returns
+    /// the token to send to the acceptor, if any.
+    pub fn step(
+        &mut self,
+        input_token: Option<&[u8]>,
+    ) -> Result<Option<Vec<u8>>> {
+        if self.completed {
+            return Err(GssError::DefectiveToken.into());
+        }
+
+        if !self.initial_token_sent {
+            let mech_token = self.context.step(None)?;
+            self.initial_token_sent = true;
+            let neg_token_init =
+                NegTokenInit::new(self.mech_types.clone(), mech_token);
+            return Ok(Some(NegotiationToken::Init(neg_token_init).build()));
+        }
+
+        let input_token = input_token.ok_or(GssError::DefectiveToken)?;
+        let neg_token_resp = match NegotiationToken::parse(input_token)? {
+            NegotiationToken::Resp(neg_token_resp) => neg_token_resp,
+            NegotiationToken::Init(_) => {
+                return Err(GssError::DefectiveToken.into());
+            }
+        };
+        return self.process_neg_token_resp(neg_token_resp);
+    }
+
+    /// Whether the negotiation is completed and the Kerberos context is
+    /// established.
+    pub fn is_established(&self) -> bool {
+        return self.completed;
+    }
+
+    pub fn context(&self) -> &InitiatorContext {
+        return &self.context;
+    }
+
+    /// Security context, available once it is established.
+    pub fn security_context(&mut self) -> Option<&mut SecurityContext> {
+        if !self.completed {
+            return None;
+        }
+        return self.context.security_context();
+    }
+
+    pub fn into_security_context(self) -> Option<SecurityContext> {
+        if !self.completed {
+            return None;
+        }
+        return self.context.into_security_context();
+    }
+
+    fn process_neg_token_resp(
+        &mut self,
+        neg_token_resp: NegTokenResp,
+    ) -> Result<Option<Vec<u8>>> {
+        if let Some(supported_mech) = &neg_token_resp.supported_mech {
+            if !self.mech_types.contains(supported_mech) {
+                return Err(GssError::BadMech.into());
+            }
+        }
+
+        // The token may be a KRB-ERROR, so it is processed before checking
+        // the state
+        if let Some(response_token) = &neg_token_resp.response_token {
+            if self.context.is_established() {
+                return Err(GssError::DefectiveToken.into());
+            }
+            self.context.step(Some(response_token))?;
+        }
+
+        let neg_state = neg_token_resp
+            .neg_state
+            .map(|neg_state| *neg_state)
+            .unwrap_or(ACCEPT_INCOMPLETE);
+        match neg_state {
+            ACCEPT_COMPLETED | REQUEST_MIC => {}
+            REJECT => return Err(GssError::BadMech.into()),
+            _ => return Ok(None),
+        }
+
+        // The acceptor did not send the AP-REP required to authenticate it
+        if !self.context.is_established() {
+            return Err(Error::MutualAuthenticationFailed);
+        }
+
+        let raw_mech_types = self.mech_types.build();
+        let security_context =
+            self.context.security_context().ok_or(GssError::NoContext)?;
+        if let Some(mech_list_mic) = &neg_token_resp.mech_list_mic {
+            security_context.verify_mic(&raw_mech_types, mech_list_mic)?;
+        }
+
+        let output_token = if neg_state == REQUEST_MIC {
+            let neg_token_resp = NegTokenResp {
+                neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+                mech_list_mic: Some(security_context.get_mic(&raw_mech_types)?),
+                ..Default::default()
+            };
+            Some(NegotiationToken::Resp(neg_token_resp).build())
+        } else {
+            None
+        };
+        self.completed = true;
+        return Ok(output_token);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::ap::test::{new_credential, new_keytab};
+    use crate::ap::Acceptor;
+    use crate::gssapi::AcceptorContext;
+    use himmelblau_kerberos_constants::gssapi::{
+        GSS_C_INTEG_FLAG, GSS_C_MUTUAL_FLAG,
+    };
+
+    /// Accepts the NegTokenInit as a SPNEGO acceptor, replying with a
+    /// NegTokenResp of the given state.
+    pub fn accept(
+        token: &[u8],
+        neg_state: i32,
+        with_mech_list_mic: bool,
+    ) -> (AcceptorContext, Vec<u8>) {
+        let neg_token_init = match NegotiationToken::parse(token).unwrap() {
+            NegotiationToken::Init(neg_token_init) => neg_token_init,
+            NegotiationToken::Resp(_) => panic!("Expected NegTokenInit"),
+        };
+        let acceptor = Acceptor::new(new_keytab());
+        let mut context = AcceptorContext::accept(
+            &acceptor,
+            &neg_token_init.mech_token.unwrap(),
+            None,
+        )
+        .unwrap();
+
+        let mech_list_mic = if with_mech_list_mic {
+            Some(
+                context
+                    .security_context()
+                    .get_mic(&neg_token_init.mech_types.build())
+                    .unwrap(),
+            )
+        } else {
+            None
+        };
+        let neg_token_resp = NegTokenResp {
+            neg_state: Some(Enumerated::new(neg_state)),
+            supported_mech: Some(neg_token_init.mech_types[0].clone()),
+            response_token: context.output_token().map(|token| token.to_vec()),
+            mech_list_mic,
+        };
+        return (context, NegotiationToken::Resp(neg_token_resp).build());
+    }
+
+    pub fn new_initiator_context(flags: u32) -> InitiatorContext {
+        let credential = new_credential();
+        return InitiatorContext::new(credential, flags);
+    }
+
+    #[test]
+    fn negotiate_kerberos_with_mutual_authentication() {
+        let mut spnego =
+            SpnegoInitiator::new(new_initiator_context(GSS_C_MUTUAL_FLAG));
+        let initial_token = spnego.step(None).unwrap().unwrap();
+
+        let (mut acceptor_context, token) =
+            accept(&initial_token, ACCEPT_COMPLETED, false);
+        assert_eq!(None, spnego.step(Some(&token)).unwrap());
+        assert!(spnego.is_established());
+
+        let token = acceptor_context
+            .security_context()
+            .wrap(b"reply", true)
+            .unwrap();
+        assert_eq!(
+            (b"reply".to_vec(), true),
+            spnego.security_context().unwrap().unwrap(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn exchange_mech_list_mic() {
+        let mut spnego =
+            SpnegoInitiator::new(new_initiator_context(GSS_C_MUTUAL_FLAG));
+        let initial_token = spnego.step(None).unwrap().unwrap();
+
+        let (mut acceptor_context, token) =
+            accept(&initial_token, REQUEST_MIC, true);
+        let token = spnego.step(Some(&token)).unwrap().unwrap();
+        assert!(spnego.is_established());
+
+        let neg_token_resp = match NegotiationToken::parse(&token).unwrap() {
+            NegotiationToken::Resp(neg_token_resp) => neg_token_resp,
+            NegotiationToken::Init(_) => panic!("Expected NegTokenResp"),
+        };
+        acceptor_context
+            .security_context()
+            .verify_mic(
+                &spnego.mech_types().to_vec().build(),
+                &neg_token_resp.mech_list_mic.unwrap(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn error_without_token_of_acceptor() {
+        let mut spnego =
+            SpnegoInitiator::new(new_initiator_context(GSS_C_MUTUAL_FLAG));
+        spnego.step(None).unwrap();
+
+        let token = NegotiationToken::Resp(NegTokenResp {
+            neg_state: Some(Enumerated::new(ACCEPT_COMPLETED)),
+            ..Default::default()
+        })
+        .build();
+        assert_eq!(
+            Error::MutualAuthenticationFailed,
+            spnego.step(Some(&token)).unwrap_err()
+        );
+        assert!(!spnego.is_established());
+    }
+
+    #[test]
+    fn error_with_rejected_negotiation() {
+        let mut spnego =
+            SpnegoInitiator::new(new_initiator_context(GSS_C_INTEG_FLAG));
+        spnego.step(None).unwrap();
+
+        let token = NegotiationToken::Resp(NegTokenResp {
+            neg_state: Some(Enumerated::new(REJECT)),
+            ..Default::default()
+        })
+        .build();
+        assert_eq!(
+            Error::GssapiError(GssError::BadMech),
+            spnego.step(Some(&token)).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use super::super::failover_transporter::{
+    kdc_addresses, unresolved_kdcs_error,
+};
+use super::super::{TransportProtocol, TransportSettings};
+use super::new_async_transporter_with_settings;
+use super::transporter_trait::*;
+use crate::discovery::{DnsResolver, KdcEndpoint};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+/// Sends Kerberos messages asynchronously to a list of KDCs, trying them in
+/// order until one responds. The host names are resolved in a blocking
+/// task, since the resolvers are synchronous.
+pub struct AsyncFailoverTransporter {
+    kdcs: Vec<KdcEndpoint>,
+    resolver: Arc<dyn DnsResolver>,
+    new_transporter:
+        Box<dyn Fn(SocketAddr) -> Box<dyn AsyncTransporter> + Send + Sync>,
+    last_kdc_address: Mutex<Option<SocketAddr>>,
+}
+
+impl AsyncFailoverTransporter {
+    pub fn new(
+        kdcs: Vec<KdcEndpoint>,
+        resolver: Arc<dyn DnsResolver>,
+        transport_protocol: TransportProtocol,
+        settings: TransportSettings,
+    ) -> Self {
+        return Self {
+            kdcs,
+            resolver,
+            new_transporter: Box::new(move |kdc_address| {
+                return new_async_transporter_with_settings(
+                    kdc_address,
+                    transport_protocol,
+                    &settings,
+                );
+            }),
+            last_kdc_address: Mutex::new(None),
+        };
+    }
+
+    #[cfg(test)]
+    fn set_new_transporter(
+        &mut self,
+        new_transporter: Box<
+            dyn Fn(SocketAddr) -> Box<dyn AsyncTransporter> + Send + Sync,
+        >,
+    ) {
+        self.new_transporter = new_transporter;
+    }
+
+    async fn kdc_addresses(&self) -> Vec<SocketAddr> {
+        let kdcs = self.kdcs.clone();
+        let resolver = self.resolver.clone();
+        let last_kdc_address = self.last_kdc_address();
+
+        return tokio::task::spawn_blocking(move || {
+            return kdc_addresses(&kdcs, &*resolver, last_kdc_address);
+        })
+        .await
+        .unwrap_or_default();
+    }
+}
+
+#[async_trait]
+impl AsyncTransporter for AsyncFailoverTransporter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        let addresses = self.kdc_addresses().await;
+        if addresses.is_empty() {
+            return Err(unresolved_kdcs_error(&self.kdcs));
+        }
+
+        let mut last_error = Error::NetworkError;
+        for address in addresses {
+            match (self.new_transporter)(address)
+                .request_and_response(raw_request)
+                .await
+            {
+                Ok(raw_response) => {
+                    *self.last_kdc_address.lock().unwrap() = Some(address);
+                    return Ok(raw_response);
+                }
+                Err(error) => {
+                    last_error = error;
+                }
+            }
+        }
+
+        return Err(last_error);
+    }
+
+    fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return *self.last_kdc_address.lock().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::test::FakeDnsResolver;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// KDC that responds with its own address, unless it is down
+    struct FakeKdc {
+        address: SocketAddr,
+        down: bool,
+    }
+
+    #[async_trait]
+    impl AsyncTransporter for FakeKdc {
+        async fn request_and_response(
+            &self,
+            _raw_request: &[u8],
+        ) -> Result<Vec<u8>> {
+            if self.down {
+                return Err(Error::NetworkError);
+            }
+            return Ok(self.address.to_string().into_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn request_first_responsive_kdc() {
+        let kdc1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let kdc2 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let mut resolver = FakeDnsResolver::default();
+        resolver
+            .ip_records
+            .insert("kdc1.kingdom.hearts".to_string(), vec![kdc1]);
+
+        let mut transporter = AsyncFailoverTransporter::new(
+            vec![
+                KdcEndpoint::new("unknown.kingdom.hearts", 88),
+                KdcEndpoint::new("kdc1.kingdom.hearts", 88),
+                KdcEndpoint::from(kdc2),
+            ],
+            Arc::new(resolver),
+            TransportProtocol::TCP,
+            TransportSettings::default(),
+        );
+        transporter.set_new_transporter(Box::new(move |address| {
+            return Box::new(FakeKdc {
+                address,
+                down: address.ip() == kdc1,
+            });
+        }));
+
+        assert_eq!(
+            b"10.0.0.2:88".to_vec(),
+            transporter.request_and_response(&[0]).await.unwrap()
+        );
+        assert_eq!(
+            Some(SocketAddr::new(kdc2, 88)),
+            transporter.last_kdc_address()
+        );
+    }
+
+    #[tokio::test]
+    async fn error_when_no_kdc_is_resolved() {
+        let transporter = AsyncFailoverTransporter::new(
+            vec![KdcEndpoint::new("unknown.kingdom.hearts", 88)],
+            Arc::new(FakeDnsResolver::default()),
+            TransportProtocol::TCP,
+            TransportSettings::default(),
+        );
+
+        assert_eq!(
+            Error::NameResolutionError("unknown.kingdom.hearts:88".to_string()),
+            transporter.request_and_response(&[0]).await.unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use super::super::fallback_transporter::TcpFallbackTransporter;
+use super::transporter_trait::*;
+use crate::config::DEFAULT_UDP_PREFERENCE_LIMIT;
+use crate::Result;
+use async_trait::async_trait;
+
+/// Sends Kerberos messages over UDP asynchronously, retrying over TCP when
+/// the KDC responds with KRB_ERR_RESPONSE_TOO_BIG. Messages bigger than
+/// the UDP preference limit are directly sent over TCP.
+pub struct AsyncTcpFallbackTransporter {
+    udp_transporter: Box<dyn AsyncTransporter>,
+    tcp_transporter: Box<dyn AsyncTransporter>,
+    udp_preference_limit: usize,
+}
+
+impl AsyncTcpFallbackTransporter {
+    pub fn new(
+        udp_transporter: Box<dyn AsyncTransporter>,
+        tcp_transporter: Box<dyn AsyncTransporter>,
+    ) -> Self {
+        return Self {
+            udp_transporter,
+            tcp_transporter,
+            udp_preference_limit: DEFAULT_UDP_PREFERENCE_LIMIT,
+        };
+    }
+
+    pub fn set_udp_preference_limit(&mut self, udp_preference_limit: usize) {
+        self.udp_preference_limit = udp_preference_limit;
+    }
+}
+
+#[async_trait]
+impl AsyncTransporter for AsyncTcpFallbackTransporter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        if raw_request.len() > self.udp_preference_limit {
+            return self
+                .tcp_transporter
+                .request_and_response(raw_request)
+                .await;
+        }
+
+        let raw_response = self
+            .udp_transporter
+            .request_and_response(raw_request)
+            .await?;
+
+        if TcpFallbackTransporter::is_response_too_big(&raw_response) {
+            return self
+                .tcp_transporter
+                .request_and_response(raw_request)
+                .await;
+        }
+
+        return Ok(raw_response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+    use himmelblau_kerberos_constants::error_codes::KRB_ERR_RESPONSE_TOO_BIG;
+
+    struct FakeTransporter {
+        response: Vec<u8>,
+    }
+
+    #[async_trait]
+    impl AsyncTransporter for FakeTransporter {
+        async fn request_and_response(
+            &self,
+            _raw_request: &[u8],
+        ) -> Result<Vec<u8>> {
+            return Ok(self.response.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_over_tcp_when_response_is_too_big() {
+        let krb_error = KrbError {
+            pvno: 5,
+            msg_type: 30,
+            error_code: KRB_ERR_RESPONSE_TOO_BIG,
+            ..Default::default()
+        };
+        let transporter = AsyncTcpFallbackTransporter::new(
+            Box::new(FakeTransporter {
+                response: krb_error.build(),
+            }),
+            Box::new(FakeTransporter {
+                response: vec![0x74, 0x63, 0x70],
+            }),
+        );
+
+        assert_eq!(
+            vec![0x74, 0x63, 0x70],
+            transporter.request_and_response(&[0]).await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use super::super::kdc_proxy_transporter::KdcProxyTransporter;
+use super::super::{TransportSettings, Transporter};
+use super::transporter_trait::*;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Sends Kerberos messages asynchronously to a KDC proxy (MS-KKDCP). The
+/// HTTP requests are sent from a blocking task, since the HTTP client is
+/// synchronous.
+pub struct AsyncKdcProxyTransporter {
+    transporter: Arc<KdcProxyTransporter>,
+}
+
+impl AsyncKdcProxyTransporter {
+    /// Creates a transporter with the proxy URL, DC locator hint and
+    /// timeouts of the settings.
+    pub fn new(settings: &TransportSettings) -> Self {
+        return Self {
+            transporter: Arc::new(KdcProxyTransporter::new(settings)),
+        };
+    }
+}
+
+#[async_trait]
+impl AsyncTransporter for AsyncKdcProxyTransporter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        let transporter = self.transporter.clone();
+        let raw_request = raw_request.to_vec();
+        return tokio::task::spawn_blocking(move || {
+            return transporter.request_and_response(&raw_request);
+        })
+        .await
+        .map_err(|_| Error::NetworkError)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::kdc_proxy_transporter::test::{
+        new_as_req, proxy_settings, spawn_kdc_proxy,
+    };
+    use super::*;
+    use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+
+    #[tokio::test]
+    async fn request_through_kdc_proxy() {
+        let url = spawn_kdc_proxy("KINGDOM.HEARTS", None);
+        let transporter = AsyncKdcProxyTransporter::new(&proxy_settings(&url));
+
+        let raw_response = transporter
+            .request_and_response(&new_as_req("KINGDOM.HEARTS"))
+            .await
+            .unwrap();
+
+        assert_eq!(30, KrbError::parse(&raw_response).unwrap().1.msg_type);
+    }
+}
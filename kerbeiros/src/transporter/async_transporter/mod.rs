@@ -0,0 +1,78 @@
+//! Asynchronous counterparts of the transporters, based on tokio
+//!
+
+use super::{TransportProtocol, TransportSettings};
+use crate::discovery::{DnsResolver, KdcEndpoint};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+mod transporter_trait;
+pub use transporter_trait::*;
+
+mod tcp_transporter;
+use tcp_transporter::*;
+
+mod udp_transporter;
+use udp_transporter::*;
+
+mod fallback_transporter;
+use fallback_transporter::*;
+
+mod failover_transporter;
+pub use failover_transporter::*;
+
+mod kdc_proxy_transporter;
+use kdc_proxy_transporter::*;
+
+/// Generates an async transporter which sends the messages to the first
+/// KDC of the list that responds or, in case of the KdcProxy protocol, to
+/// the KDC proxy of the settings.
+pub fn new_async_kdcs_transporter(
+    kdcs: Vec<KdcEndpoint>,
+    resolver: Arc<dyn DnsResolver>,
+    transport_protocol: TransportProtocol,
+    settings: TransportSettings,
+) -> Box<dyn AsyncTransporter> {
+    if transport_protocol == TransportProtocol::KdcProxy {
+        return Box::new(AsyncKdcProxyTransporter::new(&settings));
+    }
+    return Box::new(AsyncFailoverTransporter::new(
+        kdcs,
+        resolver,
+        transport_protocol,
+        settings,
+    ));
+}
+
+/// Generates an async transporter given the address, the transport
+/// protocol and the settings of the connection.
+pub fn new_async_transporter_with_settings(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+    settings: &TransportSettings,
+) -> Box<dyn AsyncTransporter> {
+    let mut tcp_transporter = AsyncTCPTransporter::new(dst_addr);
+    tcp_transporter.set_connect_timeout(settings.connect_timeout);
+    tcp_transporter.set_read_timeout(settings.read_timeout);
+
+    match transport_protocol {
+        TransportProtocol::TCP => {
+            return Box::new(tcp_transporter);
+        }
+        TransportProtocol::UDP => {
+            let mut udp_transporter = AsyncUDPTransporter::new(dst_addr);
+            udp_transporter.set_timeout(settings.udp_timeout);
+            udp_transporter.set_retries(settings.udp_retries);
+
+            let mut transporter = AsyncTcpFallbackTransporter::new(
+                Box::new(udp_transporter),
+                Box::new(tcp_transporter),
+            );
+            transporter.set_udp_preference_limit(settings.udp_preference_limit);
+            return Box::new(transporter);
+        }
+        TransportProtocol::KdcProxy => {
+            return Box::new(AsyncKdcProxyTransporter::new(settings));
+        }
+    }
+}
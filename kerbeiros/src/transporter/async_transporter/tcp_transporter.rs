@@ -0,0 +1,136 @@
+use super::super::tcp_transporter::{
+    TCPTransporter, DEFAULT_TCP_CONNECT_TIMEOUT, DEFAULT_TCP_READ_TIMEOUT,
+};
+use super::transporter_trait::*;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Send Kerberos messages over TCP asynchronously
+#[derive(Debug)]
+pub struct AsyncTCPTransporter {
+    dst_addr: SocketAddr,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+}
+
+impl AsyncTCPTransporter {
+    pub fn new(dst_addr: SocketAddr) -> Self {
+        return Self {
+            dst_addr,
+            connect_timeout: DEFAULT_TCP_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_TCP_READ_TIMEOUT,
+        };
+    }
+
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Sets the maximum time to wait in each read or write operation
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
+    }
+
+    async fn request_and_response_tcp(
+        &self,
+        raw_request: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let mut tcp_stream =
+            timeout(self.connect_timeout, TcpStream::connect(self.dst_addr))
+                .await?;
+
+        let raw_sized_request =
+            TCPTransporter::set_size_header_to_request(raw_request);
+        timeout(self.read_timeout, tcp_stream.write_all(&raw_sized_request))
+            .await?;
+
+        let mut len_data_bytes = [0_u8; 4];
+        timeout(
+            self.read_timeout,
+            tcp_stream.read_exact(&mut len_data_bytes),
+        )
+        .await?;
+        let data_length = u32::from_be_bytes(len_data_bytes);
+
+        let mut raw_response: Vec<u8> = vec![0; data_length as usize];
+        timeout(self.read_timeout, tcp_stream.read_exact(&mut raw_response))
+            .await?;
+
+        return Ok(raw_response);
+    }
+}
+
+/// Waits for the i/o operation, failing with TimedOut when it does not
+/// finish in time.
+async fn timeout<T>(
+    duration: Duration,
+    operation: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    match tokio::time::timeout(duration, operation).await {
+        Ok(result) => {
+            return result;
+        }
+        Err(_) => {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncTransporter for AsyncTCPTransporter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        let raw_response = self
+            .request_and_response_tcp(raw_request)
+            .await
+            .map_err(|_| Error::NetworkError)?;
+        return Ok(raw_response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn request_and_receive_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let transporter =
+            AsyncTCPTransporter::new(listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut raw_request = [0; 5];
+            stream.read_exact(&mut raw_request).await.unwrap();
+            assert_eq!([0, 0, 0, 1, 0x01], raw_request);
+            stream.write_all(&[0, 0, 0, 2, 0x02, 0x03]).await.unwrap();
+        });
+
+        assert_eq!(
+            vec![0x02, 0x03],
+            transporter.request_and_response(&[0x01]).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn error_when_response_is_not_received_in_time() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut transporter =
+            AsyncTCPTransporter::new(listener.local_addr().unwrap());
+        transporter.set_read_timeout(Duration::from_millis(50));
+
+        assert_eq!(
+            Error::NetworkError,
+            transporter.request_and_response(&[0x01]).await.unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::*;
+use async_trait::async_trait;
+use std::net::SocketAddr;
+
+/// Trait implemented by classes which deliver Kerberos messages
+/// asynchronously
+#[async_trait]
+pub trait AsyncTransporter: Send + Sync {
+    /// Sends a message and retrieves the response
+    async fn request_and_response(&self, raw_request: &[u8])
+        -> Result<Vec<u8>>;
+
+    /// Address of the KDC that answered the last request, if known
+    fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return None;
+    }
+}
+
+/// Adapts a synchronous transporter, in order to reuse the fake KDCs of
+/// the synchronous tests
+#[cfg(test)]
+pub(crate) struct SyncTransporterAdapter(
+    pub Box<dyn super::super::Transporter>,
+);
+
+#[cfg(test)]
+#[async_trait]
+impl AsyncTransporter for SyncTransporterAdapter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        return self.0.request_and_response(raw_request);
+    }
+}
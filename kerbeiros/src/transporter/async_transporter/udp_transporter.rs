@@ -0,0 +1,156 @@
+use super::super::udp_transporter::{
+    next_udp_timeout, DEFAULT_UDP_RETRIES, DEFAULT_UDP_TIMEOUT,
+    MAX_UDP_RESPONSE_SIZE,
+};
+use super::transporter_trait::*;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Send Kerberos messages over UDP asynchronously. The request is
+/// retransmitted when no response is received in time, doubling the
+/// timeout in each attempt up to 30 seconds.
+#[derive(Debug)]
+pub struct AsyncUDPTransporter {
+    dst_addr: SocketAddr,
+    timeout: Duration,
+    retries: u32,
+    max_response_size: usize,
+}
+
+impl AsyncUDPTransporter {
+    pub fn new(dst_addr: SocketAddr) -> Self {
+        return Self {
+            dst_addr,
+            timeout: DEFAULT_UDP_TIMEOUT,
+            retries: DEFAULT_UDP_RETRIES,
+            max_response_size: MAX_UDP_RESPONSE_SIZE,
+        };
+    }
+
+    /// Sets the timeout of the first attempt
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the number of retransmissions after the first attempt
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    async fn connect(dst_addr: SocketAddr) -> Result<UdpSocket> {
+        let bind_addr = match dst_addr {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let udp_socket = UdpSocket::bind(bind_addr)
+            .await
+            .map_err(|_| Error::NetworkError)?;
+        udp_socket
+            .connect(dst_addr)
+            .await
+            .map_err(|_| Error::NetworkError)?;
+        return Ok(udp_socket);
+    }
+}
+
+#[async_trait]
+impl AsyncTransporter for AsyncUDPTransporter {
+    async fn request_and_response(
+        &self,
+        raw_request: &[u8],
+    ) -> Result<Vec<u8>> {
+        let udp_socket = Self::connect(self.dst_addr).await?;
+
+        // one extra byte to detect responses bigger than the limit
+        let mut raw_response = vec![0; self.max_response_size + 1];
+
+        let mut timeout = self.timeout;
+        for _ in 0..=self.retries {
+            udp_socket
+                .send(raw_request)
+                .await
+                .map_err(|_| Error::NetworkError)?;
+
+            match tokio::time::timeout(
+                timeout,
+                udp_socket.recv(&mut raw_response),
+            )
+            .await
+            {
+                Err(_) => {
+                    timeout = next_udp_timeout(timeout);
+                }
+                Ok(Err(_)) => {
+                    return Err(Error::NetworkError);
+                }
+                Ok(Ok(data_length)) => {
+                    if data_length > self.max_response_size {
+                        return Err(Error::ResponseTooLarge(
+                            self.max_response_size,
+                        ));
+                    }
+                    raw_response.truncate(data_length);
+                    return Ok(raw_response);
+                }
+            }
+        }
+
+        return Err(Error::NetworkError);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawns a KDC that ignores the first `ignored` requests and
+    /// responds to the next one with the given response
+    async fn spawn_kdc(ignored: usize, response: Vec<u8>) -> SocketAddr {
+        let kdc_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let kdc_addr = kdc_socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buffer = [0; 1024];
+            for _ in 0..ignored {
+                kdc_socket.recv_from(&mut buffer).await.unwrap();
+            }
+            let (_, client_addr) =
+                kdc_socket.recv_from(&mut buffer).await.unwrap();
+            kdc_socket.send_to(&response, client_addr).await.unwrap();
+        });
+
+        return kdc_addr;
+    }
+
+    fn new_test_transporter(kdc_addr: SocketAddr) -> AsyncUDPTransporter {
+        let mut transporter = AsyncUDPTransporter::new(kdc_addr);
+        transporter.set_timeout(Duration::from_millis(50));
+        return transporter;
+    }
+
+    #[tokio::test]
+    async fn retransmit_request_without_response() {
+        let kdc_addr = spawn_kdc(2, vec![1, 2, 3]).await;
+        let transporter = new_test_transporter(kdc_addr);
+
+        assert_eq!(
+            vec![1, 2, 3],
+            transporter.request_and_response(&[0]).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn error_when_retries_are_exhausted() {
+        let kdc_addr = spawn_kdc(2, vec![1, 2, 3]).await;
+        let mut transporter = new_test_transporter(kdc_addr);
+        transporter.set_retries(1);
+
+        assert_eq!(
+            Error::NetworkError,
+            transporter.request_and_response(&[0]).await.unwrap_err()
+        );
+    }
+}
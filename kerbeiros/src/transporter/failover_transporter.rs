@@ -0,0 +1,252 @@
+use super::transporter_trait::*;
+use super::{
+    new_transporter_with_settings, TransportProtocol, TransportSettings,
+};
+use crate::discovery::{DnsResolver, KdcEndpoint};
+use crate::{Error, Result};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+/// Sends Kerberos messages to a list of KDCs, trying them in order until
+/// one responds. The host names are resolved in each request, and the KDC
+/// that responded last is tried first in the following requests.
+pub struct FailoverTransporter {
+    kdcs: Vec<KdcEndpoint>,
+    resolver: Arc<dyn DnsResolver>,
+    new_transporter:
+        Box<dyn Fn(SocketAddr) -> Box<dyn Transporter> + Send + Sync>,
+    last_kdc_address: Mutex<Option<SocketAddr>>,
+}
+
+impl FailoverTransporter {
+    pub fn new(
+        kdcs: Vec<KdcEndpoint>,
+        resolver: Arc<dyn DnsResolver>,
+        transport_protocol: TransportProtocol,
+        settings: TransportSettings,
+    ) -> Self {
+        return Self {
+            kdcs,
+            resolver,
+            new_transporter: Box::new(move |kdc_address| {
+                return new_transporter_with_settings(
+                    kdc_address,
+                    transport_protocol,
+                    &settings,
+                );
+            }),
+            last_kdc_address: Mutex::new(None),
+        };
+    }
+
+    #[cfg(test)]
+    fn set_new_transporter(
+        &mut self,
+        new_transporter: Box<
+            dyn Fn(SocketAddr) -> Box<dyn Transporter> + Send + Sync,
+        >,
+    ) {
+        self.new_transporter = new_transporter;
+    }
+}
+
+/// Resolves the addresses of the KDCs, skipping the host names that cannot
+/// be resolved, starting by the last one that responded.
+pub(crate) fn kdc_addresses(
+    kdcs: &[KdcEndpoint],
+    resolver: &dyn DnsResolver,
+    last_kdc_address: Option<SocketAddr>,
+) -> Vec<SocketAddr> {
+    let mut addresses: Vec<SocketAddr> = Vec::new();
+    for kdc in kdcs.iter() {
+        if let Ok(kdc_addresses) = kdc.resolve(resolver) {
+            for address in kdc_addresses {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+    }
+
+    if let Some(last_kdc_address) = last_kdc_address {
+        if let Some(position) =
+            addresses.iter().position(|a| *a == last_kdc_address)
+        {
+            addresses[..=position].rotate_right(1);
+        }
+    }
+
+    return addresses;
+}
+
+/// Error returned when none of the KDCs can be resolved.
+pub(crate) fn unresolved_kdcs_error(kdcs: &[KdcEndpoint]) -> Error {
+    let kdcs: Vec<String> = kdcs.iter().map(|kdc| kdc.to_string()).collect();
+    return Error::NameResolutionError(kdcs.join(", "));
+}
+
+impl Transporter for FailoverTransporter {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let addresses =
+            kdc_addresses(&self.kdcs, &*self.resolver, self.last_kdc_address());
+        if addresses.is_empty() {
+            return Err(unresolved_kdcs_error(&self.kdcs));
+        }
+
+        let mut last_error = Error::NetworkError;
+        for address in addresses {
+            match (self.new_transporter)(address)
+                .request_and_response(raw_request)
+            {
+                Ok(raw_response) => {
+                    *self.last_kdc_address.lock().unwrap() = Some(address);
+                    return Ok(raw_response);
+                }
+                Err(error) => {
+                    last_error = error;
+                }
+            }
+        }
+
+        return Err(last_error);
+    }
+
+    fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return *self.last_kdc_address.lock().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::test::FakeDnsResolver;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    /// KDC that responds with its own address, unless it is down
+    struct FakeKdc {
+        address: SocketAddr,
+        down: bool,
+        requested: Arc<Mutex<Vec<SocketAddr>>>,
+    }
+
+    impl Transporter for FakeKdc {
+        fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+            self.requested.lock().unwrap().push(self.address);
+            if self.down {
+                return Err(Error::NetworkError);
+            }
+            return Ok(self.address.to_string().into_bytes());
+        }
+    }
+
+    fn ip(last: u8) -> IpAddr {
+        return IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+    }
+
+    fn new_failover_transporter(
+        kdcs: Vec<KdcEndpoint>,
+        down_kdcs: Vec<SocketAddr>,
+    ) -> (FailoverTransporter, Arc<Mutex<Vec<SocketAddr>>>) {
+        let mut resolver = FakeDnsResolver::default();
+        resolver
+            .ip_records
+            .insert("kdc1.kingdom.hearts".to_string(), vec![ip(1), ip(11)]);
+
+        let mut transporter = FailoverTransporter::new(
+            kdcs,
+            Arc::new(resolver),
+            TransportProtocol::TCP,
+            TransportSettings::default(),
+        );
+
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let requested_clone = requested.clone();
+        transporter.set_new_transporter(Box::new(move |address| {
+            return Box::new(FakeKdc {
+                address,
+                down: down_kdcs.contains(&address),
+                requested: requested_clone.clone(),
+            });
+        }));
+
+        return (transporter, requested);
+    }
+
+    #[test]
+    fn request_first_responsive_kdc() {
+        let (transporter, requested) = new_failover_transporter(
+            vec![
+                KdcEndpoint::new("unknown.kingdom.hearts", 88),
+                KdcEndpoint::new("kdc1.kingdom.hearts", 750),
+                KdcEndpoint::from(ip(2)),
+            ],
+            vec![SocketAddr::new(ip(1), 750), SocketAddr::new(ip(11), 750)],
+        );
+
+        assert_eq!(
+            b"10.0.0.2:88".to_vec(),
+            transporter.request_and_response(&[0]).unwrap()
+        );
+        assert_eq!(
+            Some(SocketAddr::new(ip(2), 88)),
+            transporter.last_kdc_address()
+        );
+        assert_eq!(
+            vec![
+                SocketAddr::new(ip(1), 750),
+                SocketAddr::new(ip(11), 750),
+                SocketAddr::new(ip(2), 88),
+            ],
+            *requested.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn request_last_responsive_kdc_first() {
+        let (transporter, requested) = new_failover_transporter(
+            vec![
+                KdcEndpoint::from(ip(1)),
+                KdcEndpoint::from(ip(2)),
+                KdcEndpoint::from(ip(3)),
+            ],
+            vec![SocketAddr::new(ip(1), 88)],
+        );
+
+        transporter.request_and_response(&[0]).unwrap();
+        requested.lock().unwrap().clear();
+        transporter.request_and_response(&[0]).unwrap();
+
+        assert_eq!(
+            vec![SocketAddr::new(ip(2), 88)],
+            *requested.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn error_when_no_kdc_responds() {
+        let (transporter, requested) = new_failover_transporter(
+            vec![KdcEndpoint::from(ip(1)), KdcEndpoint::from(ip(2))],
+            vec![SocketAddr::new(ip(1), 88), SocketAddr::new(ip(2), 88)],
+        );
+
+        assert_eq!(
+            Error::NetworkError,
+            transporter.request_and_response(&[0]).unwrap_err()
+        );
+        assert_eq!(2, requested.lock().unwrap().len());
+        assert_eq!(None, transporter.last_kdc_address());
+    }
+
+    #[test]
+    fn error_when_no_kdc_is_resolved() {
+        let (transporter, _) = new_failover_transporter(
+            vec![KdcEndpoint::new("unknown.kingdom.hearts", 88)],
+            Vec::new(),
+        );
+
+        assert_eq!(
+            Error::NameResolutionError("unknown.kingdom.hearts:88".to_string()),
+            transporter.request_and_response(&[0]).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::config::DEFAULT_UDP_PREFERENCE_LIMIT;
+use crate::Result;
+use himmelblau_kerberos_asn1::{Asn1Object, KrbError};
+use himmelblau_kerberos_constants::error_codes::KRB_ERR_RESPONSE_TOO_BIG;
+
+use super::transporter_trait::*;
+
+/// Sends Kerberos messages over UDP, retrying over TCP when the KDC
+/// responds with KRB_ERR_RESPONSE_TOO_BIG, as required by RFC 4120.
+/// Messages bigger than the UDP preference limit are directly sent over
+/// TCP, like the udp_preference_limit of krb5.conf.
+pub struct TcpFallbackTransporter {
+    udp_transporter: Box<dyn Transporter>,
+    tcp_transporter: Box<dyn Transporter>,
+    udp_preference_limit: usize,
+}
+
+impl TcpFallbackTransporter {
+    pub fn new(
+        udp_transporter: Box<dyn Transporter>,
+        tcp_transporter: Box<dyn Transporter>,
+    ) -> Self {
+        return Self {
+            udp_transporter,
+            tcp_transporter,
+            udp_preference_limit: DEFAULT_UDP_PREFERENCE_LIMIT,
+        };
+    }
+
+    pub fn set_udp_preference_limit(&mut self, udp_preference_limit: usize) {
+        self.udp_preference_limit = udp_preference_limit;
+    }
+
+    pub(crate) fn is_response_too_big(raw_response: &[u8]) -> bool {
+        match KrbError::parse(raw_response) {
+            Ok((_, krb_error)) => {
+                return krb_error.error_code == KRB_ERR_RESPONSE_TOO_BIG;
+            }
+            Err(_) => {
+                return false;
+            }
+        }
+    }
+}
+
+impl Transporter for TcpFallbackTransporter {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        if raw_request.len() > self.udp_preference_limit {
+            return self.tcp_transporter.request_and_response(raw_request);
+        }
+
+        let raw_response =
+            self.udp_transporter.request_and_response(raw_request)?;
+
+        if Self::is_response_too_big(&raw_response) {
+            return self.tcp_transporter.request_and_response(raw_request);
+        }
+
+        return Ok(raw_response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use himmelblau_kerberos_constants::error_codes::KDC_ERR_PREAUTH_REQUIRED;
+
+    struct FakeTransporter {
+        response: Vec<u8>,
+    }
+
+    impl Transporter for FakeTransporter {
+        fn request_and_response(&self, _raw_request: &[u8]) -> Result<Vec<u8>> {
+            return Ok(self.response.clone());
+        }
+    }
+
+    fn new_fallback_transporter(
+        udp_response: Vec<u8>,
+    ) -> TcpFallbackTransporter {
+        return TcpFallbackTransporter::new(
+            Box::new(FakeTransporter {
+                response: udp_response,
+            }),
+            Box::new(FakeTransporter {
+                response: vec![0x74, 0x63, 0x70],
+            }),
+        );
+    }
+
+    fn build_krb_error(error_code: i32) -> Vec<u8> {
+        let krb_error = KrbError {
+            pvno: 5,
+            msg_type: 30,
+            error_code,
+            ..Default::default()
+        };
+        return krb_error.build();
+    }
+
+    #[test]
+    fn retry_over_tcp_when_response_is_too_big() {
+        let transporter =
+            new_fallback_transporter(build_krb_error(KRB_ERR_RESPONSE_TOO_BIG));
+
+        assert_eq!(
+            vec![0x74, 0x63, 0x70],
+            transporter.request_and_response(&[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn keep_udp_response_for_other_errors() {
+        let krb_error = build_krb_error(KDC_ERR_PREAUTH_REQUIRED);
+        let transporter = new_fallback_transporter(krb_error.clone());
+
+        assert_eq!(krb_error, transporter.request_and_response(&[0]).unwrap());
+    }
+
+    #[test]
+    fn send_over_tcp_requests_bigger_than_udp_preference_limit() {
+        let mut transporter = new_fallback_transporter(vec![0x75, 0x64, 0x70]);
+        transporter.set_udp_preference_limit(1);
+
+        assert_eq!(
+            vec![0x75, 0x64, 0x70],
+            transporter.request_and_response(&[0]).unwrap()
+        );
+        assert_eq!(
+            vec![0x74, 0x63, 0x70],
+            transporter.request_and_response(&[0, 1]).unwrap()
+        );
+    }
+}
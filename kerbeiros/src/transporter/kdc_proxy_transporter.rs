@@ -0,0 +1,328 @@
+use super::tcp_transporter::TCPTransporter;
+use super::transporter_trait::*;
+use super::TransportSettings;
+use crate::messages::{AsReq, TgsReq};
+use crate::{Error, Result};
+use himmelblau_kerberos_asn1::{Asn1Object, KdcProxyMessage, Realm};
+use std::io::Read;
+use std::time::Duration;
+
+/// Maximum size of the responses accepted from the KDC proxy
+pub const MAX_KDC_PROXY_RESPONSE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Media type of the KDC proxy requests and responses
+const KERBEROS_CONTENT_TYPE: &str = "application/kerberos";
+
+/// Sends Kerberos messages to a KDC proxy (MS-KKDCP), which forwards them
+/// to the KDCs of the realm. The messages are wrapped in a
+/// KDC-PROXY-MESSAGE and sent in HTTP POST requests to the proxy URL,
+/// like `https://kdcproxy.contoso.com/KdcProxy`.
+pub struct KdcProxyTransporter {
+    url: Option<String>,
+    dclocator_hint: Option<i32>,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+}
+
+impl KdcProxyTransporter {
+    /// Creates a transporter with the proxy URL, DC locator hint and
+    /// timeouts of the settings.
+    pub fn new(settings: &TransportSettings) -> Self {
+        return Self {
+            url: settings.kdc_proxy_url.clone(),
+            dclocator_hint: settings.dclocator_hint,
+            connect_timeout: settings.connect_timeout,
+            read_timeout: settings.read_timeout,
+        };
+    }
+
+    /// Wraps the request in a KDC-PROXY-MESSAGE. The target domain is the
+    /// realm of the AS-REQ or TGS-REQ.
+    pub(crate) fn build_proxy_request(&self, raw_request: &[u8]) -> Vec<u8> {
+        let mut message = KdcProxyMessage::new(
+            TCPTransporter::set_size_header_to_request(raw_request),
+        );
+        message.target_domain = request_realm(raw_request);
+        message.dclocator_hint = self.dclocator_hint;
+        return message.build();
+    }
+
+    /// Extracts the KDC response from the KDC-PROXY-MESSAGE returned by
+    /// the proxy.
+    pub(crate) fn parse_proxy_response(raw_response: &[u8]) -> Result<Vec<u8>> {
+        let (_, message) = KdcProxyMessage::parse(raw_response)?;
+        let kerb_message = message.kerb_message;
+
+        if kerb_message.len() < 4 {
+            return Err(Error::KdcProxyError(
+                "response without length header".to_string(),
+            ));
+        }
+        let mut len_data_bytes = [0_u8; 4];
+        len_data_bytes.copy_from_slice(&kerb_message[..4]);
+        let data_length = u32::from_be_bytes(len_data_bytes) as usize;
+
+        if data_length != kerb_message.len() - 4 {
+            return Err(Error::KdcProxyError(format!(
+                "response length header is {} but {} bytes were received",
+                data_length,
+                kerb_message.len() - 4
+            )));
+        }
+
+        return Ok(kerb_message[4..].to_vec());
+    }
+
+    fn url(&self) -> Result<&str> {
+        return self.url.as_deref().ok_or_else(|| {
+            Error::KdcProxyError("no KDC proxy URL configured".to_string())
+        });
+    }
+
+    fn post(&self, url: &str, body: &[u8]) -> Result<Vec<u8>> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(self.connect_timeout)
+            .timeout_read(self.read_timeout)
+            .timeout_write(self.read_timeout)
+            .build();
+
+        let response = match agent
+            .post(url)
+            .set("Content-Type", KERBEROS_CONTENT_TYPE)
+            .send_bytes(body)
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(Error::KdcProxyError(format!(
+                    "proxy responded with HTTP status {}",
+                    status
+                )));
+            }
+            Err(ureq::Error::Transport(_)) => {
+                return Err(Error::NetworkError);
+            }
+        };
+
+        let mut raw_response = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_KDC_PROXY_RESPONSE_SIZE as u64 + 1)
+            .read_to_end(&mut raw_response)
+            .map_err(|_| Error::NetworkError)?;
+
+        if raw_response.len() > MAX_KDC_PROXY_RESPONSE_SIZE {
+            return Err(Error::ResponseTooLarge(MAX_KDC_PROXY_RESPONSE_SIZE));
+        }
+
+        return Ok(raw_response);
+    }
+}
+
+/// Retrieves the realm of the AS-REQ or TGS-REQ, if the request is one of
+/// them.
+fn request_realm(raw_request: &[u8]) -> Option<Realm> {
+    if let Ok((_, as_req)) = AsReq::parse(raw_request) {
+        return Some(as_req.req_body.realm);
+    }
+    if let Ok((_, tgs_req)) = TgsReq::parse(raw_request) {
+        return Some(tgs_req.req_body.realm);
+    }
+    return None;
+}
+
+impl Transporter for KdcProxyTransporter {
+    fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let url = self.url()?;
+        let raw_response =
+            self.post(url, &self.build_proxy_request(raw_request))?;
+        return Self::parse_proxy_response(&raw_response);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use himmelblau_kerberos_asn1::{KdcReqBody, KrbError};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// Reads the body of an HTTP POST request, checking its headers.
+    pub fn read_http_request(stream: &TcpStream) -> Vec<u8> {
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        assert_eq!("POST /KdcProxy HTTP/1.1\r\n", request_line);
+
+        let mut content_length = 0;
+        let mut content_type = String::new();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header == "\r\n" {
+                break;
+            }
+            let (name, value) = header.split_once(':').unwrap();
+            match name.to_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value.trim().parse().unwrap();
+                }
+                "content-type" => {
+                    content_type = value.trim().to_string();
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(KERBEROS_CONTENT_TYPE, content_type);
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        return body;
+    }
+
+    pub fn write_http_response(
+        mut stream: &TcpStream,
+        status: &str,
+        body: &[u8],
+    ) {
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            status,
+            KERBEROS_CONTENT_TYPE,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+    }
+
+    /// KDC proxy stand-in which responds to one request with a
+    /// KRB-ERROR, after checking the proxy message. Returns the URL
+    /// of the proxy.
+    pub fn spawn_kdc_proxy(
+        expected_domain: &'static str,
+        expected_hint: Option<i32>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/KdcProxy", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let body = read_http_request(&stream);
+            let (_, message) = KdcProxyMessage::parse(&body).unwrap();
+            assert_eq!(
+                Some(Realm::from(expected_domain)),
+                message.target_domain
+            );
+            assert_eq!(expected_hint, message.dclocator_hint);
+            AsReq::parse(&message.kerb_message[4..]).unwrap();
+
+            let krb_error = KrbError {
+                pvno: 5,
+                msg_type: 30,
+                ..Default::default()
+            };
+            let response = KdcProxyMessage::new(
+                TCPTransporter::set_size_header_to_request(&krb_error.build()),
+            );
+            write_http_response(&stream, "200 OK", &response.build());
+        });
+
+        return url;
+    }
+
+    pub fn new_as_req(realm: &str) -> Vec<u8> {
+        return AsReq {
+            pvno: 5,
+            msg_type: 10,
+            req_body: KdcReqBody {
+                realm: Realm::from(realm),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+        .build();
+    }
+
+    pub fn proxy_settings(url: &str) -> TransportSettings {
+        return TransportSettings {
+            kdc_proxy_url: Some(url.to_string()),
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn request_through_kdc_proxy() {
+        let url = spawn_kdc_proxy("KINGDOM.HEARTS", Some(0x40));
+        let transporter = KdcProxyTransporter::new(&TransportSettings {
+            kdc_proxy_url: Some(url),
+            dclocator_hint: Some(0x40),
+            ..Default::default()
+        });
+
+        let raw_response = transporter
+            .request_and_response(&new_as_req("KINGDOM.HEARTS"))
+            .unwrap();
+
+        assert_eq!(30, KrbError::parse(&raw_response).unwrap().1.msg_type);
+    }
+
+    #[test]
+    fn error_on_http_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/KdcProxy", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            read_http_request(&stream);
+            write_http_response(&stream, "503 Service Unavailable", &[]);
+        });
+
+        assert_eq!(
+            Error::KdcProxyError(
+                "proxy responded with HTTP status 503".to_string()
+            ),
+            KdcProxyTransporter::new(&proxy_settings(&url))
+                .request_and_response(&new_as_req("KINGDOM.HEARTS"))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_without_proxy_url() {
+        let transporter =
+            KdcProxyTransporter::new(&TransportSettings::default());
+
+        assert_eq!(
+            Error::KdcProxyError("no KDC proxy URL configured".to_string()),
+            transporter.request_and_response(&[0x01]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn error_on_wrong_response_length_header() {
+        let response =
+            KdcProxyMessage::new(vec![0x0, 0x0, 0x0, 0x3, 0x30, 0x00]).build();
+
+        assert_eq!(
+            Error::KdcProxyError(
+                "response length header is 3 but 2 bytes were received"
+                    .to_string()
+            ),
+            KdcProxyTransporter::parse_proxy_response(&response).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn proxy_message_without_target_domain_for_unknown_requests() {
+        let transporter = KdcProxyTransporter::new(&proxy_settings(
+            "https://kdcproxy/KdcProxy",
+        ));
+        let (_, message) = KdcProxyMessage::parse(
+            &transporter.build_proxy_request(&[0x01, 0x02]),
+        )
+        .unwrap();
+
+        assert_eq!(vec![0x0, 0x0, 0x0, 0x2, 0x01, 0x02], message.kerb_message);
+        assert_eq!(None, message.target_domain);
+    }
+}
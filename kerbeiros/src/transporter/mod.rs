@@ -1,7 +1,11 @@
 //! Module to provide means to transport Kerberos messages
 //!
 
+use crate::config::DEFAULT_UDP_PREFERENCE_LIMIT;
+use crate::discovery::{DnsResolver, KdcEndpoint};
 use std::net::*;
+use std::sync::Arc;
+use std::time::Duration;
 
 mod transporter_trait;
 pub use transporter_trait::*;
@@ -12,6 +16,22 @@ use tcp_transporter::*;
 mod udp_transporter;
 use udp_transporter::*;
 
+mod fallback_transporter;
+use fallback_transporter::*;
+
+mod failover_transporter;
+pub use failover_transporter::*;
+
+mod kdc_proxy_transporter;
+#[cfg(test)]
+pub(crate) use kdc_proxy_transporter::test::spawn_kdc_proxy;
+use kdc_proxy_transporter::*;
+
+#[cfg(feature = "async")]
+mod async_transporter;
+#[cfg(feature = "async")]
+pub use async_transporter::*;
+
 /// Default Kerberos port 88
 pub const DEFAULT_KERBEROS_PORT: u16 = 88;
 
@@ -20,21 +40,103 @@ pub const DEFAULT_KERBEROS_PORT: u16 = 88;
 pub enum TransportProtocol {
     TCP,
     UDP,
+    /// HTTPS through a KDC proxy (MS-KKDCP), whose URL is given by
+    /// [TransportSettings::kdc_proxy_url]
+    KdcProxy,
+}
+
+/// Settings of the connections with the KDCs
+#[derive(Debug, PartialEq, Clone)]
+pub struct TransportSettings {
+    /// Maximum time to establish a TCP connection
+    pub connect_timeout: Duration,
+
+    /// Maximum time to wait for a TCP response
+    pub read_timeout: Duration,
+
+    /// Time to wait for the first UDP response, which is doubled in each
+    /// retransmission
+    pub udp_timeout: Duration,
+
+    /// Number of UDP retransmissions to each KDC
+    pub udp_retries: u32,
+
+    /// Maximum size of the messages sent over UDP, since bigger messages
+    /// are sent over TCP
+    pub udp_preference_limit: usize,
+
+    /// URL of the KDC proxy, like `https://kdcproxy.contoso.com/KdcProxy`,
+    /// used by the KdcProxy transport protocol
+    pub kdc_proxy_url: Option<String>,
+
+    /// Flags sent to the KDC proxy to locate the domain controller, as
+    /// defined for DsGetDcName
+    pub dclocator_hint: Option<i32>,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        return Self {
+            connect_timeout: DEFAULT_TCP_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_TCP_READ_TIMEOUT,
+            udp_timeout: DEFAULT_UDP_TIMEOUT,
+            udp_retries: DEFAULT_UDP_RETRIES,
+            udp_preference_limit: DEFAULT_UDP_PREFERENCE_LIMIT,
+            kdc_proxy_url: None,
+            dclocator_hint: None,
+        };
+    }
 }
 
-/// Generates a transporter given and address and transport protocol
-pub fn new_transporter(
-    host_address: IpAddr,
+/// Generates a transporter which sends the messages to the first KDC of
+/// the list that responds or, in case of the KdcProxy protocol, to the
+/// KDC proxy of the settings.
+pub fn new_kdcs_transporter(
+    kdcs: Vec<KdcEndpoint>,
+    resolver: Arc<dyn DnsResolver>,
     transport_protocol: TransportProtocol,
+    settings: TransportSettings,
 ) -> Box<dyn Transporter> {
-    let dst_addr = SocketAddr::new(host_address, DEFAULT_KERBEROS_PORT);
+    if transport_protocol == TransportProtocol::KdcProxy {
+        return Box::new(KdcProxyTransporter::new(&settings));
+    }
+    return Box::new(FailoverTransporter::new(
+        kdcs,
+        resolver,
+        transport_protocol,
+        settings,
+    ));
+}
+
+/// Generates a transporter given the address, the transport protocol and
+/// the settings of the connection.
+pub fn new_transporter_with_settings(
+    dst_addr: SocketAddr,
+    transport_protocol: TransportProtocol,
+    settings: &TransportSettings,
+) -> Box<dyn Transporter> {
+    let mut tcp_transporter = TCPTransporter::new(dst_addr);
+    tcp_transporter.set_connect_timeout(settings.connect_timeout);
+    tcp_transporter.set_read_timeout(settings.read_timeout);
 
     match transport_protocol {
         TransportProtocol::TCP => {
-            return Box::new(TCPTransporter::new(dst_addr));
+            return Box::new(tcp_transporter);
         }
         TransportProtocol::UDP => {
-            return Box::new(UDPTransporter::new(dst_addr));
+            let mut udp_transporter = UDPTransporter::new(dst_addr);
+            udp_transporter.set_timeout(settings.udp_timeout);
+            udp_transporter.set_retries(settings.udp_retries);
+
+            let mut transporter = TcpFallbackTransporter::new(
+                Box::new(udp_transporter),
+                Box::new(tcp_transporter),
+            );
+            transporter.set_udp_preference_limit(settings.udp_preference_limit);
+            return Box::new(transporter);
+        }
+        TransportProtocol::KdcProxy => {
+            return Box::new(KdcProxyTransporter::new(settings));
         }
     }
 }
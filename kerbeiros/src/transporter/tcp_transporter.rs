@@ -6,15 +6,36 @@ use std::time::Duration;
 
 use super::transporter_trait::*;
 
+/// Default time to establish a TCP connection
+pub const DEFAULT_TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time to wait for the TCP response
+pub const DEFAULT_TCP_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Send Kerberos messages over TCP
 #[derive(Debug)]
 pub struct TCPTransporter {
     dst_addr: SocketAddr,
+    connect_timeout: Duration,
+    read_timeout: Duration,
 }
 
 impl TCPTransporter {
     pub fn new(dst_addr: SocketAddr) -> Self {
-        return Self { dst_addr };
+        return Self {
+            dst_addr,
+            connect_timeout: DEFAULT_TCP_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_TCP_READ_TIMEOUT,
+        };
+    }
+
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Sets the maximum time to wait in each read or write operation
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
     }
 
     fn request_and_response_tcp(
@@ -22,7 +43,9 @@ impl TCPTransporter {
         raw_request: &[u8],
     ) -> io::Result<Vec<u8>> {
         let mut tcp_stream =
-            TcpStream::connect_timeout(&self.dst_addr, Duration::new(5, 0))?;
+            TcpStream::connect_timeout(&self.dst_addr, self.connect_timeout)?;
+        tcp_stream.set_read_timeout(Some(self.read_timeout))?;
+        tcp_stream.set_write_timeout(Some(self.read_timeout))?;
 
         let raw_sized_request = Self::set_size_header_to_request(raw_request);
         tcp_stream.write_all(&raw_sized_request)?;
@@ -37,7 +60,7 @@ impl TCPTransporter {
         return Ok(raw_response);
     }
 
-    fn set_size_header_to_request(raw_request: &[u8]) -> Vec<u8> {
+    pub(crate) fn set_size_header_to_request(raw_request: &[u8]) -> Vec<u8> {
         let request_length = raw_request.len() as u32;
         let mut raw_sized_request: Vec<u8> =
             request_length.to_be_bytes().to_vec();
@@ -69,4 +92,17 @@ mod tests {
         ));
         requester.request_and_response(&vec![]).unwrap();
     }
+
+    #[test]
+    fn error_when_response_is_not_received_in_time() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transporter =
+            TCPTransporter::new(listener.local_addr().unwrap());
+        transporter.set_read_timeout(Duration::from_millis(50));
+
+        assert_eq!(
+            Error::NetworkError,
+            transporter.request_and_response(&[0x01]).unwrap_err()
+        );
+    }
 }
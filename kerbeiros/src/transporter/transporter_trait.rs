@@ -1,7 +1,13 @@
 use crate::error::*;
+use std::net::SocketAddr;
 
 /// Trait implemented by classes which deliver Kerberos messages
-pub trait Transporter {
+pub trait Transporter: Send + Sync {
     /// Sends a message and retrieves the response
     fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>>;
+
+    /// Address of the KDC that answered the last request, if known
+    fn last_kdc_address(&self) -> Option<SocketAddr> {
+        return None;
+    }
 }
@@ -1,63 +1,137 @@
 use crate::{Error, Result};
 use std::io;
 use std::net::*;
+use std::time::Duration;
 
 use super::transporter_trait::*;
 
-/// Send Kerberos messages over UDP
+/// Default time to wait for the first response over UDP
+pub const DEFAULT_UDP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default number of retransmissions over UDP
+pub const DEFAULT_UDP_RETRIES: u32 = 2;
+
+/// Maximum size of a UDP response
+pub const MAX_UDP_RESPONSE_SIZE: usize = 65507;
+
+/// Maximum time to wait for a response over UDP when the timeout is
+/// doubled in the retransmissions
+pub const MAX_UDP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Send Kerberos messages over UDP. The request is retransmitted when no
+/// response is received in time, doubling the timeout in each attempt up
+/// to [MAX_UDP_TIMEOUT].
 #[derive(Debug)]
 pub struct UDPTransporter {
     dst_addr: SocketAddr,
+    timeout: Duration,
+    retries: u32,
+    max_response_size: usize,
 }
 
 impl UDPTransporter {
     pub fn new(dst_addr: SocketAddr) -> Self {
-        return Self { dst_addr };
+        return Self {
+            dst_addr,
+            timeout: DEFAULT_UDP_TIMEOUT,
+            retries: DEFAULT_UDP_RETRIES,
+            max_response_size: MAX_UDP_RESPONSE_SIZE,
+        };
     }
 
-    fn request_and_response_udp(
+    /// Sets the timeout of the first attempt
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the number of retransmissions after the first attempt
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    #[cfg(test)]
+    pub fn set_max_response_size(&mut self, max_response_size: usize) {
+        self.max_response_size = max_response_size;
+    }
+
+    fn request_and_response_udp(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
+        let udp_socket = Self::connect(self.dst_addr)?;
+
+        let mut timeout = self.timeout;
+        for _ in 0..=self.retries {
+            match self.send_and_receive(&udp_socket, raw_request, timeout) {
+                Err(error) if Self::is_timeout(&error) => {
+                    timeout = next_udp_timeout(timeout);
+                }
+                Err(_) => {
+                    return Err(Error::NetworkError);
+                }
+                Ok(raw_response) => {
+                    if raw_response.len() > self.max_response_size {
+                        return Err(Error::ResponseTooLarge(
+                            self.max_response_size,
+                        ));
+                    }
+                    return Ok(raw_response);
+                }
+            }
+        }
+
+        return Err(Error::NetworkError);
+    }
+
+    fn connect(dst_addr: SocketAddr) -> Result<UdpSocket> {
+        let bind_addr = match dst_addr {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let udp_socket =
+            UdpSocket::bind(bind_addr).map_err(|_| Error::NetworkError)?;
+        udp_socket
+            .connect(dst_addr)
+            .map_err(|_| Error::NetworkError)?;
+        return Ok(udp_socket);
+    }
+
+    fn send_and_receive(
         &self,
+        udp_socket: &UdpSocket,
         raw_request: &[u8],
+        timeout: Duration,
     ) -> io::Result<Vec<u8>> {
-        let udp_socket = UdpSocket::bind("0.0.0.0:0")?;
-        udp_socket.connect(self.dst_addr)?;
-
         udp_socket.send(raw_request)?;
+        udp_socket.set_read_timeout(Some(timeout))?;
 
-        let data_length = self.calculate_response_size(&udp_socket)?;
-
-        let mut raw_response = vec![0; data_length];
-        udp_socket.recv(&mut raw_response)?;
+        // one extra byte to detect responses bigger than the limit
+        let mut raw_response = vec![0; self.max_response_size + 1];
+        let data_length = udp_socket.recv(&mut raw_response)?;
+        raw_response.truncate(data_length);
 
         return Ok(raw_response);
     }
 
-    fn calculate_response_size(
-        &self,
-        udp_socket: &UdpSocket,
-    ) -> io::Result<usize> {
-        let mut raw_response = vec![0; 2048];
-        let mut data_length = udp_socket.peek(&mut raw_response)?;
-        while data_length == raw_response.len() {
-            raw_response.append(&mut raw_response.clone());
-            data_length = udp_socket.peek(&mut raw_response)?;
-        }
-        return Ok(data_length);
+    fn is_timeout(error: &io::Error) -> bool {
+        return error.kind() == io::ErrorKind::WouldBlock
+            || error.kind() == io::ErrorKind::TimedOut;
     }
 }
 
 impl Transporter for UDPTransporter {
     fn request_and_response(&self, raw_request: &[u8]) -> Result<Vec<u8>> {
-        let raw_response = self
-            .request_and_response_udp(raw_request)
-            .map_err(|_| Error::NetworkError)?;
-        return Ok(raw_response);
+        return self.request_and_response_udp(raw_request);
     }
 }
 
+/// Doubles the timeout of the next attempt, up to MAX_UDP_TIMEOUT unless
+/// the timeout was already greater.
+pub(crate) fn next_udp_timeout(timeout: Duration) -> Duration {
+    return timeout.max(timeout.saturating_mul(2).min(MAX_UDP_TIMEOUT));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::thread;
 
     #[should_panic(expected = "NetworkError")]
     #[test]
@@ -68,4 +142,77 @@ mod tests {
         ));
         requester.request_and_response(&vec![]).unwrap();
     }
+
+    /// Spawns a KDC that ignores the first `ignored` requests and
+    /// responds to the next one with the given response
+    fn spawn_kdc(ignored: usize, response: Vec<u8>) -> SocketAddr {
+        let kdc_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let kdc_addr = kdc_socket.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            for _ in 0..ignored {
+                kdc_socket.recv_from(&mut buffer).unwrap();
+            }
+            let (_, client_addr) = kdc_socket.recv_from(&mut buffer).unwrap();
+            kdc_socket.send_to(&response, client_addr).unwrap();
+        });
+
+        return kdc_addr;
+    }
+
+    fn new_test_transporter(kdc_addr: SocketAddr) -> UDPTransporter {
+        let mut transporter = UDPTransporter::new(kdc_addr);
+        transporter.set_timeout(Duration::from_millis(50));
+        return transporter;
+    }
+
+    #[test]
+    fn test_retransmit_request_without_response() {
+        let kdc_addr = spawn_kdc(2, vec![1, 2, 3]);
+        let transporter = new_test_transporter(kdc_addr);
+
+        assert_eq!(
+            vec![1, 2, 3],
+            transporter.request_and_response(&[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_error_when_retries_are_exhausted() {
+        let kdc_addr = spawn_kdc(2, vec![1, 2, 3]);
+        let mut transporter = new_test_transporter(kdc_addr);
+        transporter.set_retries(1);
+
+        assert_eq!(
+            Error::NetworkError,
+            transporter.request_and_response(&[0]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_error_when_response_is_too_large() {
+        let kdc_addr = spawn_kdc(0, vec![0; 100]);
+        let mut transporter = new_test_transporter(kdc_addr);
+        transporter.set_max_response_size(50);
+
+        assert_eq!(
+            Error::ResponseTooLarge(50),
+            transporter.request_and_response(&[0]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn double_timeout_up_to_maximum() {
+        assert_eq!(
+            Duration::from_secs(2),
+            next_udp_timeout(Duration::from_secs(1))
+        );
+        assert_eq!(MAX_UDP_TIMEOUT, next_udp_timeout(Duration::from_secs(20)));
+        assert_eq!(
+            Duration::from_secs(60),
+            next_udp_timeout(Duration::from_secs(60))
+        );
+        assert_eq!(Duration::MAX, next_udp_timeout(Duration::MAX));
+    }
 }
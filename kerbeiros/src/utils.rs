@@ -1,21 +1,51 @@
 //! Implement functions that can be useful to support the main library functionality.
 
-use crate::{Error, Result};
+use crate::discovery::{discover_kdcs, SystemDnsResolver};
+use crate::transporter::TransportProtocol;
+use crate::Result;
 use ascii::AsciiString;
-use dns_lookup;
+use himmelblau_kerberos_asn1::{EncryptedData, EncryptionKey};
+use himmelblau_kerberos_crypto::new_kerberos_cipher;
 use std::net::IpAddr;
 
-/// Resolve the address of the KDC from the name of the realm.
+/// Resolve the address of the KDC from the name of the realm, through the
+/// `_kerberos._tcp` SRV records or, if missing, the realm name itself.
 ///
 /// # Errors
 /// Returns [`Error`](../error/struct.Error.html) if it is not possible to resolve the domain name or the resolution does not include any IP address.
 pub fn resolve_realm_kdc(realm: &AsciiString) -> Result<IpAddr> {
-    let ips = dns_lookup::lookup_host(realm.as_ref())
-        .map_err(|_| Error::NameResolutionError(realm.to_string()))?;
+    let kdcs = discover_kdcs(
+        &SystemDnsResolver::new(),
+        realm.as_str(),
+        TransportProtocol::TCP,
+    )?;
+    return Ok(kdcs[0].ip());
+}
 
-    if ips.is_empty() {
-        return Err(Error::NameResolutionError(realm.to_string()));
-    }
+/// Encrypts the plaintext with the key, for the given key usage.
+pub(crate) fn encrypt(
+    key: &EncryptionKey,
+    key_usage: i32,
+    plaintext: &[u8],
+) -> Result<EncryptedData> {
+    let cipher = new_kerberos_cipher(key.keytype)?;
+    return Ok(EncryptedData::new(
+        key.keytype,
+        None,
+        cipher.encrypt(&key.keyvalue, key_usage, plaintext),
+    ));
+}
 
-    return Ok(ips[0]);
+/// Decrypts the encrypted data with the key, for the given key usage.
+pub(crate) fn decrypt(
+    key: &EncryptionKey,
+    key_usage: i32,
+    encrypted_data: &EncryptedData,
+) -> Result<Vec<u8>> {
+    let cipher = new_kerberos_cipher(key.keytype)?;
+    return Ok(cipher.decrypt(
+        &key.keyvalue,
+        key_usage,
+        &encrypted_data.cipher,
+    )?);
 }
@@ -0,0 +1,54 @@
+use crate::{PrincipalName, Realm};
+use red_asn1::{Asn1Object, OctetString};
+use red_asn1_derive::Sequence;
+
+/// (*ChangePasswdData*) User data of the set password requests, which
+/// includes the principal whose password is set.
+/// Defined in RFC3244, section 2.
+/// ```asn1
+/// ChangePasswdData ::=  SEQUENCE {
+///        newpasswd[0]   OCTET STRING,
+///        targname[1]    PrincipalName OPTIONAL,
+///        targrealm[2]   Realm OPTIONAL
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct ChangePasswdData {
+    #[seq_field(context_tag = 0)]
+    pub newpasswd: OctetString,
+    #[seq_field(context_tag = 1)]
+    pub targname: Option<PrincipalName>,
+    #[seq_field(context_tag = 2)]
+    pub targrealm: Option<Realm>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::KerberosString;
+    use himmelblau_kerberos_constants::principal_names::NT_PRINCIPAL;
+
+    #[test]
+    fn build_change_passwd_data() {
+        let change_passwd_data = ChangePasswdData {
+            newpasswd: b"S3cr3t".to_vec(),
+            targname: Some(PrincipalName::new(
+                NT_PRINCIPAL,
+                KerberosString::from("mickey"),
+            )),
+            targrealm: Some(Realm::from("KINGDOM.HEARTS")),
+        };
+
+        assert_eq!(
+            vec![
+                0x30, 0x31, 0xa0, 0x08, 0x04, 0x06, 0x53, 0x33, 0x63, 0x72,
+                0x33, 0x74, 0xa1, 0x13, 0x30, 0x11, 0xa0, 0x03, 0x02, 0x01,
+                0x01, 0xa1, 0x0a, 0x30, 0x08, 0x1b, 0x06, 0x6d, 0x69, 0x63,
+                0x6b, 0x65, 0x79, 0xa2, 0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e,
+                0x47, 0x44, 0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52, 0x54,
+                0x53,
+            ],
+            change_passwd_data.build()
+        );
+    }
+}
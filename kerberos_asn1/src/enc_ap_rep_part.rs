@@ -20,7 +20,28 @@ pub struct EncApRepPart {
     #[seq_field(context_tag = 1)]
     pub cusec: Microseconds,
     #[seq_field(context_tag = 2)]
-    pub subkey: EncryptionKey,
+    pub subkey: Option<EncryptionKey>,
     #[seq_field(context_tag = 3)]
-    pub seq_number: UInt32,
+    pub seq_number: Option<UInt32>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn parse_enc_ap_rep_part_without_subkey() {
+        let enc_ap_rep_part = EncApRepPart {
+            ctime: Utc.with_ymd_and_hms(2020, 4, 5, 10, 30, 0).unwrap().into(),
+            cusec: 123,
+            subkey: None,
+            seq_number: Some(7),
+        };
+
+        assert_eq!(
+            enc_ap_rep_part,
+            EncApRepPart::parse(&enc_ap_rep_part.build()).unwrap().1
+        );
+    }
 }
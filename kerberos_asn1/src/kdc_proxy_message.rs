@@ -0,0 +1,72 @@
+use crate::{Int32, Realm};
+use red_asn1::{Asn1Object, OctetString};
+use red_asn1_derive::Sequence;
+
+/// (*KDC-PROXY-MESSAGE*) Wraps a Kerberos message sent through a KDC proxy
+/// over HTTPS. The message includes the 4 bytes length header used in TCP.
+/// Defined in MS-KKDCP, section 2.2.2.
+/// ```asn1
+/// KDC-PROXY-MESSAGE ::= SEQUENCE {
+///        kerb-message           [0] OCTET STRING,
+///        target-domain          [1] KERB-REALM OPTIONAL,
+///        dclocator-hint         [2] INTEGER OPTIONAL
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct KdcProxyMessage {
+    #[seq_field(context_tag = 0)]
+    pub kerb_message: OctetString,
+    #[seq_field(context_tag = 1)]
+    pub target_domain: Option<Realm>,
+    #[seq_field(context_tag = 2)]
+    pub dclocator_hint: Option<Int32>,
+}
+
+impl KdcProxyMessage {
+    pub fn new(kerb_message: OctetString) -> Self {
+        return Self {
+            kerb_message,
+            target_domain: None,
+            dclocator_hint: None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_kdc_proxy_message() {
+        let mut message = KdcProxyMessage::new(vec![0x0, 0x0, 0x0, 0x1, 0x30]);
+        message.target_domain = Some(Realm::from("KINGDOM.HEARTS"));
+        message.dclocator_hint = Some(2);
+
+        assert_eq!(
+            vec![
+                0x30, 0x20, 0xa0, 0x07, 0x04, 0x05, 0x00, 0x00, 0x00, 0x01,
+                0x30, 0xa1, 0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44,
+                0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52, 0x54, 0x53, 0xa2,
+                0x03, 0x02, 0x01, 0x02,
+            ],
+            message.build()
+        );
+    }
+
+    #[test]
+    fn parse_kdc_proxy_message() {
+        let mut message = KdcProxyMessage::new(vec![0x0, 0x0, 0x0, 0x1, 0x30]);
+        message.target_domain = Some(Realm::from("KINGDOM.HEARTS"));
+
+        assert_eq!(
+            message,
+            KdcProxyMessage::parse(&[
+                0x30, 0x1b, 0xa0, 0x07, 0x04, 0x05, 0x00, 0x00, 0x00, 0x01,
+                0x30, 0xa1, 0x10, 0x1b, 0x0e, 0x4b, 0x49, 0x4e, 0x47, 0x44,
+                0x4f, 0x4d, 0x2e, 0x48, 0x45, 0x41, 0x52, 0x54, 0x53,
+            ])
+            .unwrap()
+            .1
+        );
+    }
+}
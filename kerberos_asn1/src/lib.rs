@@ -23,8 +23,10 @@
 //! ```
 //! ## References
 //! - [RFC 4120, The Kerberos Network Authentication Service (V5)](https://tools.ietf.org/html/rfc4120)
+//! - [RFC 3244, Microsoft Windows 2000 Kerberos Change Password and Set Password Protocols](https://tools.ietf.org/html/rfc3244)
 //! - [RFC 6806, Kerberos Principal Name Canonicalization and Cross-Realm Referrals](https://tools.ietf.org/html/rfc6806)
 //! - [MS-KILE](https://docs.microsoft.com/en-us/openspecs/windows_protocols/MS-KILE/2a32282e-dd48-4ad9-a542-609804b02cc9)
+//! - [MS-KKDCP](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-kkdcp/5bcebb8d-b747-4ee5-9453-428aec1c5c38)
 //! - [MS-SFU](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-sfu/3bff5864-8135-400e-bdd9-33b552051d94)
 //!
 
@@ -43,6 +45,9 @@ pub use authenticator::Authenticator;
 mod authorization_data;
 pub use authorization_data::{AuthorizationData, AuthorizationDataEntry};
 
+mod change_passwd_data;
+pub use change_passwd_data::ChangePasswdData;
+
 mod checksum;
 pub use checksum::Checksum;
 
@@ -91,6 +96,15 @@ pub use krb_cred_info::KrbCredInfo;
 mod krb_priv;
 pub use krb_priv::KrbPriv;
 
+mod kdc_proxy_message;
+pub use kdc_proxy_message::KdcProxyMessage;
+
+mod neg_token_init;
+pub use neg_token_init::NegTokenInit;
+
+mod neg_token_resp;
+pub use neg_token_resp::NegTokenResp;
+
 mod kdc_req;
 pub use kdc_req::KdcReq;
 
@@ -178,6 +192,9 @@ mod krb_error;
 pub use krb_error::KrbError;
 
 pub use red_asn1::Asn1Object;
+pub use red_asn1::BitString;
+pub use red_asn1::Enumerated;
 pub use red_asn1::Error;
+pub use red_asn1::Oid;
 pub use red_asn1::Result;
 pub use red_asn1::TagClass;
@@ -0,0 +1,73 @@
+use red_asn1::{Asn1Object, BitString, OctetString, Oid};
+use red_asn1_derive::Sequence;
+
+/// (*NegTokenInit*) First token of SPNEGO, sent by the initiator with the
+/// mechanisms it supports, ordered by preference, and optionally the
+/// initial token of the first one.
+/// Defined in RFC4178, section 4.2.1.
+/// ```asn1
+/// NegTokenInit ::= SEQUENCE {
+///     mechTypes       [0] MechTypeList,
+///     reqFlags        [1] ContextFlags  OPTIONAL,
+///     mechToken       [2] OCTET STRING  OPTIONAL,
+///     mechListMIC     [3] OCTET STRING  OPTIONAL,
+///     ...
+/// }
+///
+/// MechTypeList ::= SEQUENCE OF MechType
+/// MechType ::= OBJECT IDENTIFIER
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct NegTokenInit {
+    #[seq_field(context_tag = 0)]
+    pub mech_types: Vec<Oid>,
+    #[seq_field(context_tag = 1)]
+    pub req_flags: Option<BitString>,
+    #[seq_field(context_tag = 2)]
+    pub mech_token: Option<OctetString>,
+    #[seq_field(context_tag = 3)]
+    pub mech_list_mic: Option<OctetString>,
+}
+
+impl NegTokenInit {
+    pub fn new(mech_types: Vec<Oid>, mech_token: Option<OctetString>) -> Self {
+        return Self {
+            mech_types,
+            req_flags: None,
+            mech_token,
+            mech_list_mic: None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn krb5_oid() -> Oid {
+        return Oid::new(1, 2, vec![840, 113554, 1, 2, 2]);
+    }
+
+    fn raw_neg_token_init() -> Vec<u8> {
+        return vec![
+            0x30, 0x15, 0xa0, 0x0d, 0x30, 0x0b, 0x06, 0x09, 0x2a, 0x86, 0x48,
+            0x86, 0xf7, 0x12, 0x01, 0x02, 0x02, 0xa2, 0x04, 0x04, 0x02, 0x01,
+            0x02,
+        ];
+    }
+
+    #[test]
+    fn build_neg_token_init() {
+        let neg_token_init =
+            NegTokenInit::new(vec![krb5_oid()], Some(vec![0x01, 0x02]));
+        assert_eq!(raw_neg_token_init(), neg_token_init.build());
+    }
+
+    #[test]
+    fn parse_neg_token_init() {
+        assert_eq!(
+            NegTokenInit::new(vec![krb5_oid()], Some(vec![0x01, 0x02])),
+            NegTokenInit::parse(&raw_neg_token_init()).unwrap().1
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use red_asn1::{Asn1Object, Enumerated, OctetString, Oid};
+use red_asn1_derive::Sequence;
+
+/// (*NegTokenResp*) Token of SPNEGO sent in the subsequent messages, with
+/// the state of the negotiation, the mechanism selected by the acceptor and
+/// the tokens of that mechanism.
+/// Defined in RFC4178, section 4.2.2.
+/// ```asn1
+/// NegTokenResp ::= SEQUENCE {
+///     negState       [0] ENUMERATED {
+///         accept-completed    (0),
+///         accept-incomplete   (1),
+///         reject              (2),
+///         request-mic         (3)
+///     }                                 OPTIONAL,
+///     supportedMech   [1] MechType      OPTIONAL,
+///     responseToken   [2] OCTET STRING  OPTIONAL,
+///     mechListMIC     [3] OCTET STRING  OPTIONAL,
+///     ...
+/// }
+/// ```
+#[derive(Sequence, Default, Debug, Clone, PartialEq)]
+pub struct NegTokenResp {
+    #[seq_field(context_tag = 0)]
+    pub neg_state: Option<Enumerated<i32>>,
+    #[seq_field(context_tag = 1)]
+    pub supported_mech: Option<Oid>,
+    #[seq_field(context_tag = 2)]
+    pub response_token: Option<OctetString>,
+    #[seq_field(context_tag = 3)]
+    pub mech_list_mic: Option<OctetString>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn raw_neg_token_resp() -> Vec<u8> {
+        return vec![
+            0x30, 0x18, 0xa0, 0x03, 0x0a, 0x01, 0x00, 0xa1, 0x0b, 0x06, 0x09,
+            0x2a, 0x86, 0x48, 0x82, 0xf7, 0x12, 0x01, 0x02, 0x02, 0xa2, 0x04,
+            0x04, 0x02, 0x03, 0x04,
+        ];
+    }
+
+    fn neg_token_resp() -> NegTokenResp {
+        return NegTokenResp {
+            neg_state: Some(Enumerated::new(0)),
+            supported_mech: Some(Oid::new(1, 2, vec![840, 48018, 1, 2, 2])),
+            response_token: Some(vec![0x03, 0x04]),
+            mech_list_mic: None,
+        };
+    }
+
+    #[test]
+    fn build_neg_token_resp() {
+        assert_eq!(raw_neg_token_resp(), neg_token_resp().build());
+    }
+
+    #[test]
+    fn parse_neg_token_resp() {
+        assert_eq!(
+            neg_token_resp(),
+            NegTokenResp::parse(&raw_neg_token_resp()).unwrap().1
+        );
+    }
+}
@@ -1,3 +1,13 @@
+//! Options used by the message [`ApReq`](../../messages/struct.ApReq.html).
+//!
+//! `RESERVED`, `USE_SESSION_KEY` and `MUTUAL_REQUIRED` are the indexes of
+//! the bits, whereas the `AP_OPTION_*` constants are the masks of the
+//! KerberosFlags, to be combined with `|` and checked with `&`.
+
 pub const RESERVED: u32 = 0;
 pub const USE_SESSION_KEY: u32 = 1;
 pub const MUTUAL_REQUIRED: u32 = 2;
+
+pub const AP_OPTION_RESERVED: u32 = 0x80000000;
+pub const AP_OPTION_USE_SESSION_KEY: u32 = 0x40000000;
+pub const AP_OPTION_MUTUAL_REQUIRED: u32 = 0x20000000;
@@ -0,0 +1,49 @@
+//! Constants of the Kerberos V5 GSS-API mechanism, defined in RFC 1964,
+//! RFC 4121 and RFC 4757.
+
+/// DER encoding of the OID of the Kerberos V5 mechanism
+/// (1.2.840.113554.1.2.2).
+pub const KRB5_OID: &[u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x02,
+];
+
+/// DER encoding of the OID of the Kerberos V5 mechanism used by Microsoft
+/// (1.2.840.48018.1.2.2).
+pub const MS_KRB5_OID: &[u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x82, 0xf7, 0x12, 0x01, 0x02, 0x02,
+];
+
+/// Checksum type of the authenticator checksum that carries the context
+/// flags and channel bindings
+pub const GSS_CHECKSUM_TYPE: i32 = 0x8003;
+
+pub const GSS_C_DELEG_FLAG: u32 = 0x01;
+pub const GSS_C_MUTUAL_FLAG: u32 = 0x02;
+pub const GSS_C_REPLAY_FLAG: u32 = 0x04;
+pub const GSS_C_SEQUENCE_FLAG: u32 = 0x08;
+pub const GSS_C_CONF_FLAG: u32 = 0x10;
+pub const GSS_C_INTEG_FLAG: u32 = 0x20;
+
+pub const TOK_ID_AP_REQ: u16 = 0x0100;
+pub const TOK_ID_AP_REP: u16 = 0x0200;
+pub const TOK_ID_KRB_ERROR: u16 = 0x0300;
+
+/// MIC token of RFC 1964
+pub const TOK_ID_MIC_V1: u16 = 0x0101;
+/// Wrap token of RFC 1964
+pub const TOK_ID_WRAP_V1: u16 = 0x0201;
+/// MIC token of RFC 4121
+pub const TOK_ID_MIC: u16 = 0x0404;
+/// Wrap token of RFC 4121
+pub const TOK_ID_WRAP: u16 = 0x0504;
+
+/// Flags of the RFC 4121 tokens
+pub const FLAG_SENT_BY_ACCEPTOR: u8 = 0x01;
+pub const FLAG_SEALED: u8 = 0x02;
+pub const FLAG_ACCEPTOR_SUBKEY: u8 = 0x04;
+
+/// Signature algorithm of the RFC 4757 tokens (HMAC-MD5)
+pub const SGN_ALG_HMAC_MD5: u16 = 0x1100;
+/// Sealing algorithm of the RFC 4757 tokens (RC4)
+pub const SEAL_ALG_RC4: u16 = 0x1000;
+pub const SEAL_ALG_NONE: u16 = 0xffff;
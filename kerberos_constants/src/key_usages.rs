@@ -65,3 +65,22 @@ pub const KEY_USAGE_KRB_SAFE_CKSUM: i32 = 15;
 
 pub const KEY_USAGE_KERB_NON_KERB_SALT: i32 = 16;
 pub const KEY_USAGE_KERB_NON_KERB_CKSUM_SALT: i32 = 17;
+
+/// GSS-API Wrap tokens sent by the acceptor (RFC 4121)
+pub const KEY_USAGE_ACCEPTOR_SEAL: i32 = 22;
+
+/// GSS-API MIC tokens sent by the acceptor (RFC 4121)
+pub const KEY_USAGE_ACCEPTOR_SIGN: i32 = 23;
+
+/// GSS-API Wrap tokens sent by the initiator (RFC 4121)
+pub const KEY_USAGE_INITIATOR_SEAL: i32 = 24;
+
+/// GSS-API MIC tokens sent by the initiator (RFC 4121)
+pub const KEY_USAGE_INITIATOR_SIGN: i32 = 25;
+
+/// PA-S4U-X509-USER checksum in a TGS-REQ, keyed with the TGS session key
+/// or subkey
+pub const KEY_USAGE_PA_S4U_X509_USER_REQUEST: i32 = 26;
+
+/// PA-S4U-X509-USER checksum in a TGS-REP, keyed with the reply key
+pub const KEY_USAGE_PA_S4U_X509_USER_REPLY: i32 = 27;
@@ -0,0 +1,20 @@
+//! Versions and result codes of the Kerberos password change protocol.
+//!
+//! # References
+//! * RFC 3244, Section 2.
+//! * MS-KILE, Section 3.1.5.12.
+
+/// Version of the change password requests and of the replies
+pub const KPASSWD_VERSION_CHANGE: u16 = 0x0001;
+
+/// Version of the set password requests
+pub const KPASSWD_VERSION_SET: u16 = 0xff80;
+
+pub const KRB5_KPASSWD_SUCCESS: u16 = 0;
+pub const KRB5_KPASSWD_MALFORMED: u16 = 1;
+pub const KRB5_KPASSWD_HARDERROR: u16 = 2;
+pub const KRB5_KPASSWD_AUTHERROR: u16 = 3;
+pub const KRB5_KPASSWD_SOFTERROR: u16 = 4;
+pub const KRB5_KPASSWD_ACCESSDENIED: u16 = 5;
+pub const KRB5_KPASSWD_BAD_VERSION: u16 = 6;
+pub const KRB5_KPASSWD_INITIAL_FLAG_NEEDED: u16 = 7;
@@ -7,13 +7,19 @@ pub mod ap_options;
 pub mod checksum_types;
 pub mod error_codes;
 pub mod etypes;
+pub mod gssapi;
 pub mod kdc_options;
 pub mod kerb_error_data_type;
 pub mod key_usages;
+pub mod kpasswd;
 pub mod message_types;
 pub mod pa_data_types;
 pub mod pa_pac_options;
+pub mod pac;
 pub mod principal_names;
 pub mod protocol_version;
+pub mod s4u_options;
+pub mod sasl;
+pub mod spnego;
 pub mod ticket_flags;
 pub mod tr_types;
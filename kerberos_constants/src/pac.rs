@@ -0,0 +1,34 @@
+//! Constants of the Privilege Attribute Certificate (PAC), defined in
+//! MS-PAC.
+
+/// Types of the buffers of the PAC (ulType of PAC_INFO_BUFFER)
+pub const PAC_LOGON_INFO: u32 = 1;
+pub const PAC_CREDENTIALS_INFO: u32 = 2;
+pub const PAC_SERVER_CHECKSUM: u32 = 6;
+pub const PAC_PRIVSVR_CHECKSUM: u32 = 7;
+pub const PAC_CLIENT_INFO: u32 = 10;
+pub const PAC_DELEGATION_INFO: u32 = 11;
+pub const PAC_UPN_DNS_INFO: u32 = 12;
+pub const PAC_CLIENT_CLAIMS_INFO: u32 = 13;
+pub const PAC_DEVICE_INFO: u32 = 14;
+pub const PAC_DEVICE_CLAIMS_INFO: u32 = 15;
+pub const PAC_TICKET_CHECKSUM: u32 = 16;
+pub const PAC_ATTRIBUTES_INFO: u32 = 17;
+pub const PAC_REQUESTOR: u32 = 18;
+pub const PAC_FULL_CHECKSUM: u32 = 19;
+
+/// The user has no UPN, so the UPN of UPN_DNS_INFO is a constructed one
+pub const UPN_DNS_INFO_NO_UPN: u32 = 0x01;
+/// UPN_DNS_INFO includes the SAM name and the SID of the user
+pub const UPN_DNS_INFO_EXTENDED: u32 = 0x02;
+
+/// Flags of PAC_ATTRIBUTES_INFO
+pub const PAC_WAS_REQUESTED: u32 = 0x01;
+pub const PAC_WAS_GIVEN_IMPLICITLY: u32 = 0x02;
+
+/// Attributes of the groups and SIDs of KERB_VALIDATION_INFO
+pub const SE_GROUP_MANDATORY: u32 = 0x00000001;
+pub const SE_GROUP_ENABLED_BY_DEFAULT: u32 = 0x00000002;
+pub const SE_GROUP_ENABLED: u32 = 0x00000004;
+pub const SE_GROUP_OWNER: u32 = 0x00000008;
+pub const SE_GROUP_RESOURCE: u32 = 0x20000000;
@@ -0,0 +1,7 @@
+//! Options used by the S4UUserID struct of PA-S4U-X509-USER
+//!
+//! # References
+//! * MS-SFU, Section 2.2.2.
+
+pub const CHECK_LOGON_HOUR_RESTRICTIONS: u32 = 0x40000000;
+pub const SIGNED_WITH_KUN_27: u32 = 0x20000000;
@@ -0,0 +1,9 @@
+//! Constants of the GSSAPI mechanism of SASL, defined in RFC 4752.
+
+/// Name of the mechanism
+pub const SASL_GSSAPI_MECHANISM: &str = "GSSAPI";
+
+/// Security layers negotiated after the authentication
+pub const SECURITY_LAYER_NONE: u8 = 0x01;
+pub const SECURITY_LAYER_INTEGRITY: u8 = 0x02;
+pub const SECURITY_LAYER_CONFIDENTIALITY: u8 = 0x04;
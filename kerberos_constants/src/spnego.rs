@@ -0,0 +1,10 @@
+//! Constants of SPNEGO, defined in RFC 4178.
+
+/// DER encoding of the OID of SPNEGO (1.3.6.1.5.5.2).
+pub const SPNEGO_OID: &[u8] = &[0x06, 0x06, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x02];
+
+/// States of the negotiation, sent by the acceptor in the NegTokenResp
+pub const ACCEPT_COMPLETED: i32 = 0;
+pub const ACCEPT_INCOMPLETE: i32 = 1;
+pub const REJECT: i32 = 2;
+pub const REQUEST_MIC: i32 = 3;
@@ -1,4 +1,6 @@
-use crate::cryptography::{dk, pbkdf2_sha1, AesSizes};
+use crate::cryptography::{
+    dk, pbkdf2_sha1, pbkdf2_sha1_with_iterations, AesSizes,
+};
 
 /// Derive the AES key used to encrypt/decrypt from the user secret (password)
 pub fn generate_key(
@@ -10,6 +12,23 @@ pub fn generate_key(
     return dk(&key, "kerberos".as_bytes(), aes_sizes);
 }
 
+/// Derive the AES key used to encrypt/decrypt from the user secret (password)
+/// with a custom iteration count, as indicated by the s2kparams
+pub fn generate_key_with_iterations(
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    aes_sizes: &AesSizes,
+) -> Vec<u8> {
+    let key = pbkdf2_sha1_with_iterations(
+        passphrase,
+        salt,
+        aes_sizes.seed_size(),
+        iterations,
+    );
+    return dk(&key, "kerberos".as_bytes(), aes_sizes);
+}
+
 /// Derive the AES key used to encrypt/decrypt from the string representation of the user secret (password)
 pub fn generate_key_from_string(
     string: &str,
@@ -31,6 +50,36 @@ mod test {
         return generate_key(passphrase, salt, &AesSizes::Aes256);
     }
 
+    #[test]
+    fn test_generate_aes_key_with_iterations() {
+        assert_eq!(
+            generate_aes_128_key(
+                "Minnie1234".as_bytes(),
+                "KINGDOM.HEARTSmickey".as_bytes()
+            ),
+            generate_key_with_iterations(
+                "Minnie1234".as_bytes(),
+                "KINGDOM.HEARTSmickey".as_bytes(),
+                0x1000,
+                &AesSizes::Aes128
+            )
+        );
+
+        // RFC 3962 test vector: 2 iterations, ATHENA.MIT.EDUraeburn
+        assert_eq!(
+            vec![
+                0xc6, 0x51, 0xbf, 0x29, 0xe2, 0x30, 0x0a, 0xc2, 0x7f, 0xa4,
+                0x69, 0xd6, 0x93, 0xbd, 0xda, 0x13
+            ],
+            generate_key_with_iterations(
+                "password".as_bytes(),
+                "ATHENA.MIT.EDUraeburn".as_bytes(),
+                2,
+                &AesSizes::Aes128
+            )
+        );
+    }
+
     #[test]
     fn test_generate_aes_128_key() {
         assert_eq!(
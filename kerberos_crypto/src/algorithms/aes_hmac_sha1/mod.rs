@@ -3,7 +3,9 @@
 //! AES256_CTS_HMAC_SHA1_96.
 
 mod keys;
-pub use keys::{generate_key, generate_key_from_string};
+pub use keys::{
+    generate_key, generate_key_from_string, generate_key_with_iterations,
+};
 
 mod decrypt;
 pub use decrypt::{decrypt, encrypt};
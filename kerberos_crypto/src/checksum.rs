@@ -1,4 +1,11 @@
 use crate::cryptography::{dk, hmac_md5, hmac_sha1, md5, AesSizes};
+use crate::{Error, Result};
+use himmelblau_kerberos_constants::checksum_types::{
+    HMAC_MD5, HMAC_SHA1_96_AES128, HMAC_SHA1_96_AES256,
+};
+use himmelblau_kerberos_constants::etypes::{
+    AES128_CTS_HMAC_SHA1_96, AES256_CTS_HMAC_SHA1_96, RC4_HMAC,
+};
 
 pub fn checksum_hmac_md5(
     key: &[u8],
@@ -32,6 +39,48 @@ pub fn checksum_sha_aes(
     return mac[..12].to_vec();
 }
 
+/// Returns the keyed checksum type that goes along with an encryption type,
+/// as defined in RFC 3961 (mandatory checksum).
+pub fn checksum_type_from_etype(etype: i32) -> Result<i32> {
+    match etype {
+        AES256_CTS_HMAC_SHA1_96 => return Ok(HMAC_SHA1_96_AES256),
+        AES128_CTS_HMAC_SHA1_96 => return Ok(HMAC_SHA1_96_AES128),
+        RC4_HMAC => return Ok(HMAC_MD5),
+        _ => return Err(Error::UnsupportedAlgorithm(etype)),
+    }
+}
+
+/// Calculates a keyed checksum of the specified type
+pub fn keyed_checksum(
+    cksumtype: i32,
+    key: &[u8],
+    key_usage: i32,
+    plaintext: &[u8],
+) -> Result<Vec<u8>> {
+    match cksumtype {
+        HMAC_SHA1_96_AES256 => {
+            return Ok(checksum_sha_aes(
+                key,
+                key_usage,
+                plaintext,
+                &AesSizes::Aes256,
+            ));
+        }
+        HMAC_SHA1_96_AES128 => {
+            return Ok(checksum_sha_aes(
+                key,
+                key_usage,
+                plaintext,
+                &AesSizes::Aes128,
+            ));
+        }
+        HMAC_MD5 => {
+            return Ok(checksum_hmac_md5(key, key_usage, plaintext));
+        }
+        _ => return Err(Error::UnsupportedAlgorithm(cksumtype)),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -101,4 +150,45 @@ mod test {
             checksum_sha_aes(key, 17, blob, &AesSizes::Aes128)
         );
     }
+
+    #[test]
+    fn test_checksum_type_from_etype() {
+        assert_eq!(
+            HMAC_SHA1_96_AES256,
+            checksum_type_from_etype(AES256_CTS_HMAC_SHA1_96).unwrap()
+        );
+        assert_eq!(
+            HMAC_SHA1_96_AES128,
+            checksum_type_from_etype(AES128_CTS_HMAC_SHA1_96).unwrap()
+        );
+        assert_eq!(HMAC_MD5, checksum_type_from_etype(RC4_HMAC).unwrap());
+    }
+
+    #[should_panic(expected = "UnsupportedAlgorithm(3)")]
+    #[test]
+    fn test_checksum_type_from_unsupported_etype() {
+        checksum_type_from_etype(3).unwrap();
+    }
+
+    #[test]
+    fn test_keyed_checksum() {
+        let key = &[
+            0x25, 0xB2, 0x07, 0x6C, 0xDA, 0x3B, 0xFD, 0x62, 0x09, 0x16, 0x1A,
+            0x6C, 0x78, 0xA6, 0x9C, 0x1C,
+        ];
+
+        let blob: &[u8] = &[
+            0xFF, 0xFF, 0xFF, 0x7F, 0x80, 0x23, 0xB8, 0xA3, 0x10, 0x31, 0xD6,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x45,
+        ];
+
+        assert_eq!(
+            checksum_sha_aes(key, 17, blob, &AesSizes::Aes128),
+            keyed_checksum(HMAC_SHA1_96_AES128, key, 17, blob).unwrap()
+        );
+        assert_eq!(
+            checksum_hmac_md5(key, 17, blob),
+            keyed_checksum(HMAC_MD5, key, 17, blob).unwrap()
+        );
+    }
 }
@@ -5,7 +5,11 @@ use himmelblau_kerberos_constants::etypes::{
 };
 
 use crate::KerberosCipher;
-use crate::Result;
+use crate::{Error, Result};
+
+/// Maximum iteration count accepted in the s2kparams, as MIT Kerberos does,
+/// since they come from the unauthenticated ETYPE-INFO2 of the KDC
+const MAX_ITERATIONS: u32 = 0x1000000;
 
 /// Cipher for the AES algorithm, used by the encryption types AES128_CTS_HMAC_SHA1_96 and AES256_CTS_HMAC_SHA1_96
 pub struct AesCipher {
@@ -72,6 +76,36 @@ impl KerberosCipher for AesCipher {
         );
     }
 
+    /// The s2kparams of AES are the iteration count as 4 bytes big-endian
+    /// integer (RFC 3962). An iteration count of 0 (2^32) or greater than
+    /// 0x1000000 is not supported.
+    fn generate_key_from_string_with_params(
+        &self,
+        password: &str,
+        salt: &[u8],
+        s2kparams: &[u8],
+    ) -> Result<Vec<u8>> {
+        if s2kparams.is_empty() {
+            return Ok(self.generate_key_from_string(password, salt));
+        }
+
+        let iterations = match s2kparams {
+            [a, b, c, d] => u32::from_be_bytes([*a, *b, *c, *d]),
+            _ => return Err(Error::InvalidS2kParams(s2kparams.to_vec())),
+        };
+
+        if iterations == 0 || iterations > MAX_ITERATIONS {
+            return Err(Error::InvalidS2kParams(s2kparams.to_vec()));
+        }
+
+        return Ok(aes_hmac_sha1::generate_key_with_iterations(
+            password.as_bytes(),
+            salt,
+            iterations,
+            &self.aes_sizes,
+        ));
+    }
+
     fn decrypt(
         &self,
         key: &[u8],
@@ -619,4 +653,56 @@ mod test {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn test_generate_key_from_string_with_s2kparams() {
+        let aes128_cipher = AesCipher::new(AesSizes::Aes128);
+
+        assert_eq!(
+            aes128_cipher.generate_key_from_string("Minnie1234", b"salt"),
+            aes128_cipher
+                .generate_key_from_string_with_params(
+                    "Minnie1234",
+                    b"salt",
+                    &[]
+                )
+                .unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                0xc6, 0x51, 0xbf, 0x29, 0xe2, 0x30, 0x0a, 0xc2, 0x7f, 0xa4,
+                0x69, 0xd6, 0x93, 0xbd, 0xda, 0x13
+            ],
+            aes128_cipher
+                .generate_key_from_string_with_params(
+                    "password",
+                    b"ATHENA.MIT.EDUraeburn",
+                    &[0, 0, 0, 2]
+                )
+                .unwrap()
+        );
+
+        assert_eq!(
+            Error::InvalidS2kParams(vec![0, 2]),
+            aes128_cipher
+                .generate_key_from_string_with_params(
+                    "password",
+                    b"ATHENA.MIT.EDUraeburn",
+                    &[0, 2]
+                )
+                .unwrap_err()
+        );
+
+        assert_eq!(
+            Error::InvalidS2kParams(vec![0xff, 0xff, 0xff, 0xff]),
+            aes128_cipher
+                .generate_key_from_string_with_params(
+                    "password",
+                    b"ATHENA.MIT.EDUraeburn",
+                    &[0xff, 0xff, 0xff, 0xff]
+                )
+                .unwrap_err()
+        );
+    }
 }
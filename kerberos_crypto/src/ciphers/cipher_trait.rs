@@ -6,6 +6,17 @@ pub trait KerberosCipher {
     fn generate_salt(&self, realm: &str, client_name: &str) -> Vec<u8>;
     fn generate_key(&self, raw_key: &[u8], salt: &[u8]) -> Vec<u8>;
     fn generate_key_from_string(&self, password: &str, salt: &[u8]) -> Vec<u8>;
+
+    /// Derive the key from the password with the string-to-key parameters
+    /// supplied by the KDC. By default the parameters are ignored.
+    fn generate_key_from_string_with_params(
+        &self,
+        password: &str,
+        salt: &[u8],
+        _s2kparams: &[u8],
+    ) -> Result<Vec<u8>> {
+        return Ok(self.generate_key_from_string(password, salt));
+    }
     fn decrypt(
         &self,
         key: &[u8],
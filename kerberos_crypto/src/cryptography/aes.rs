@@ -48,10 +48,26 @@ impl AesSizes {
     }
 }
 
+/// Default iteration count of the AES string-to-key function (RFC 3962)
+pub const AES_DEFAULT_ITERATIONS: u32 = 0x1000;
+
 pub fn pbkdf2_sha1(key: &[u8], salt: &[u8], seed_size: usize) -> Vec<u8> {
-    let iteration_count = 0x1000;
+    return pbkdf2_sha1_with_iterations(
+        key,
+        salt,
+        seed_size,
+        AES_DEFAULT_ITERATIONS,
+    );
+}
+
+pub fn pbkdf2_sha1_with_iterations(
+    key: &[u8],
+    salt: &[u8],
+    seed_size: usize,
+    iterations: u32,
+) -> Vec<u8> {
     let mut seed: Vec<u8> = vec![0; seed_size];
-    pbkdf2_hmac::<Sha1>(key, salt, iteration_count, &mut seed);
+    pbkdf2_hmac::<Sha1>(key, salt, iterations, &mut seed);
     return seed;
 }
 
@@ -2,9 +2,9 @@
 
 mod aes;
 pub use aes::{
-    decrypt_aes_ecb, encrypt_aes_cbc, pbkdf2_sha1, AesSizes, AES128_KEY_SIZE,
-    AES128_SEED_SIZE, AES256_KEY_SIZE, AES256_SEED_SIZE, AES_BLOCK_SIZE,
-    AES_MAC_SIZE,
+    decrypt_aes_ecb, encrypt_aes_cbc, pbkdf2_sha1, pbkdf2_sha1_with_iterations,
+    AesSizes, AES128_KEY_SIZE, AES128_SEED_SIZE, AES256_KEY_SIZE,
+    AES256_SEED_SIZE, AES_BLOCK_SIZE, AES_DEFAULT_ITERATIONS, AES_MAC_SIZE,
 };
 
 mod hmac;
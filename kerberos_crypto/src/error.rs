@@ -24,4 +24,8 @@ pub enum Error {
     /// Invalid key
     #[error("Invalid key: Length should be {}", _0)]
     InvalidKeyLength(usize),
+
+    /// Invalid string-to-key parameters
+    #[error("Invalid s2kparams: {:?}", _0)]
+    InvalidS2kParams(Vec<u8>),
 }
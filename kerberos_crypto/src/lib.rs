@@ -14,8 +14,9 @@ pub use algorithms::rc4_hmac_md5;
 
 mod cryptography;
 pub use cryptography::{
-    AesSizes, AES128_KEY_SIZE, AES128_SEED_SIZE, AES256_KEY_SIZE,
-    AES256_SEED_SIZE, AES_BLOCK_SIZE, AES_MAC_SIZE, RC4_KEY_SIZE,
+    hmac_md5, md5, rc4_decrypt, rc4_encrypt, AesSizes, AES128_KEY_SIZE,
+    AES128_SEED_SIZE, AES256_KEY_SIZE, AES256_SEED_SIZE, AES_BLOCK_SIZE,
+    AES_DEFAULT_ITERATIONS, AES_MAC_SIZE, RC4_KEY_SIZE,
 };
 
 mod utils;
@@ -24,7 +25,10 @@ mod error;
 pub use error::{Error, Result};
 
 mod checksum;
-pub use checksum::{checksum_hmac_md5, checksum_sha_aes};
+pub use checksum::{
+    checksum_hmac_md5, checksum_sha_aes, checksum_type_from_etype,
+    keyed_checksum,
+};
 
 mod ciphers;
 pub use ciphers::{new_kerberos_cipher, AesCipher, KerberosCipher, Rc4Cipher};